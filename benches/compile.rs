@@ -0,0 +1,73 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    process::{self, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+// Compile time of a generated struct-heavy program, --disassemble compiles it without running:
+//   cargo bench --bench compile
+const STRUCTS: usize = 200;
+const MEMBERS: usize = 20;
+const RUNS: usize = 5;
+
+// STRUCTS structs with MEMBERS fields and MEMBERS methods each, main reads every field
+// and calls every method of every struct
+fn generate() -> String {
+    let mut source = String::new();
+    for s in 0..STRUCTS {
+        writeln!(source, "struct S{} {{", s).unwrap();
+        for f in 0..MEMBERS {
+            writeln!(source, "    f{}: int,", f).unwrap();
+        }
+        source.push_str("\n    methods {\n");
+        for m in 0..MEMBERS {
+            writeln!(source, "        m{}(self, n: int) int {{\n            return self.f{} + n\n        }}", m, m).unwrap();
+        }
+        source.push_str("    }\n}\n\n");
+    }
+
+    source.push_str("fn main() null {\n    var total: int = 0\n");
+    for s in 0..STRUCTS {
+        let values: Vec<String> = (0..MEMBERS).map(|f| f.to_string()).collect();
+        writeln!(source, "    var s{}: S{} = {{{}}}", s, s, values.join(", ")).unwrap();
+        for m in 0..MEMBERS {
+            writeln!(source, "    total = total + s{}.m{}(s{}.f{})", s, m, s, m).unwrap();
+        }
+    }
+    source.push_str("    println(total)\n}\n");
+    source
+}
+
+// Best of RUNS, the fastest run is the least disturbed by other processes
+fn measure(path: &PathBuf) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let output = Command::new(env!("CARGO_BIN_EXE_shlang"))
+            .arg("--disassemble")
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .unwrap();
+        let elapsed = start.elapsed();
+
+        if !output.status.success() {
+            eprintln!("{} failed:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+            process::exit(1);
+        }
+        best = best.min(elapsed);
+    }
+    best
+}
+
+fn main() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("compile_bench.shl");
+    fs::write(&path, generate()).unwrap();
+
+    let elapsed = measure(&path);
+    println!("{} structs x {} fields/methods: {:.1} ms", STRUCTS, MEMBERS, elapsed.as_secs_f64() * 1000.0);
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+// Every benches/programs/<name>.shl is run with the shlang binary (output is discarded),
+// its first line "# ops: N" tells how many operations one run performs.
+//   cargo bench --bench programs         - print ops/sec of every program
+//   cargo bench --bench programs -- --check [--tolerance 0.25]
+//                                        - compare with baseline, exit 1 when a program got slower than tolerance allows
+//   cargo bench --bench programs -- --save - store current results as baseline
+const PROGRAMS_DIR: &str = "benches/programs";
+const BASELINE: &str = "benches/baseline.json";
+const RUNS: usize = 5;
+const DEFAULT_TOLERANCE: f64 = 0.25;
+
+struct Options {
+    check: bool,
+    save: bool,
+    tolerance: f64,
+}
+
+fn parse_args() -> Options {
+    let mut options = Options { check: false, save: false, tolerance: DEFAULT_TOLERANCE };
+
+    // cargo passes --bench to harness-less targets, unknown flags are ignored
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => options.check = true,
+            "--save" => options.save = true,
+            "--tolerance" => {
+                options.tolerance = match args.next().and_then(|value| value.parse().ok()) {
+                    Some(tolerance) => tolerance,
+                    None => {
+                        eprintln!("--tolerance expects a number, e.g. 0.25");
+                        process::exit(2);
+                    },
+                };
+            },
+            _ => {},
+        }
+    }
+    options
+}
+
+fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(PROGRAMS_DIR);
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "shl"))
+        .collect();
+
+    files.sort();
+    files
+}
+
+fn ops_count(path: &Path) -> u64 {
+    let source = fs::read_to_string(path).unwrap();
+    source.lines().next()
+        .and_then(|line| line.strip_prefix("# ops:"))
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or_else(|| panic!("{}: first line has to be \"# ops: N\"", path.display()))
+}
+
+// Best of RUNS, the fastest run is the least disturbed by other processes
+fn measure(path: &Path) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let output = Command::new(env!("CARGO_BIN_EXE_shlang"))
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .unwrap();
+        let elapsed = start.elapsed();
+
+        if !output.status.success() {
+            eprintln!("{} failed:\n{}", path.display(), String::from_utf8_lossy(&output.stderr));
+            process::exit(1);
+        }
+        best = best.min(elapsed);
+    }
+    best
+}
+
+fn read_baseline(path: &Path) -> BTreeMap<String, f64> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("cannot read baseline {}: {}", path.display(), e);
+            process::exit(1);
+        },
+    };
+
+    // flat object of "name": number pairs, written by write_baseline
+    content.trim().trim_start_matches('{').trim_end_matches('}')
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (name, value) = entry.split_once(':').unwrap_or_else(|| panic!("bad baseline entry: {}", entry));
+            (name.trim().trim_matches('"').to_string(), value.trim().parse().unwrap_or_else(|_| panic!("bad baseline value: {}", entry)))
+        })
+        .collect()
+}
+
+fn write_baseline(path: &Path, results: &BTreeMap<String, f64>) {
+    let entries: Vec<String> = results.iter().map(|(name, ops)| format!("  \"{}\": {:.0}", name, ops)).collect();
+    fs::write(path, format!("{{\n{}\n}}\n", entries.join(",\n"))).unwrap();
+}
+
+fn main() {
+    let options = parse_args();
+    let baseline_path = Path::new(env!("CARGO_MANIFEST_DIR")).join(BASELINE);
+    let baseline = if options.check { read_baseline(&baseline_path) } else { BTreeMap::new() };
+
+    let mut results = BTreeMap::new();
+    let mut regressions = vec![];
+
+    for path in programs() {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        let ops_per_sec = ops_count(&path) as f64 / measure(&path).as_secs_f64();
+
+        match baseline.get(&name) {
+            Some(expected) => {
+                let change = ops_per_sec / expected - 1.0;
+                println!("{:<20} {:>14.0} ops/sec  {:+.1}%", name, ops_per_sec, change * 100.0);
+                if change < -options.tolerance {
+                    regressions.push(name.clone());
+                }
+            },
+            None if options.check => println!("{:<20} {:>14.0} ops/sec  (no baseline)", name, ops_per_sec),
+            None => println!("{:<20} {:>14.0} ops/sec", name, ops_per_sec),
+        }
+        results.insert(name, ops_per_sec);
+    }
+
+    if options.save {
+        write_baseline(&baseline_path, &results);
+        println!("baseline written to {}", baseline_path.display());
+    }
+
+    if !regressions.is_empty() {
+        eprintln!("slower than baseline by more than {:.0}%: {}", options.tolerance * 100.0, regressions.join(", "));
+        process::exit(1);
+    }
+}
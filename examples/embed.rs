@@ -0,0 +1,33 @@
+// Runs a script calling functions provided by the host application:
+//     cargo run --example embed
+use shlang::{Engine, TokenType, Value};
+
+fn send_message(args: Vec<Value>) -> Value {
+    println!("[{}] {}", args[0].get_string(), args[1].get_string());
+    Value::Null
+}
+
+fn user_count(_args: Vec<Value>) -> Value {
+    Value::Int(42)
+}
+
+const SCRIPT: &str = r#"
+fn main() null {
+    var channel: String = "general"
+    var users: int = userCount()
+
+    sendMessage(channel, "hello from shlang")
+    sendMessage("stats", "users online: " + convstr(users))
+}
+"#;
+
+fn main() {
+    let mut engine = Engine::new();
+    engine.register_native("sendMessage", &[TokenType::STRING, TokenType::STRING], TokenType::NULL, send_message).unwrap();
+    engine.register_native("userCount", &[], TokenType::INT, user_count).unwrap();
+
+    // errors are already reported to stderr, the result tells which kind stopped the script
+    if let Err(termination) = engine.run_source(SCRIPT) {
+        eprintln!("script stopped: {:?}", termination);
+    }
+}
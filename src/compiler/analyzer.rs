@@ -0,0 +1,178 @@
+use crate::{
+    compiler::compiler::Symbol,
+    frontend::tokens::{Keywords, TokenType},
+    vm::{bytecode::{Chunk, OpCode}, value::Value},
+};
+
+// (values needed on the stack, values left on the stack after instruction)
+pub fn stack_effect(op: &OpCode, symbols: &[Symbol]) -> (usize, usize) {
+    match op {
+        OpCode::FUNCTION_CALL(index) => {
+            let symbol = &symbols[*index];
+            match symbol.output_type {
+                TokenType::STRUCT(_) => (symbol.arg_count, 0),
+                _ => (symbol.arg_count, 1),
+            }
+        },
+        // native call always replaces its arguments with the output, Null included
+        OpCode::NATIVE_FN_CALL(index) => (symbols[*index].arg_count, 1),
+        OpCode::IO_FN_CALL(index, arg_count) => {
+            if symbols[*index].output_type == TokenType::KEYWORD(Keywords::NULL) {
+                return (*arg_count, *arg_count)
+            }
+            (*arg_count, 1)
+        },
+        OpCode::METHOD_CALL(mth) => {
+            let adder = if mth.is_self_arg { 1 } else { 0 };
+            match mth.output_type {
+                TokenType::STRUCT(_) => (mth.arg_count + adder, 0),
+                _ => (mth.arg_count + adder, 1),
+            }
+        },
+
+        OpCode::INSTANCE_DEC(_, field_count) | OpCode::LIST_DEC(_, field_count) => (*field_count, 0),
+        OpCode::STRING_DEC_VALUE(_) | OpCode::BYTES_DEC_VALUE(_) | OpCode::LIST_DEC_VALUE(_) | OpCode::HOST_STRUCT_DEC(_, _) | OpCode::INSTANCE_DEC_VALUE(_) => (1, 0),
+
+        OpCode::GET_INSTANCE_FIELD(_, _) | OpCode::GET_INSTANCE_RF(_) | OpCode::GET_INSTANCE_W_OFFSET_RF(_) | OpCode::INSTANCE_VALUE(_) |
+        OpCode::GET_LIST(_) | OpCode::LIST_IS_EMPTY(_) | OpCode::VAR_CALL(_) | OpCode::PUSH_STACK(_) | OpCode::LIST_SORT_KEY(_, _) | OpCode::LIST_SORT(_, _) |
+        OpCode::CONSTANT_BOOL(_) | OpCode::CONSTANT_FLOAT(_) | OpCode::CONSTANT_INT(_) | OpCode::CONSTANT_NULL(_) => (0, 1),
+
+        OpCode::SET_INSTANCE_FIELD(_, _) | OpCode::GET_LIST_FIELD(_) | OpCode::VAR_SET(_) |
+        OpCode::GET_LIST_ELEMENT_FIELD(_, _) | OpCode::GET_LIST_ELEMENT_RF(_) |
+        OpCode::IF_STMT_OFFSET(_) | OpCode::JUMP_IF_FALSE(_) | OpCode::NEGATE | OpCode::DBG(_, _, None) | OpCode::LIST_EXTEND(_) | OpCode::LIST_REMOVE(_) | OpCode::LIST_FILL(_) |
+        OpCode::LIST_LOWER_BOUND(_) | OpCode::LIST_SORTED_INSERT(_) => (1, 1),
+
+        OpCode::SET_LIST_FIELD(_) | OpCode::SET_LIST_ELEMENT_FIELD(_, _) | OpCode::LIST_FILLED | OpCode::LIST_FROM_RANGE |
+        OpCode::LIST_CONTAINS | OpCode::LIST_CONCAT | OpCode::LIST_SWAP(_) | OpCode::LIST_INSERT_AT(_) | OpCode::LIST_INDEX(_) => (2, 1),
+
+        OpCode::LIST_INSERT => (3, 1),
+
+        OpCode::TUPLE_PACK(count) | OpCode::LIST_PACK(count) => (*count, 1),
+        OpCode::TUPLE_UNPACK(elements) => (1, elements.iter().filter(|element| element.is_none()).count()),
+
+        OpCode::POP | OpCode::RETURN => (1, 0),
+
+        OpCode::ADD_STRING | OpCode::EQ_STRING | OpCode::NEG_EQ_STRING | OpCode::CONTAINS_STRING |
+        OpCode::EQ_BOOL | OpCode::NEG_EQ_BOOL |
+        OpCode::ADD_FLOAT | OpCode::SUB_FLOAT | OpCode::MUL_FLOAT | OpCode::DIV_FLOAT | OpCode::MOD_FLOAT |
+        OpCode::EQ_FLOAT | OpCode::NEG_EQ_FLOAT | OpCode::GREATER_FLOAT | OpCode::EQ_GREATER_FLOAT |
+        OpCode::LESS_FLOAT | OpCode::EQ_LESS_FLOAT |
+        OpCode::ADD_INT | OpCode::SUB_INT | OpCode::MUL_INT | OpCode::DIV_INT | OpCode::MOD_INT |
+        OpCode::EQ_INT | OpCode::NEG_EQ_INT | OpCode::GREATER_INT | OpCode::EQ_GREATER_INT |
+        OpCode::LESS_INT | OpCode::EQ_LESS_INT => (2, 1),
+
+        OpCode::FUNCTION_DEC(_) | OpCode::STRUCT_DEC(_) | OpCode::STRING_DEC(_) |
+        OpCode::JUMP(_) | OpCode::LOOP(_) | OpCode::BREAK | OpCode::FOR_LOOP(_, _, _, _) |
+        OpCode::DEC_RC(_) | OpCode::DEC_TO(_) | OpCode::INSTANCE_INDEX(_) | OpCode::INC_RC(_) | OpCode::RF_REMOVE | OpCode::DBG(_, _, Some(_)) |
+        OpCode::END_OF_FN => (0, 0),
+    }
+}
+
+fn successors(op: &OpCode, offset: usize) -> Vec<i64> {
+    let next = offset as i64 + 1;
+    match op {
+        OpCode::JUMP(jump) => vec![next + *jump as i64],
+        OpCode::LOOP(jump) => vec![next - *jump as i64],
+        OpCode::IF_STMT_OFFSET(jump) | OpCode::JUMP_IF_FALSE(jump) | OpCode::FOR_LOOP(_, _, _, jump) => vec![next, next + *jump as i64],
+        OpCode::RETURN | OpCode::END_OF_FN => vec![],
+        _ => vec![next],
+    }
+}
+
+// Walks every reachable instruction of the chunk and computes stack depth before it,
+// starting with `params` values (function arguments) already on the stack.
+// Errors give the offset of the offending instruction and the reason
+pub fn stack_depths(chunk: &Chunk, params: usize, symbols: &[Symbol]) -> Result<Vec<Option<usize>>, (usize, String)> {
+    let mut depths: Vec<Option<usize>> = vec![None; chunk.code.len()];
+    if chunk.code.is_empty() {
+        return Ok(depths)
+    }
+
+    depths[0] = Some(params);
+    let mut to_visit: Vec<usize> = vec![0];
+
+    while let Some(offset) = to_visit.pop() {
+        let instruction = &chunk.code[offset];
+        let depth = depths[offset].unwrap();
+
+        let (needed, left) = stack_effect(&instruction.op, symbols);
+        if needed > depth {
+            return Err((offset, format!("stack underflow, {:?} needs {} values, but found {}", instruction.op, needed, depth)));
+        }
+        let new_depth = depth - needed + left;
+
+        for target in successors(&instruction.op, offset) {
+            if target < 0 || target as usize >= chunk.code.len() {
+                return Err((offset, format!("jump target {} of {:?} is out of chunk with {} instructions", target, instruction.op, chunk.code.len())));
+            }
+
+            let target = target as usize;
+            match depths[target] {
+                Some(val) if val != new_depth => {
+                    return Err((target, format!("inconsistent stack depth, {:?} reached with {} and {} values", chunk.code[target].op, val, new_depth)));
+                },
+                Some(_) => {},
+                None => {
+                    depths[target] = Some(new_depth);
+                    to_visit.push(target);
+                },
+            }
+        }
+    }
+
+    Ok(depths)
+}
+
+// Deepest stack of the chunk, arguments included. Every reachable instruction is entered with
+// its depth and leaves it at its successor, so the largest depth bounds the frame stack
+pub fn max_stack(chunk: &Chunk, params: usize, symbols: &[Symbol]) -> Result<usize, (usize, String)> {
+    let depths = stack_depths(chunk, params, symbols)?;
+    Ok(depths.into_iter().flatten().max().unwrap_or(params))
+}
+
+// Whether `target` can be executed, conditions on literal true (while true) never jump,
+// unless the chunk has a break, which leaves the loop through its condition jump
+pub fn is_reachable(chunk: &Chunk, target: usize) -> bool {
+    let has_break = chunk.code.iter().any(|instruction| instruction.op == OpCode::BREAK);
+    let mut visited = vec![false; chunk.code.len()];
+    let mut to_visit: Vec<usize> = vec![0];
+
+    while let Some(offset) = to_visit.pop() {
+        if offset >= chunk.code.len() || visited[offset] {
+            continue
+        }
+        visited[offset] = true;
+
+        let op = &chunk.code[offset].op;
+        let always_true = !has_break && matches!(op, OpCode::IF_STMT_OFFSET(_)) && offset > 0 &&
+            matches!(chunk.code[offset - 1].op, OpCode::CONSTANT_BOOL(index) if index < chunk.values.len() && chunk.values.get(index) == Value::Bool(true));
+        if always_true {
+            to_visit.push(offset + 1);
+            continue
+        }
+
+        to_visit.extend(successors(op, offset).into_iter().filter(|target| *target >= 0).map(|target| target as usize));
+    }
+
+    visited.get(target).copied().unwrap_or(false)
+}
+
+pub fn check_chunk(chunk: &Chunk, params: usize, symbols: &[Symbol]) -> Result<(), String> {
+    let depths = stack_depths(chunk, params, symbols)
+        .map_err(|(offset, reason)| format!("{} at offset {} {}:", reason, offset, chunk.code[offset].line))?;
+
+    for stmt in chunk.statements.iter() {
+        let (start, end) = match (depths.get(stmt.start), depths.get(stmt.end)) {
+            (Some(Some(start)), Some(Some(end))) => (*start, *end),
+            // statement ends with return/break or is never reached
+            _ => continue,
+        };
+
+        if end != start + stmt.kept {
+            return Err(format!("statement leaves {} values on the stack, expected {} {}:",
+                end as i64 - start as i64, stmt.kept, stmt.line));
+        }
+    }
+
+    Ok(())
+}
@@ -1,24 +1,33 @@
-use std::collections::HashMap;
+use std::{collections::{HashMap, HashSet}, panic::{self, AssertUnwindSafe}, sync::OnceLock};
 
 use crate::{
-    objects::{functions::{Function, Local, NativeFn, SpecialType}, lists::ListObj, rc::Object, string::StringObj, structs::{Struct, StructInstance}}, vm::{bytecode::{Chunk, Instruction, OpCode}, value::{Convert, Value}
+    objects::{bytes::{BytesMethods, BytesObj}, functions::{Function, Local, NativeFn, SpecialType}, lists::{ListMethods, ListObj}, ordered_map::OrderedMap, rc::Object, registry::NativeRegistry, string::StringObj, structs::{Struct, StructInstance}}, vm::{bytecode::{Chunk, Instruction, OpCode}, value::{Convert, Value}
 }};
 use crate::frontend::tokens::{Token, TokenType, Keywords};
 
-use super::errors::{self, error_message};
+use super::{analyzer, errors::{self, error_message}, flags::{self, CompilerFlags}};
 
 pub struct LoopInfo {
     pub loop_type: TokenType,
     pub start: usize,
+    // FOR_LOOP instruction of a for loop, continue jumps there
+    pub step_start: usize,
     pub locals_start: usize,
     pub instance_start: usize,
 }
 
+impl Default for LoopInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LoopInfo {
     pub fn new() -> Self {
         LoopInfo {
             loop_type: TokenType::NULL,
             start: 0,
+            step_start: 0,
             locals_start: 0,
             instance_start: 0,
         }
@@ -40,7 +49,10 @@ pub struct ParseRule {
     prec: Precedence,
 }
 
-pub fn init_rules() -> HashMap<TokenType, ParseRule> {
+// Built once and shared by every parser
+static RULES: OnceLock<HashMap<TokenType, ParseRule>> = OnceLock::new();
+
+fn init_rules() -> HashMap<TokenType, ParseRule> {
     HashMap::from([
         (TokenType::INT, ParseRule { prefix: Some(Compiler::number), infix: None, prec: Precedence::NONE }),
         (TokenType::FLOAT, ParseRule { prefix: Some(Compiler::number), infix: None, prec: Precedence::NONE }),
@@ -55,6 +67,10 @@ pub fn init_rules() -> HashMap<TokenType, ParseRule> {
         (TokenType::KEYWORD(Keywords::VAR), ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
 
         (TokenType::KEYWORD(Keywords::RETURN), ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
+        (TokenType::KEYWORD(Keywords::SWITCH), ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
+        (TokenType::KEYWORD(Keywords::CASE), ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
+        (TokenType::KEYWORD(Keywords::DO), ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
+        (TokenType::KEYWORD(Keywords::DISCARD), ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
 
         (TokenType::KEYWORD(Keywords::IF), ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
 
@@ -73,10 +89,11 @@ pub fn init_rules() -> HashMap<TokenType, ParseRule> {
 
         (TokenType::COMMA, ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
 
-        (TokenType::LEFT_PAREN, ParseRule { prefix: None, infix: Some(Compiler::fn_call), prec: Precedence::CALL }),
+        (TokenType::LEFT_PAREN, ParseRule { prefix: None, infix: Some(Compiler::call), prec: Precedence::CALL }),
         (TokenType::RIGHT_PAREN, ParseRule { prefix: None, infix: None, prec: Precedence::NONE }),
 
         (TokenType::INTERJ, ParseRule { prefix: Some(Compiler::negation), infix: None, prec: Precedence::NONE }),
+        (TokenType::KEYWORD(Keywords::NOT), ParseRule { prefix: Some(Compiler::negation), infix: None, prec: Precedence::NONE }),
 
         (TokenType::INTERJ_EQ, ParseRule { prefix: None, infix: Some(Compiler::logic_operator), prec: Precedence::EQUALITY }),
         (TokenType::EQ_EQ, ParseRule { prefix: None, infix: Some(Compiler::logic_operator), prec: Precedence::EQUALITY }),
@@ -84,6 +101,7 @@ pub fn init_rules() -> HashMap<TokenType, ParseRule> {
         (TokenType::GREATER_EQ, ParseRule { prefix: None, infix: Some(Compiler::logic_operator), prec: Precedence::COMPARISON }),
         (TokenType::LESS, ParseRule { prefix: None, infix: Some(Compiler::logic_operator), prec: Precedence::COMPARISON }),
         (TokenType::LESS_EQ, ParseRule { prefix: None, infix: Some(Compiler::logic_operator), prec: Precedence::COMPARISON }),
+        (TokenType::KEYWORD(Keywords::IN), ParseRule { prefix: None, infix: Some(Compiler::in_operator), prec: Precedence::COMPARISON }),
         
         (TokenType::KEYWORD(Keywords::AND), ParseRule { prefix: None, infix: Some(Compiler::and_op), prec: Precedence::AND }),
         (TokenType::KEYWORD(Keywords::OR), ParseRule { prefix: None, infix: Some(Compiler::or_op), prec: Precedence::OR }),
@@ -129,7 +147,7 @@ impl From<u32> for Precedence {
             10 => Precedence::PRIMARY,
             _ => {
                 errors::conversion_error("u32", "Precedence");
-                std::process::exit(1);
+                errors::compile_error();
             }
         }
     }
@@ -141,8 +159,175 @@ pub struct Parser {
     prev: Token,
     line: u32,
     index: usize,
-    rules: HashMap<TokenType, ParseRule>,
     symbols: Vec<Symbol>,
+    // name -> position of the first symbol with that name, instance symbols are blank and not indexed
+    symbol_index: HashMap<String, usize>,
+    // argument types of every declared function, filled before compiling,
+    // so calls to functions declared later can be checked too
+    fn_arg_types: HashMap<String, Vec<TokenType>>,
+    // element types of List<T> parameters, by function name and argument index
+    fn_list_elements: HashMap<(String, usize), TokenType>,
+    // count of unclosed (), [] and literal {} before every token
+    nesting: Vec<usize>,
+    // element types of every distinct tuple shape, TokenType::TUPLE holds index into it
+    tuple_types: Vec<Vec<TokenType>>,
+    // constructs being parsed, e.g. "call to 'foo'", the innermost one is named by consume errors
+    contexts: Vec<String>,
+    // token recovery skips from instead of the statement start, its line is part of the failed statement
+    resync_from: Option<usize>,
+}
+
+// "a" "b" and "a" + "b" are folded into one literal, so the program declares a single String
+// instead of concatenating at runtime. "+" is kept when a neighbour binds tighter, as in "b".len()
+fn fold_string_literals(tokens: Vec<Token>) -> Vec<Token> {
+    let mut folded: Vec<Token> = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let mut token = tokens[i].clone();
+        i += 1;
+        if token.token_type != TokenType::STRING {
+            folded.push(token);
+            continue;
+        }
+
+        let bound_left = matches!(folded.last().map(|prev| prev.token_type),
+            Some(TokenType::STAR | TokenType::SLASH | TokenType::MOD | TokenType::MINUS | TokenType::INTERJ | TokenType::KEYWORD(Keywords::NOT)));
+        loop {
+            let right = match tokens.get(i).map(|next| next.token_type) {
+                Some(TokenType::STRING) => i,
+                Some(TokenType::PLUS) if !bound_left && tokens.get(i + 1).map(|next| next.token_type) == Some(TokenType::STRING) => {
+                    let bound_right = matches!(tokens.get(i + 2).map(|next| next.token_type),
+                        Some(TokenType::DOT | TokenType::LEFT_BRACKET | TokenType::STAR | TokenType::SLASH | TokenType::MOD));
+                    if bound_right {
+                        break
+                    }
+                    i + 1
+                },
+                _ => break,
+            };
+            token.value.extend(tokens[right].value.iter());
+            i = right + 1;
+        }
+        folded.push(token);
+    }
+    folded
+}
+
+// Block braces do not count, literal braces follow "=", "return" or another opening token
+fn nesting_depths(tokens: &[Token]) -> Vec<usize> {
+    let mut depths = Vec::with_capacity(tokens.len());
+    let mut braces: Vec<bool> = vec![];
+    let mut depth: usize = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACKET => depth = depth.saturating_sub(1),
+            TokenType::RIGHT_BRACE if braces.pop() == Some(true) => depth = depth.saturating_sub(1),
+            _ => {},
+        }
+        depths.push(depth);
+
+        match token.token_type {
+            TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET => depth += 1,
+            TokenType::LEFT_BRACE => {
+                // typed literal P{...} is recognized by the token before its type name
+                let start = if i > 1 && tokens[i - 1].token_type == TokenType::IDENTIFIER { i - 1 } else { i };
+                let is_literal = start > 0 && matches!(tokens[start - 1].token_type,
+                    TokenType::EQ | TokenType::KEYWORD(Keywords::RETURN) | TokenType::COMMA | TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET | TokenType::LEFT_BRACE);
+                let is_literal = is_literal && (tokens[start - 1].token_type != TokenType::LEFT_BRACE || braces.last() == Some(&true));
+                if is_literal {
+                    depth += 1;
+                }
+                braces.push(is_literal);
+            },
+            _ => {},
+        }
+    }
+    depths
+}
+
+// Pre-pass over the whole token stream of a file, so unbalanced brackets are reported
+// at their opening location before the parser hits confusing errors at EOF
+fn check_brackets(tokens: &[Token]) {
+    // (bracket, line, enclosing declaration)
+    let mut opened: Vec<(char, u32, String)> = vec![];
+    let mut declaration = String::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let bracket = match token.token_type {
+            TokenType::KEYWORD(Keywords::FN) | TokenType::KEYWORD(Keywords::STRUCT) | TokenType::KEYWORD(Keywords::METHODS) if opened.is_empty() => {
+                let kind = match token.token_type {
+                    TokenType::KEYWORD(Keywords::FN) => "function",
+                    TokenType::KEYWORD(Keywords::STRUCT) => "struct",
+                    _ => "methods",
+                };
+                if let Some(name) = tokens.get(i + 1).filter(|next| next.token_type == TokenType::IDENTIFIER) {
+                    declaration = format!(" ({} '{}')", kind, name.value.iter().collect::<String>());
+                }
+                continue
+            },
+            TokenType::LEFT_BRACE | TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET => {
+                opened.push((token.value[0], token.line, declaration.clone()));
+                continue
+            },
+            TokenType::RIGHT_BRACE => '{',
+            TokenType::RIGHT_PAREN => '(',
+            TokenType::RIGHT_BRACKET => '[',
+            _ => continue,
+        };
+
+        match opened.pop() {
+            Some((open, _, _)) if open == bracket => {},
+            Some((open, line, declaration)) => {
+                errors::error_message("PARSER ERROR", format!("Mismatched '{}' closes '{}' opened at line {}{} {}:",
+                    token.value[0], open, line, declaration, token.line));
+                errors::compile_error();
+            },
+            None => {
+                errors::error_message("PARSER ERROR", format!("Unexpected '{}' without matching opening bracket {}:", token.value[0], token.line));
+                errors::compile_error();
+            },
+        }
+    }
+
+    if let Some((open, line, declaration)) = opened.pop() {
+        errors::error_message("PARSER ERROR", format!("Unclosed '{}' opened at line {}{} {}:", open, line, declaration, line));
+        errors::compile_error();
+    }
+}
+
+// Tuples hold only values that can be copied, struct instances are not allowed
+fn tuple_element_type(token: &Token) -> TokenType {
+    match token.token_type {
+        TokenType::KEYWORD(Keywords::INT) => TokenType::INT,
+        TokenType::KEYWORD(Keywords::FLOAT) => TokenType::FLOAT,
+        TokenType::KEYWORD(Keywords::BOOL) => TokenType::BOOL,
+        TokenType::KEYWORD(Keywords::STRING) => TokenType::STRING,
+        _ => {
+            errors::error_message("COMPILER ERROR", format!("Tuple element has to be int, float, bool or String, found: \"{}\" {}:",
+                token.value.iter().collect::<String>(),
+                token.line,
+            ));
+            errors::compile_error();
+        },
+    }
+}
+
+// Returns index of the tuple shape, the same shapes share one index
+fn intern_tuple(tuple_types: &mut Vec<Vec<TokenType>>, elements: Vec<TokenType>, line: u32) -> usize {
+    if elements.len() < 2 {
+        errors::error_message("COMPILER ERROR", format!("Tuple has to have at least two elements, found: {} {}:", elements.len(), line));
+        errors::compile_error();
+    }
+
+    match tuple_types.iter().position(|tuple_type| *tuple_type == elements) {
+        Some(index) => index,
+        None => {
+            tuple_types.push(elements);
+            tuple_types.len() - 1
+        },
+    }
 }
 
 impl Parser {
@@ -157,6 +342,72 @@ impl Parser {
         }
     }
 
+    // Newlines are insignificant inside unclosed (), [] and literal {},
+    // elsewhere an expression continues on the next line only if the line ends with an operator
+    pub fn check_line_start_operator(&mut self) {
+        if self.cur.line > self.prev.line && self.nesting[self.index - 1] == 0 {
+            self.resync_from = Some(self.index - 1);
+            errors::error_message("PARSER ERROR", format!("Unexpected '{}' at start of line, operators must end the previous line {}:",
+                self.cur.value.iter().collect::<String>(),
+                self.cur.line,
+            ));
+            errors::compile_error();
+        }
+    }
+
+    // Error recovery: skips the statement starting at token `start` with everything in its brackets,
+    // stops on the first token of the next line, closing brace of the enclosing block or EOF
+    pub fn skip_statement(&mut self, start: usize) {
+        let mut depth: usize = 0;
+        let mut i = start;
+        loop {
+            let token = &self.tokens[i];
+            if token.token_type == TokenType::EOF {
+                break
+            }
+            if i > start && depth == 0 && (token.token_type == TokenType::RIGHT_BRACE || token.line > self.tokens[i - 1].line) {
+                break
+            }
+
+            match token.token_type {
+                TokenType::LEFT_BRACE | TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET => depth += 1,
+                TokenType::RIGHT_BRACE | TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACKET => depth = depth.saturating_sub(1),
+                _ => {},
+            }
+            i += 1;
+        }
+
+        self.index = i + 1;
+        self.prev = self.tokens[i.saturating_sub(1)].clone();
+        self.cur = self.tokens[i].clone();
+        self.line = self.cur.line;
+    }
+
+    // Makes tokens[pos] the current token, prev is the token before it
+    pub fn seek(&mut self, pos: usize) {
+        self.index = pos - 1;
+        self.advance();
+        self.advance();
+    }
+
+    // Skips {...} starting at the current token, nested braces included
+    pub fn skip_block(&mut self) {
+        self.consume(TokenType::LEFT_BRACE);
+        let mut depth: usize = 1;
+        while depth > 0 && self.cur.token_type != TokenType::EOF {
+            match self.cur.token_type {
+                TokenType::LEFT_BRACE => depth += 1,
+                TokenType::RIGHT_BRACE => depth -= 1,
+                _ => {},
+            }
+            self.advance();
+        }
+    }
+
+    pub fn peek_next(&self) -> Token {
+        self.tokens[self.index].clone()
+    }
+
     pub fn peek_prev(&self) -> Token {
         self.tokens[self.index - 3].clone()
     }
@@ -170,28 +421,42 @@ impl Parser {
 
     pub fn consume(&mut self, token_type: TokenType) {
         if self.cur.token_type != token_type {
-            errors::error_message("PARSER ERROR", format!("Expected to find a {:?}, but found: {:?} {}:", token_type, self.cur.token_type, self.line));
-            std::process::exit(1);
+            let context = self.contexts.last().map(|context| format!(" in {}", context)).unwrap_or_default();
+            errors::error_message("PARSER ERROR", format!("Expected {}{}, but found {} {}:", token_type.describe(), context, self.cur.describe(), self.line));
+            errors::compile_error();
         }
         self.advance();
     }
 
-    pub fn get_symbols(&mut self, string_mths_offset: usize, list_mths_offset: usize) {
-        let mut symbols: Vec<Symbol> = NativeFn::get_natives_symbols();
+    pub fn push_context(&mut self, context: String) {
+        self.contexts.push(context);
+    }
 
-        symbols.push(Symbol { name: "String".to_string(), symbol_type: TokenType::KEYWORD(Keywords::STRUCT), output_type: TokenType::STRING, arg_count: 1 });
+    pub fn pop_context(&mut self) {
+        self.contexts.pop();
+    }
 
-        for _ in 0..string_mths_offset { 
-            symbols.push(Symbol { name: String::new(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 1 });
+    // Items of a list are followed by ',' or by the closing token, which is left for the caller
+    pub fn consume_separator(&mut self, closing: TokenType) {
+        if self.cur.token_type == TokenType::COMMA {
+            self.advance();
+        }else if self.cur.token_type != closing {
+            self.consume(closing);
         }
+    }
 
-        symbols.push(Symbol { name: "List".to_string(), symbol_type: TokenType::KEYWORD(Keywords::STRUCT), output_type: TokenType::INT, arg_count: 0 });
-
-        for _ in 0..list_mths_offset {
-            symbols.push(Symbol { name: String::new(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 1 });
+    pub fn get_symbols(&mut self, registry: &NativeRegistry) {
+        let mut symbols: Vec<Symbol> = registry.symbols();
+        for host_fn in registry.host_fns() {
+            self.fn_arg_types.insert(host_fn.name.clone(), host_fn.arg_types.clone());
         }
 
         let mut is_main_fn_found = false;
+        let mut tuple_types = vec![];
+        // (function, argument index, struct name)
+        let mut struct_args: Vec<(String, usize, String)> = vec![];
+        // (function, argument index, element token) of List<T> arguments
+        let mut list_args: Vec<(String, usize, Token)> = vec![];
 
         let mut iter = self.tokens.iter_mut();
         'l: while let Some(token) = iter.next()  {
@@ -206,7 +471,7 @@ impl Parser {
 
                 if symbols.iter().any(| symbol | symbol.name == fn_name) {
                     errors::error_message("COMPILER ERROR", format!("Function: \"{}\" is already defined {}:", fn_name, token.line));
-                    std::process::exit(1);
+                    errors::compile_error();
                 }
 
                 if fn_name == "main".to_ascii_lowercase() {
@@ -214,13 +479,48 @@ impl Parser {
                 }
 
                 let mut arg_count = 0;
-                'args: while let Some(tok) = iter.next() {
+                let mut arg_types: Vec<TokenType> = vec![];
+                let mut is_arg_type = false;
+                let mut list_arg: Option<usize> = None;
+                'args: for tok in iter.by_ref() {
+                    if let Some(index) = list_arg {
+                        if tok.token_type != TokenType::LESS {
+                            list_args.push((fn_name.clone(), index, tok.clone()));
+                            list_arg = None;
+                        }
+                    }
+                    if is_arg_type {
+                        // struct types are resolved once every struct symbol is known
+                        if tok.token_type == TokenType::IDENTIFIER {
+                            let struct_name = tok.value.iter().collect::<String>();
+                            if struct_name == "List" {
+                                list_arg = Some(arg_types.len());
+                            }
+                            struct_args.push((fn_name.clone(), arg_types.len(), struct_name));
+                        }
+                        arg_types.push(match tok.token_type {
+                            TokenType::KEYWORD(Keywords::INT) => TokenType::INT,
+                            TokenType::KEYWORD(Keywords::FLOAT) => TokenType::FLOAT,
+                            TokenType::KEYWORD(Keywords::BOOL) => TokenType::BOOL,
+                            _ => TokenType::NULL,
+                        });
+                        is_arg_type = false;
+                    }
+
                     match tok.token_type {
-                        TokenType::COLON => arg_count += 1,
+                        TokenType::COLON => {
+                            arg_count += 1;
+                            is_arg_type = true;
+                        },
                         TokenType::RIGHT_PAREN | TokenType::EOF => break 'args,
                         _ => {},
                     }
                 }
+                if fn_name == "main" && arg_count > 0 {
+                    errors::error_message("COMPILER ERROR", format!("Function \"main\" cannot take parameters, found: {} {}:", arg_count, token.line));
+                    errors::compile_error();
+                }
+                self.fn_arg_types.insert(fn_name.clone(), arg_types);
 
                 let out_type = match iter.next() {
                     Some(val) => {
@@ -230,6 +530,19 @@ impl Parser {
                             TokenType::KEYWORD(Keywords::FLOAT) => TokenType::FLOAT,
                             TokenType::KEYWORD(Keywords::BOOL) => TokenType::BOOL,
                             TokenType::KEYWORD(Keywords::STRING) => TokenType::STRING,
+                            TokenType::LEFT_PAREN => {
+                                let line = val.line;
+                                let mut elements = vec![];
+                                for tok in iter.by_ref() {
+                                    match tok.token_type {
+                                        TokenType::RIGHT_PAREN | TokenType::EOF => break,
+                                        TokenType::COMMA => {},
+                                        _ => elements.push(tuple_element_type(tok)),
+                                    }
+                                }
+
+                                TokenType::TUPLE(intern_tuple(&mut tuple_types, elements, line))
+                            },
                             TokenType::IDENTIFIER => {
                                 let struct_name = val.value.iter().collect::<String>();
                                 
@@ -243,7 +556,7 @@ impl Parser {
                                 if pos == -1 {
                                     errors::error_message("COMPILER ERROR",
                                     format!("Symbol: \"{}\" is not defined as struct in this scope, failed to create a function with that output type {}:", struct_name, self.line));
-                                    std::process::exit(1);
+                                    errors::compile_error();
                                 }
                         
                                 TokenType::STRUCT(pos as usize)
@@ -254,7 +567,7 @@ impl Parser {
                     None => break 'l,
                 };
 
-                symbols.push(Symbol{name: fn_name, symbol_type: TokenType::KEYWORD(Keywords::FN), output_type: out_type, arg_count: arg_count });
+                symbols.push(Symbol{name: fn_name, symbol_type: TokenType::KEYWORD(Keywords::FN), output_type: out_type, arg_count });
             }
 
             if token.token_type == TokenType::KEYWORD(Keywords::STRUCT) {
@@ -268,7 +581,7 @@ impl Parser {
 
                 if symbols.iter().any(| symbol | symbol.name == struct_name) {
                     errors::error_message("COMPILER ERROR", format!("Struct: \"{}\" is already defined {}:", struct_name, token.line));
-                    std::process::exit(1);
+                    errors::compile_error();
                 }
 
                 symbols.push(Symbol{name: struct_name, symbol_type: TokenType::KEYWORD(Keywords::STRUCT), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
@@ -276,53 +589,175 @@ impl Parser {
         }
 
         if !is_main_fn_found {
-            errors::error_message("COMPILE ERROR", format!("Cannot find \"main\" function"));
-            std::process::exit(1);
+            errors::error_message("COMPILE ERROR", "Cannot find \"main\" function".to_string());
+            errors::compile_error();
+        }
+
+        // unknown struct names are left unchecked here and reported by fn_declare
+        for (fn_name, index, struct_name) in struct_args {
+            let pos = symbols.iter().position(|symbol| symbol.name == struct_name && symbol.symbol_type == TokenType::KEYWORD(Keywords::STRUCT));
+            if let (Some(pos), Some(arg_types)) = (pos, self.fn_arg_types.get_mut(&fn_name)) {
+                arg_types[index] = TokenType::STRUCT(pos);
+            }
+        }
+
+        for (fn_name, index, tok) in list_args {
+            let element = match tok.token_type {
+                TokenType::KEYWORD(keyword @ (Keywords::INT | Keywords::FLOAT | Keywords::BOOL | Keywords::STRING)) => keyword.convert(),
+                TokenType::IDENTIFIER => {
+                    let struct_name = tok.value.iter().collect::<String>();
+                    match symbols.iter().position(|symbol| symbol.name == struct_name && symbol.symbol_type == TokenType::KEYWORD(Keywords::STRUCT)) {
+                        Some(pos) => TokenType::STRUCT(pos),
+                        None => continue,
+                    }
+                },
+                _ => continue,
+            };
+            self.fn_list_elements.insert((fn_name, index), element);
+        }
+
+        for host_fn in registry.host_fns() {
+            let struct_name = match &host_fn.output_struct {
+                Some(struct_name) => struct_name,
+                None => continue,
+            };
+
+            let struct_pos = symbols.iter().position(|symbol| symbol.name == *struct_name && symbol.symbol_type == TokenType::KEYWORD(Keywords::STRUCT));
+            let native_pos = symbols.iter().position(|symbol| symbol.name == host_fn.name && symbol.symbol_type == TokenType::NATIVE_FN);
+            match (struct_pos, native_pos) {
+                (Some(struct_pos), Some(native_pos)) => symbols[native_pos].output_type = TokenType::STRUCT(struct_pos),
+                _ => {
+                    errors::error_message("COMPILER ERROR", format!("Native \"{}\" returns struct \"{}\" which is not declared in the program", host_fn.name, struct_name));
+                    errors::compile_error();
+                },
+            }
         }
 
+        for (pos, symbol) in symbols.iter().enumerate().filter(|(_, symbol)| !symbol.name.is_empty()) {
+            self.symbol_index.entry(symbol.name.clone()).or_insert(pos);
+        }
         self.symbols = symbols;
+        self.tuple_types = tuple_types;
+    }
+
+    // Same as the first symbol of that name and kind in symbols, named symbols are only declared by get_symbols
+    fn find_symbol(&self, name: &str, is_kind: impl Fn(&Symbol) -> bool) -> Option<usize> {
+        match self.symbol_index.get(name) {
+            Some(&pos) if is_kind(&self.symbols[pos]) => Some(pos),
+            Some(_) => self.symbols.iter().position(|symbol| symbol.name == name && is_kind(symbol)),
+            None => None,
+        }
+    }
+
+    // Tokens without a rule cannot appear in expressions
+    pub fn get_rule(&self, token_type: &TokenType) -> Option<&'static ParseRule> {
+        RULES.get_or_init(init_rules).get(token_type)
     }
 
-    pub fn get_rule(&self, token_type: &TokenType) -> &ParseRule {
-        &self.rules[token_type]
+    pub fn prec(&self, token_type: &TokenType) -> Precedence {
+        self.get_rule(token_type).map_or(Precedence::NONE, |rule| rule.prec)
     }
 }
 
 pub struct Compiler {
     pub parser: Parser,
     cur_function: Function,
-    functions: HashMap<String, Function>,
     scope_depth: u32,
-    symbol_to_hold: usize,
+    // symbols of the calls being compiled, pushed when the callee is resolved and popped when
+    // its call is emitted, so calls nested in the arguments cannot redirect the outer one
+    call_targets: Vec<usize>,
     loop_info: LoopInfo,
-    structs: HashMap<String, Struct>,
+    structs: OrderedMap<String, Struct>,
     changing_fn: bool,
     declaring_list: bool,
+    flags: CompilerFlags,
+    registry: NativeRegistry,
+    // set while compiling the call whose tuple result is destructured or returned
+    tuple_allowed: bool,
+    error_count: usize,
+    // vars whose declaration failed, their later uses are not reported again
+    failed_vars: HashSet<String>,
+    // current error is a consequence of an already reported one
+    error_suppressed: bool,
+    // retained source of every file, indexed by Token::file
+    file_lines: Vec<Vec<String>>,
+    // element type of the list last read by GET_LIST, checked by the 'in' operator
+    list_element: Option<Value>,
+    // (struct, field) declaration lines, a method cannot take a field's name
+    field_lines: HashMap<(String, String), u32>,
+}
+
+// Compilation stops after that many errors
+const MAX_ERRORS: usize = 20;
+
+// Predefined constants, resolved by name before locals
+fn builtin_constant(name: &str) -> Option<Value> {
+    match name {
+        "PI" => Some(Value::Float(std::f64::consts::PI)),
+        "E" => Some(Value::Float(std::f64::consts::E)),
+        "MAX_INT" => Some(Value::Int(i64::MAX)),
+        "MIN_INT" => Some(Value::Int(i64::MIN)),
+        _ => None,
+    }
 }
 
 impl Compiler {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<Token>, flags: CompilerFlags) -> Self {
+        let tokens = fold_string_literals(tokens);
         Self {
             parser: Parser {
-                tokens: tokens,
-                cur: Token { token_type: TokenType::ERROR, value: vec![], line: 0},
-                prev: Token { token_type: TokenType::ERROR, value: vec![], line: 0},
+                nesting: nesting_depths(&tokens),
+                tokens,
+                cur: Token { token_type: TokenType::ERROR, value: vec![], line: 0, file: 0},
+                prev: Token { token_type: TokenType::ERROR, value: vec![], line: 0, file: 0},
                 line: 0,
                 index: 0,
-                rules: init_rules(),
                 symbols: vec![],
+                symbol_index: HashMap::new(),
+                fn_arg_types: HashMap::new(),
+                fn_list_elements: HashMap::new(),
+                tuple_types: vec![],
+                contexts: vec![],
+                resync_from: None,
             },
             cur_function: Function::new(String::new()),
-            functions: HashMap::new(),
             scope_depth: 0,
-            symbol_to_hold: 0,
+            call_targets: vec![],
             loop_info: LoopInfo::new(),
-            structs: HashMap::new(),
+            structs: OrderedMap::new(),
             changing_fn: false,
             declaring_list: false,
+            flags,
+            registry: NativeRegistry::default(),
+            tuple_allowed: false,
+            error_count: 0,
+            failed_vars: HashSet::new(),
+            error_suppressed: false,
+            file_lines: vec![],
+            list_element: None,
+            field_lines: HashMap::new(),
         }
     }
 
+    pub fn set_registry(&mut self, registry: NativeRegistry) {
+        self.registry = registry;
+    }
+
+    // Debug only, every function chunk keeps a copy of its file's source for the disassembler and runtime errors.
+    // Sources are in the order of Token::file
+    pub fn set_sources(&mut self, sources: &[&str]) {
+        self.file_lines = sources.iter().map(|source| source.lines().map(String::from).collect()).collect();
+    }
+
+    // Chunk ends in the file of its last token
+    fn set_chunk_file(&mut self) {
+        let file = self.parser.prev.file;
+        let lines = self.file_lines.get(file).cloned().unwrap_or_default();
+        let chunk = self.get_cur_chunk();
+        chunk.file = file;
+        chunk.source_lines = lines;
+    }
+
     pub fn get_cur_chunk(&mut self) -> &mut Chunk {
         self.cur_function.get_chunk()
     }
@@ -335,6 +770,30 @@ impl Compiler {
         self.cur_function.get_instances()
     }
 
+    // Locals are frame slots, declaration is the name reported when the function has too many
+    fn push_local(&mut self, local: Local, declaration: &str) {
+        if self.get_cur_locals().len() >= self.flags.max_locals {
+            let function = self.cur_function.name.clone();
+            self.too_many_locals(&function, declaration);
+        }
+
+        self.get_cur_locals().push(local);
+    }
+
+    fn too_many_locals(&self, function: &str, declaration: &str) -> ! {
+        let owner = match function {
+            "" => "Top level code".to_string(),
+            name => format!("Function \"{}\"", name),
+        };
+        errors::error_message("COMPILER ERROR", format!("{} declares more than {} locals, {} exceeds the limit {}:",
+            owner,
+            self.flags.max_locals,
+            declaration,
+            self.parser.line,
+        ));
+        errors::compile_error();
+    }
+
     pub fn negation(&mut self) {
         let negation_token = self.parser.prev.clone();
 
@@ -342,10 +801,22 @@ impl Compiler {
 
         match negation_token.token_type {
             TokenType::MINUS => self.emit_byte(OpCode::NEGATE, self.parser.line),
-            TokenType::INTERJ => self.emit_byte(OpCode::NEGATE, self.parser.line),
+            TokenType::INTERJ | TokenType::KEYWORD(Keywords::NOT) => {
+                let operand_type = self.get_cur_chunk().get_last_value().convert();
+                if operand_type != TokenType::BOOL {
+                    errors::error_message("COMPILING ERROR", format!("Operator '{}' expects bool operand, found: {} {}:",
+                        negation_token.value.iter().collect::<String>(),
+                        self.type_name(operand_type),
+                        self.parser.line,
+                    ));
+                    errors::compile_error();
+                }
+
+                self.emit_byte(OpCode::NEGATE, self.parser.line)
+            },
             _ => {
                 errors::error_unexpected(self.parser.prev.clone(), "negation function");
-                std::process::exit(1);
+                errors::compile_error();
             }
         }
     }
@@ -356,9 +827,9 @@ impl Compiler {
         let chunk = self.get_cur_chunk();
         let left_side = chunk.get_value(chunk.values.len() - 1).convert();
 
-        let rule = self.parser.get_rule(&logic_token.token_type);
+        let prec = self.parser.prec(&logic_token.token_type);
 
-        self.parse((rule.prec as u32 + 1).into());
+        self.parse((prec as u32 + 1).into());
 
         let values_len = self.get_cur_chunk().values.len();
         let right_side = self.get_cur_chunk().values.get(values_len - 1).convert();
@@ -376,11 +847,20 @@ impl Compiler {
                     TokenType::LESS_EQ => self.emit_byte(OpCode::EQ_LESS_INT, self.parser.line),
                     _ => {
                         errors::error_unexpected(logic_token, "logic operator function");
-                        std::process::exit(1);
+                        errors::compile_error();
                     }
                 };
             },
             TokenType::FLOAT => {
+                if !self.flags.allow_float_eq && matches!(logic_token.token_type, TokenType::EQ_EQ | TokenType::INTERJ_EQ) {
+                    let msg = format!("Operator '{}' checks float operands for exact equality, use approxEq(a, b, eps) instead {}:",
+                        logic_token.value.iter().collect::<String>(),
+                        self.parser.line,
+                    );
+
+                    self.warning(flags::FLOAT_EQUALITY, msg);
+                }
+
                 match logic_token.token_type {
                     TokenType::EQ_EQ => self.emit_byte(OpCode::EQ_FLOAT, self.parser.line),
                     TokenType::INTERJ_EQ => self.emit_byte(OpCode::NEG_EQ_FLOAT, self.parser.line),
@@ -390,7 +870,7 @@ impl Compiler {
                     TokenType::LESS_EQ => self.emit_byte(OpCode::EQ_LESS_FLOAT, self.parser.line),
                     _ => {
                         errors::error_unexpected(logic_token, "logic operator function");
-                        std::process::exit(1);
+                        errors::compile_error();
                     }
                 };
             },
@@ -400,7 +880,7 @@ impl Compiler {
                     TokenType::INTERJ_EQ => self.emit_byte(OpCode::NEG_EQ_BOOL, self.parser.line),
                     _ => {
                         errors::error_unexpected(logic_token, "logic operator function");
-                        std::process::exit(1);
+                        errors::compile_error();
                     }
                 };
             },
@@ -410,15 +890,51 @@ impl Compiler {
                     TokenType::INTERJ_EQ => self.emit_byte(OpCode::NEG_EQ_STRING, self.parser.line),
                     _ => {
                         errors::error_unexpected(logic_token, "logic operator function");
-                        std::process::exit(1);
+                        errors::compile_error();
                     }
                 };
             }
             _ => {
                 errors::error_unexpected_token_type(constants_type, self.parser.line, "logic operator function");
-                std::process::exit(1);
+                errors::compile_error();
             }
         };
+
+        // result of comparison is always BOOL
+        self.get_cur_chunk().push_value(Value::Bool(true));
+    }
+
+    // "err" in line checks for a substring, x in xs for an equal element of List<T>
+    pub fn in_operator(&mut self) {
+        let left_side = self.get_cur_chunk().get_last_value().convert();
+
+        // list and String operands are read by value, also inside of call arguments
+        self.list_element = None;
+        self.outside_args(|compiler| compiler.parse((Precedence::COMPARISON as u32 + 1).into()));
+
+        let right_side = self.get_cur_chunk().get_last_value().convert();
+        let element_type = self.list_operand_element().map(|element| element.convert());
+
+        match (left_side, right_side, element_type) {
+            (TokenType::STRING, TokenType::STRING, _) => self.emit_byte(OpCode::CONTAINS_STRING, self.parser.line),
+            (TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRING, TokenType::LIST, Some(element_type)) if element_type == left_side => {
+                self.emit_byte(OpCode::LIST_CONTAINS, self.parser.line)
+            },
+            _ => {
+                let right_name = match (right_side, element_type) {
+                    (TokenType::LIST, Some(element_type)) => format!("List<{}>", self.type_name(element_type)),
+                    _ => self.type_name(right_side),
+                };
+                errors::error_message("COMPILING ERROR", format!("Operator 'in' cannot check {} in {}, expected String in String or element in List of the same type {}:",
+                    self.type_name(left_side),
+                    right_name,
+                    self.parser.line,
+                ));
+                errors::compile_error();
+            },
+        };
+
+        self.get_cur_chunk().push_value(Value::Bool(true));
     }
 
     pub fn bool(&mut self) {
@@ -442,13 +958,13 @@ impl Compiler {
                     },
                     _ => {
                         errors::error_unexpected_keyword(val, self.parser.line, "bool function");
-                        std::process::exit(1);
+                        errors::compile_error();
                     }
                 }
             }
             _ => {
                 errors::error_unexpected(self.parser.prev.clone(), "bool function");
-                std::process::exit(1);
+                errors::compile_error();
             }
         };
     }
@@ -460,7 +976,7 @@ impl Compiler {
                     Ok(v) => v,
                     Err(_) => {
                         errors::conversion_error("Vec<char>", "i64");
-                        std::process::exit(1);
+                        errors::compile_error();
                     },
                 };
 
@@ -473,7 +989,7 @@ impl Compiler {
                     Ok(v) => v,
                     Err(_) => {
                         errors::conversion_error("Vec<char>", "f64");
-                        std::process::exit(1);
+                        errors::compile_error();
                     },
                 };
 
@@ -483,7 +999,7 @@ impl Compiler {
             }
             _ => {
                 errors::error_unexpected(self.parser.prev.clone(), "number function");
-                std::process::exit(1);
+                errors::compile_error();
             },
         }
     }
@@ -494,15 +1010,26 @@ impl Compiler {
         let chunk = self.get_cur_chunk();
 
         let left_side = chunk.get_value(chunk.values.len() - 1).convert();
+        let left_element = self.list_operand_element();
 
-        let rule = self.parser.get_rule(&arithmetic_token.token_type);
+        let prec = self.parser.prec(&arithmetic_token.token_type);
 
-        self.parse((rule.prec as u32 + 1).into());
+        self.parse((prec as u32 + 1).into());
 
         let values_len = self.get_cur_chunk().values.len();
         
         let right_side = self.get_cur_chunk().values.get(values_len - 1).convert();
 
+        if left_side == TokenType::LIST || right_side == TokenType::LIST {
+            let right_element = self.list_operand_element();
+            self.list_concat(&arithmetic_token, (left_side, left_element), (right_side, right_element));
+            return
+        }
+
+        if arithmetic_token.token_type == TokenType::PLUS && (left_side == TokenType::STRING) != (right_side == TokenType::STRING) {
+            self.concat_error(left_side, right_side);
+        }
+
         let constants_type = self.check_static_types(&right_side, left_side, &arithmetic_token);
 
         match constants_type {
@@ -515,7 +1042,7 @@ impl Compiler {
                     TokenType::MOD => self.emit_byte(OpCode::MOD_INT, self.parser.line),
                     _ => {
                         errors::error_unexpected(arithmetic_token, "arithmetic function");
-                        std::process::exit(1);
+                        errors::compile_error();
                     }
                 };
             },
@@ -528,37 +1055,137 @@ impl Compiler {
                     TokenType::MOD => self.emit_byte(OpCode::MOD_FLOAT, self.parser.line),
                     _ => {
                         errors::error_unexpected(arithmetic_token, "arithmetic function");
-                        std::process::exit(1);
+                        errors::compile_error();
                     }
                 };
             },
             TokenType::STRING => {
                 match arithmetic_token.token_type {
                     TokenType::PLUS => self.emit_byte(OpCode::ADD_STRING, self.parser.line),
-                    _ => {
-                        errors::error_unexpected(arithmetic_token, "arithmetic function");
-                        std::process::exit(1);
-                    }        
+                    _ => self.operator_not_defined(&arithmetic_token, constants_type),
                 };
             },
+            _ => self.operator_not_defined(&arithmetic_token, constants_type),
+        };
+    }
+
+    // Element type of the list operand that was compiled last, None for other operands
+    fn list_operand_element(&mut self) -> Option<Value> {
+        match self.get_cur_chunk().get_last_value() {
+            Value::ListObj(elements) => elements.first().cloned(),
+            Value::List => self.list_element.take(),
+            _ => None,
+        }
+    }
+
+    // Type of the operand compiled last, a list is shown with its element type
+    fn operand_name(&mut self, value_type: TokenType) -> String {
+        let element = match self.get_cur_chunk().get_last_instruction().op {
+            // list variable passed by reference
+            OpCode::INC_RC(pos) => match &self.get_cur_instances()[pos].is_special {
+                SpecialType::List(element) => Some(element.clone()),
+                _ => None,
+            },
+            _ => self.list_operand_element(),
+        };
+        match element {
+            Some(element) => format!("List<{}>", self.type_name(element.convert())),
+            None => self.type_name(value_type),
+        }
+    }
+
+    fn list_type_name(&self, value_type: TokenType, element: &Option<Value>) -> String {
+        match (value_type, element) {
+            (TokenType::LIST, Some(element)) => format!("List<{}>", self.type_name(element.convert())),
+            (value_type, _) => self.type_name(value_type),
+        }
+    }
+
+    // a + b builds a new list with elements of a followed by elements of b, elements are copied by value
+    fn list_concat(&mut self, op: &Token, left: (TokenType, Option<Value>), right: (TokenType, Option<Value>)) {
+        if op.token_type != TokenType::PLUS {
+            self.operator_not_defined(op, TokenType::LIST);
+        }
+
+        let element = match (&left, &right) {
+            ((TokenType::LIST, Some(left_element)), (TokenType::LIST, Some(right_element))) if left_element.convert() == right_element.convert() => left_element.clone(),
             _ => {
-                errors::error_unexpected_token_type(constants_type, self.parser.line, "arithmetic function");
-                std::process::exit(1);
-            }
+                let reason = if left.0 == right.0 { "element types differ" } else { "both operands have to be lists" };
+                errors::error_message("COMPILING ERROR", format!("Cannot concatenate {} and {}; {} {}:",
+                    self.list_type_name(left.0, &left.1),
+                    self.list_type_name(right.0, &right.1),
+                    reason,
+                    self.parser.line,
+                ));
+                errors::compile_error();
+            },
+        };
+        self.check_copied_elements(&element);
+
+        self.emit_byte(OpCode::LIST_CONCAT, self.parser.line);
+        self.get_cur_chunk().push_value(Value::ListObj(vec![element]));
+    }
+
+    // GET_LIST passes struct elements as copies of their fields, those cannot be stored in a list again
+    fn check_copied_elements(&self, element: &Value) {
+        if let Value::InstanceRef(struct_pos) = element {
+            errors::error_message("COMPILING ERROR", format!("Lists of {} elements cannot be concatenated or extended, only int, float, bool and String elements are copied {}:",
+                self.parser.symbols[*struct_pos].name,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+    }
+
+    fn operator_not_defined(&self, op: &Token, operand_type: TokenType) {
+        errors::error_message("COMPILING ERROR", format!("Operator '{}' is not defined for {} operands {}:",
+            op.value.iter().collect::<String>(),
+            self.type_name(operand_type),
+            self.parser.line,
+        ));
+        errors::compile_error();
+    }
+
+    // Kind is "Field", "Method" or "Constructor", candidates are the names the struct declares
+    fn undeclared_member_error<'a>(&self, kind: &str, name: &str, struct_name: &str, candidates: impl Iterator<Item = &'a String>) -> ! {
+        errors::error_message("COMPILING ERROR", format!("{}: \"{}\" is not declared in struct \"{}\"{} {}:",
+            kind,
+            name,
+            struct_name,
+            errors::suggest(name, candidates),
+            self.parser.line,
+        ));
+        errors::compile_error();
+    }
+
+    // Most common mismatch, so it gets a hint how to convert the other operand
+    fn concat_error(&self, left_side: TokenType, right_side: TokenType) {
+        let other = if left_side == TokenType::STRING { right_side } else { left_side };
+        let hint = match other {
+            TokenType::INT | TokenType::FLOAT => format!("use convstr() to convert the {} operand", self.type_name(other)),
+            _ => format!("{} cannot be converted to String", self.type_name(other)),
         };
+
+        errors::error_message("COMPILING ERROR", format!("Cannot concatenate {} and {}; {} {}:",
+            self.type_name(left_side),
+            self.type_name(right_side),
+            hint,
+            self.parser.line,
+        ));
+        errors::compile_error();
     }
 
     pub fn check_static_types(&self, a_token_type: &TokenType, b_type: TokenType, op: &Token) -> TokenType {
-        if !self.check_num_types(a_token_type.clone(), b_type) {
-            errors::error_message("COMPILING ERROR", format!("Mismatched types: {:?} {} {:?} {}:",
-                b_type,
+        if !self.check_num_types(*a_token_type, b_type) {
+            errors::error_message("COMPILING ERROR", format!("Mismatched types: {} {} {} {}:",
+                self.type_name(b_type),
                 op.value.iter().collect::<String>(),
-                a_token_type,
+                self.type_name(*a_token_type),
                 self.parser.line,
             ));
-            std::process::exit(1);
+            errors::compile_error();
         }
-        a_token_type.clone()
+        *a_token_type
     }
 
     pub fn check_num_types(&self, a_type: TokenType, b_type: TokenType) -> bool {
@@ -572,18 +1199,87 @@ impl Compiler {
         self.parse(Precedence::ASSIGNMENT);
     }
 
-    pub fn block(&mut self) {
+    // Reported error does not stop compilation, compiler skips to the next statement
+    // and keeps going, so independent errors are reported in one run. Bytecode is never run then
+    fn compile_statement(&mut self) {
+        let start = self.parser.index - 1;
+        let scope_depth = self.scope_depth;
+        let locals = self.get_cur_locals().len();
+        let instances = self.get_cur_instances().len();
+        let contexts = self.parser.contexts.len();
+        let call_targets = self.call_targets.len();
+
+        let payload = match panic::catch_unwind(AssertUnwindSafe(|| self.compile_line())) {
+            Ok(()) => return,
+            Err(payload) => payload,
+        };
+        if !payload.is::<errors::CompileError>() {
+            panic::resume_unwind(payload);
+        }
+
+        let mut i = start + 1;
+        while self.parser.tokens[start].token_type == TokenType::KEYWORD(Keywords::VAR) && self.parser.tokens[i].token_type == TokenType::IDENTIFIER {
+            self.failed_vars.insert(self.parser.tokens[i].value.iter().collect::<String>());
+            if self.parser.tokens[i + 1].token_type != TokenType::COMMA {
+                break
+            }
+            i += 2;
+        }
+
+        if !std::mem::take(&mut self.error_suppressed) {
+            self.error_count += 1;
+        }
+        if self.error_count >= MAX_ERRORS {
+            eprintln!("Too many errors, stopping after {}", MAX_ERRORS);
+            errors::terminate(errors::Termination::Compile);
+        }
+
+        self.scope_depth = scope_depth;
+        self.get_cur_locals().truncate(locals);
+        self.get_cur_instances().truncate(instances);
+        self.parser.contexts.truncate(contexts);
+        self.call_targets.truncate(call_targets);
+        self.changing_fn = false;
+        self.declaring_list = false;
+        self.tuple_allowed = false;
+
+        let start = self.parser.resync_from.take().unwrap_or(start);
+        self.parser.skip_statement(start);
+    }
+
+    // Returns count of locals declared by var statements of the block itself
+    pub fn block(&mut self) -> usize {
+        let mut declared = 0;
         while !(self.parser.cur.token_type == TokenType::RIGHT_BRACE) && !self.parser.check_if_eof() {
-            self.compile_line();
+            let stmt_start = self.get_cur_chunk().code.len();
+            let locals_start = self.get_cur_locals().len();
+            let line = self.parser.cur.line;
+            let is_var = self.parser.cur.token_type == TokenType::KEYWORD(Keywords::VAR);
+
+            self.compile_statement();
+
+            let kept = self.get_cur_locals().len().saturating_sub(locals_start);
+            self.get_cur_chunk().mark_statement(stmt_start, kept, line);
+            if is_var {
+                declared += kept;
+            }
         }
 
         self.parser.consume(TokenType::RIGHT_BRACE);
+        declared
     }
  
     pub fn string_dec(&mut self) {
-        let is_assign = if self.parser.peek_prev().token_type == TokenType::EQ {
-            true
-        }else { false };
+        let is_assign = self.parser.peek_prev().token_type == TokenType::EQ;
+
+        // operand of a larger expression is only a value, hidden instance would land on the heap
+        // between instances passed as arguments
+        if !is_assign && !self.declaring_list && self.parser.cur.token_type != TokenType::DOT && !self.is_whole_argument() {
+            let value = self.parser.prev.value.iter().collect::<String>();
+            self.emit_byte(OpCode::PUSH_STACK(Value::String(value)), self.parser.line);
+            self.get_cur_chunk().push_value(Value::String(String::new()));
+            return
+        }
 
         let pos = self.get_struct_symbol_pos("String".to_string());
 
@@ -595,6 +1291,10 @@ impl Compiler {
         let value = self.parser.prev.value.iter().collect::<String>();
         instance_obj.fields_values.push(Value::String(value.clone()));
 
+        if self.is_whole_argument() {
+            instance_obj.inc_counter();
+        }
+
         self.emit_byte(OpCode::STRING_DEC(instance_obj), self.parser.line);
         self.emit_byte(OpCode::PUSH_STACK(Value::StringRef(len)), self.parser.line);
 
@@ -620,7 +1320,8 @@ impl Compiler {
         }
     }
 
-    pub fn list_dec(&mut self, name: String) {
+    // Element type between List< and >, the closing > is left for the caller
+    fn list_element_type(&mut self) -> TokenType {
         let list_type = match self.parser.cur.token_type {
             TokenType::KEYWORD(keyword) => keyword.convert(),
             TokenType::IDENTIFIER => {
@@ -633,6 +1334,31 @@ impl Compiler {
         };
         self.parser.advance();
 
+        list_type
+    }
+
+    // Value kept in SpecialType::List, element reads and writes are typed by it
+    fn list_element_value(&self, list_type: TokenType) -> Value {
+        match list_type {
+            TokenType::INT => Value::Int(0),
+            TokenType::FLOAT => Value::Float(0.0),
+            TokenType::STRING => Value::String(String::new()),
+            TokenType::BOOL =>  Value::Bool(false),
+            TokenType::STRUCT(val) => Value::InstanceRef(val),
+            _ => {
+                errors::error_message("COMPILER ERROR",
+                format!("List of {} is not implemented yet {}:", 
+                    self.type_name(list_type), 
+                    self.parser.line
+                ));
+                errors::compile_error();
+            }
+        }
+    }
+
+    pub fn list_dec(&mut self, name: String) {
+        let list_type = self.list_element_type();
+
         self.parser.consume(TokenType::GREATER);
         self.parser.consume(TokenType::EQ);
 
@@ -641,66 +1367,160 @@ impl Compiler {
 
         let mut field_count = 0;
 
-        self.declaring_list = true;
-        self.parser.consume(TokenType::LEFT_BRACKET);        
-        while self.parser.cur.token_type != TokenType::RIGHT_BRACKET {
-            self.expression();
+        let is_literal = self.parser.cur.token_type == TokenType::LEFT_BRACKET;
+        let is_constructor = self.parser.cur.token_type == TokenType::IDENTIFIER && self.parser.cur.value.iter().collect::<String>() == "List";
+        if is_literal {
+            self.declaring_list = true;
+            self.parser.consume(TokenType::LEFT_BRACKET);        
+            while self.parser.cur.token_type != TokenType::RIGHT_BRACKET {
+                let element_start = self.get_cur_chunk().code.len();
+                self.expression();
+                self.mark_operand(element_start);
 
-            if self.get_cur_chunk().get_last_value().convert() != list_type {
-                let value_type = self.get_cur_chunk().get_last_value().convert();
+                if self.get_cur_chunk().get_last_value().convert() != list_type {
+                    let value_type = self.get_cur_chunk().get_last_value().convert();
 
-                let list_type_error = match list_type {
-                    TokenType::STRUCT(pos) => {
-                        format!("STRUCT: {}", self.parser.symbols[pos].name.clone())  
-                    },
-                    val => val.to_string(),
-                };
+                    errors::error_message("COMPILER ERROR",
+                    format!("Expected to find {} but found {} at element {} {}:", 
+                        self.type_name(list_type), 
+                        self.type_name(value_type),
+                        field_count,
+                        self.parser.line
+                    ));
+                    errors::compile_error();
+                }
 
-                errors::error_message("COMPILER ERROR",
-                format!("Expected to find {} but found {:?} {}:", 
-                    list_type_error, 
-                    value_type,
-                    self.parser.line
-                ));
-                std::process::exit(1);
-            }
+                if let Some(rf_index) = self.keep_returned_struct() {
+                    self.emit_byte(OpCode::PUSH_STACK(Value::InstanceRef(rf_index)), self.parser.line);
+                }
             
-            if self.parser.cur.token_type == TokenType::COMMA {
-                self.parser.consume(TokenType::COMMA);
-            }
+                if self.parser.cur.token_type == TokenType::COMMA {
+                    self.parser.consume(TokenType::COMMA);
+                }
             
-            field_count += 1;
+                field_count += 1;
+            }
+            self.parser.consume(TokenType::RIGHT_BRACKET);       
+            self.declaring_list = false;
+        }else if is_constructor {
+            self.list_constructor(list_type);
+        }else {
+            self.list_dec_value(list_type);
         }
-        self.parser.consume(TokenType::RIGHT_BRACKET);       
-        self.declaring_list = false;
         
         let len = self.parser.symbols.len();
         list_obj.set_index(len);
-        println!("{:?}", len);
-        self.emit_byte(OpCode::INSTANCE_DEC(list_obj, field_count), self.parser.line);
-
-        let list_type_value = match list_type {
-            TokenType::INT => Value::Int(0),
-            TokenType::FLOAT => Value::Float(0.0),
-            TokenType::STRING => Value::String(String::new()),
-            TokenType::BOOL =>  Value::Bool(false),
-            TokenType::STRUCT(val) => Value::InstanceRef(val),
-            _ => {
-                errors::error_message("COMPILER ERROR",
-                format!("List of {:?} is not implemented yet {}:", 
-                    list_type, 
-                    self.parser.line
-                ));
-                std::process::exit(1);
-            }
-        };
+        if is_literal {
+            self.emit_byte(OpCode::LIST_DEC(list_obj, field_count), self.parser.line);
+        }else {
+            self.emit_byte(OpCode::LIST_DEC_VALUE(list_obj), self.parser.line);
+        }
+
+        let list_type_value = self.list_element_value(list_type);
 
-        self.get_cur_instances().push(Local{ name: name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::List(list_type_value) });
+        self.get_cur_instances().push(Local{ name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::List(list_type_value) });
 
         self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), output_type: list_type, arg_count: 0 })
     }
     
+    // List.filled(count, value) or List.fromRange(start, stop), list is built at runtime
+    fn list_constructor(&mut self, list_type: TokenType) {
+        self.parser.consume(TokenType::IDENTIFIER);
+        self.parser.consume(TokenType::DOT);
+        self.parser.consume(TokenType::IDENTIFIER);
+
+        let name = self.parser.prev.value.iter().collect::<String>();
+        let constructors = ["filled".to_string(), "fromRange".to_string()];
+        if !constructors.contains(&name) {
+            self.undeclared_member_error("Constructor", &name, "List", constructors.iter());
+        }
+
+        self.parser.consume(TokenType::LEFT_PAREN);
+        self.constructor_arg(&name, TokenType::INT);
+        self.parser.consume(TokenType::COMMA);
+
+        if name == "filled" {
+            if matches!(list_type, TokenType::STRUCT(_)) {
+                errors::error_message("COMPILER ERROR", format!("List.filled is not implemented for List<{}> {}:", self.type_name(list_type), self.parser.line));
+                errors::compile_error();
+            }
+            self.constructor_arg(&name, list_type);
+        }else {
+            if list_type != TokenType::INT {
+                errors::error_message("COMPILER ERROR", format!("List.fromRange creates List<int>, found: List<{}> {}:", self.type_name(list_type), self.parser.line));
+                errors::compile_error();
+            }
+            self.constructor_arg(&name, TokenType::INT);
+        }
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        let op = if name == "filled" { OpCode::LIST_FILLED } else { OpCode::LIST_FROM_RANGE };
+        self.emit_byte(op, self.parser.line);
+    }
+
+    fn constructor_arg(&mut self, name: &str, expected: TokenType) {
+        self.expression();
+
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if value_type != expected {
+            errors::error_message("COMPILER ERROR", format!("Expected to find {} as List.{} argument but found: {} {}:",
+                self.type_name(expected),
+                name,
+                self.type_name(value_type),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+    }
+
+    // List created from value returned at runtime e.g. String.chars(),
+    // last value holds element type of that list
+    fn list_dec_value(&mut self, list_type: TokenType) {
+        self.expression();
+
+        let value_type = match self.get_cur_chunk().get_last_value() {
+            Value::ListObj(elements) if !elements.is_empty() => elements[0].convert(),
+            value => value.convert(),
+        };
+
+        if value_type != list_type {
+            let last_type = self.get_cur_chunk().get_last_value().convert();
+            let found = self.operand_name(last_type);
+            let expected = format!("List<{}>", self.type_name(list_type));
+            if found == expected {
+                errors::error_message("COMPILER ERROR", format!("{} has to be declared with a list literal or a list value, not with another list variable {}:",
+                    expected,
+                    self.parser.line,
+                ));
+            }else {
+                errors::error_message("COMPILER ERROR", format!("Expected to find {} but found {} {}:",
+                    expected,
+                    found,
+                    self.parser.line,
+                ));
+            }
+            errors::compile_error();
+        }
+
+        // list values hold copies of struct elements, a list local refers to its instances
+        if let TokenType::STRUCT(struct_pos) = list_type {
+            errors::error_message("COMPILER ERROR", format!("List<{}> has to be declared with a list literal, struct elements of a list value are copies {}:",
+                self.parser.symbols[struct_pos].name,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+    }
+
     pub fn identifier(&mut self) {
+        if self.builtin_constant() {
+            return
+        }
+
+        if self.self_field_fallback() {
+            return
+        }
+
         if self.parser.cur.token_type == TokenType::EQ {
             self.var_assign();
             return
@@ -711,14 +1531,128 @@ impl Compiler {
             return
         }
 
+        if self.parser.cur.token_type == TokenType::LEFT_BRACE {
+            let name = self.parser.prev.value.iter().collect::<String>();
+            if name != "String" && name != "List" && name != "Bytes" && self.structs.contains_key(&name) {
+                let struct_pos = self.get_struct_symbol_pos(name);
+                self.typed_struct_literal(struct_pos);
+                return
+            }
+        }
+
         if self.parser.cur.token_type != TokenType::LEFT_PAREN {
             self.var_call();
             return
         } 
 
-        let pos = self.get_fn_symbol_pos(self.parser.prev.value.iter().collect::<String>());
+        let name = self.parser.prev.value.iter().collect::<String>();
+        // a declared fn dbg shadows the builtin form
+        if name == "dbg" && self.parser.find_symbol(&name, |_| true).is_none() {
+            self.dbg_call();
+            return
+        }
+
+        let pos = self.get_fn_symbol_pos(name);
+
+        self.call_targets.push(pos);
+    }
+
+    // dbg(expr) compiles expr by value and prints it with its type to stderr, the value is the result
+    fn dbg_call(&mut self) {
+        let line = self.parser.line;
+        self.parser.push_context("call to 'dbg'".to_string());
+        self.parser.consume(TokenType::LEFT_PAREN);
+
+        self.outside_args(Self::expression);
+
+        self.parser.consume(TokenType::RIGHT_PAREN);
+        self.parser.pop_context();
+
+        if !self.expression_left_value() {
+            errors::error_message("COMPILER ERROR", format!("dbg() cannot wrap a call returning a struct, store the result in a variable first {}:", line));
+            errors::compile_error();
+        }
+
+        let value = self.get_cur_chunk().get_last_value();
+        let element = self.list_operand_element();
+        let type_name = self.list_type_name(value.convert(), &element);
+        // the list element type stays known to the enclosing expression
+        if value == Value::List {
+            self.list_element = element;
+        }
+
+        // a struct variable would leave a ref behind, it is printed from its instance and
+        // like a call returning a struct leaves nothing on the stack
+        let instance = match self.get_cur_chunk().get_last_instruction().op {
+            OpCode::GET_INSTANCE_W_OFFSET_RF(pos) | OpCode::GET_INSTANCE_RF(pos) => Some(pos),
+            _ => None,
+        };
+        if instance.is_some() {
+            self.get_cur_chunk().code.pop();
+        }
+
+        let index = self.get_cur_chunk().push_value(Value::String(type_name));
+        self.get_cur_chunk().push_value(value);
+        self.emit_byte(OpCode::DBG(line, index, instance), line);
+    }
+
+    fn builtin_constant(&mut self) -> bool {
+        let name = self.parser.prev.value.iter().collect::<String>();
+        let value = match builtin_constant(&name) {
+            Some(value) => value,
+            None => return false,
+        };
+
+        if self.parser.cur.token_type == TokenType::EQ {
+            errors::error_message("COMPILER ERROR", format!("Cannot assign to builtin constant \"{}\" {}:", name, self.parser.line));
+            errors::compile_error();
+        }
+
+        let pos = self.get_cur_chunk().push_value(value.clone());
+        match value {
+            Value::Int(_) => self.emit_byte(OpCode::CONSTANT_INT(pos), self.parser.line),
+            _ => self.emit_byte(OpCode::CONSTANT_FLOAT(pos), self.parser.line),
+        };
+        true
+    }
+
+    // Locals cannot shadow predefined constants
+    fn check_not_constant(&self, name: &str) {
+        if builtin_constant(name).is_some() {
+            errors::error_message("COMPILER ERROR", format!("Symbol: \"{}\" is a builtin constant and cannot be declared {}:", name, self.parser.line));
+            errors::compile_error();
+        }
+    }
+
+    // Inside methods bare field name is resolved as self.field, when no local with that name exists,
+    // a following DOT is handled by instance_field like after self.field
+    pub fn self_field_fallback(&mut self) -> bool {
+        if self.parser.cur.token_type == TokenType::LEFT_PAREN {
+            return false
+        }
+
+        let name = self.parser.prev.value.iter().collect::<String>();
+        if self.get_cur_locals().iter().any(|local| local.name == name) || self.get_cur_instances().iter().any(|local| local.name == name) {
+            return false
+        }
+
+        let root_struct_pos = match self.get_cur_instances().iter().find(|local| local.name == "self").map(|local| local.local_type) {
+            Some(TokenType::KEYWORD(Keywords::INSTANCE(root_struct_pos))) => root_struct_pos,
+            _ => return false,
+        };
+
+        let root_struct_name = self.parser.symbols[root_struct_pos].name.clone();
+        let is_field = match self.structs.get(&root_struct_name) {
+            Some(root_struct) => root_struct.locals.iter().any(|local| local.name == name),
+            None => false,
+        };
+
+        if !is_field {
+            return false
+        }
 
-        self.symbol_to_hold = pos;
+        self.instance_field("self".to_string(), root_struct_name, name);
+        true
     }
 
     pub fn var_assign(&mut self) {
@@ -735,35 +1669,63 @@ impl Compiler {
             .map(|(index, _)| index as i32)
             .unwrap_or(-1);
 
+        // s = s + piece reads both texts by value and overwrites the text in place,
+        // the String instance is kept, so no rc counter changes
         if pos != -1 && self.get_cur_instances()[pos as usize].is_special == SpecialType::String {
             self.emit_byte(OpCode::SET_INSTANCE_FIELD(pos as usize, 0), self.parser.line);
 
+            let value_type = self.get_cur_chunk().get_last_value().convert();
+
             if !matches!(self.get_cur_chunk().get_last_value(), Value::String(_)) {
-                errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {:?} found: {:?} {}:",
-                    TokenType::STRING,
-                    self.get_cur_chunk().get_last_value().convert(),
+                let found = self.operand_name(value_type);
+                errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                    self.type_name(TokenType::STRING),
+                    found,
                     self.parser.line,
                 ));
-                std::process::exit(1);
+                errors::compile_error();
             }
 
             return;
         }
 
+        // list locals keep their instance, only their elements change
+        if pos != -1 {
+            if let SpecialType::List(element) = self.get_cur_instances()[pos as usize].is_special.clone() {
+                let value_type = self.get_cur_chunk().get_last_value().convert();
+                let expected = self.list_type_name(TokenType::LIST, &Some(element));
+                let found = self.operand_name(value_type);
+                if found != expected {
+                    errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                        expected,
+                        found,
+                        self.parser.line,
+                    ));
+                }else {
+                    errors::error_message("COMPILING ERROR", format!("List \"{}\" cannot be assigned a new list, only its elements can be changed {}:",
+                        var_name,
+                        self.parser.line,
+                    ));
+                }
+                errors::compile_error();
+            }
+        }
+
         let pos = self.get_local_pos(var_name);
 
         let value_type = self.get_cur_chunk().get_last_value().convert();
-        let var_type = self.get_cur_locals()[pos as usize].local_type;
+        let var_type = self.get_cur_locals()[pos].local_type;
         if value_type != var_type {
-            errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {:?} found: {:?} {}:",
-                var_type,
-                value_type,
+            let found = self.operand_name(value_type);
+            errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                self.type_name(var_type),
+                found,
                 self.parser.line,
             ));
-            std::process::exit(1);
+            errors::compile_error();
         }
 
-        self.emit_byte(OpCode::VAR_SET(pos as usize), self.parser.line);
+        self.emit_byte(OpCode::VAR_SET(pos), self.parser.line);
     }
 
     pub fn var_call(&mut self) {
@@ -778,12 +1740,19 @@ impl Compiler {
             .unwrap_or(-1);
 
         if pos != -1 {
-            match self.get_cur_instances()[pos as usize].local_type {
-                TokenType::KEYWORD(Keywords::INSTANCE(root_struct_pos)) => {
+            if let TokenType::KEYWORD(Keywords::INSTANCE(root_struct_pos)) = self.get_cur_instances()[pos as usize].local_type {
                     self.get_cur_chunk().push_value(Value::InstanceRef(root_struct_pos));
-                    
-                    if self.get_cur_instances()[pos as usize].is_special == SpecialType::String && !self.changing_fn {
-                        self.get_cur_chunk().push_value(Value::String(String::new()));
+
+                    // String var is passed by reference only as a whole argument, inside an expression it is a value
+                    let by_value = matches!(self.get_cur_instances()[pos as usize].is_special, SpecialType::String | SpecialType::Bytes) && !self.is_whole_argument();
+                    // a whole List argument is passed by reference, its element is a value
+                    let read_list = matches!(self.get_cur_instances()[pos as usize].is_special, SpecialType::List(_)) &&
+                        (!self.changing_fn || self.parser.cur.token_type == TokenType::LEFT_BRACKET);
+                    if by_value {
+                        match self.get_cur_instances()[pos as usize].is_special {
+                            SpecialType::Bytes => self.get_cur_chunk().push_value(Value::Bytes(vec![])),
+                            _ => self.get_cur_chunk().push_value(Value::String(String::new())),
+                        };
 
                         let mut root_string_pos = pos as usize;
                         if self.get_cur_instances()[pos as usize].is_redirected {
@@ -791,9 +1760,12 @@ impl Compiler {
                         }
                                                 
                         self.emit_byte(OpCode::GET_INSTANCE_FIELD(root_string_pos, 0), self.parser.line);
-                    }else if matches!(self.get_cur_instances()[pos as usize].is_special, SpecialType::List(_)) && !self.changing_fn {
+                    }else if read_list {
                         if self.parser.cur.token_type != TokenType::LEFT_BRACKET {
                             self.get_cur_chunk().push_value(Value::List);
+                            if let SpecialType::List(element) = self.get_cur_instances()[pos as usize].is_special.clone() {
+                                self.list_element = Some(element);
+                            }
 
                             self.emit_byte(OpCode::GET_LIST(pos as usize), self.parser.line);
                         }else {
@@ -804,29 +1776,50 @@ impl Compiler {
                                         self.get_cur_instances()[pos as usize].is_special.clone(),
                                         self.parser.line,
                                     ));
-                                    std::process::exit(1);
+                                    errors::compile_error();
                                 }
                             };
                         
                             self.parser.consume(TokenType::LEFT_BRACKET);
-                            self.expression();
+                            self.outside_args(Self::expression);
                             self.parser.consume(TokenType::RIGHT_BRACKET);
 
+                            if self.parser.cur.token_type == TokenType::DOT {
+                                match list_type {
+                                    Value::InstanceRef(struct_pos) => self.list_element_call(pos as usize, struct_pos),
+                                    _ => {
+                                        errors::error_message("COMPILER ERROR", format!("Cannot access fields of {} list element {}:",
+                                            self.type_name(list_type.convert()),
+                                            self.parser.line,
+                                        ));
+                                        errors::compile_error();
+                                    },
+                                };
+                                return
+                            }
+
                             if self.parser.cur.token_type == TokenType::EQ {
                                 self.parser.consume(TokenType::EQ);
 
+                                // struct element is stored by the instance index, same as in list literal
+                                self.declaring_list = matches!(list_type, Value::InstanceRef(_));
                                 self.expression();
+                                self.declaring_list = false;
 
                                 if self.get_cur_chunk().get_last_value().convert() != list_type.convert() {
                                     let value_type = self.get_cur_chunk().get_last_value().convert();
 
                                     errors::error_message("COMPILER ERROR",
-                                        format!("Expected to find {:?} but found: {:?} {}:", 
-                                        list_type.convert(), 
-                                        value_type,
+                                        format!("Expected to find {} but found: {} {}:", 
+                                        self.type_name(list_type.convert()), 
+                                        self.type_name(value_type),
                                         self.parser.line
                                     ));
-                                    std::process::exit(1);
+                                    errors::compile_error();
+                                }
+
+                                if let Some(rf_index) = self.keep_returned_struct() {
+                                    self.emit_byte(OpCode::PUSH_STACK(Value::InstanceRef(rf_index)), self.parser.line);
                                 }
             
                                 self.emit_byte(OpCode::SET_LIST_FIELD(pos as usize), self.parser.line);
@@ -834,6 +1827,13 @@ impl Compiler {
                                 return
                             }
                             
+                            // struct element passed as a whole argument is referenced like a method receiver
+                            if matches!(list_type, Value::InstanceRef(_)) && self.changing_fn && matches!(self.parser.cur.token_type, TokenType::COMMA | TokenType::RIGHT_PAREN) {
+                                self.emit_byte(OpCode::GET_LIST_ELEMENT_RF(pos as usize), self.parser.line);
+                                self.get_cur_chunk().push_value(list_type);
+                                return
+                            }
+
                             self.emit_byte(OpCode::GET_LIST_FIELD(pos as usize), self.parser.line);
                             
                             self.get_cur_chunk().push_value(list_type);
@@ -845,23 +1845,22 @@ impl Compiler {
                         if self.get_cur_instances()[pos as usize].is_redirected {
                             pos = self.get_cur_instances()[pos as usize].redirect_pos as i32;
                         }
-                        self.emit_byte(OpCode::GET_INSTANCE_RF(pos as usize), self.parser.line);
+                        // self and instance params are refs themselves, new ref has to point at the instance
+                        self.emit_byte(OpCode::GET_INSTANCE_W_OFFSET_RF(pos as usize), self.parser.line);
                     }
 
-                    if self.changing_fn {
+                    if self.changing_fn && !by_value && !read_list {
                         self.emit_byte(OpCode::INC_RC(pos as usize), self.parser.line);
                     }
 
 
                     return
-                },
-                _ => {},
-            }
+                }
             
         }
 
         let pos = self.get_local_pos(var_name);
-        match self.get_cur_locals()[pos as usize].local_type {
+        match self.get_cur_locals()[pos].local_type {
             TokenType::INT => {
                 self.get_cur_chunk().push_value(Value::Int(0));
             },
@@ -876,28 +1875,100 @@ impl Compiler {
             },
             local_type => {
                 errors::error_message("COMPILER ERROR", format!("Unexpected local type \"{:?}\" {}:", local_type, self.parser.line));
-                std::process::exit(1);
+                errors::compile_error();
+            }
+        };
+
+        self.emit_byte(OpCode::VAR_CALL(pos), self.parser.line);
+    }
+
+    // xs[i].field, xs[i].field = value or xs[i].method(...) on list of structs, index is already on the stack
+    fn list_element_call(&mut self, list_pos: usize, struct_pos: usize) {
+        self.parser.consume(TokenType::DOT);
+        self.parser.consume(TokenType::IDENTIFIER);
+
+        let field_name = self.parser.prev.value.iter().collect::<String>();
+        let struct_name = self.parser.symbols[struct_pos].name.clone();
+
+        if self.parser.cur.token_type == TokenType::LEFT_PAREN {
+            let mth = match self.structs.get(&struct_name).unwrap().methods.get(&field_name) {
+                Some(mth) => mth.clone(),
+                None => {
+                    let methods = self.structs.get(&struct_name).unwrap().methods.keys();
+                    self.undeclared_member_error("Method", &field_name, &struct_name, methods);
+                },
+            };
+
+            if mth.is_self_arg {
+                self.emit_byte(OpCode::GET_LIST_ELEMENT_RF(list_pos), self.parser.line);
+            }else {
+                self.emit_byte(OpCode::POP, self.parser.line);
             }
+
+            self.mth_call(mth.output_type, mth.arg_types.clone(), String::new(), false);
+            self.emit_byte(OpCode::METHOD_CALL(mth), self.parser.line);
+            return
+        }
+
+        let field_index = match self.structs.get(&struct_name).unwrap().field_index(&field_name) {
+            Some(index) => index,
+            None => {
+                let fields = self.structs.get(&struct_name).unwrap().locals.iter().map(|local| &local.name);
+                self.undeclared_member_error("Field", &field_name, &struct_name, fields);
+            },
         };
+        let field_type = self.structs.get(&struct_name).unwrap().locals[field_index].local_type;
+
+        if self.parser.cur.token_type == TokenType::EQ {
+            self.parser.consume(TokenType::EQ);
+
+            self.expression();
+
+            let value_type = self.get_cur_chunk().get_last_value().convert();
+            if value_type != field_type {
+                errors::error_message("COMPILER ERROR", format!("Expected to find {} but found: {} {}:",
+                    self.type_name(field_type),
+                    self.type_name(value_type),
+                    self.parser.line
+                ));
+                errors::compile_error();
+            }
+
+            self.emit_byte(OpCode::SET_LIST_ELEMENT_FIELD(list_pos, field_index), self.parser.line);
+            return
+        }
 
-        self.emit_byte(OpCode::VAR_CALL(pos as usize), self.parser.line);
+        self.push_type_value(field_type);
+        self.emit_byte(OpCode::GET_LIST_ELEMENT_FIELD(list_pos, field_index), self.parser.line);
+
+        if field_type == TokenType::STRING {
+            self.string_receiver(field_name);
+        }
     }
 
     pub fn var_declare(&mut self) {
         self.parser.consume(TokenType::IDENTIFIER);
 
         let var_name = self.parser.prev.value.iter().collect::<String>();
+        self.check_not_constant(&var_name);
         if self.get_cur_locals().iter().any(| local | local.name == var_name ) {
             errors::error_message("COMPILER ERROR", format!("Symbol: \"{}\" is already defined {}:", var_name, self.parser.line));
-            std::process::exit(1);
+            errors::compile_error();
         }
 
         if self.get_cur_instances().iter().any(| local | local.name == var_name ) {
             errors::error_message("COMPILER ERROR", format!("Symbol: \"{}\" is already defined {}:", var_name, self.parser.line));
-            std::process::exit(1);
+            errors::compile_error();
         }
 
+        if self.parser.cur.token_type == TokenType::COMMA {
+            self.tuple_declare(var_name);
+            return
+        }
+
+        self.parser.push_context(format!("declaration of '{}'", var_name));
         self.parser.consume(TokenType::COLON);
+        self.parser.pop_context();
         match self.parser.cur.token_type {
             TokenType::KEYWORD(Keywords::INT) |
             TokenType::KEYWORD(Keywords::FLOAT) |
@@ -906,7 +1977,7 @@ impl Compiler {
             TokenType::IDENTIFIER => {},
             _ => {
                 errors::error_message("COMPILER ERROR", format!("Expected var type after \":\" {}:", self.parser.line));
-                std::process::exit(1);
+                errors::compile_error();
             },
         };
 
@@ -919,17 +1990,19 @@ impl Compiler {
             TokenType::KEYWORD(keyword) => keyword.convert(),
             _ => {
                 errors::error_message("COMPILER ERROR", format!("Expected var type after \":\" {}:", self.parser.line));
-                std::process::exit(1);
+                errors::compile_error();
             },
         };
         self.parser.advance();
 
-        match var_type {
-            TokenType::STRUCT(pos) => {
-                self.instance_declare(pos, var_name);
-                return
-            }
-            _ => {},
+        if self.flags.strict && self.parser.cur.token_type != TokenType::EQ {
+            errors::strict_error(flags::VAR_WITHOUT_INIT, format!("Var \"{}\" has to be initialized {}:", var_name, self.parser.line));
+            errors::compile_error();
+        }
+
+        if let TokenType::STRUCT(pos) = var_type {
+            self.instance_declare(pos, var_name);
+            return
         }
 
         if self.parser.cur.token_type == TokenType::EQ {
@@ -938,19 +2011,83 @@ impl Compiler {
 
             let value_type = self.get_cur_chunk().get_last_value().convert();
             if value_type != var_type {
-                errors::error_message("COMPILING ERROR", format!("Mismatched types while declaring var, expected: {:?} found: {:?} {}:",
-                    var_type,
-                    value_type,
+                let found = self.operand_name(value_type);
+                errors::error_message("COMPILING ERROR", format!("Mismatched types while declaring var, expected: {} found: {} {}:",
+                    self.type_name(var_type),
+                    found,
                     self.parser.line,
                 ));
-                std::process::exit(1);
+                errors::compile_error();
             }
         }else {
             let pos = self.get_cur_chunk().push_value(Value::Null);
             self.emit_byte(OpCode::CONSTANT_NULL(pos), self.parser.line);
         }
 
-        self.get_cur_locals().push(Local { name: var_name, local_type: var_type, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
+        let declaration = format!("var \"{}\"", var_name);
+        self.push_local(Local { name: var_name, local_type: var_type, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null }, &declaration);
+    }
+
+    // var q, r = divmod(7, 2), types of vars are taken from the tuple
+    fn tuple_declare(&mut self, first_name: String) {
+        let mut names = vec![first_name];
+        while self.parser.cur.token_type == TokenType::COMMA {
+            self.parser.consume(TokenType::COMMA);
+            self.parser.consume(TokenType::IDENTIFIER);
+
+            let name = self.parser.prev.value.iter().collect::<String>();
+            self.check_not_constant(&name);
+            let is_defined = names.contains(&name) ||
+                self.get_cur_locals().iter().any(|local| local.name == name) ||
+                self.get_cur_instances().iter().any(|local| local.name == name);
+            if is_defined {
+                errors::error_message("COMPILER ERROR", format!("Symbol: \"{}\" is already defined {}:", name, self.parser.line));
+                errors::compile_error();
+            }
+            names.push(name);
+        }
+
+        self.parser.consume(TokenType::EQ);
+
+        self.tuple_allowed = true;
+        self.expression();
+        self.tuple_allowed = false;
+
+        let elements = match self.get_cur_chunk().get_last_value() {
+            Value::Tuple(elements) => elements,
+            value => {
+                errors::error_message("COMPILING ERROR", format!("Cannot destructure {} into {} vars, expected tuple {}:", self.type_name(value.convert()), names.len(), self.parser.line));
+                errors::compile_error();
+            },
+        };
+
+        if elements.len() != names.len() {
+            errors::error_message("COMPILING ERROR", format!("Cannot destructure tuple of {} values into {} vars {}:", elements.len(), names.len(), self.parser.line));
+            errors::compile_error();
+        }
+
+        let string_pos = self.get_struct_symbol_pos("String".to_string());
+        let mut instances = vec![];
+        for (name, element) in names.into_iter().zip(elements) {
+            match element {
+                Value::String(_) => {
+                    let mut instance_obj = StructInstance::new(string_pos);
+                    let len = self.parser.symbols.len();
+                    instance_obj.set_index(len);
+                    instances.push(Some(instance_obj));
+
+                    self.get_cur_instances().push(Local{ name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(string_pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::String });
+                    self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(string_pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
+                },
+                element => {
+                    instances.push(None);
+                    let declaration = format!("var \"{}\"", name);
+                    self.push_local(Local { name, local_type: element.convert(), is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null }, &declaration);
+                },
+            }
+        }
+
+        self.emit_byte(OpCode::TUPLE_UNPACK(instances), self.parser.line);
     }
 
     pub fn instance_call(&mut self) {
@@ -972,73 +2109,77 @@ impl Compiler {
                     name,
                     self.parser.line,
                 ));
-                std::process::exit(1);
+                errors::compile_error();
             },
         };
 
+        if self.parser.cur.token_type == TokenType::LEFT_PAREN && root_struct_name == "List" {
+            self.list_mth_call(instance_pos, field_name);
+            return
+        }
+
+        if self.parser.cur.token_type == TokenType::LEFT_PAREN && root_struct_name == "Bytes" {
+            self.bytes_mth_call(instance_pos, field_name);
+            return
+        }
+
         if self.parser.cur.token_type == TokenType::LEFT_PAREN {
-            match self.structs.get(&root_struct_name).unwrap().methods.get(&field_name) {
-                Some(mth) => {
-                    self.mth_call(mth.output_type, mth.arg_count, name.clone(), mth.is_self_arg);
-                },
+            let mth = match self.structs.get(&root_struct_name).unwrap().methods.get(&field_name) {
+                Some(mth) => mth.clone(),
                 None => {
-                    errors::error_message("COMPILING ERROR", format!("Method: \"{}\" is not declared in struct \"{}\" {}:",
-                        field_name,
-                        root_struct_name,
-                        self.parser.line,
-                    ));
-                    std::process::exit(1);
+                    let methods = self.structs.get(&root_struct_name).unwrap().methods.keys();
+                    self.undeclared_member_error("Method", &field_name, &root_struct_name, methods);
                 },
-            }
-            
-            match self.structs.get(&root_struct_name).unwrap().methods.get(&field_name) {
-                Some(mth) => {
-                    self.emit_byte(OpCode::METHOD_CALL(mth.clone()), self.parser.line);
-                },
-                _ => {},
-            }
+            };
 
+            self.mth_call(mth.output_type, mth.arg_types.clone(), name.clone(), mth.is_self_arg);
+            self.emit_byte(OpCode::METHOD_CALL(mth), self.parser.line);
             return
         }
 
-        let field_index = self.structs.get(&root_struct_name).unwrap().locals
-            .iter()
-            .enumerate()
-            .find(|(_, local)| *local.name == field_name)
-            .map(|(index, _)| index as i32)
-            .unwrap_or(-1);
+        self.instance_field(name, root_struct_name, field_name);
+    }
 
-        if field_index == -1 {
-            errors::error_message("COMPILING ERROR", format!("Field: \"{}\" is not declared in struct \"{}\" {}:",
-                field_name,
-                root_struct_name,
-                self.parser.line,
-            ));
-            std::process::exit(1);
-        }
+    pub fn instance_field(&mut self, name: String, root_struct_name: String, field_name: String) {
+        let root_struct = self.structs.get(&root_struct_name).unwrap();
+        let field_index = match root_struct.field_index(&field_name) {
+            Some(index) => index,
+            None => {
+                let fields = root_struct.locals.iter().map(|local| &local.name);
+                self.undeclared_member_error("Field", &field_name, &root_struct_name, fields);
+            },
+        };
+        let field_type = root_struct.locals[field_index].local_type;
+        let field_element = match &root_struct.locals[field_index].is_special {
+            SpecialType::List(element) => Some(element.clone()),
+            _ => None,
+        };
 
         let pos = self.get_instance_local_pos(name);
 
         if self.parser.cur.token_type == TokenType::EQ {
             self.parser.consume(TokenType::EQ);
 
-            self.expression();
+            match &field_element {
+                Some(element) => self.list_field_value(element),
+                None => self.expression(),
+            }
 
-            if self.get_cur_chunk().get_last_value().convert() != self.structs.get(&root_struct_name).unwrap().locals[field_index as usize].local_type {
+            if self.get_cur_chunk().get_last_value().convert() != field_type {
                 let value_type = self.get_cur_chunk().get_last_value().convert();
 
                 errors::error_message("COMPILER ERROR",
-                format!("Expected to find {:?} but found: {:?} {}:", 
-                    self.structs.get(&root_struct_name).unwrap().locals[field_index as usize].local_type, 
-                    value_type,
+                format!("Expected to find {} but found: {} {}:", 
+                    self.type_name(field_type), 
+                    self.type_name(value_type),
                     self.parser.line
                 ));
-                std::process::exit(1);
+                errors::compile_error();
             }
 
-            self.emit_byte(OpCode::SET_INSTANCE_FIELD(pos as usize, field_index as usize), self.parser.line);
+            self.emit_byte(OpCode::SET_INSTANCE_FIELD(pos, field_index), self.parser.line);
         }else{
-            match self.structs.get(&root_struct_name).unwrap().locals[field_index as usize].local_type {
+            match field_type {
                 TokenType::INT => {
                     self.get_cur_chunk().push_value(Value::Int(0));
                 },
@@ -1057,28 +2198,355 @@ impl Compiler {
                 _ => {},
             }
 
-            self.emit_byte(OpCode::GET_INSTANCE_FIELD(pos as usize, field_index as usize), self.parser.line);
+            self.emit_byte(OpCode::GET_INSTANCE_FIELD(pos, field_index), self.parser.line);
+
+            if let Some(element) = field_element {
+                self.list_field_read(pos, field_index, element);
+            }else if field_type == TokenType::STRING {
+                self.string_receiver(field_name);
+            }
         }
     }
 
-    pub fn instance_declare(&mut self, var_pos: usize, name: String) {
-        if self.parser.prev.value.iter().collect::<String>() == "List" {
-            self.parser.consume(TokenType::LESS);
-            self.list_dec(name);
-
+    // String field or call result is a plain value, method receiver needs it as an instance,
+    // so the value is copied into a hidden String instance named after the previous token
+    fn string_receiver(&mut self, name: String) {
+        if self.parser.cur.token_type != TokenType::DOT {
             return
         }
-        
-        if self.parser.cur.token_type != TokenType::EQ {
-            errors::error_message("COMPILING ERROR", format!("Struct cannot be left undeclared {}:",
-                self.parser.line,
-            ));
-            std::process::exit(1);
-        }
-        self.parser.consume(TokenType::EQ);
 
-        if self.parser.cur.token_type != TokenType::LEFT_BRACE {
-            if self.parser.cur.token_type == TokenType::STRING {
+        self.string_instance(false);
+
+        // instance_call looks the receiver up by the name of the previous token
+        let instance_pos = self.get_cur_instances().len() - 1;
+        self.get_cur_instances()[instance_pos].name = name;
+
+        self.instance_call();
+
+        self.get_cur_instances()[instance_pos].name = String::new();
+    }
+
+    // String passed by value to a function is copied into a hidden String instance,
+    // instances (literals, vars) are already passed by reference
+    fn string_argument(&mut self) {
+        if !matches!(self.get_cur_chunk().get_last_value(), Value::String(_)) {
+            return
+        }
+
+        let last_op = self.get_cur_chunk().get_last_instruction().op.clone();
+        if matches!(last_op, OpCode::PUSH_STACK(Value::StringRef(_)) | OpCode::INC_RC(_)) {
+            return
+        }
+
+        let len = self.string_instance(true);
+
+        self.emit_byte(OpCode::PUSH_STACK(Value::StringRef(len)), self.parser.line);
+        self.get_cur_chunk().push_value(Value::String(String::new()));
+    }
+
+    // Pops string value into a new hidden String instance, returns its symbol index
+    fn string_instance(&mut self, is_argument: bool) -> usize {
+        let pos = self.get_struct_symbol_pos("String".to_string());
+        let mut instance_obj = StructInstance::new(pos);
+        if is_argument {
+            instance_obj.inc_counter();
+        }
+
+        let len = self.parser.symbols.len();
+        instance_obj.set_index(len);
+
+        self.emit_byte(OpCode::STRING_DEC_VALUE(instance_obj), self.parser.line);
+
+        self.get_cur_instances().push(Local{ name: String::new(), local_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::String });
+        self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
+
+        len
+    }
+
+    // Value of a List<T> field: a list literal or a list with the same element type,
+    // struct elements are copied by their field values
+    fn list_field_value(&mut self, element: &Value) {
+        self.outside_list_and_args(|compiler| compiler.list_field_expression(element));
+    }
+
+    fn list_field_expression(&mut self, element: &Value) {
+        if self.parser.cur.token_type == TokenType::LEFT_BRACKET {
+            self.parser.consume(TokenType::LEFT_BRACKET);
+            let mut count = 0;
+            while self.parser.cur.token_type != TokenType::RIGHT_BRACKET {
+                self.expression();
+
+                let value_type = self.get_cur_chunk().get_last_value().convert();
+                if value_type != element.convert() {
+                    errors::error_message("COMPILER ERROR", format!("Expected to find {} but found {} at element {} {}:",
+                        self.type_name(element.convert()),
+                        self.type_name(value_type),
+                        count,
+                        self.parser.line,
+                    ));
+                    errors::compile_error();
+                }
+                self.copy_struct_element();
+
+                self.parser.consume_separator(TokenType::RIGHT_BRACKET);
+                count += 1;
+            }
+            self.parser.consume(TokenType::RIGHT_BRACKET);
+            self.emit_byte(OpCode::LIST_PACK(count), self.parser.line);
+        }else {
+            self.list_element = None;
+            self.expression();
+
+            let value_type = self.get_cur_chunk().get_last_value().convert();
+            let value_element = self.list_operand_element();
+            if value_type != TokenType::LIST || value_element.as_ref().map(|value| value.convert()) != Some(element.convert()) {
+                errors::error_message("COMPILER ERROR", format!("Expected to find {} but found: {} {}:",
+                    self.list_type_name(TokenType::LIST, &Some(element.clone())),
+                    self.list_type_name(value_type, &value_element),
+                    self.parser.line,
+                ));
+                errors::compile_error();
+            }
+        }
+        self.get_cur_chunk().push_value(Value::ListObj(vec![element.clone()]));
+    }
+
+    // Struct compiled last is replaced by a copy of its fields, the ref pushed for it would outlive the statement
+    fn copy_struct_element(&mut self) {
+        if !matches!(self.get_cur_chunk().get_last_value(), Value::InstanceRef(_)) {
+            return
+        }
+
+        let pos = match self.get_cur_chunk().get_last_instruction().op {
+            OpCode::GET_INSTANCE_W_OFFSET_RF(pos) | OpCode::GET_INSTANCE_RF(pos) => {
+                self.get_cur_chunk().code.pop();
+                pos
+            },
+            // element of a list local is pushed by its object index, LIST_PACK copies it
+            OpCode::GET_LIST_FIELD(_) => return,
+            _ => match self.keep_returned_struct() {
+                Some(_) => self.get_cur_instances().len() - 1,
+                None => {
+                    errors::error_message("COMPILER ERROR", format!("List element has to be a struct variable, literal or call result {}:", self.parser.line));
+                    errors::compile_error();
+                },
+            },
+        };
+        self.emit_byte(OpCode::INSTANCE_VALUE(pos), self.parser.line);
+    }
+
+    // g.nodes[i], g.nodes.method(...) or the whole list value, the list is already on the stack
+    fn list_field_read(&mut self, pos: usize, field_index: usize, element: Value) {
+        match self.parser.cur.token_type {
+            TokenType::LEFT_BRACKET => self.list_field_element(element),
+            TokenType::DOT => self.list_field_mth_call(pos, field_index, element),
+            _ => {
+                self.get_cur_chunk().push_value(Value::ListObj(vec![element]));
+            },
+        }
+    }
+
+    // Elements of a List field are copies, a field of a struct element is read directly,
+    // a whole struct element is copied into a hidden instance and used like a struct literal
+    fn list_field_element(&mut self, element: Value) {
+        self.parser.consume(TokenType::LEFT_BRACKET);
+        self.outside_list_and_args(Self::expression);
+        self.parser.consume(TokenType::RIGHT_BRACKET);
+
+        let index_type = self.get_cur_chunk().get_last_value().convert();
+        if index_type != TokenType::INT {
+            errors::error_message("COMPILER ERROR", format!("Expected to find int as list index but found: {} {}:", self.type_name(index_type), self.parser.line));
+            errors::compile_error();
+        }
+
+        if self.parser.cur.token_type == TokenType::EQ {
+            errors::error_message("COMPILER ERROR", format!("Elements of a List field cannot be assigned, assign the whole field or use insert {}:", self.parser.line));
+            errors::compile_error();
+        }
+
+        let struct_pos = match element {
+            Value::InstanceRef(struct_pos) => struct_pos,
+            element => {
+                self.emit_byte(OpCode::LIST_INDEX(None), self.parser.line);
+                self.get_cur_chunk().push_value(element.clone());
+                if element == Value::String(String::new()) {
+                    let name = self.parser.prev.value.iter().collect::<String>();
+                    self.string_receiver(name);
+                }
+                return
+            },
+        };
+
+        if self.parser.cur.token_type != TokenType::DOT {
+            self.emit_byte(OpCode::LIST_INDEX(None), self.parser.line);
+            self.element_instance(struct_pos);
+            return
+        }
+
+        self.parser.consume(TokenType::DOT);
+        self.parser.consume(TokenType::IDENTIFIER);
+        let field_name = self.parser.prev.value.iter().collect::<String>();
+        let struct_name = self.parser.symbols[struct_pos].name.clone();
+
+        if matches!(self.parser.cur.token_type, TokenType::LEFT_PAREN | TokenType::EQ) {
+            let action = if self.parser.cur.token_type == TokenType::EQ {
+                format!("field \"{}\" cannot be assigned", field_name)
+            }else {
+                format!("method \"{}\" cannot be called", field_name)
+            };
+            errors::error_message("COMPILER ERROR", format!("Elements of a List field are copies, {}; copy the element into a variable first: var n: {} = <list>[i] {}:",
+                action,
+                struct_name,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
+        let root_struct = self.structs.get(&struct_name).unwrap();
+        let field_index = match root_struct.field_index(&field_name) {
+            Some(index) => index,
+            None => {
+                let fields = root_struct.locals.iter().map(|local| &local.name);
+                self.undeclared_member_error("Field", &field_name, &struct_name, fields);
+            },
+        };
+        let field_type = root_struct.locals[field_index].local_type;
+
+        if field_type == TokenType::LIST {
+            errors::error_message("COMPILER ERROR", format!("List field \"{}\" of a list element cannot be read directly, copy the element into a variable first: var n: {} = <list>[i] {}:",
+                field_name,
+                struct_name,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
+        self.emit_byte(OpCode::LIST_INDEX(Some(field_index)), self.parser.line);
+        self.push_type_value(field_type);
+
+        if field_type == TokenType::STRING {
+            self.string_receiver(field_name);
+        }
+    }
+
+    // InstanceObj on the stack becomes a hidden instance, passed on like a struct literal
+    fn element_instance(&mut self, struct_pos: usize) {
+        let mut instance_obj = StructInstance::new(struct_pos);
+        let len = self.parser.symbols.len();
+        instance_obj.set_index(len);
+
+        if self.changing_fn && matches!(self.parser.cur.token_type, TokenType::COMMA | TokenType::RIGHT_PAREN) {
+            instance_obj.inc_counter();
+        }
+        self.emit_byte(OpCode::INSTANCE_DEC_VALUE(instance_obj), self.parser.line);
+
+        self.get_cur_instances().push(Local{ name: String::new(), local_type: TokenType::KEYWORD(Keywords::INSTANCE(struct_pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::Null });
+        self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(struct_pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
+        let pos = self.get_cur_instances().len() - 1;
+
+        self.get_cur_chunk().push_value(Value::InstanceRef(struct_pos));
+        if self.declaring_list || self.changing_fn {
+            self.emit_byte(OpCode::PUSH_STACK(Value::InstanceRef(len)), self.parser.line);
+        }else {
+            self.emit_byte(OpCode::GET_INSTANCE_RF(pos), self.parser.line);
+        }
+    }
+
+    // push(x) and insert(i, x) store the changed list back into the field, like mutating Bytes methods,
+    // len() and join(...) get the list value
+    fn list_field_mth_call(&mut self, pos: usize, field_index: usize, element: Value) {
+        self.parser.consume(TokenType::DOT);
+        self.parser.consume(TokenType::IDENTIFIER);
+        let mth_name = self.parser.prev.value.iter().collect::<String>();
+
+        if mth_name != "push" && mth_name != "insert" {
+            if matches!(mth_name.as_str(), "sort" | "sortKey" | "extend" | "remove" | "swap" | "fill" | "lowerBound" | "sortedInsert") {
+                errors::error_message("COMPILER ERROR", format!("Method: \"{}\" is not supported for List fields, only push, insert, len, isEmpty and join {}:", mth_name, self.parser.line));
+                errors::compile_error();
+            }
+            self.list_native_call(mth_name);
+            return
+        }
+
+        self.parser.consume(TokenType::LEFT_PAREN);
+        let mut arg_types = vec![element.convert()];
+        if mth_name == "insert" {
+            arg_types.insert(0, TokenType::INT);
+        }
+        self.outside_list_and_args(|compiler| {
+            for (index, arg_type) in arg_types.iter().enumerate() {
+                if index > 0 {
+                    compiler.parser.consume(TokenType::COMMA);
+                }
+                compiler.expression();
+
+                let value_type = compiler.get_cur_chunk().get_last_value().convert();
+                if value_type != *arg_type {
+                    errors::error_message("COMPILER ERROR", format!("Expected to find {} as List.{} argument but found: {} {}:",
+                        compiler.type_name(*arg_type),
+                        mth_name,
+                        compiler.type_name(value_type),
+                        compiler.parser.line,
+                    ));
+                    errors::compile_error();
+                }
+            }
+            compiler.copy_struct_element();
+        });
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        if mth_name == "push" {
+            self.emit_byte(OpCode::LIST_PACK(1), self.parser.line);
+            self.emit_byte(OpCode::LIST_CONCAT, self.parser.line);
+        }else {
+            self.emit_byte(OpCode::LIST_INSERT, self.parser.line);
+        }
+        self.emit_byte(OpCode::SET_INSTANCE_FIELD(pos, field_index), self.parser.line);
+        self.emit_byte(OpCode::POP, self.parser.line);
+
+        let null = self.get_cur_chunk().push_value(Value::Null);
+        self.emit_byte(OpCode::CONSTANT_NULL(null), self.parser.line);
+    }
+
+    pub fn instance_declare(&mut self, var_pos: usize, name: String) {
+        if self.parser.prev.value.iter().collect::<String>() == "List" {
+            self.parser.consume(TokenType::LESS);
+            self.list_dec(name);
+
+            return
+        }
+        
+        if self.parser.cur.token_type != TokenType::EQ {
+            errors::error_message("COMPILING ERROR", format!("Struct cannot be left undeclared {}:",
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+        self.parser.consume(TokenType::EQ);
+
+        // Bytes var always gets its own copy of the value
+        if self.parser.symbols[var_pos].name == "Bytes" {
+            self.bytes_value_declare(name);
+            return
+        }
+
+        // var p: P = P{...} is the same as var p: P = {...}
+        if self.parser.cur.token_type == TokenType::IDENTIFIER && self.parser.peek_next().token_type == TokenType::LEFT_BRACE {
+            self.parser.consume(TokenType::IDENTIFIER);
+
+            let value = self.parser.prev.value.iter().collect::<String>();
+            if value != self.parser.symbols[var_pos].name {
+                errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                    self.parser.symbols[var_pos].name,
+                    value,
+                    self.parser.line,
+                ));
+                errors::compile_error();
+            }
+        }
+
+        if self.parser.cur.token_type != TokenType::LEFT_BRACE {
+            if self.parser.cur.token_type == TokenType::STRING {
                 let pos = self.get_cur_instances().len();
                 
                 self.compile_line();
@@ -1086,23 +2554,35 @@ impl Compiler {
                 self.emit_byte(OpCode::POP, self.parser.line);
 
                 if !matches!(self.get_cur_chunk().get_last_value(), Value::String(_)) {
-                    errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {:?} found: {:?} {}:",
-                        TokenType::STRING,
-                        self.get_cur_chunk().get_last_value().convert(),
+                    let value_type = self.get_cur_chunk().get_last_value().convert();
+                    errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                        self.type_name(TokenType::STRING),
+                        self.type_name(value_type),
                         self.parser.line,
                     ));
-                    std::process::exit(1);
+                    errors::compile_error();
                 }
 
                 self.get_cur_instances()[pos].name = name;
                 return
             }
             
+            let is_string_var = self.parser.symbols[var_pos].name == "String";
+            if is_string_var && self.parser.cur.token_type == TokenType::IDENTIFIER && self.parser.peek_next().token_type == TokenType::DOT {
+                self.string_value_declare(name);
+                return
+            }
+
+            if self.parser.cur.token_type == TokenType::IDENTIFIER && self.parser.peek_next().token_type == TokenType::DOT {
+                self.struct_value_declare(var_pos, name);
+                return
+            }
+
             if self.parser.cur.token_type != TokenType::IDENTIFIER {
                 errors::error_message("COMPILING ERROR", format!("Expected to find instance {}:",
                     self.parser.line,
                 ));
-                std::process::exit(1);
+                errors::compile_error();
             }            
             self.parser.consume(TokenType::IDENTIFIER);
 
@@ -1120,21 +2600,25 @@ impl Compiler {
                     TokenType::STRUCT(root_pos) => root_pos,
                     TokenType::STRING => self.get_struct_symbol_pos("String".to_string()),
                     _ => {
-                        println!("CHECK THIS TYPE OF ERRORS line 1117 in compiler.rs {:?}", self.parser.symbols[pos as usize]);
-                        std::process::exit(1);                            
+                        errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                            self.parser.symbols[var_pos].name,
+                            self.type_name(self.parser.symbols[pos as usize].output_type),
+                            self.parser.line,
+                        ));
+                        errors::compile_error();
                     }
                 };
                 
-                self.symbol_to_hold = pos as usize;
+                self.call_targets.push(pos as usize);
                 self.parser.consume(TokenType::LEFT_PAREN);
 
                 if root_struct_pos != var_pos {
-                    errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {:?} found: {:?} {}:",
-                        self.parser.symbols[var_pos as usize].output_type,
+                    errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                        self.parser.symbols[var_pos].name,
                         self.parser.symbols[root_struct_pos].name,
                         self.parser.line,
                     ));
-                    std::process::exit(1);
+                    errors::compile_error();
                 }
                 
                 self.fn_call();
@@ -1145,7 +2629,7 @@ impl Compiler {
                     let len = self.parser.symbols.len();
                     instance_obj.set_index(len);
 
-                    self.get_cur_instances().push(Local{ name: name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::String });
+                    self.get_cur_instances().push(Local{ name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::String });
                     self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
 
                     self.emit_byte(OpCode::STRING_DEC_VALUE(instance_obj), self.parser.line);
@@ -1166,11 +2650,12 @@ impl Compiler {
                             value, 
                             self.parser.line
                         ));
-                        std::process::exit(1);
+                        errors::compile_error();
                     },
                 };
                 
-                self.get_cur_instances().push(Local{ name: name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(root_struct_pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::Null });
+                self.emit_byte(OpCode::INSTANCE_INDEX(len), self.parser.line);
+                self.get_cur_instances().push(Local{ name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(root_struct_pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::Null });
                 self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(root_struct_pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
                 
                 return
@@ -1183,51 +2668,188 @@ impl Compiler {
             let local_rf_pos = self.get_cur_instances()[pos].rf_index;
             let is_special = self.get_cur_instances()[pos].is_special.clone();
 
-            self.get_cur_instances().push(Local{ name: name, local_type: local_type, is_redirected: true, redirect_pos: pos, rf_index: local_rf_pos, is_special: is_special });
+            self.get_cur_instances().push(Local{ name, local_type, is_redirected: true, redirect_pos: pos, rf_index: local_rf_pos, is_special });
 
             return
         }
+        self.struct_literal(var_pos, name);
+    }
+
+    // P{1, "a"} used as an expression, instance lives in a hidden local like a String literal
+    fn typed_struct_literal(&mut self, struct_pos: usize) {
+        let in_list = self.declaring_list;
+        let in_args = self.changing_fn;
+
+        let pos = self.struct_literal(struct_pos, String::new());
+        let rf_index = self.get_cur_instances()[pos].rf_index;
+
+        self.get_cur_chunk().push_value(Value::InstanceRef(struct_pos));
+
+        if in_list || in_args {
+            // callee takes the instance itself, so no RefObject is pushed between arguments
+            self.emit_byte(OpCode::PUSH_STACK(Value::InstanceRef(rf_index)), self.parser.line);
+        }else {
+            self.emit_byte(OpCode::GET_INSTANCE_RF(pos), self.parser.line);
+        }
+    }
+
+    // Compiles {field, ...} of given struct into INSTANCE_DEC and a new instance local, returns its position
+    fn struct_literal(&mut self, struct_pos: usize, name: String) -> usize {
+        let field_counts = self.outside_list_and_args(|compiler| compiler.struct_literal_fields(struct_pos));
+
+        let mut instance_obj = StructInstance::new(struct_pos);
+
+        if field_counts != self.parser.symbols[struct_pos].arg_count {
+            errors::error_message("COMPILER ERROR",
+            format!("Expected to find {} fields but found: {} {}:", self.parser.symbols[struct_pos].arg_count, field_counts, self.parser.line));
+            errors::compile_error();
+        }
+        let len = self.parser.symbols.len();
+        instance_obj.set_index(len);
+
+        // literal cannot be an operand, so it is a whole argument when it ends one
+        if self.changing_fn && matches!(self.parser.cur.token_type, TokenType::COMMA | TokenType::RIGHT_PAREN) {
+            instance_obj.inc_counter();
+        }
+
+        self.emit_byte(OpCode::INSTANCE_DEC(instance_obj, field_counts), self.parser.line);
+
+        self.get_cur_instances().push(Local{ name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(struct_pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::Null });
+
+        self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(struct_pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
+
+        self.get_cur_instances().len() - 1
+    }
+
+    // Fields between the braces of a struct literal, returns how many were compiled
+    fn struct_literal_fields(&mut self, struct_pos: usize) -> usize {
         self.parser.consume(TokenType::LEFT_BRACE);
         let mut field_counts = 0;
 
-        let root_struct_name = self.parser.symbols[var_pos].name.clone();
+        let root_struct_name = self.parser.symbols[struct_pos].name.clone();
+        self.parser.push_context(format!("struct literal for '{}'", root_struct_name));
         while self.parser.cur.token_type != TokenType::RIGHT_BRACE {
-            self.expression();
+            if field_counts >= self.parser.symbols[struct_pos].arg_count {
+                errors::error_message("COMPILER ERROR",
+                format!("Expected to find {} fields but found more {}:", self.parser.symbols[struct_pos].arg_count, self.parser.line));
+                errors::compile_error();
+            }
+
+            // plain String field is stored by value, without a hidden String instance
+            if self.parser.cur.token_type == TokenType::STRING && matches!(self.parser.peek_next().token_type, TokenType::COMMA | TokenType::RIGHT_BRACE) {
+                self.parser.advance();
+                let value = Value::String(self.parser.prev.value.iter().collect::<String>());
+                self.emit_byte(OpCode::PUSH_STACK(value), self.parser.line);
+                self.get_cur_chunk().push_value(Value::String(String::new()));
+            }else if let SpecialType::List(element) = self.structs.get(&root_struct_name).unwrap().locals[field_counts].is_special.clone() {
+                self.list_field_value(&element);
+            }else {
+                let field_start = self.get_cur_chunk().code.len();
+                self.expression();
+                self.mark_operand(field_start);
+            }
 
             if self.get_cur_chunk().get_last_value().convert() != self.structs.get(&root_struct_name).unwrap().locals[field_counts].local_type {
                 let value_type = self.get_cur_chunk().get_last_value().convert();
 
                 errors::error_message("COMPILER ERROR",
-                format!("Expected to find {:?} but found: {:?} {}:", 
-                    self.structs.get(&root_struct_name).unwrap().locals[field_counts].local_type, 
-                    value_type,
+                format!("Expected to find {} but found: {} {}:", 
+                    self.type_name(self.structs.get(&root_struct_name).unwrap().locals[field_counts].local_type), 
+                    self.type_name(value_type),
                     self.parser.line
                 ));
-                std::process::exit(1);
+                errors::compile_error();
             }
             
-            if self.parser.cur.token_type == TokenType::COMMA {
-                self.parser.consume(TokenType::COMMA);
-            }
+            self.parser.consume_separator(TokenType::RIGHT_BRACE);
             field_counts += 1;
         }
         self.parser.consume(TokenType::RIGHT_BRACE);
+        self.parser.pop_context();
 
-        let mut instance_obj = StructInstance::new(var_pos);
+        field_counts
+    }
 
-        if field_counts != self.parser.symbols[var_pos].arg_count {
-            errors::error_message("COMPILER ERROR",
-            format!("Expected to find {} fields but found: {} {}:", self.parser.symbols[var_pos].arg_count, field_counts, self.parser.line));
-            std::process::exit(1);
+    // Compiles body as a value of its own, not as a whole argument of the enclosing call,
+    // the enclosing flag is restored afterwards
+    fn outside_args<T>(&mut self, body: impl FnOnce(&mut Self) -> T) -> T {
+        let changing_fn = std::mem::replace(&mut self.changing_fn, false);
+        let result = body(self);
+        self.changing_fn = changing_fn;
+        result
+    }
+
+    // Same as outside_args, body is also not an element of the enclosing list literal
+    fn outside_list_and_args<T>(&mut self, body: impl FnOnce(&mut Self) -> T) -> T {
+        let declaring_list = std::mem::replace(&mut self.declaring_list, false);
+        let result = self.outside_args(body);
+        self.declaring_list = declaring_list;
+        result
+    }
+
+    // String instance passed as a whole argument is owned by the hidden local and by the callee,
+    // callee releases its reference at the end, the hidden local at the end of the caller.
+    // Checked for single token operands (literal, var name)
+    fn is_whole_argument(&self) -> bool {
+        self.changing_fn && matches!(self.parser.cur.token_type, TokenType::COMMA | TokenType::RIGHT_PAREN)
+            && matches!(self.parser.peek_prev().token_type, TokenType::LEFT_PAREN | TokenType::COMMA)
+    }
+
+    // var s: String = u.name or s.toUpper(), value is copied into a new String instance
+    fn string_value_declare(&mut self, name: String) {
+        self.expression();
+
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if value_type != TokenType::STRING {
+            errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                self.type_name(TokenType::STRING),
+                self.type_name(value_type),
+                self.parser.line,
+            ));
+            errors::compile_error();
         }
+
+        let pos = self.get_struct_symbol_pos("String".to_string());
+        let mut instance_obj = StructInstance::new(pos);
+
         let len = self.parser.symbols.len();
         instance_obj.set_index(len);
 
-        self.emit_byte(OpCode::INSTANCE_DEC(instance_obj, field_counts), self.parser.line);
+        self.emit_byte(OpCode::STRING_DEC_VALUE(instance_obj), self.parser.line);
+
+        self.get_cur_instances().push(Local{ name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::String });
+        self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
+        self.get_cur_chunk().push_value(Value::String(String::new()));
+    }
+
+    // var n: Node = g.nodes[i] or a.method(), the hidden instance the expression leaves becomes the var
+    fn struct_value_declare(&mut self, var_pos: usize, name: String) {
+        self.expression();
 
-        self.get_cur_instances().push(Local{ name: name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(var_pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::Null });
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if value_type != TokenType::STRUCT(var_pos) {
+            errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                self.parser.symbols[var_pos].name,
+                self.type_name(value_type),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
 
-        self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(var_pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 })
+        let pos = match self.get_cur_chunk().get_last_instruction().op {
+            OpCode::GET_INSTANCE_RF(pos) => {
+                self.get_cur_chunk().code.pop();
+                pos
+            },
+            _ => match self.keep_returned_struct() {
+                Some(_) => self.get_cur_instances().len() - 1,
+                None => {
+                    errors::error_message("COMPILING ERROR", format!("Expected to find instance {}:", self.parser.line));
+                    errors::compile_error();
+                },
+            },
+        };
+        self.get_cur_instances()[pos].name = name;
     }
 
     pub fn struct_declare(&mut self) {
@@ -1237,40 +2859,116 @@ impl Compiler {
 
         if self.scope_depth != 0 {
             errors::error_message("COMPILE ERROR", format!("Struct \"{}\" declaration inside bounds {}:", name, self.parser.line));
-            std::process::exit(1)
+            errors::compile_error()
         }
 
         let mut struct_obj = Struct::new(name.clone());
 
         self.scope_depth += 1;
+        self.parser.push_context(format!("declaration of struct '{}'", name));
         self.parser.consume(TokenType::LEFT_BRACE);
-        while self.parser.cur.token_type != TokenType::RIGHT_BRACE && self.parser.cur.token_type != TokenType::KEYWORD(Keywords::METHODS) {
+        // methods can refer to any field, so the block is compiled once all of them are known
+        let mut methods_start: Option<usize> = None;
+        // sortkey may come before its field, it is resolved once all of them are known
+        let mut sort_key: Option<(String, u32)> = None;
+        while self.parser.cur.token_type != TokenType::RIGHT_BRACE {
+            if self.parser.cur.token_type == TokenType::KEYWORD(Keywords::METHODS) {
+                if methods_start.is_some() {
+                    errors::error_message("COMPILER ERROR", format!("Struct \"{}\" can have only one methods block {}:", name, self.parser.cur.line));
+                    errors::compile_error();
+                }
+                methods_start = Some(self.parser.index - 1);
+                self.parser.advance();
+                self.parser.skip_block();
+                continue
+            }
+
+            // "sortkey: int" is a field named sortkey, "sortkey x" marks the key field
+            if self.parser.cur.value.iter().collect::<String>() == "sortkey" && self.parser.peek_next().token_type == TokenType::IDENTIFIER {
+                if sort_key.is_some() {
+                    errors::error_message("COMPILER ERROR", format!("Struct \"{}\" can have only one sortkey {}:", name, self.parser.cur.line));
+                    errors::compile_error();
+                }
+                self.parser.advance();
+                self.parser.consume(TokenType::IDENTIFIER);
+                sort_key = Some((self.parser.prev.value.iter().collect::<String>(), self.parser.prev.line));
+
+                if self.parser.cur.token_type == TokenType::COMMA {
+                    self.parser.advance();
+                }
+                continue
+            }
+
             self.parser.consume(TokenType::IDENTIFIER);
 
             let field_name = self.parser.prev.value.iter().collect::<String>();
 
             self.parser.consume(TokenType::COLON);
 
+            let mut field_element = None;
             let field_type = match self.parser.cur.token_type {
-                TokenType::KEYWORD(keyword) => keyword.convert(),
-                _ => {
-                    errors::error_message("COMPILER ERROR", format!("Expected field type after \":\" {}:", self.parser.line));
-                    std::process::exit(1);
+                TokenType::KEYWORD(keyword) => {
+                    self.parser.advance();
+                    keyword.convert()
                 },
-            };
-            self.parser.advance();
-
-            self.parser.consume(TokenType::COMMA);
+                // List<T> field holds copies of its elements, like a String field holds its text
+                TokenType::IDENTIFIER if self.parser.cur.value.iter().collect::<String>() == "List" => {
+                    self.parser.advance();
+                    self.parser.consume(TokenType::LESS);
+                    field_element = Some(self.list_field_type(&field_name, &name));
+                    TokenType::LIST
+                },
+                // instances are not stored inside of other instances, Node { next: Node } could never be constructed
+                TokenType::IDENTIFIER => {
+                    let type_name = self.parser.cur.value.iter().collect::<String>();
+                    let reason = if type_name == name {
+                        "a struct cannot contain itself".to_string()
+                    }else {
+                        format!("struct type \"{}\" is not supported", type_name)
+                    };
+                    errors::error_message("COMPILER ERROR", format!("Field \"{}\" of struct \"{}\": {}, fields can only hold int, float, bool, String or List<T> values {}:",
+                        field_name,
+                        name,
+                        reason,
+                        self.parser.line,
+                    ));
+                    errors::compile_error();
+                },
+                _ => {
+                    errors::error_message("COMPILER ERROR", format!("Expected field type after \":\" {}:", self.parser.line));
+                    errors::compile_error();
+                },
+            };
+
+            // field ends with a comma or at the end of its line
+            let field_ends = self.parser.cur.line > self.parser.prev.line || matches!(self.parser.cur.token_type, TokenType::RIGHT_BRACE | TokenType::KEYWORD(Keywords::METHODS));
+            if self.parser.cur.token_type == TokenType::COMMA || !field_ends {
+                self.parser.consume(TokenType::COMMA);
+            }
 
-            struct_obj.locals.push(Local { name: field_name, local_type: field_type, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
+            let is_special = match field_element {
+                Some(element) => SpecialType::List(element),
+                None => SpecialType::Null,
+            };
+            self.field_lines.insert((name.clone(), field_name.clone()), self.parser.prev.line);
+            struct_obj.locals.push(Local { name: field_name, local_type: field_type, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special });
+        }
+        self.parser.pop_context();
+        struct_obj.index_fields();
+
+        if let Some((key_name, line)) = sort_key {
+            struct_obj.sort_key = self.sort_key_field(&struct_obj, &key_name, line);
         }
 
         // need to do that, because methods will not be compiled otherwise
         self.structs.insert(name.clone(), struct_obj.clone());
 
-        if self.parser.cur.token_type == TokenType::KEYWORD(Keywords::METHODS) {
+        if let Some(methods_start) = methods_start {
+            let struct_end = self.parser.index - 1;
+            self.parser.seek(methods_start);
             self.parser.advance();
             self.mth_stmt(name.clone());
+            self.parser.seek(struct_end);
         }
 
         self.parser.consume(TokenType::RIGHT_BRACE);
@@ -1286,40 +2984,124 @@ impl Compiler {
         self.scope_depth -= 1;
     }
 
-    pub fn mth_call(&mut self, output_type: TokenType, mth_arg_count: usize, instance_name: String, is_self: bool) {
+    // Element of a List<T> field, lists and Bytes cannot be nested in a field
+    fn list_field_type(&mut self, field_name: &str, struct_name: &str) -> Value {
+        let type_name = self.parser.cur.value.iter().collect::<String>();
+        if self.parser.cur.token_type == TokenType::IDENTIFIER && (type_name == "List" || type_name == "Bytes") {
+            errors::error_message("COMPILER ERROR", format!("Field \"{}\" of struct \"{}\": List<{}> is not supported, list fields can hold int, float, bool, String or struct elements {}:",
+                field_name,
+                struct_name,
+                type_name,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
+        let list_type = self.list_element_type();
+        self.parser.consume(TokenType::GREATER);
+        self.list_element_value(list_type)
+    }
+
+    // Key field has to be declared in the struct and hold int, float or String
+    fn sort_key_field(&self, struct_obj: &Struct, key_name: &str, line: u32) -> Option<usize> {
+        let field = match struct_obj.field_index(key_name) {
+            Some(field) => field,
+            None => {
+                errors::error_message("COMPILER ERROR", format!("sortkey \"{}\" is not a field of struct \"{}\"{} {}:",
+                    key_name,
+                    struct_obj.name,
+                    errors::suggest(key_name, struct_obj.field_indices.keys()),
+                    line,
+                ));
+                errors::compile_error();
+            },
+        };
+
+        let field_type = struct_obj.locals[field].local_type;
+        if !matches!(field_type, TokenType::INT | TokenType::FLOAT | TokenType::STRING) {
+            errors::error_message("COMPILER ERROR", format!("sortkey \"{}\" of struct \"{}\" has type {}, only int, float and String fields can be compared {}:",
+                key_name,
+                struct_obj.name,
+                self.type_name(field_type),
+                line,
+            ));
+            errors::compile_error();
+        }
+        Some(field)
+    }
+
+    // methods StructName { ... } at file scope, attaches methods to a struct declared earlier in the file
+    pub fn methods_declare(&mut self) {
+        let name = self.parser.cur.value.iter().collect::<String>();
+
+        if name == "String" || name == "List" || name == "Bytes" {
+            errors::error_message("COMPILE ERROR", format!("Cannot add methods to builtin struct \"{}\" {}:", name, self.parser.line));
+            errors::compile_error()
+        }
+
+        self.parser.consume(TokenType::IDENTIFIER);
+
+        if !self.structs.contains_key(&name) {
+            self.get_struct_symbol_pos(name.clone());
+
+            errors::error_message("COMPILE ERROR", format!("Struct \"{}\" has to be declared before its methods block {}:", name, self.parser.line));
+            errors::compile_error()
+        }
+
+        self.scope_depth += 1;
+        self.mth_stmt(name.clone());
+        self.scope_depth -= 1;
+
+        // STRUCT_DEC was emitted with the struct body, update it so it holds every method
+        let struct_obj = self.structs.get(&name).unwrap().clone();
+        for instruction in self.get_cur_chunk().code.iter_mut() {
+            if let OpCode::STRUCT_DEC(declared) = &mut instruction.op {
+                if declared.name == name {
+                    *declared = struct_obj;
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn mth_call(&mut self, output_type: TokenType, arg_types: Vec<TokenType>, instance_name: String, is_self: bool) {
         self.parser.consume(TokenType::LEFT_PAREN);
         if is_self {
             let pos = self.get_instance_local_pos(instance_name);
 
             let heap_pos = self.get_cur_instances()[pos].rf_index;
 
-            self.emit_byte(OpCode::GET_INSTANCE_RF(pos), self.parser.line);
+            // both push RefObject on the heap, so only one of them can be emitted
             if heap_pos == 0 {
-                self.emit_byte(OpCode::POP, self.parser.line);
                 self.emit_byte(OpCode::GET_INSTANCE_W_OFFSET_RF(pos), self.parser.line);
+            }else {
+                self.emit_byte(OpCode::GET_INSTANCE_RF(pos), self.parser.line);
             }
 
-            self.emit_byte(OpCode::INC_RC(pos as usize), self.parser.line);
+            self.emit_byte(OpCode::INC_RC(pos), self.parser.line);
         }
 
+        // self is not a part of arg_types, so args are indexed from 0 on both sides
         let mut arg_count = 0;
-        self.changing_fn = true;
+        let changing_fn_enclosing = self.changing_fn;
         while self.parser.cur.token_type != TokenType::RIGHT_PAREN {
-            arg_count += 1;
-            
+            self.changing_fn = true;
             self.expression();
+            self.check_arg_type(&arg_types, arg_count);
+            self.string_argument();
+            arg_count += 1;
 
             if self.parser.cur.token_type == TokenType::COMMA {
                 self.parser.consume(TokenType::COMMA);
             }
         }
         self.parser.consume(TokenType::RIGHT_PAREN);
-        self.changing_fn = false;
+        self.changing_fn = changing_fn_enclosing;
 
-        if arg_count != mth_arg_count {
+        if arg_count != arg_types.len() {
             errors::error_message("COMPILER ERROR",
-            format!("Expected to find {} arguments but found: {} {}:", mth_arg_count, arg_count, self.parser.line));
-            std::process::exit(1);
+            format!("Expected to find {} arguments but found: {} {}:", arg_types.len(), arg_count, self.parser.line));
+            errors::compile_error();
         }
 
         match output_type {
@@ -1338,13 +3120,394 @@ impl Compiler {
             TokenType::STRING => {
                 self.get_cur_chunk().push_value(Value::String(String::new()));
             }
+            // native methods return lists of strings only
+            TokenType::LIST => {
+                self.get_cur_chunk().push_value(Value::ListObj(vec![Value::String(String::new())]));
+            }
             output_type => {
                 errors::error_message("COMPILER ERROR", format!("Unexpected output type \"{:?}\" {}:", output_type, self.parser.line));
-                std::process::exit(1);
+                errors::compile_error();
             }
         };
     }
 
+    pub fn list_mth_call(&mut self, list_pos: usize, mth_name: String) {
+        if mth_name == "sortKey" {
+            self.list_sort_key(list_pos);
+            return
+        }
+
+        if mth_name == "sort" {
+            self.list_sort(list_pos);
+            return
+        }
+
+        if mth_name == "extend" {
+            self.list_extend(list_pos);
+            return
+        }
+
+        if matches!(mth_name.as_str(), "remove" | "swap" | "fill" | "push" | "insert") {
+            self.list_in_place(list_pos, mth_name);
+            return
+        }
+
+        if mth_name == "lowerBound" || mth_name == "sortedInsert" {
+            self.list_ordered(list_pos, mth_name);
+            return
+        }
+
+        if mth_name == "isEmpty" {
+            self.parser.consume(TokenType::LEFT_PAREN);
+            self.parser.consume(TokenType::RIGHT_PAREN);
+            self.emit_byte(OpCode::LIST_IS_EMPTY(list_pos), self.parser.line);
+            self.get_cur_chunk().push_value(Value::Bool(true));
+            return
+        }
+
+        self.emit_byte(OpCode::GET_LIST(list_pos), self.parser.line);
+        self.list_native_call(mth_name);
+    }
+
+    // len(), join(...) on the list value already on the stack
+    fn list_native_call(&mut self, mth_name: String) {
+        let (mth, native_offset) = match (self.structs.get("List").unwrap().methods.get(&mth_name), ListMethods::get_native_offset(&mth_name)) {
+            (Some(mth), Some(offset)) => (mth.clone(), offset),
+            _ => {
+                let methods = self.structs.get("List").unwrap().methods.keys();
+                self.undeclared_member_error("Method", &mth_name, "List", methods);
+            },
+        };
+
+        self.parser.consume(TokenType::LEFT_PAREN);
+        let mut arg_count = 0;
+        while self.parser.cur.token_type != TokenType::RIGHT_PAREN {
+            self.expression();
+
+            let value_type = self.get_cur_chunk().get_last_value().convert();
+            if let Some(arg_type) = mth.arg_types.get(arg_count) {
+                if *arg_type != value_type {
+                    errors::error_message("COMPILER ERROR", format!("Expected to find {} but found: {} {}:", self.type_name(*arg_type), self.type_name(value_type), self.parser.line));
+                    errors::compile_error();
+                }
+            }
+            arg_count += 1;
+
+            if self.parser.cur.token_type == TokenType::COMMA {
+                self.parser.consume(TokenType::COMMA);
+            }
+        }
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        if arg_count != mth.arg_count {
+            errors::error_message("COMPILER ERROR",
+            format!("Expected to find {} arguments but found: {} {}:", mth.arg_count, arg_count, self.parser.line));
+            errors::compile_error();
+        }
+
+        let native_pos = self.get_struct_symbol_pos("List".to_string()) + native_offset;
+        self.emit_byte(OpCode::NATIVE_FN_CALL(native_pos), self.parser.line);
+
+        match mth.output_type {
+            TokenType::INT => self.get_cur_chunk().push_value(Value::Int(0)),
+            _ => self.get_cur_chunk().push_value(Value::String(String::new())),
+        };
+    }
+
+    // b.method(...) on Bytes var, the value is passed as first argument,
+    // value returned by set and push replaces the value held by the instance
+    fn bytes_mth_call(&mut self, mut bytes_pos: usize, mth_name: String) {
+        let (mth, native_offset) = match (self.structs.get("Bytes").unwrap().methods.get(&mth_name), BytesMethods::get_native_offset(&mth_name)) {
+            (Some(mth), Some(offset)) => (mth.clone(), offset),
+            _ => {
+                let methods = self.structs.get("Bytes").unwrap().methods.keys();
+                self.undeclared_member_error("Method", &mth_name, "Bytes", methods);
+            },
+        };
+
+        if self.get_cur_instances()[bytes_pos].is_redirected {
+            bytes_pos = self.get_cur_instances()[bytes_pos].redirect_pos;
+        }
+        self.emit_byte(OpCode::GET_INSTANCE_FIELD(bytes_pos, 0), self.parser.line);
+
+        self.parser.consume(TokenType::LEFT_PAREN);
+        let mut arg_count = 0;
+        while self.parser.cur.token_type != TokenType::RIGHT_PAREN {
+            self.expression();
+
+            let value_type = self.get_cur_chunk().get_last_value().convert();
+            if let Some(arg_type) = mth.arg_types.get(arg_count) {
+                if *arg_type != value_type {
+                    errors::error_message("COMPILER ERROR", format!("Expected to find {} but found: {} {}:", self.type_name(*arg_type), self.type_name(value_type), self.parser.line));
+                    errors::compile_error();
+                }
+            }
+            arg_count += 1;
+
+            if self.parser.cur.token_type == TokenType::COMMA {
+                self.parser.consume(TokenType::COMMA);
+            }
+        }
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        if arg_count != mth.arg_count {
+            errors::error_message("COMPILER ERROR",
+            format!("Expected to find {} arguments but found: {} {}:", mth.arg_count, arg_count, self.parser.line));
+            errors::compile_error();
+        }
+
+        let native_pos = self.get_struct_symbol_pos("Bytes".to_string()) + native_offset;
+        self.emit_byte(OpCode::NATIVE_FN_CALL(native_pos), self.parser.line);
+
+        if BytesMethods::is_mutating(&mth_name) {
+            self.emit_byte(OpCode::SET_INSTANCE_FIELD(bytes_pos, 0), self.parser.line);
+            self.emit_byte(OpCode::POP, self.parser.line);
+
+            let pos = self.get_cur_chunk().push_value(Value::Null);
+            self.emit_byte(OpCode::CONSTANT_NULL(pos), self.parser.line);
+            return
+        }
+        self.push_type_value(mth.output_type);
+    }
+
+    // var b: Bytes = <expression giving Bytes value>
+    fn bytes_value_declare(&mut self, name: String) {
+        self.expression();
+
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if value_type != TokenType::BYTES {
+            errors::error_message("COMPILING ERROR", format!("Mismatched types while assigning var, expected: {} found: {} {}:",
+                self.type_name(TokenType::BYTES),
+                self.type_name(value_type),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
+        let pos = self.get_struct_symbol_pos("Bytes".to_string());
+        let mut instance_obj = StructInstance::new(pos);
+
+        let len = self.parser.symbols.len();
+        instance_obj.set_index(len);
+
+        self.emit_byte(OpCode::BYTES_DEC_VALUE(instance_obj), self.parser.line);
+
+        self.get_cur_instances().push(Local{ name, local_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::Bytes });
+        self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
+    }
+
+    // xs.sortKey("method") - method name has to be a literal, so it is checked against the element type here
+    // a.extend(b) appends copies of b elements to a, b is read before a changes so a.extend(a) doubles a
+    fn list_extend(&mut self, list_pos: usize) {
+        let element = match self.get_cur_instances()[list_pos].is_special.clone() {
+            SpecialType::List(element) => element,
+            _ => Value::Null,
+        };
+
+        self.parser.consume(TokenType::LEFT_PAREN);
+        self.list_element = None;
+        self.outside_args(Self::expression);
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        let value_element = self.list_operand_element();
+        if value_type != TokenType::LIST || value_element.as_ref().map(|value| value.convert()) != Some(element.convert()) {
+            errors::error_message("COMPILING ERROR", format!("Cannot extend {} with {}; element types have to match {}:",
+                self.list_type_name(TokenType::LIST, &Some(element)),
+                self.list_type_name(value_type, &value_element),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+        self.check_copied_elements(&element);
+
+        self.emit_byte(OpCode::LIST_EXTEND(list_pos), self.parser.line);
+        self.get_cur_chunk().push_value(Value::Null);
+    }
+
+    // remove(i) returns the element, swap(i, j), fill(value), push(value) and insert(i, value) change the list in place,
+    // removed and added elements are copied by value, so lists of structs can only swap
+    fn list_in_place(&mut self, list_pos: usize, mth_name: String) {
+        let element = match self.get_cur_instances()[list_pos].is_special.clone() {
+            SpecialType::List(element) => element,
+            _ => Value::Null,
+        };
+
+        if let (Value::InstanceRef(struct_pos), true) = (&element, mth_name != "swap") {
+            errors::error_message("COMPILING ERROR", format!("Method: \"{}\" is not supported for List<{}>, only int, float, bool and String elements are copied {}:",
+                mth_name,
+                self.parser.symbols[*struct_pos].name,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
+        let arg_types = match mth_name.as_str() {
+            "remove" => vec![TokenType::INT],
+            "swap" => vec![TokenType::INT, TokenType::INT],
+            "insert" => vec![TokenType::INT, element.convert()],
+            _ => vec![element.convert()],
+        };
+
+        self.parser.consume(TokenType::LEFT_PAREN);
+        self.outside_args(|compiler| {
+            for (index, arg_type) in arg_types.iter().enumerate() {
+                if index > 0 {
+                    compiler.parser.consume(TokenType::COMMA);
+                }
+                compiler.expression();
+
+                let value_type = compiler.get_cur_chunk().get_last_value().convert();
+                if value_type != *arg_type {
+                    errors::error_message("COMPILER ERROR", format!("Expected to find {} but found: {} {}:", compiler.type_name(*arg_type), compiler.type_name(value_type), compiler.parser.line));
+                    errors::compile_error();
+                }
+            }
+        });
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        match mth_name.as_str() {
+            "remove" => {
+                self.emit_byte(OpCode::LIST_REMOVE(list_pos), self.parser.line);
+                self.get_cur_chunk().push_value(element);
+            },
+            "swap" => {
+                self.emit_byte(OpCode::LIST_SWAP(list_pos), self.parser.line);
+                self.get_cur_chunk().push_value(Value::Null);
+            },
+            "push" => {
+                self.emit_byte(OpCode::LIST_PACK(1), self.parser.line);
+                self.emit_byte(OpCode::LIST_EXTEND(list_pos), self.parser.line);
+                self.get_cur_chunk().push_value(Value::Null);
+            },
+            "insert" => {
+                self.emit_byte(OpCode::LIST_INSERT_AT(list_pos), self.parser.line);
+                self.get_cur_chunk().push_value(Value::Null);
+            },
+            _ => {
+                self.emit_byte(OpCode::LIST_FILL(list_pos), self.parser.line);
+                self.get_cur_chunk().push_value(Value::Null);
+            },
+        }
+    }
+
+    // lowerBound(x) and sortedInsert(x) on a list kept in sort() order, elements are compared by value
+    fn list_ordered(&mut self, list_pos: usize, mth_name: String) {
+        let element = match self.get_cur_instances()[list_pos].is_special.clone() {
+            SpecialType::List(element) => element,
+            _ => Value::Null,
+        };
+
+        if !matches!(element, Value::Int(_) | Value::Float(_) | Value::String(_)) {
+            errors::error_message("COMPILING ERROR", format!("Method: \"{}\" is not supported for List<{}>, only int, float and String elements are ordered {}:",
+                mth_name,
+                self.type_name(element.convert()),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
+        self.parser.consume(TokenType::LEFT_PAREN);
+        self.outside_args(Self::expression);
+
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if value_type != element.convert() {
+            errors::error_message("COMPILER ERROR", format!("Expected to find {} but found: {} {}:", self.type_name(element.convert()), self.type_name(value_type), self.parser.line));
+            errors::compile_error();
+        }
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        if mth_name == "lowerBound" {
+            self.emit_byte(OpCode::LIST_LOWER_BOUND(list_pos), self.parser.line);
+            self.get_cur_chunk().push_value(Value::Int(0));
+        } else {
+            self.emit_byte(OpCode::LIST_SORTED_INSERT(list_pos), self.parser.line);
+            self.get_cur_chunk().push_value(Value::Null);
+        }
+    }
+
+    // sort() orders numbers and Strings by value, struct elements by the sortkey field of their struct
+    fn list_sort(&mut self, list_pos: usize) {
+        self.parser.consume(TokenType::LEFT_PAREN);
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        let element_type = match self.get_cur_instances()[list_pos].is_special.clone() {
+            SpecialType::List(element_type) => element_type,
+            _ => Value::Null,
+        };
+
+        let field = match element_type {
+            Value::Int(_) | Value::Float(_) | Value::String(_) => None,
+            Value::InstanceRef(struct_pos) => {
+                let struct_name = self.parser.symbols[struct_pos].name.clone();
+                match self.structs.get(&struct_name).unwrap().sort_key {
+                    Some(field) => Some(field),
+                    None => {
+                        errors::error_message("COMPILER ERROR", format!("Struct \"{}\" has no sort key, declare one with \"sortkey field\" in its body or use sortKey(\"method\") {}:", struct_name, self.parser.line));
+                        errors::compile_error();
+                    },
+                }
+            },
+            element_type => {
+                errors::error_message("COMPILER ERROR", format!("sort needs a list of int, float, String or struct elements, found: List<{}> {}:", self.type_name(element_type.convert()), self.parser.line));
+                errors::compile_error();
+            },
+        };
+
+        self.emit_byte(OpCode::LIST_SORT(list_pos, field), self.parser.line);
+        self.get_cur_chunk().push_value(Value::Null);
+    }
+
+    fn list_sort_key(&mut self, list_pos: usize) {
+        self.parser.consume(TokenType::LEFT_PAREN);
+        if self.parser.cur.token_type != TokenType::STRING {
+            errors::error_message("COMPILER ERROR", format!("sortKey expects the method name as a string literal {}:", self.parser.line));
+            errors::compile_error();
+        }
+        self.parser.advance();
+        let key_name = self.parser.prev.value.iter().collect::<String>();
+        self.parser.consume(TokenType::RIGHT_PAREN);
+
+        let element_type = match self.get_cur_instances()[list_pos].is_special.clone() {
+            SpecialType::List(element_type) => element_type,
+            _ => Value::Null,
+        };
+
+        let mth = match element_type {
+            // String methods are natives, only length is computed directly
+            Value::String(_) => {
+                if key_name != "len" {
+                    errors::error_message("COMPILER ERROR", format!("Method: \"{}\" cannot be used as sort key of List<String>, only \"len\" is supported {}:", key_name, self.parser.line));
+                    errors::compile_error();
+                }
+                None
+            },
+            Value::InstanceRef(struct_pos) => {
+                let struct_name = self.parser.symbols[struct_pos].name.clone();
+                let mth = match self.structs.get(&struct_name).unwrap().methods.get(&key_name) {
+                    Some(mth) => mth.clone(),
+                    None => {
+                        let methods = self.structs.get(&struct_name).unwrap().methods.keys();
+                        self.undeclared_member_error("Method", &key_name, &struct_name, methods);
+                    },
+                };
+
+                if !mth.is_self_arg || mth.arg_count != 0 || !matches!(mth.output_type, TokenType::INT | TokenType::FLOAT | TokenType::STRING) {
+                    errors::error_message("COMPILER ERROR", format!("Method: \"{}\" cannot be used as sort key, it has to take only self and return int, float or String {}:", key_name, self.parser.line));
+                    errors::compile_error();
+                }
+                Some(mth)
+            },
+            _ => {
+                errors::error_message("COMPILER ERROR", format!("sortKey needs a list of String or struct elements {}:", self.parser.line));
+                errors::compile_error();
+            },
+        };
+
+        self.emit_byte(OpCode::LIST_SORT_KEY(list_pos, mth), self.parser.line);
+        self.get_cur_chunk().push_value(Value::Null);
+    }
+
     pub fn mth_stmt(&mut self, struct_name: String) {
         self.parser.consume(TokenType::LEFT_BRACE);
 
@@ -1352,8 +3515,20 @@ impl Compiler {
             let name = self.parser.cur.value.iter().collect::<String>();
 
             if self.structs.get(&struct_name).unwrap().methods.contains_key(&name) {
-                errors::error_message("COMPILER ERROR", format!("Method: \"{}\" is already defined for struct: \"{}\" {}:", name, struct_name, self.parser.line));
-                std::process::exit(1);
+                errors::error_message("COMPILER ERROR", format!("Method: \"{}\" is already defined for struct: \"{}\" {}:", name, struct_name, self.parser.cur.line));
+                errors::compile_error();
+            }
+
+            // instance.name would read the field and instance.name() call the method
+            if let Some(field_line) = self.field_lines.get(&(struct_name.clone(), name.clone())) {
+                errors::error_message("COMPILER ERROR", format!("Method: \"{}\" of struct: \"{}\" declared at line {} has the same name as its field declared at line {} {}:",
+                    name,
+                    struct_name,
+                    self.parser.cur.line,
+                    field_line,
+                    self.parser.cur.line,
+                ));
+                errors::compile_error();
             }
 
             let root_struct_pos = self.get_struct_symbol_pos(struct_name.clone());
@@ -1366,37 +3541,37 @@ impl Compiler {
     }
 
     pub fn get_fn_symbol_pos(&mut self, fn_name: String) -> usize {
-        let pos = self.parser.symbols
-            .iter()
-            .enumerate()
-            .find(|(_, name)| *name.name == fn_name && name.symbol_type != TokenType::KEYWORD(Keywords::STRUCT))
-            .map(|(index, _)| index as i32)
-            .unwrap_or(-1);
-
-        if pos == -1 {
-            errors::error_message("COMPILER ERROR",
-            format!("Symbol: \"{}\" is not defined as function in this scope {}:", fn_name, self.parser.line));
-            std::process::exit(1);
-        }
-
-        pos as usize
-    }
-    
-    pub fn get_struct_symbol_pos(&mut self, struct_name: String) -> usize {
-        let pos = self.parser.symbols
-            .iter()
-            .enumerate()
-            .find(|(_, name)| *name.name == struct_name && name.symbol_type == TokenType::KEYWORD(Keywords::STRUCT))
-            .map(|(index, _)| index as i32)
-            .unwrap_or(-1);
-
-        if pos == -1 {
-            errors::error_message("COMPILER ERROR",
-            format!("Symbol: \"{}\" is not defined as struct in this scope {}:", struct_name, self.parser.line));
-            std::process::exit(1);
+        match self.parser.find_symbol(&fn_name, |symbol| symbol.symbol_type != TokenType::KEYWORD(Keywords::STRUCT)) {
+            Some(pos) => pos,
+            None => {
+                errors::error_message("COMPILER ERROR",
+                format!("Symbol: \"{}\" is not defined as function in this scope{} {}:",
+                    fn_name,
+                    errors::suggest(&fn_name, self.parser.symbols.iter()
+                        .filter(|symbol| matches!(symbol.symbol_type, TokenType::KEYWORD(Keywords::FN) | TokenType::NATIVE_FN))
+                        .map(|symbol| &symbol.name)),
+                    self.parser.line,
+                ));
+                errors::compile_error();
+            },
+        }
+    }
+    
+    pub fn get_struct_symbol_pos(&mut self, struct_name: String) -> usize {
+        match self.parser.find_symbol(&struct_name, |symbol| symbol.symbol_type == TokenType::KEYWORD(Keywords::STRUCT)) {
+            Some(pos) => pos,
+            None => {
+                errors::error_message("COMPILER ERROR",
+                format!("Symbol: \"{}\" is not defined as struct in this scope{} {}:",
+                    struct_name,
+                    errors::suggest(&struct_name, self.parser.symbols.iter()
+                        .filter(|symbol| symbol.symbol_type == TokenType::KEYWORD(Keywords::STRUCT))
+                        .map(|symbol| &symbol.name)),
+                    self.parser.line,
+                ));
+                errors::compile_error();
+            },
         }
-
-        pos as usize
     }
 
     pub fn get_local_pos(&mut self, name: String) -> usize {
@@ -1407,10 +3582,19 @@ impl Compiler {
             .map(|(index, _)| index as i32)
             .unwrap_or(-1);
 
+        if pos == -1 && self.failed_vars.contains(&name) {
+            self.error_suppressed = true;
+            errors::compile_error();
+        }
+
         if pos == -1 {
             errors::error_message("COMPILER ERROR",
-            format!("Symbol: \"{}\" is not defined as var in this scope {}:", name, self.parser.line));
-            std::process::exit(1);
+            format!("Symbol: \"{}\" is not defined as var in this scope{} {}:",
+                name,
+                errors::suggest(&name, self.cur_function.locals.iter().chain(self.cur_function.instances.iter()).map(|local| &local.name)),
+                self.parser.line,
+            ));
+            errors::compile_error();
         }
 
         pos as usize
@@ -1428,10 +3612,16 @@ impl Compiler {
             .map(|(index, _)| index as i32)
             .unwrap_or(-1);
 
+        // String, List and struct declarations that failed are poisoned like scalar ones
+        if pos == -1 && self.failed_vars.contains(&instance_name) {
+            self.error_suppressed = true;
+            errors::compile_error();
+        }
+
         if pos == -1 {
             errors::error_message("COMPILER ERROR",
             format!("Local: \"{}\" is not defined as instance in this scope {}:", instance_name, self.parser.line));
-            std::process::exit(1);
+            errors::compile_error();
         }
 
         if self.get_cur_instances()[pos as usize].is_redirected {
@@ -1441,36 +3631,65 @@ impl Compiler {
         pos as usize
     }
 
+    // f(...).method(), String result of the call is the receiver
+    pub fn call(&mut self) {
+        self.fn_call();
+
+        if matches!(self.get_cur_chunk().get_last_value(), Value::String(_)) {
+            self.string_receiver(self.parser.prev.value.iter().collect::<String>());
+        }
+    }
+
     pub fn fn_call(&mut self) {
         let mut arg_count: usize = 0;
-        self.changing_fn = true;
-        
-        if self.parser.symbols[self.symbol_to_hold].symbol_type == TokenType::NATIVE_FN {
-            self.changing_fn = false;
+        let changing_fn_enclosing = self.changing_fn;
+        let mut target = *self.call_targets.last().unwrap();
+        let is_native = self.parser.symbols[target].symbol_type == TokenType::NATIVE_FN;
+        if is_native && self.registry.is_disabled(&self.parser.symbols[target].name) {
+            errors::error_message("COMPILER ERROR", format!("native '{}' is disabled in sandbox mode {}:", self.parser.symbols[target].name, self.parser.line));
+            errors::compile_error();
         }
         
-        let symbol_to_hold_enclosing = self.symbol_to_hold;
+        let arg_types = self.parser.fn_arg_types.get(&self.parser.symbols[target].name).cloned().unwrap_or_default();
+        let is_hash = self.parser.symbols[target].name == "hash";
+        let is_convf = is_native && self.parser.symbols[target].name == "convf";
+        let mut value_types = vec![];
+        self.parser.push_context(format!("call to '{}'", self.parser.symbols[target].name));
         while self.parser.cur.token_type != TokenType::RIGHT_PAREN {
-            arg_count += 1;
-
+            // nested call in previous argument resets the flag
+            self.changing_fn = !is_native;
+            let arg_start = self.get_cur_chunk().code.len();
             self.expression();
-
-            if self.parser.cur.token_type == TokenType::COMMA {
-                self.parser.consume(TokenType::COMMA);
+            if is_convf {
+                self.check_truncated_division();
+            }
+            self.mark_operand(arg_start);
+            self.check_arg_type(&arg_types, arg_count);
+            self.check_list_arg(target, arg_count);
+            if !is_native {
+                self.string_argument();
             }
+            if is_hash {
+                self.check_hash_arg();
+            }
+            value_types.push(self.get_cur_chunk().get_last_value().convert());
+            arg_count += 1;
+
+            self.parser.consume_separator(TokenType::RIGHT_PAREN);
         }
         self.parser.consume(TokenType::RIGHT_PAREN);
-        self.symbol_to_hold = symbol_to_hold_enclosing;
+        self.parser.pop_context();
+        self.call_targets.pop();
 
-        self.changing_fn = false;
-        if self.parser.symbols[self.symbol_to_hold].name == "print" || 
-           self.parser.symbols[self.symbol_to_hold].name == "println" || 
-           self.parser.symbols[self.symbol_to_hold].name == "input"
+        self.changing_fn = changing_fn_enclosing;
+        if self.parser.symbols[target].name == "print" || 
+           self.parser.symbols[target].name == "println" || 
+           self.parser.symbols[target].name == "input"
         {
-            self.emit_byte(OpCode::IO_FN_CALL(self.symbol_to_hold, arg_count), self.parser.line);
+            self.emit_byte(OpCode::IO_FN_CALL(target, arg_count), self.parser.line);
 
-            if self.parser.symbols[self.symbol_to_hold].name == "input" {
-                self.get_cur_chunk().push_value(Value::Int(0));
+            if self.parser.symbols[target].name == "input" {
+                self.get_cur_chunk().push_value(Value::String(String::new()));
             }else {
                 let pos = self.get_cur_chunk().push_value(Value::Null);
                 self.emit_byte(OpCode::CONSTANT_NULL(pos), self.parser.line);
@@ -1483,49 +3702,236 @@ impl Compiler {
             return
         }
 
-        if arg_count != self.parser.symbols[self.symbol_to_hold].arg_count {
+        if self.parser.symbols[target].symbol_type == TokenType::NATIVE_FN {
+            if let Some(float_name) = NativeFn::get_float_overload(&self.parser.symbols[target].name) {
+                target = self.resolve_overload(target, float_name, &value_types);
+            }
+        }
+
+        if arg_count != self.parser.symbols[target].arg_count {
             errors::error_message("COMPILER ERROR",
-            format!("Expected to find {} arguments but found: {} {}:", self.parser.symbols[self.symbol_to_hold].arg_count, arg_count, self.parser.line));
-            std::process::exit(1);
+            format!("Expected to find {} arguments but found: {} {}:", self.parser.symbols[target].arg_count, arg_count, self.parser.line));
+            errors::compile_error();
+        }
+
+        if self.parser.symbols[target].symbol_type == TokenType::NATIVE_FN {
+            self.emit_byte(OpCode::NATIVE_FN_CALL(target), self.parser.line);
+        }else{
+            self.emit_byte(OpCode::FUNCTION_CALL(target), self.parser.line);
+        }
+
+        if self.host_output(target) {
+            return
+        }
+        self.push_type_value(self.parser.symbols[target].output_type);
+
+        if matches!(self.parser.symbols[target].output_type, TokenType::TUPLE(_)) && !self.tuple_allowed {
+            errors::error_message("COMPILER ERROR", format!("Tuple returned by \"{}\" has to be destructured, e.g. var a, b = {}(...) {}:",
+                self.parser.symbols[target].name,
+                self.parser.symbols[target].name,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+    }
+
+    // Struct returned by host native becomes an instance on the heap, like one returned by FUNCTION_CALL,
+    // list keeps its element type for the declaration it is assigned to
+    fn host_output(&mut self, target: usize) -> bool {
+        let symbol = &self.parser.symbols[target];
+        if symbol.symbol_type != TokenType::NATIVE_FN {
+            return false
+        }
+
+        let list_element = match self.registry.host_fns().iter().find(|host_fn| host_fn.name == symbol.name) {
+            Some(host_fn) => host_fn.list_element,
+            None => return false,
+        };
+
+        match (symbol.output_type, list_element) {
+            (TokenType::STRUCT(pos), _) => {
+                let struct_pos = self.get_struct_symbol_pos(self.parser.symbols[pos].name.clone());
+                self.emit_byte(OpCode::HOST_STRUCT_DEC(target, StructInstance::new(struct_pos)), self.parser.line);
+                self.push_type_value(TokenType::STRUCT(pos));
+            },
+            (_, Some(element_type)) => {
+                let element = match element_type {
+                    TokenType::INT => Value::Int(0),
+                    TokenType::FLOAT => Value::Float(0.0),
+                    TokenType::BOOL => Value::Bool(true),
+                    _ => Value::String(String::new()),
+                };
+                self.get_cur_chunk().push_value(Value::ListObj(vec![element]));
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    // Arguments of overloaded native have to be all INT or all FLOAT
+    fn resolve_overload(&mut self, target: usize, float_name: &str, value_types: &[TokenType]) -> usize {
+        if value_types.iter().all(|value_type| *value_type == TokenType::INT) {
+            return target
+        }
+
+        if value_types.iter().all(|value_type| *value_type == TokenType::FLOAT) {
+            return self.get_fn_symbol_pos(float_name.to_string())
+        }
+
+        errors::error_message("COMPILER ERROR", format!("Cannot resolve {}() for argument types ({}), expected all int or all float {}:",
+            self.parser.symbols[target].name,
+            value_types.iter().map(|value_type| self.type_name(*value_type)).collect::<Vec<String>>().join(", "),
+            self.parser.line,
+        ));
+        errors::compile_error();
+    }
+
+    // Hashing of instances is not defined yet
+    fn check_hash_arg(&mut self) {
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if !matches!(value_type, TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRING) {
+            errors::error_message("COMPILER ERROR", format!("hash() expects int, float, bool or String argument, found: {} {}:", self.type_name(value_type), self.parser.line));
+            errors::compile_error();
+        }
+    }
+
+    // Only primitive args are checked, instances are verified when they are bound in callee
+    pub fn check_arg_type(&mut self, arg_types: &[TokenType], index: usize) {
+        let expected = match arg_types.get(index) {
+            Some(arg_type @ (TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRUCT(_))) => *arg_type,
+            _ => return,
+        };
+
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if value_type != expected {
+            let found = self.operand_name(value_type);
+            errors::error_message("COMPILER ERROR", format!("Expected to find {} as argument type but found: {} {}:",
+                self.type_name(expected),
+                found,
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+    }
+
+    // List<T> argument is the caller's list variable, its elements have to match the parameter
+    fn check_list_arg(&mut self, fn_pos: usize, index: usize) {
+        let expected = match self.parser.fn_list_elements.get(&(self.parser.symbols[fn_pos].name.clone(), index)) {
+            Some(element) => *element,
+            None => return,
+        };
+        let found = match self.get_cur_chunk().get_last_instruction().op {
+            OpCode::INC_RC(pos) => match &self.get_cur_instances()[pos].is_special {
+                SpecialType::List(element) => element.convert(),
+                _ => return,
+            },
+            _ => return,
+        };
+
+        if found != expected {
+            errors::error_message("COMPILER ERROR", format!("Expected to find List<{}> as argument type but found: List<{}> {}:",
+                self.type_name(expected),
+                self.type_name(found),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+    }
+
+    // Struct returned by a call stays on the heap and leaves nothing on the stack,
+    // it is kept as hidden instance, so list element can refer to it by index
+    fn keep_returned_struct(&mut self) -> Option<usize> {
+        let output_type = match self.get_cur_chunk().get_last_instruction().op.clone() {
+            OpCode::FUNCTION_CALL(index) | OpCode::HOST_STRUCT_DEC(index, _) => self.parser.symbols[index].output_type,
+            OpCode::METHOD_CALL(mth) => mth.output_type,
+            _ => return None,
+        };
+        let struct_pos = match output_type {
+            TokenType::STRUCT(pos) => self.get_struct_symbol_pos(self.parser.symbols[pos].name.clone()),
+            _ => return None,
+        };
+
+        let len = self.parser.symbols.len();
+        self.emit_byte(OpCode::INSTANCE_INDEX(len), self.parser.line);
+
+        self.get_cur_instances().push(Local{ name: String::new(), local_type: TokenType::KEYWORD(Keywords::INSTANCE(struct_pos)), is_redirected: false, redirect_pos: 0, rf_index: len, is_special: SpecialType::Null });
+        self.parser.symbols.push(Symbol { name: String::new(), symbol_type: TokenType::KEYWORD(Keywords::INSTANCE(struct_pos)), output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 0 });
+
+        Some(len)
+    }
+
+    // Type as written in source, every type error renders types through it
+    pub fn type_name(&self, value_type: TokenType) -> String {
+        match value_type {
+            TokenType::INT | TokenType::KEYWORD(Keywords::INT) => "int".to_string(),
+            TokenType::FLOAT | TokenType::KEYWORD(Keywords::FLOAT) => "float".to_string(),
+            TokenType::BOOL | TokenType::KEYWORD(Keywords::BOOL) => "bool".to_string(),
+            TokenType::STRING | TokenType::KEYWORD(Keywords::STRING) => "String".to_string(),
+            TokenType::BYTES => "Bytes".to_string(),
+            TokenType::LIST => "List".to_string(),
+            TokenType::NULL | TokenType::KEYWORD(Keywords::NULL) => "null".to_string(),
+            TokenType::STRUCT(pos) | TokenType::KEYWORD(Keywords::INSTANCE(pos)) => self.parser.symbols[pos].name.clone(),
+            TokenType::TUPLE(tuple_pos) => self.tuple_name(&self.parser.tuple_types[tuple_pos]),
+            value_type => value_type.describe(),
+        }
+    }
+
+    // Element types of a tuple as in its declaration, a single value is shown without parentheses
+    fn tuple_name(&self, elements: &[TokenType]) -> String {
+        let names = elements.iter().map(|element| self.type_name(*element)).collect::<Vec<String>>();
+        match names.len() {
+            1 => names[0].clone(),
+            _ => format!("({})", names.join(", ")),
         }
+    }
 
-        if self.parser.symbols[self.symbol_to_hold].symbol_type == TokenType::NATIVE_FN {
-            self.emit_byte(OpCode::NATIVE_FN_CALL(self.symbol_to_hold), self.parser.line);
+    // Instance of struct N is STRUCT(N) in signatures and expression types, KEYWORD(INSTANCE(N)) only in locals
+    fn signature_type(&self, local_type: TokenType) -> TokenType {
+        match local_type {
+            TokenType::KEYWORD(Keywords::INSTANCE(pos)) if self.parser.symbols[pos].name == "String" => TokenType::STRING,
+            TokenType::KEYWORD(Keywords::INSTANCE(pos)) => TokenType::STRUCT(pos),
+            local_type => local_type,
+        }
+    }
 
-            if self.parser.symbols[self.symbol_to_hold].name == "conv" {
+    // Pushes sentinel value of given type, so the expression type can be read back from the last value
+    pub fn push_type_value(&mut self, value_type: TokenType) {
+        match value_type {
+            TokenType::INT => {
                 self.get_cur_chunk().push_value(Value::Int(0));
-            }else if self.parser.symbols[self.symbol_to_hold].name == "convf" {
+            },
+            TokenType::FLOAT => {
                 self.get_cur_chunk().push_value(Value::Float(0.0));
-            }else if self.parser.symbols[self.symbol_to_hold].name == "convstr" {
-                self.get_cur_chunk().push_value(Value::String("".to_string()));
+            },
+            TokenType::BOOL => {
+                self.get_cur_chunk().push_value(Value::Bool(true));
+            },
+            TokenType::NULL | TokenType::KEYWORD(Keywords::NULL) => {
+                self.get_cur_chunk().push_value(Value::Null);
+            },
+            TokenType::STRING => {
+                self.get_cur_chunk().push_value(Value::String(String::new()));
+            },
+            TokenType::BYTES => {
+                self.get_cur_chunk().push_value(Value::Bytes(vec![]));
+            },
+            TokenType::STRUCT(val) => {
+                self.get_cur_chunk().push_value(Value::InstanceRef(val));  
+            },
+            TokenType::TUPLE(tuple_pos) => {
+                let elements = self.parser.tuple_types[tuple_pos].iter().map(|element| match element {
+                    TokenType::INT => Value::Int(0),
+                    TokenType::FLOAT => Value::Float(0.0),
+                    TokenType::BOOL => Value::Bool(true),
+                    _ => Value::String(String::new()),
+                }).collect();
+                self.get_cur_chunk().push_value(Value::Tuple(elements));
+            },
+            output_type => {
+                errors::error_message("COMPILER ERROR", format!("Unexpected output type \"{:?}\" {}:", output_type, self.parser.line));
+                errors::compile_error();
             }
-        }else{
-            self.emit_byte(OpCode::FUNCTION_CALL(self.symbol_to_hold), self.parser.line);
-            match self.parser.symbols[self.symbol_to_hold].output_type {
-                TokenType::INT => {
-                    self.get_cur_chunk().push_value(Value::Int(0));
-                },
-                TokenType::FLOAT => {
-                    self.get_cur_chunk().push_value(Value::Float(0.0));
-                },
-                TokenType::BOOL => {
-                    self.get_cur_chunk().push_value(Value::Bool(true));
-                },
-                TokenType::NULL => {
-                    self.get_cur_chunk().push_value(Value::Null);
-                },
-                TokenType::STRING => {
-                    self.get_cur_chunk().push_value(Value::String(String::new()));
-                },
-                TokenType::STRUCT(val) => {
-                    self.get_cur_chunk().push_value(Value::InstanceRef(val));  
-                },
-                output_type => {
-                    errors::error_message("COMPILER ERROR", format!("Unexpected output type \"{:?}\" {}:", output_type, self.parser.line));
-                    std::process::exit(1);
-                }
-            };
-        }
+        };
     }
 
     pub fn fn_declare(&mut self, is_mth: bool, root_struct_pos: usize) -> Function {
@@ -1533,12 +3939,13 @@ impl Compiler {
 
         if (self.scope_depth != 0 && !is_mth) || (self.scope_depth == 0 && is_mth) {
             errors::error_message("COMPILE ERROR", format!("Function/Method \"{}\" declaration inside bounds {}:", name, self.parser.line));
-            std::process::exit(1)
+            errors::compile_error()
         }
         let mut function = Function::new(name.clone());
 
         self.parser.advance();
 
+        self.parser.push_context(format!("parameter list of '{}'", name));
         self.parser.consume(TokenType::LEFT_PAREN);
 
         while self.parser.cur.token_type != TokenType::RIGHT_PAREN {
@@ -1546,19 +3953,18 @@ impl Compiler {
 
             self.parser.consume(TokenType::IDENTIFIER);
             let arg_name = self.parser.prev.value.iter().collect::<String>();
+            self.check_not_constant(&arg_name);
 
             if arg_name == "self" && is_mth {
                 if function.arg_count != 1 {
                     errors::error_message("COMPILE ERROR", format!("\"self\" keyword need to be first in argument list {}:", self.parser.line));
-                    std::process::exit(1)
+                    errors::compile_error()
                 }
 
                 function.is_self_arg =  true;
                 function.arg_count -= 1;
 
-                if self.parser.cur.token_type == TokenType::COMMA {
-                    self.parser.consume(TokenType::COMMA);
-                }
+                self.parser.consume_separator(TokenType::RIGHT_PAREN);
 
                 function.instances.push(Local { name: "self".to_string(), local_type: TokenType::KEYWORD(Keywords::INSTANCE(root_struct_pos)), is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
 
@@ -1576,30 +3982,47 @@ impl Compiler {
                 TokenType::KEYWORD(keyword) => keyword.convert(),
                 _ => {
                     errors::error_message("COMPILER ERROR", format!("Expected arg type after \":\" {}:", self.parser.line));
-                    std::process::exit(1);
+                    errors::compile_error();
                 }
             };
             self.parser.advance();
-
-            if self.parser.cur.token_type == TokenType::COMMA {
-                self.parser.consume(TokenType::COMMA);
+            function.arg_types.push(self.signature_type(arg_type));
+
+            // List<T> param is the caller's list, passed by reference like a struct
+            let mut list_element = None;
+            if matches!(arg_type, TokenType::KEYWORD(Keywords::INSTANCE(pos)) if self.parser.symbols[pos].name == "List") {
+                self.parser.consume(TokenType::LESS);
+                let list_type = self.list_element_type();
+                list_element = Some(self.list_element_value(list_type));
+                self.parser.consume(TokenType::GREATER);
             }
 
-            match arg_type {
-                TokenType::KEYWORD(Keywords::INSTANCE(pos)) => {
+            self.parser.consume_separator(TokenType::RIGHT_PAREN);
+
+            match (arg_type, list_element) {
+                (_, Some(element)) => {
+                    function.instances.push(Local { name: arg_name, local_type: arg_type , is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::List(element) });
+                },
+                (TokenType::KEYWORD(Keywords::INSTANCE(pos)), None) => {
                     if self.parser.symbols[pos].name == "String" {
                         function.instances.push(Local { name: arg_name, local_type: arg_type , is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::String });
+                    }else if self.parser.symbols[pos].name == "Bytes" {
+                        function.instances.push(Local { name: arg_name, local_type: arg_type , is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Bytes });
                     }else {
                         function.instances.push(Local { name: arg_name, local_type: arg_type , is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
                     }
                 },
                 _ => {
+                    if function.locals.len() >= self.flags.max_locals {
+                        self.too_many_locals(&name, &format!("param \"{}\"", arg_name));
+                    }
                     function.locals.push(Local { name: arg_name, local_type: arg_type , is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
                 },
             };
 
         }
         self.parser.consume(TokenType::RIGHT_PAREN);
+        self.parser.pop_context();
 
         if !is_mth {
             let pos = self.get_fn_symbol_pos(name.clone());        
@@ -1607,6 +4030,11 @@ impl Compiler {
         }
 
         match self.parser.cur.token_type {
+            TokenType::KEYWORD(Keywords::NULL) => {
+                function.output_type = TokenType::NULL;
+
+                self.parser.consume(TokenType::KEYWORD(Keywords::NULL))
+            },
             TokenType::KEYWORD(keyword) => {
                 function.output_type = keyword.convert();
 
@@ -1617,12 +4045,34 @@ impl Compiler {
                         
                 self.parser.consume(TokenType::KEYWORD(keyword))
             },
+            TokenType::LEFT_PAREN => {
+                if is_mth {
+                    errors::error_message("COMPILER ERROR", format!("Method \"{}\" cannot return a tuple, only functions can {}:", name, self.parser.line));
+                    errors::compile_error();
+                }
+
+                let line = self.parser.line;
+                self.parser.consume(TokenType::LEFT_PAREN);
+
+                let mut elements = vec![];
+                while self.parser.cur.token_type != TokenType::RIGHT_PAREN {
+                    elements.push(tuple_element_type(&self.parser.cur));
+                    self.parser.advance();
+
+                    if self.parser.cur.token_type == TokenType::COMMA {
+                        self.parser.consume(TokenType::COMMA);
+                    }
+                }
+                self.parser.consume(TokenType::RIGHT_PAREN);
+
+                function.output_type = TokenType::TUPLE(intern_tuple(&mut self.parser.tuple_types, elements, line));
+            },
             TokenType::IDENTIFIER => {
                 let val = self.parser.cur.value.iter().collect::<String>();
 
                 if !self.structs.contains_key(&val) {
-                    errors::error_message("COMPILER ERROR", format!("Unexpected return type {:?} {}:", self.parser.cur.token_type, self.parser.line));
-                    std::process::exit(1);
+                    errors::error_message("COMPILER ERROR", format!("Unexpected return type {} {}:", self.parser.cur.describe(), self.parser.line));
+                    errors::compile_error();
                 }
                 
                 let pos = self.get_struct_symbol_pos(val); 
@@ -1631,16 +4081,25 @@ impl Compiler {
                 self.parser.consume(TokenType::IDENTIFIER)
             },
             _ => {
+                if self.flags.strict {
+                    errors::strict_error(flags::IMPLICIT_OUTPUT_TYPE, format!("Function \"{}\" has no output type, write \"null\" if it returns nothing {}:", name, self.parser.line));
+                    errors::compile_error();
+                }
                 function.output_type = TokenType::NULL;
             }
         };
         
         if !is_mth {
-            // This if stmt is left, because of tests on reference counting, it should never panic
+            // symbols are declared ahead with the same output type, a mismatch is a compiler bug
             let fn_pos = self.get_fn_symbol_pos(function.name.clone());
             if function.output_type != self.parser.symbols[fn_pos].output_type {
-                println!("{:?} {:?}", function.output_type,self.parser.symbols[fn_pos].symbol_type);
-                panic!()
+                errors::error_message("COMPILER INVARIANT ERROR", format!("Function \"{}\" was declared with output type {:?}, compiled with {:?} {}:",
+                    function.name,
+                    self.parser.symbols[fn_pos].output_type,
+                    function.output_type,
+                    self.parser.line,
+                ));
+                errors::terminate(errors::Termination::Compile);
             }
         }
 
@@ -1648,46 +4107,100 @@ impl Compiler {
 
         self.scope_depth += 1;
 
-        let enclosing = self.cur_function.clone();
-        self.cur_function = function;
+        let params = function.locals.len();
 
-        self.block();
+        let enclosing = std::mem::replace(&mut self.cur_function, function);
 
+        let declared = self.block();
+        // vars of the body keep their slots until RETURN drops the frame, only names go out of scope
+        let body_end = self.get_cur_locals().len().saturating_sub(declared);
+        self.get_cur_locals().truncate(body_end);
+
+        let fallthrough = self.get_cur_chunk().code.len();
         let pos = self.get_cur_chunk().push_value(Value::Null);
         self.emit_byte(OpCode::CONSTANT_NULL(pos), self.parser.line);
 
         self.emit_byte(OpCode::RETURN, self.parser.line);
 
+        // implicit return gives null, which no other output type can hold. After an error the
+        // chunk misses the skipped statements, its returns included
+        if self.cur_function.output_type != TokenType::NULL && self.error_count == 0 && analyzer::is_reachable(&self.cur_function.chunk, fallthrough) {
+            errors::error_message("COMPILING ERROR", format!("Function \"{}\" can reach its end without returning {} {}:",
+                self.cur_function.name,
+                self.type_name(self.cur_function.output_type),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
         for index in 0..self.get_cur_instances().len() {
-            match self.get_cur_instances()[index].local_type.clone() {
-                TokenType::KEYWORD(Keywords::INSTANCE(_)) => {
-                    self.emit_byte(OpCode::DEC_RC(index), self.parser.line);
-                },
-                _ => {},
+            if let TokenType::KEYWORD(Keywords::INSTANCE(_)) = self.get_cur_instances()[index].local_type {
+                self.emit_byte(OpCode::DEC_RC(index), self.parser.line);
             }
         }
 
         self.emit_byte(OpCode::END_OF_FN, self.parser.line);
+        self.set_chunk_file();
+
+        if self.error_count == 0 {
+            if cfg!(debug_assertions) {
+                self.check_stack_effects(params);
+                self.check_locals_popped(params);
+            }
+            self.record_max_stack(params);
+        }
 
+        let function = std::mem::replace(&mut self.cur_function, enclosing);
         if is_mth {
-            let fun = self.cur_function.clone();
-            self.cur_function = enclosing;
             self.scope_depth -= 1;
 
-            return fun
+            return function
         }
 
-        let op_code = OpCode::FUNCTION_DEC(self.cur_function.clone());
+        self.emit_byte(OpCode::FUNCTION_DEC(function), self.parser.line);
+
+        self.scope_depth -= 1;
 
-        self.functions.insert(name, enclosing.clone());
+        Function::new(String::new())
+    }
 
-        self.cur_function = enclosing;
+    // Argument and element expressions leave exactly one value, instances live on the heap instead
+    fn mark_operand(&mut self, start: usize) {
+        let kept = match self.get_cur_chunk().get_last_value().convert() {
+            TokenType::STRUCT(_) | TokenType::STRING | TokenType::LIST => return,
+            _ => 1,
+        };
+        let line = self.parser.line;
+        self.get_cur_chunk().mark_statement(start, kept, line);
+    }
 
-        self.emit_byte(op_code, self.parser.line);
+    // Debug builds only: every statement has to leave the stack as it found it,
+    // apart from the locals it declares.
+    fn check_stack_effects(&mut self, params: usize) {
+        if let Err(msg) = analyzer::check_chunk(&self.cur_function.chunk, params, &self.parser.symbols) {
+            errors::error_message("COMPILER INVARIANT ERROR", format!("Function \"{}\": {}", self.cur_function.name, msg));
+            errors::terminate(errors::Termination::Compile);
+        }
+    }
 
-        self.scope_depth -= 1;
+    // Scopes pop what they declare, a local left over would shift every slot of the next scope
+    fn check_locals_popped(&mut self, params: usize) {
+        let locals = self.get_cur_locals().len();
+        if locals != params {
+            errors::error_message("COMPILER INVARIANT ERROR", format!("Function \"{}\" ends with {} locals, expected its {} params {}:",
+                self.cur_function.name,
+                locals,
+                params,
+                self.parser.line,
+            ));
+            errors::terminate(errors::Termination::Compile);
+        }
+    }
 
-        Function::new(String::new())
+    // Frames of the function are preallocated with it, the chunk is known to be consistent here
+    fn record_max_stack(&mut self, params: usize) {
+        let max_stack = analyzer::max_stack(&self.cur_function.chunk, params, &self.parser.symbols).unwrap_or_default();
+        self.get_cur_chunk().max_stack = max_stack;
     }
 
     pub fn declare(&mut self) {
@@ -1703,15 +4216,20 @@ impl Compiler {
     }
 
     pub fn return_stmt(&mut self) {
+        if let TokenType::TUPLE(tuple_pos) = self.cur_function.output_type {
+            self.tuple_return(tuple_pos);
+            return
+        }
+
         self.expression();
         
         let var_type = match self.get_cur_chunk().get_last_instruction().op {
             OpCode::VAR_CALL(index) => {           
                 self.get_cur_locals()[index].local_type
             },
-            OpCode::GET_INSTANCE_RF(index) => {
+            OpCode::GET_INSTANCE_RF(index) | OpCode::GET_INSTANCE_W_OFFSET_RF(index) => {
                 self.emit_byte(OpCode::INC_RC(index), self.parser.line);
-                self.emit_byte(OpCode::GET_INSTANCE_RF(index), self.parser.line);
+                self.emit_byte(OpCode::GET_INSTANCE_W_OFFSET_RF(index), self.parser.line);
                 self.emit_byte(OpCode::POP, self.parser.line);
                 self.get_cur_chunk().get_last_value().convert()
             }
@@ -1721,114 +4239,183 @@ impl Compiler {
         };
 
         if var_type != self.cur_function.output_type {
-            errors::error_message("COMPILING ERROR", format!("Mismatched types while returning function, expected: {:?} found: {:?} {}:",
-                self.cur_function.output_type,
-                var_type,
+            let found = self.operand_name(var_type);
+            errors::error_message("COMPILING ERROR", format!("Mismatched types while returning function, expected: {} found: {} {}:",
+                self.type_name(self.cur_function.output_type),
+                found,
                 self.parser.line,
             ));
-            std::process::exit(1);
+            errors::compile_error();
         }
 
         self.emit_byte(OpCode::RETURN, self.parser.line);
     }
 
-    pub fn if_stmt(&mut self) {
-        if self.parser.cur.token_type == TokenType::LEFT_BRACE {
+    // return (a, b) packs values into one tuple, return f() passes tuple of the same shape
+    fn tuple_return(&mut self, tuple_pos: usize) {
+        let expected = self.parser.tuple_types[tuple_pos].clone();
+
+        let mut found = vec![];
+        if self.parser.cur.token_type == TokenType::LEFT_PAREN {
+            self.parser.consume(TokenType::LEFT_PAREN);
+            while self.parser.cur.token_type != TokenType::RIGHT_PAREN {
+                self.expression();
+                found.push(self.get_cur_chunk().get_last_value().convert());
+
+                if self.parser.cur.token_type == TokenType::COMMA {
+                    self.parser.consume(TokenType::COMMA);
+                }
+            }
+            self.parser.consume(TokenType::RIGHT_PAREN);
+
+            if found == expected {
+                self.emit_byte(OpCode::TUPLE_PACK(found.len()), self.parser.line);
+            }
+        }else {
+            self.tuple_allowed = true;
+            self.expression();
+            self.tuple_allowed = false;
+
+            found = match self.get_cur_chunk().get_last_value() {
+                Value::Tuple(elements) => elements.iter().map(|element| element.convert()).collect(),
+                value => vec![value.convert()],
+            };
+        }
+
+        if found != expected {
+            errors::error_message("COMPILING ERROR", format!("Mismatched types while returning function, expected: {} found: {} {}:",
+                self.tuple_name(&expected),
+                self.tuple_name(&found),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+
+        self.emit_byte(OpCode::RETURN, self.parser.line);
+    }
+
+    // Condition of if/elif/while/do-while, keyword has to be followed by an expression
+    pub fn condition(&mut self) {
+        if matches!(self.parser.cur.token_type, TokenType::LEFT_BRACE | TokenType::RIGHT_BRACE | TokenType::EOF) {
             errors::error_message("COMPILING ERROR", format!("Expected to find expression after {} statement {}:",
                 self.parser.prev.value.iter().collect::<String>().to_ascii_uppercase(),
                 self.parser.line,
             ));
-            std::process::exit(1);
+            errors::compile_error();
         }
-        
 
         self.expression();
 
-        if self.parser.symbols.len() > 1 && 
-        self.parser.symbols[self.symbol_to_hold].symbol_type == TokenType::KEYWORD(Keywords::FN) &&
-        self.parser.symbols[self.symbol_to_hold].output_type != TokenType::BOOL {
-            errors::error_message("COMPILING ERROR", format!("Expected to find BOOL but found {:?} {}:",
-                self.parser.symbols[self.symbol_to_hold].output_type,
+        let cond_call = match self.get_cur_chunk().get_last_instruction().op {
+            OpCode::FUNCTION_CALL(index) => Some(index),
+            _ => None,
+        };
+        if let Some(index) = cond_call.filter(|index| {
+            self.parser.symbols[*index].symbol_type == TokenType::KEYWORD(Keywords::FN) &&
+            self.parser.symbols[*index].output_type != TokenType::BOOL
+        }) {
+            errors::error_message("COMPILING ERROR", format!("Expected to find bool but found {} {}:",
+                self.type_name(self.parser.symbols[index].output_type),
                 self.parser.line,
             ));
-            std::process::exit(1);
+            errors::compile_error();
         }
+    }
+
+    pub fn if_stmt(&mut self) {
+        self.condition();
 
         let index_jump_to_stmt = self.get_cur_chunk().code.len();
         self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
 
         self.emit_byte(OpCode::POP, self.parser.line);
 
+        self.scoped_block();
+
+        let index_exit_if = self.get_cur_chunk().code.len();
+        self.emit_byte(OpCode::JUMP(0), self.parser.line);
+
+        let offset_stmt = (self.get_cur_chunk().code.len() - index_jump_to_stmt) - 1;
+        self.get_cur_chunk().code[index_jump_to_stmt] = Instruction { op: OpCode::IF_STMT_OFFSET(offset_stmt), line: self.parser.line };
+
+        self.emit_byte(OpCode::POP, self.parser.line);
+
+        if self.parser.cur.token_type == TokenType::KEYWORD(Keywords::ELIF) || self.parser.cur.token_type == TokenType::KEYWORD(Keywords::ELSE) {
+            self.compile_line();
+        }
+
+        let offset_exit_if = (self.get_cur_chunk().code.len() - index_exit_if) - 1; 
+        self.get_cur_chunk().code[index_exit_if] = Instruction { op: OpCode::JUMP(offset_exit_if), line: self.parser.line };
+    }
+
+    // else locals are dropped at its closing brace like in the if branch, break/continue inside
+    // and code after the statement see only the locals still in scope
+    pub fn else_stmt(&mut self) {
+        self.scoped_block();
+    }
+
+    pub fn while_stmt(&mut self) {
+        let loop_start_index = self.get_cur_chunk().code.len();
+
+        self.condition();
+
+        let index_exit_stmt = self.get_cur_chunk().code.len();
+        self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
+        self.emit_byte(OpCode::POP, self.parser.line);
+
         self.parser.consume(TokenType::LEFT_BRACE);
 
         let local_counter = self.get_cur_locals().len();
         let instance_counter = self.get_cur_instances().len();
+        self.scope_depth += 1;
+
+        self.loop_info.loop_type = TokenType::KEYWORD(Keywords::WHILE);
+        self.loop_info.locals_start = local_counter;
+        self.loop_info.instance_start = instance_counter;
+        self.loop_info.start = loop_start_index;
 
         self.block();
 
+        self.loop_info.loop_type = TokenType::KEYWORD(Keywords::WHILE);
+        self.loop_info.locals_start = local_counter;
+        self.loop_info.instance_start = instance_counter;
+        self.loop_info.start = loop_start_index;
+        self.scope_depth -= 1;
+
         for _ in 0..self.get_cur_locals().len() - local_counter {
             self.emit_byte(OpCode::POP, self.parser.line);
             self.get_cur_locals().pop();
         }
 
-        for index in (0..self.get_cur_instances().len() - instance_counter).rev() {
-            match self.get_cur_instances()[index].local_type.clone() {
-                TokenType::KEYWORD(Keywords::INSTANCE(_)) => {
-                    self.emit_byte(OpCode::DEC_TO(instance_counter), self.parser.line);
-                    self.get_cur_instances().pop();
-                },
-                _ => {},
+        for index in (0..self.get_cur_instances().len() - self.loop_info.instance_start).rev() {
+            if let TokenType::KEYWORD(Keywords::INSTANCE(_)) = self.get_cur_instances()[index].local_type {
+                self.get_cur_instances().pop();
             }
         }
-        self.emit_byte(OpCode::RF_REMOVE, self.parser.line);
 
-        let index_exit_if = self.get_cur_chunk().code.len();
-        self.emit_byte(OpCode::JUMP(0), self.parser.line);
+        self.emit_byte(OpCode::DEC_TO(self.loop_info.instance_start), self.parser.line);
 
-        let offset_stmt = (self.get_cur_chunk().code.len() - index_jump_to_stmt) - 1;
-        self.get_cur_chunk().code[index_jump_to_stmt] = Instruction { op: OpCode::IF_STMT_OFFSET(offset_stmt), line: self.parser.line };
+        self.emit_byte(OpCode::RF_REMOVE, self.parser.line);
 
-        self.emit_byte(OpCode::POP, self.parser.line);
+        let offset_loop = (self.get_cur_chunk().code.len() - loop_start_index) + 1;
+        self.emit_byte(OpCode::LOOP(offset_loop), self.parser.line);
 
-        if self.parser.cur.token_type == TokenType::KEYWORD(Keywords::ELIF) || self.parser.cur.token_type == TokenType::KEYWORD(Keywords::ELSE) {
-            self.compile_line();
-        }
+        let offset_stmt = (self.get_cur_chunk().code.len() - index_exit_stmt) - 1;
+        self.get_cur_chunk().code[index_exit_stmt] = Instruction { op: OpCode::IF_STMT_OFFSET(offset_stmt), line: self.parser.line };
 
-        let offset_exit_if = (self.get_cur_chunk().code.len() - index_exit_if) - 1; 
-        self.get_cur_chunk().code[index_exit_if] = Instruction { op: OpCode::JUMP(offset_exit_if), line: self.parser.line };
+        self.emit_byte(OpCode::POP, self.parser.line);
     }
 
-    pub fn else_stmt(&mut self) {
-        self.parser.consume(TokenType::LEFT_BRACE);
-        self.block();
-    }
+    // Body runs before the condition is tested. Condition is compiled after the body, so loop start
+    // (target of break/continue) is a jump forward to it, skipped when entering the loop:
+    //   JUMP(1), start: JUMP(cond), body: block, cond: condition, IF_STMT_OFFSET(exit), POP, LOOP(body), exit: POP
+    pub fn do_while_stmt(&mut self) {
+        self.emit_byte(OpCode::JUMP(1), self.parser.line);
 
-    pub fn while_stmt(&mut self) {
         let loop_start_index = self.get_cur_chunk().code.len();
+        self.emit_byte(OpCode::JUMP(0), self.parser.line);
 
-        if self.parser.cur.token_type == TokenType::LEFT_BRACE {
-            errors::error_message("COMPILING ERROR", format!("Expected to find expression after {} statement {}:",
-                self.parser.prev.value.iter().collect::<String>().to_ascii_uppercase(),
-                self.parser.line,
-            ));
-            std::process::exit(1);
-        }
-
-        self.expression();
-
-        if self.parser.symbols.len() > 1 && 
-        self.parser.symbols[self.symbol_to_hold].symbol_type == TokenType::KEYWORD(Keywords::FN) &&
-        self.parser.symbols[self.symbol_to_hold].output_type != TokenType::BOOL {
-            errors::error_message("COMPILING ERROR", format!("Expected to find BOOL but found {:?} {}:",
-                self.parser.symbols[self.symbol_to_hold].output_type,
-                self.parser.line,
-            ));
-            std::process::exit(1);
-        };
-
-        let index_exit_stmt = self.get_cur_chunk().code.len();
-        self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
-        self.emit_byte(OpCode::POP, self.parser.line);
+        let body_start_index = self.get_cur_chunk().code.len();
 
         self.parser.consume(TokenType::LEFT_BRACE);
 
@@ -1855,11 +4442,8 @@ impl Compiler {
         }
 
         for index in (0..self.get_cur_instances().len() - self.loop_info.instance_start).rev() {
-            match self.get_cur_instances()[index].local_type.clone() {
-                TokenType::KEYWORD(Keywords::INSTANCE(_)) => {
-                    self.get_cur_instances().pop();
-                },
-                _ => {},
+            if let TokenType::KEYWORD(Keywords::INSTANCE(_)) = self.get_cur_instances()[index].local_type {
+                self.get_cur_instances().pop();
             }
         }
 
@@ -1867,7 +4451,17 @@ impl Compiler {
 
         self.emit_byte(OpCode::RF_REMOVE, self.parser.line);
 
-        let offset_loop = (self.get_cur_chunk().code.len() - loop_start_index) + 1;
+        let offset_cond = self.get_cur_chunk().code.len() - loop_start_index - 1;
+        self.get_cur_chunk().code[loop_start_index] = Instruction { op: OpCode::JUMP(offset_cond), line: self.parser.line };
+
+        self.parser.consume(TokenType::KEYWORD(Keywords::WHILE));
+        self.condition();
+
+        let index_exit_stmt = self.get_cur_chunk().code.len();
+        self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
+        self.emit_byte(OpCode::POP, self.parser.line);
+
+        let offset_loop = (self.get_cur_chunk().code.len() - body_start_index) + 1;
         self.emit_byte(OpCode::LOOP(offset_loop), self.parser.line);
 
         let offset_stmt = (self.get_cur_chunk().code.len() - index_exit_stmt) - 1;
@@ -1876,11 +4470,133 @@ impl Compiler {
         self.emit_byte(OpCode::POP, self.parser.line);
     }
 
+    // Block with its own locals, cleaned up the same way as in if statement
+    fn scoped_block(&mut self) {
+        self.parser.consume(TokenType::LEFT_BRACE);
+
+        let local_counter = self.get_cur_locals().len();
+        let instance_counter = self.get_cur_instances().len();
+
+        self.block();
+
+        for _ in 0..self.get_cur_locals().len() - local_counter {
+            self.emit_byte(OpCode::POP, self.parser.line);
+            self.get_cur_locals().pop();
+        }
+
+        for index in (0..self.get_cur_instances().len() - instance_counter).rev() {
+            if let TokenType::KEYWORD(Keywords::INSTANCE(_)) = self.get_cur_instances()[index].local_type {
+                self.emit_byte(OpCode::DEC_TO(instance_counter), self.parser.line);
+                self.get_cur_instances().pop();
+            }
+        }
+        self.emit_byte(OpCode::RF_REMOVE, self.parser.line);
+    }
+
+    // Scrutinee is kept in hidden local, every case compares it with its label and jumps to the end after its block
+    pub fn switch_stmt(&mut self) {
+        if matches!(self.parser.cur.token_type, TokenType::LEFT_BRACE | TokenType::RIGHT_BRACE | TokenType::EOF) {
+            errors::error_message("COMPILING ERROR", format!("Expected to find expression after SWITCH statement {}:", self.parser.line));
+            errors::compile_error();
+        }
+
+        self.expression();
+
+        let switch_type = self.get_cur_chunk().get_last_value().convert();
+        if switch_type != TokenType::INT && switch_type != TokenType::STRING {
+            errors::error_message("COMPILING ERROR", format!("Switch expects int or String value, found: {} {}:", self.type_name(switch_type), self.parser.line));
+            errors::compile_error();
+        }
+
+        let switch_pos = self.get_cur_locals().len();
+        self.push_local(Local { name: String::new(), local_type: switch_type, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null }, "switch value");
+
+        self.parser.consume(TokenType::LEFT_BRACE);
+
+        let mut labels: Vec<String> = vec![];
+        let mut exit_jumps: Vec<usize> = vec![];
+        while self.parser.cur.token_type == TokenType::KEYWORD(Keywords::CASE) {
+            self.parser.advance();
+
+            let is_negative = switch_type == TokenType::INT && self.parser.cur.token_type == TokenType::MINUS;
+            if is_negative {
+                self.parser.advance();
+            }
+
+            if self.parser.cur.token_type != switch_type {
+                errors::error_message("COMPILING ERROR", format!("Case label has to be {} literal, found: {} {}:", self.type_name(switch_type), self.parser.cur.describe(), self.parser.line));
+                errors::compile_error();
+            }
+            self.parser.advance();
+
+            let mut label = self.parser.prev.value.iter().collect::<String>();
+            if is_negative {
+                label.insert(0, '-');
+            }
+
+            if labels.contains(&label) {
+                errors::error_message("COMPILING ERROR", format!("Duplicate case label {:?} {}:", label, self.parser.line));
+                errors::compile_error();
+            }
+
+            self.emit_byte(OpCode::VAR_CALL(switch_pos), self.parser.line);
+            if switch_type == TokenType::INT {
+                let value: i64 = match label.parse() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        errors::conversion_error("Vec<char>", "i64");
+                        errors::compile_error();
+                    },
+                };
+                let pos = self.get_cur_chunk().push_value(Value::Int(value));
+                self.emit_byte(OpCode::CONSTANT_INT(pos), self.parser.line);
+                self.emit_byte(OpCode::EQ_INT, self.parser.line);
+            }else {
+                // label is compared by value, so it doesn't need a heap instance
+                self.emit_byte(OpCode::PUSH_STACK(Value::String(label.clone())), self.parser.line);
+                self.emit_byte(OpCode::EQ_STRING, self.parser.line);
+            }
+            labels.push(label);
+
+            let index_next_case = self.get_cur_chunk().code.len();
+            self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
+            self.emit_byte(OpCode::POP, self.parser.line);
+
+            self.scoped_block();
+
+            exit_jumps.push(self.get_cur_chunk().code.len());
+            self.emit_byte(OpCode::JUMP(0), self.parser.line);
+
+            let offset_next_case = (self.get_cur_chunk().code.len() - index_next_case) - 1;
+            self.get_cur_chunk().code[index_next_case] = Instruction { op: OpCode::IF_STMT_OFFSET(offset_next_case), line: self.parser.line };
+
+            self.emit_byte(OpCode::POP, self.parser.line);
+        }
+
+        if self.parser.cur.token_type == TokenType::KEYWORD(Keywords::ELSE) {
+            self.parser.advance();
+            self.scoped_block();
+        }
+
+        self.parser.consume(TokenType::RIGHT_BRACE);
+
+        for index in exit_jumps {
+            let offset = (self.get_cur_chunk().code.len() - index) - 1;
+            self.get_cur_chunk().code[index] = Instruction { op: OpCode::JUMP(offset), line: self.parser.line };
+        }
+
+        self.emit_byte(OpCode::POP, self.parser.line);
+        self.get_cur_locals().pop();
+    }
+
     pub fn for_stmt(&mut self) {
         self.parser.consume(TokenType::IDENTIFIER);
 
         let identifier = self.parser.prev.value.iter().collect::<String>();
-        self.get_cur_locals().push(Local { name: identifier, local_type: TokenType::INT, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
+        self.check_not_constant(&identifier);
+        self.parser.push_context(format!("range of for loop over '{}'", identifier));
+        let declaration = format!("for loop over \"{}\"", identifier);
+        self.push_local(Local { name: identifier, local_type: TokenType::INT, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null }, &declaration);
 
         self.parser.consume(TokenType::KEYWORD(Keywords::IN));
 
@@ -1893,30 +4609,28 @@ impl Compiler {
 
         self.expression();
 
-        self.get_cur_locals().push(Local { name: "".to_string(), local_type: TokenType::INT, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
+        self.push_local(Local { name: "".to_string(), local_type: TokenType::INT, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null }, &declaration);
 
         if self.parser.cur.token_type != TokenType::RIGHT_PAREN {
             self.parser.consume(TokenType::COMMA);
 
             self.expression();
 
-            match self.get_cur_chunk().get_last_instruction().op {
-                OpCode::FUNCTION_CALL(_) => {
-                    errors::error_message("COMPILING ERROR", format!("Functions cannot be used as STEP BY argument {}:",
-                        self.parser.line,
-                    ));
-                    std::process::exit(1);
-                },
-                _ => {},
+            if let OpCode::FUNCTION_CALL(_) = self.get_cur_chunk().get_last_instruction().op {
+                errors::error_message("COMPILING ERROR", format!("Functions cannot be used as STEP BY argument {}:",
+                    self.parser.line,
+                ));
+                errors::compile_error();
             }
         }else {
             let pos = self.get_cur_chunk().push_value(Value::Int(1));
             self.emit_byte(OpCode::CONSTANT_INT(pos), self.parser.line);
         }
 
-        self.get_cur_locals().push(Local { name: "".to_string(), local_type: TokenType::INT, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
+        self.push_local(Local { name: "".to_string(), local_type: TokenType::INT, is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null }, &declaration);
 
         self.parser.consume(TokenType::RIGHT_PAREN);
+        self.parser.pop_context();
 
         let loop_start_index = self.get_cur_chunk().code.len();
 
@@ -1933,6 +4647,11 @@ impl Compiler {
         self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
         self.emit_byte(OpCode::POP, self.parser.line);
 
+        // first iteration skips the step, later ones enter the body through FOR_LOOP
+        self.emit_byte(OpCode::JUMP(1), self.parser.line);
+        let step_index = self.get_cur_chunk().code.len();
+        self.emit_byte(OpCode::FOR_LOOP(len_locals - 3, len_locals - 2, len_locals - 1, 0), self.parser.line);
+
         self.parser.consume(TokenType::LEFT_BRACE);
 
         let local_counter = self.get_cur_locals().len();
@@ -1943,36 +4662,25 @@ impl Compiler {
         self.loop_info.locals_start = local_counter;
         self.loop_info.instance_start = instance_counter;
         self.loop_info.start = loop_start_index;
+        self.loop_info.step_start = step_index;
 
         self.block();
 
         self.loop_info.loop_type = TokenType::KEYWORD(Keywords::FOR);
         self.loop_info.start = loop_start_index;
+        self.loop_info.step_start = step_index;
         self.loop_info.locals_start = local_counter;
         self.loop_info.instance_start = instance_counter;
         self.scope_depth -= 1;
 
-        // adding
-        self.emit_byte(OpCode::VAR_CALL(len_locals - 3), self.parser.line);
-
-        self.emit_byte(OpCode::VAR_CALL(len_locals - 1), self.parser.line);
-
-        self.emit_byte(OpCode::ADD_INT, self.parser.line);
-
-        self.emit_byte(OpCode::VAR_SET(len_locals - 3), self.parser.line);
-        //
-
-        for _ in (0..self.get_cur_locals().len() - local_counter + 1).rev() {
+        for _ in 0..self.get_cur_locals().len() - local_counter {
             self.emit_byte(OpCode::POP, self.parser.line);
             self.get_cur_locals().pop();
         }
 
         for index in (0..self.get_cur_instances().len() - self.loop_info.instance_start).rev() {
-            match self.get_cur_instances()[index].local_type.clone() {
-                TokenType::KEYWORD(Keywords::INSTANCE(_)) => {
-                    self.get_cur_instances().pop();
-                },
-                _ => {},
+            if let TokenType::KEYWORD(Keywords::INSTANCE(_)) = self.get_cur_instances()[index].local_type {
+                self.get_cur_instances().pop();
             }
         }
 
@@ -1980,7 +4688,7 @@ impl Compiler {
 
         self.emit_byte(OpCode::RF_REMOVE, self.parser.line);
 
-        let offset_loop = (self.get_cur_chunk().code.len() - loop_start_index) + 1;
+        let offset_loop = (self.get_cur_chunk().code.len() - step_index) + 1;
         self.emit_byte(OpCode::LOOP(offset_loop), self.parser.line);
 
         let offset_stmt = (self.get_cur_chunk().code.len() - index_exit_stmt) - 1;
@@ -1988,35 +4696,48 @@ impl Compiler {
 
         self.emit_byte(OpCode::POP, self.parser.line);
 
-        for _ in 0..2 {
+        // FOR_LOOP exits past the POP above, its condition is not on the stack
+        let offset_step = (self.get_cur_chunk().code.len() - step_index) - 1;
+        self.get_cur_chunk().code[step_index] = Instruction { op: OpCode::FOR_LOOP(len_locals - 3, len_locals - 2, len_locals - 1, offset_step), line: self.parser.line };
+
+        for _ in 0..3 {
             self.emit_byte(OpCode::POP, self.parser.line);
             self.get_cur_locals().pop();
         }
-        self.emit_byte(OpCode::POP, self.parser.line);
     }
 
     pub fn and_op(&mut self) {
+        self.logical_operand("and");
+
         let index = self.get_cur_chunk().code.len();
-        self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
+        self.emit_byte(OpCode::JUMP_IF_FALSE(0), self.parser.line);
 
         if self.parser.cur.token_type == TokenType::LEFT_BRACE {
             errors::error_message("COMPILING ERROR", format!("Expected to find expression after {} statement {}:",
                 self.parser.prev.value.iter().collect::<String>().to_ascii_uppercase(),
                 self.parser.line,
             ));
-            std::process::exit(1);
+            errors::compile_error();
         };
         self.emit_byte(OpCode::POP, self.parser.line);
+        let operand_start = self.get_cur_chunk().code.len();
         self.parse(Precedence::AND);
-
-        let offset = (self.get_cur_chunk().code.len() - index) - 1;
-        self.get_cur_chunk().code[index] = Instruction { op: OpCode::IF_STMT_OFFSET(offset), line: self.parser.line };
+        self.logical_operand("and");
+        let line = self.parser.line;
+        self.get_cur_chunk().mark_statement(operand_start, 1, line);
+
+        self.patch_jump(index, OpCode::JUMP_IF_FALSE);
+        // left operand stays on the stack on both paths
+        self.get_cur_chunk().mark_statement(index, 0, line);
+        self.get_cur_chunk().push_value(Value::Bool(true));
     }
 
     pub fn or_op(&mut self) {
+        self.logical_operand("or");
+
         let index = self.get_cur_chunk().code.len();
 
-        self.emit_byte(OpCode::IF_STMT_OFFSET(0), self.parser.line);
+        self.emit_byte(OpCode::JUMP_IF_FALSE(0), self.parser.line);
 
         let index_or = self.get_cur_chunk().code.len();
         self.emit_byte(OpCode::JUMP(0), self.parser.line);
@@ -2026,20 +4747,52 @@ impl Compiler {
                 self.parser.prev.value.iter().collect::<String>().to_ascii_uppercase(),
                 self.parser.line,
             ));
-            std::process::exit(1);
+            errors::compile_error();
         };
-        let offset = (self.get_cur_chunk().code.len() - index) - 1;
-        self.get_cur_chunk().code[index] = Instruction { op: OpCode::IF_STMT_OFFSET(offset), line: self.parser.line };
+        self.patch_jump(index, OpCode::JUMP_IF_FALSE);
 
         self.emit_byte(OpCode::POP, self.parser.line);
 
+        let operand_start = self.get_cur_chunk().code.len();
         self.parse(Precedence::OR);
+        self.logical_operand("or");
+        let line = self.parser.line;
+        self.get_cur_chunk().mark_statement(operand_start, 1, line);
+
+        self.patch_jump(index_or, OpCode::JUMP);
+        self.get_cur_chunk().mark_statement(index, 0, line);
+        self.get_cur_chunk().push_value(Value::Bool(true));
+    }
+
+    // Both operands of and/or are bool, so the value left by either path is a Bool
+    fn logical_operand(&mut self, operator: &str) {
+        let operand_type = self.get_cur_chunk().get_last_value().convert();
+        if operand_type != TokenType::BOOL {
+            errors::error_message("COMPILING ERROR", format!("Operator '{}' expects bool operands, found: {} {}:",
+                operator,
+                self.operand_name(operand_type),
+                self.parser.line,
+            ));
+            errors::compile_error();
+        }
+    }
 
-        let offset = (self.get_cur_chunk().code.len() - index_or) - 1;
-        self.get_cur_chunk().code[index_or] = Instruction { op: OpCode::JUMP(offset), line: self.parser.line };
+    // Jump offsets are counted from the jump instruction itself to the current end of chunk,
+    // so code emitted before the jump (other arguments, type sentinels) does not shift them
+    fn patch_jump(&mut self, index: usize, jump: fn(usize) -> OpCode) {
+        let offset = (self.get_cur_chunk().code.len() - index) - 1;
+        self.get_cur_chunk().code[index] = Instruction { op: jump(offset), line: self.parser.line };
     }
 
     fn compile_line(&mut self) {
+        if self.scope_depth == 0 && !matches!(self.parser.cur.token_type, TokenType::KEYWORD(Keywords::FN) | TokenType::KEYWORD(Keywords::STRUCT) | TokenType::KEYWORD(Keywords::METHODS)) {
+            errors::error_message("COMPILER ERROR", format!("Only function, struct and methods declarations are allowed at file scope (found a statement starting with '{}') {}:",
+                self.parser.cur.value.iter().collect::<String>(),
+                self.parser.cur.line,
+            ));
+            errors::compile_error();
+        }
+
         match self.parser.cur.token_type {
             TokenType::KEYWORD(Keywords::FN) | TokenType::KEYWORD(Keywords::VAR) | TokenType::KEYWORD(Keywords::LIST) => {
                 self.parser.advance();
@@ -2057,6 +4810,10 @@ impl Compiler {
                 self.parser.advance();
                 self.struct_declare();
             },
+            TokenType::KEYWORD(Keywords::METHODS) if self.scope_depth == 0 => {
+                self.parser.advance();
+                self.methods_declare();
+            },
             TokenType::KEYWORD(Keywords::IF) => {
                 self.parser.advance();
                 self.if_stmt();
@@ -2064,7 +4821,7 @@ impl Compiler {
             TokenType::KEYWORD(Keywords::ELIF) => {
                 if self.parser.prev.token_type != TokenType::RIGHT_BRACE {
                     error_message("COMPILER ERROR", format!("Expected to find }} before ELIF statment {}:", self.parser.line));
-                    std::process::exit(1);
+                    errors::compile_error();
                 }
                 self.parser.advance();
                 self.if_stmt();
@@ -2072,7 +4829,7 @@ impl Compiler {
             TokenType::KEYWORD(Keywords::ELSE) => {
                 if self.parser.prev.token_type != TokenType::RIGHT_BRACE {
                     error_message("COMPILER ERROR", format!("Expected to find }} before ELSE statment {}:", self.parser.line));
-                    std::process::exit(1);
+                    errors::compile_error();
                 }
                 self.parser.advance();
                 self.else_stmt();
@@ -2085,6 +4842,14 @@ impl Compiler {
                 self.parser.advance();
                 self.for_stmt();
             },
+            TokenType::KEYWORD(Keywords::DO) => {
+                self.parser.advance();
+                self.do_while_stmt();
+            },
+            TokenType::KEYWORD(Keywords::SWITCH) => {
+                self.parser.advance();
+                self.switch_stmt();
+            },
             TokenType::KEYWORD(Keywords::BREAK) => {
                 self.parser.advance();
 
@@ -2092,9 +4857,10 @@ impl Compiler {
                     errors::error_message("COMPILING ERROR", format!("BREAK statment used out of loop {}:",
                         self.parser.line,
                     ));
-                    std::process::exit(1);
+                    errors::compile_error();
                 };
 
+                self.pop_loop_locals();
                 self.emit_byte(OpCode::BREAK, self.parser.line);
 
                 let offset = (self.get_cur_chunk().code.len() - self.loop_info.start) + 1;
@@ -2107,121 +4873,270 @@ impl Compiler {
                     errors::error_message("COMPILING ERROR", format!("CONTINUE statment used out of loop {}:",
                         self.parser.line,
                     ));
-                    std::process::exit(1);
+                    errors::compile_error();
                 };
 
+                self.pop_loop_locals();
+
                 if self.loop_info.loop_type == TokenType::KEYWORD(Keywords::WHILE) {
                     let offset = (self.get_cur_chunk().code.len() - self.loop_info.start) + 1;
-                    self.emit_byte(OpCode::DEC_TO(self.loop_info.instance_start), self.parser.line);
-                    self.emit_byte(OpCode::RF_REMOVE, self.parser.line);
                     self.emit_byte(OpCode::LOOP(offset), self.parser.line);
 
                     return
                 }
 
-                self.emit_byte(OpCode::VAR_CALL(self.loop_info.locals_start - 3), self.parser.line);
-
-                self.emit_byte(OpCode::VAR_CALL(self.loop_info.locals_start - 1), self.parser.line);
-        
-                self.emit_byte(OpCode::ADD_INT, self.parser.line);
-        
-                self.emit_byte(OpCode::VAR_SET(self.loop_info.locals_start - 3), self.parser.line);
-
-                let offset = (self.get_cur_chunk().code.len() - self.loop_info.start) + 1;
+                let offset = (self.get_cur_chunk().code.len() - self.loop_info.step_start) + 1;
                 self.emit_byte(OpCode::LOOP(offset), self.parser.line);
             },
+            TokenType::KEYWORD(Keywords::DISCARD) => {
+                self.parser.advance();
+                self.expression();
+
+                if self.expression_left_value() {
+                    self.emit_byte(OpCode::POP, self.parser.line);
+                }
+            },
             _ => {
                 self.expression();
-                self.emit_byte(OpCode::POP, self.parser.line);
+                self.check_unused_result();
+
+                if self.expression_left_value() {
+                    self.emit_byte(OpCode::POP, self.parser.line);
+                }
             },
         }
     }
 
+    // Rule violations of strict mode, reported as errors with --strict and hidden with --quiet
+    fn warning(&self, code: &str, msg: String) {
+        if self.flags.strict {
+            errors::strict_error(code, msg);
+            errors::compile_error();
+        }
+        if !self.flags.quiet {
+            errors::warning(code, msg);
+        }
+    }
+
+    // convf(a / b) of ints converts the already truncated quotient
+    fn check_truncated_division(&mut self) {
+        if self.get_cur_chunk().get_last_instruction().op != OpCode::DIV_INT {
+            return
+        }
+
+        let msg = format!("Integer division truncates before conversion to float; convert the operands instead (convf(a) / convf(b)) {}:", self.parser.line);
+        self.warning(flags::TRUNCATED_DIVISION, msg);
+    }
+
+    // Result of a call has to be used or explicitly dropped with "discard",
+    // warning in normal mode and error in strict mode. IO natives are not checked
+    fn check_unused_result(&mut self) {
+        let name = match self.get_cur_chunk().get_last_instruction().op.clone() {
+            OpCode::FUNCTION_CALL(index) | OpCode::NATIVE_FN_CALL(index) => self.parser.symbols[index].name.clone(),
+            OpCode::METHOD_CALL(mth) => mth.name.clone(),
+            _ => return,
+        };
+
+        let value_type = self.get_cur_chunk().get_last_value().convert();
+        if value_type == TokenType::NULL {
+            return
+        }
+
+        let msg = format!("Result of '{}' of type {} is ignored, assign it or drop it with \"discard\" {}:", name, self.type_name(value_type), self.parser.line);
+        self.warning(flags::UNUSED_RESULT, msg);
+    }
+
+    // break/continue jump back to the loop start, so locals declared inside the loop body
+    // (including hidden ones, e.g. switch value) have to be dropped from the stack first.
+    // Locals of enclosing if/else blocks are still in cur locals, blocks already closed are not.
+    // Body instances are released as at the end of an iteration, a leftover one would shift
+    // the instances declared after the loop
+    fn pop_loop_locals(&mut self) {
+        for _ in self.loop_info.locals_start..self.get_cur_locals().len() {
+            self.emit_byte(OpCode::POP, self.parser.line);
+        }
+        self.emit_byte(OpCode::DEC_TO(self.loop_info.instance_start), self.parser.line);
+        self.emit_byte(OpCode::RF_REMOVE, self.parser.line);
+    }
+
+    // Calls returning struct instance leave nothing on the caller stack (the instance lives on the heap),
+    // so there is no value to discard after such expression statement.
+    fn expression_left_value(&mut self) -> bool {
+        let last_op = self.get_cur_chunk().get_last_instruction().op.clone();
+        let output_type = match &last_op {
+            OpCode::FUNCTION_CALL(index) => self.parser.symbols[*index].output_type,
+            OpCode::METHOD_CALL(mth) => mth.output_type,
+            OpCode::DBG(_, _, Some(_)) => return false,
+            _ => return true,
+        };
+
+        !matches!(output_type, TokenType::STRUCT(_))
+    }
+
     pub fn impl_native_types(&mut self) {
         // STRING
 
-        // 19 natives builtin functions
-        let string_type = StringObj::init(19);
+        let string_type = StringObj::init(self.registry.struct_pos("String"));
         let list_type = ListObj::init();
+        let bytes_type = BytesObj::init();
 
-        self.parser.get_symbols(string_type.clone().methods.len(), list_type.clone().methods.len());
+        self.parser.get_symbols(&self.registry);
 
         self.get_cur_chunk().push(Instruction { op: OpCode::STRUCT_DEC(string_type.clone()), line: 0 });
         self.structs.insert("String".to_string(), string_type);
 
+        // list methods are called as natives directly, so their symbols need real signatures
+        let list_pos = self.get_struct_symbol_pos("List".to_string());
+        for native in ListMethods::get_methods_rc() {
+            let pos = list_pos + ListMethods::get_native_offset(&native.name).unwrap();
+            self.parser.symbols[pos].output_type = list_type.methods.get(&native.name).unwrap().output_type;
+            self.parser.symbols[pos].arg_count = native.arg_count;
+        }
+
         self.get_cur_chunk().push(Instruction { op: OpCode::STRUCT_DEC(list_type.clone()), line: 0 });
         self.structs.insert("List".to_string(), list_type);
+
+        // Bytes methods are natives called the same way as list methods
+        let bytes_pos = self.get_struct_symbol_pos("Bytes".to_string());
+        for native in BytesMethods::get_methods_rc() {
+            let pos = bytes_pos + BytesMethods::get_native_offset(&native.name).unwrap();
+            self.parser.symbols[pos].output_type = bytes_type.methods.get(&native.name).unwrap().output_type;
+            self.parser.symbols[pos].arg_count = native.arg_count;
+        }
+
+        self.get_cur_chunk().push(Instruction { op: OpCode::STRUCT_DEC(bytes_type.clone()), line: 0 });
+        self.structs.insert("Bytes".to_string(), bytes_type);
     }
 
     pub fn compile(&mut self) -> Chunk {
+        for file_tokens in self.parser.tokens.chunk_by(|a, b| a.file == b.file) {
+            errors::set_cur_file(file_tokens[0].file);
+            check_brackets(file_tokens);
+        }
         self.impl_native_types();
 
         self.parser.advance();
+        errors::set_recovering(true);
         loop {
             self.parser.line = self.parser.cur.line;
             if self.parser.check_if_eof() {
                 break;
             }
-            self.compile_line();
+            // declarations at file scope never span files
+            errors::set_cur_file(self.parser.cur.file);
+            self.compile_statement();
             self.loop_info = LoopInfo::new();
+            self.failed_vars.clear();
 
             // tries and errors
-            self.get_cur_instances().retain(| obj | obj.name != "");
+            self.get_cur_instances().retain(| obj | !obj.name.is_empty());
+        }
+        errors::set_recovering(false);
+
+        if self.error_count > 0 {
+            errors::terminate(errors::Termination::Compile);
         }
         // Dunno if that help with memory
-        self.structs = HashMap::new();
+        self.structs = OrderedMap::new();
 
+        self.set_chunk_file();
         self.get_cur_chunk().clone()
     }
 
     pub fn parse(&mut self, prec: Precedence) {
         self.parser.advance();
 
-        if !self.parser.rules.contains_key(&self.parser.prev.token_type) {
-            errors::error_message("PARSING ERROR", format!("Cannot get a parse rule for: {:?}: \"{}\", {}:",
-                self.parser.prev.token_type,
-                self.parser.prev.value.iter().collect::<String>(),
-                self.parser.line,
-            ));
-            std::process::exit(1);
-        }
-        let rule = self.parser.get_rule(&self.parser.prev.token_type);
-
-        match rule.prefix {
+        match self.prev_rule().prefix {
             Some(f) => f(self),
+            _ if self.parser.prev.token_type == TokenType::LEFT_BRACE => {
+                errors::error_message("PARSING ERROR", format!("Cannot determine type of struct literal, write it as TypeName{{...}} {}:", self.parser.line));
+                errors::compile_error();
+            },
             _ => {
                 errors::error_message("PARSING ERROR", format!("Expected prefix for: {:?}, {}:", self.parser.prev.token_type, self.parser.line));
-                std::process::exit(1);
+                errors::compile_error();
             },
         };
 
-        while prec <= self.parser.get_rule(&self.parser.cur.token_type).prec {
+        while prec <= self.parser.prec(&self.parser.cur.token_type) {
+            self.parser.check_line_start_operator();
             self.parser.advance();
 
-            if !self.parser.rules.contains_key(&self.parser.prev.token_type) {
-                errors::error_message("PARSING ERROR", format!("Cannot get a parse rule for: {:?}: \"{}\", {}:",
-                    self.parser.prev.token_type,
-                    self.parser.prev.value.iter().collect::<String>(),
-                    self.parser.line,
-                ));
-                std::process::exit(1);
-            }
-            let rule = self.parser.get_rule(&self.parser.prev.token_type);
-            match rule.infix {
+            match self.prev_rule().infix {
                 Some(f) => f(self),
                 _ => {
                     errors::error_message("PARSING ERROR", format!("Expected infix for: {:?}, {}:", self.parser.prev.token_type, self.parser.line));
-                    std::process::exit(1);
+                    errors::compile_error();
                 },
             }
         }
     }
 
+    fn prev_rule(&self) -> &'static ParseRule {
+        match self.parser.get_rule(&self.parser.prev.token_type) {
+            Some(rule) => rule,
+            None => {
+                errors::error_message("PARSING ERROR", format!("Cannot get a parse rule for: {:?}: \"{}\", {}:",
+                    self.parser.prev.token_type,
+                    self.parser.prev.value.iter().collect::<String>(),
+                    self.parser.line,
+                ));
+                errors::compile_error();
+            },
+        }
+    }
+
     pub fn emit_byte(&mut self, op: OpCode, line: u32) {
         if self.scope_depth == 0 {
             errors::error_message("PARSER ERROR", format!("Expression found outside of bounds {}:",self.parser.line));
-            std::process::exit(1)
+            errors::compile_error()
         }
-        self.get_cur_chunk().push(Instruction{ op: op, line: line });
+        self.get_cur_chunk().push(Instruction{ op, line });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::lexer::Scanner;
+
+    fn compile_main(source: &str) -> Vec<OpCode> {
+        let tokens = Scanner::init(source).get_tokens();
+        let mut compiler = Compiler::new(tokens, CompilerFlags::new(false, source));
+
+        compiler.compile().code.into_iter()
+            .find_map(|instruction| match instruction.op {
+                OpCode::FUNCTION_DEC(function) if function.name == "main" => Some(function.chunk.code.into_iter().map(|instruction| instruction.op).collect()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn list_and_struct_literals_use_own_opcodes() {
+        let code = compile_main("struct P {\n    x: int,\n    y: int,\n}\nfn main() {\n    var p: P = {1, 2}\n    var xs: List<int> = [1, 2, 3]\n}\n");
+
+        assert!(code.iter().any(|op| matches!(op, OpCode::INSTANCE_DEC(_, 2))), "{:?}", code);
+        assert!(code.iter().any(|op| matches!(op, OpCode::LIST_DEC(_, 3))), "{:?}", code);
+        assert!(!code.iter().any(|op| matches!(op, OpCode::INSTANCE_DEC(_, 3))), "{:?}", code);
+    }
+
+    #[test]
+    fn string_literals_are_folded_unless_bound_tighter() {
+        let code = compile_main("fn main() {\n    println(\"a\\n\" + \"b\" \"c\")\n    println(\"ab\" + \"cd\".toUpper())\n}\n");
+
+        assert!(code.iter().any(|op| *op == OpCode::PUSH_STACK(Value::String("a\nbc".to_string()))), "{:?}", code);
+        assert_eq!(code.iter().filter(|op| matches!(op, OpCode::ADD_STRING)).count(), 1, "{:?}", code);
+    }
+
+    #[test]
+    fn compiling_twice_gives_identical_chunks() {
+        let source = "struct P {\n    x: int,\n    y: int,\n\n    methods {\n        sum(self) int {\n            return self.x + self.y\n        }\n\n        dx(self, d: int) int {\n            return self.x + d\n        }\n    }\n}\n\nstruct Q {\n    name: String,\n}\n\nmethods Q {\n    greet(self) String {\n        return self.name\n    }\n\n    size(self) int {\n        return self.name.len()\n    }\n}\n\nfn helper(a: int) int {\n    return a\n}\n\nfn main() null {\n    var p: P = P{1, 2}\n    var q: Q = Q{\"q\"}\n    println(p.sum() + p.dx(3) + q.size() + helper(4))\n}\n";
+        let compile = || {
+            let tokens = Scanner::init(source).get_tokens();
+            format!("{:?}", Compiler::new(tokens, CompilerFlags::new(false, source)).compile())
+        };
+
+        assert_eq!(compile(), compile());
     }
 }
@@ -1,17 +1,124 @@
+use std::cell::{Cell, RefCell};
+
 use crate::frontend::tokens::{Token, TokenType, Keywords};
 
+thread_local! {
+    // source line of the native call being executed, natives do not get it as argument
+    static RUNTIME_LINE: Cell<u32> = const { Cell::new(0) };
+    // set while compiler recovers from errors at statement boundaries
+    static RECOVERING: Cell<bool> = const { Cell::new(false) };
+    // files of the program, errors of a multi-file program name their file and runtime errors
+    // show the text of their line when the source is retained
+    static SOURCES: RefCell<Vec<SourceFile>> = const { RefCell::new(vec![]) };
+    // file the compiler or the running function is in, position in SOURCES
+    static CUR_FILE: Cell<usize> = const { Cell::new(0) };
+}
+
+// Lines are empty unless the source is retained (--with-source)
+pub struct SourceFile {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+// Payload of a reported compile error, caught by the compiler at the statement boundary
+pub struct CompileError;
+
+// Why the program stopped after its error was reported, Engine returns it and main.rs maps it to the exit code
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Termination {
+    Compile,
+    Runtime,
+    // source file could not be read or written
+    Io,
+    // bad command line arguments, fmt --check finding unformatted source
+    Failure,
+}
+
+// Run ended by an interpreter panic, the next run must not see its recovery mode or source
+pub fn reset_state() {
+    RECOVERING.with(|cur| cur.set(false));
+    RUNTIME_LINE.with(|cur| cur.set(0));
+    SOURCES.with(|cur| cur.borrow_mut().clear());
+    CUR_FILE.with(|cur| cur.set(0));
+}
+
+pub fn set_recovering(recovering: bool) {
+    RECOVERING.with(|cur| cur.set(recovering));
+}
+
+// Unwinds up to Engine, which returns the termination instead of exiting in place
+pub fn terminate(termination: Termination) -> ! {
+    std::panic::resume_unwind(Box::new(termination));
+}
+
+// Called after the error message is printed, outside of recovery compilation stops
+pub fn compile_error() -> ! {
+    if RECOVERING.with(|cur| cur.get()) {
+        std::panic::resume_unwind(Box::new(CompileError));
+    }
+    terminate(Termination::Compile);
+}
+
+// Called after the runtime error message is printed
+pub fn runtime_error() -> ! {
+    terminate(Termination::Runtime);
+}
+
+pub fn set_runtime_line(line: u32) {
+    RUNTIME_LINE.with(|cur| cur.set(line));
+}
+
+pub fn runtime_line() -> u32 {
+    RUNTIME_LINE.with(|cur| cur.get())
+}
+
+pub fn set_sources(sources: Vec<SourceFile>) {
+    SOURCES.with(|cur| *cur.borrow_mut() = sources);
+}
+
+pub fn set_cur_file(file: usize) {
+    CUR_FILE.with(|cur| cur.set(file));
+}
+
+// Messages end with "<line>:", the line is read back from there. The file is named only
+// when the program has more than one, the source line is shown for runtime errors
+fn location_of_message(msg: &str, show_source: bool) -> Vec<String> {
+    let Some(line) = msg.trim_end().strip_suffix(':').and_then(|msg| msg.rsplit(' ').next()).and_then(|line| line.parse::<usize>().ok()) else {
+        return vec![]
+    };
+
+    SOURCES.with(|cur| {
+        let sources = cur.borrow();
+        let Some(file) = sources.get(CUR_FILE.with(|cur| cur.get())) else {
+            return vec![]
+        };
+
+        let mut location = vec![];
+        if sources.len() > 1 {
+            location.push(format!("  --> {}:{}", file.name, line));
+        }
+        if let Some(text) = line.checked_sub(1).and_then(|index| file.lines.get(index)).filter(|_| show_source) {
+            location.push(format!("{:>4} | {}", line, text.trim()));
+        }
+        location
+    })
+}
+
 pub fn error_message(title: &str, msg: String) {
     eprintln!("==== {} ====", title);
     eprintln!("{}", msg);
+    for location in location_of_message(&msg, title.starts_with("RUNTIME")) {
+        eprintln!("{}", location);
+    }
 }
 
 pub fn conversion_error(from: &str, to: &str) {
     error_message("CONVERSION ERROR", format!("ERROR: Unable to convert {} to {}; exit code: 1", from, to));
 }
 
-pub fn token_error(token: Token) {
+pub fn token_error(token: Token) -> ! {
     error_message("TOKEN ERROR", token.value.iter().collect::<String>());
-    std::process::exit(1);
+    compile_error();
 }
 
 pub fn error_unexpected(token: Token, place: &str) {
@@ -24,4 +131,50 @@ pub fn error_unexpected_token_type(token_type: TokenType, line: u32, place: &str
 
 pub fn error_unexpected_keyword(keyword: Keywords, line: u32, place: &str) {
     error_message("COMPILER ERROR", format!("Unexpected keyword ({:?}) in {} {}:", keyword, place.to_ascii_uppercase(), line));
-}
\ No newline at end of file
+}
+pub fn strict_error(code: &str, msg: String) {
+    error_message("STRICT MODE ERROR", format!("[{}] {}", code, msg));
+}
+
+pub fn warning(code: &str, msg: String) {
+    error_message("WARNING", format!("[{}] {}", code, msg));
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        prev = cur;
+    }
+
+    prev[b.len()]
+}
+
+// Returns ", did you mean ...?" with up to three candidates close to the unknown name
+// (edit distance up to 2 or one being a prefix of another), or empty string when nothing is close
+pub fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a String>) -> String {
+    let mut close: Vec<(usize, &String)> = candidates
+        .filter(|candidate| !candidate.is_empty() && candidate.as_str() != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| {
+            let is_prefix = name.len().min(candidate.len()) >= 3 && (candidate.starts_with(name) || name.starts_with(candidate.as_str()));
+            (*distance <= 2 && *distance < name.len()) || is_prefix
+        })
+        .collect();
+
+    close.sort();
+    close.dedup();
+    if close.is_empty() {
+        return String::new()
+    }
+
+    let names: Vec<String> = close.iter().take(3).map(|(_, candidate)| format!("\"{}\"", candidate)).collect();
+    format!(", did you mean {}?", names.join(", "))
+}
@@ -0,0 +1,48 @@
+// Dialect switches of the compiler, strict mode is enabled by the --strict flag
+// or by the "#!strict" pragma in the first line of the source
+#[derive(Debug, Clone, Copy)]
+pub struct CompilerFlags {
+    pub strict: bool,
+    // --allow-float-eq, == and != on FLOAT operands are accepted silently
+    pub allow_float_eq: bool,
+    // --quiet, warnings are not printed, errors still are
+    pub quiet: bool,
+    // --max-locals, locals a single function may declare, its params included
+    pub max_locals: usize,
+}
+
+pub const STRICT_PRAGMA: &str = "#!strict";
+
+pub const DEFAULT_MAX_LOCALS: usize = 256;
+
+// Codes reported by strict mode rules
+pub const VAR_WITHOUT_INIT: &str = "S001";
+pub const IMPLICIT_OUTPUT_TYPE: &str = "S002";
+pub const UNUSED_RESULT: &str = "S003";
+pub const FLOAT_EQUALITY: &str = "S004";
+pub const TRUNCATED_DIVISION: &str = "S005";
+
+impl Default for CompilerFlags {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            allow_float_eq: false,
+            quiet: false,
+            max_locals: DEFAULT_MAX_LOCALS,
+        }
+    }
+}
+
+impl CompilerFlags {
+    pub fn new(strict: bool, source_code: &str) -> Self {
+        let pragma = source_code
+            .lines()
+            .next()
+            .is_some_and(|line| line.trim() == STRICT_PRAGMA);
+
+        Self {
+            strict: strict || pragma,
+            ..Self::default()
+        }
+    }
+}
@@ -1,2 +1,5 @@
+#[allow(clippy::module_inception, reason = "the compiler module holds the Compiler itself")]
 pub mod compiler;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+pub mod analyzer;
+pub mod flags;
\ No newline at end of file
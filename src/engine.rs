@@ -0,0 +1,410 @@
+use std::{any::Any, collections::HashMap, panic::{self, AssertUnwindSafe}, path::{Path, PathBuf}};
+
+use crate::{
+    compiler::{compiler::Compiler, errors::{self, SourceFile, Termination}, flags::CompilerFlags},
+    frontend::{lexer, tokens::{Keywords, Token, TokenType}},
+    objects::{functions::HostFn, registry::{NativeRegistry, NativeSet}},
+    vm::{bytecode::{Chunk, OpCode}, debugger::Debugger, value::Value, vm::{Frame, VmLimits, VM}},
+};
+
+// Entry point for embedding, host functions are callable from scripts like builtin natives.
+// Compile and runtime errors are reported to stderr and returned as Termination, the CLI maps it to the exit code.
+#[derive(Default)]
+pub struct Engine {
+    host_fns: Vec<HostFn>,
+    native_set: NativeSet,
+    strict: bool,
+    allow_float_eq: bool,
+    quiet: bool,
+    // None keeps flags::DEFAULT_MAX_LOCALS
+    max_locals: Option<usize>,
+    limits: VmLimits,
+    with_source: bool,
+    disassemble: bool,
+    leak_report: bool,
+    step: bool,
+    // --run, function called instead of main with its command line arguments
+    entry: Option<(String, Vec<String>)>,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    // NativeSet::Sandbox rejects programs calling natives that read stdin or touch files
+    pub fn set_native_set(&mut self, native_set: NativeSet) {
+        self.native_set = native_set;
+    }
+
+    pub fn set_allow_float_eq(&mut self, allow_float_eq: bool) {
+        self.allow_float_eq = allow_float_eq;
+    }
+
+    // Compiler warnings are not printed, errors and requested reports still go to stderr
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    // Locals a single function may declare, more is a compile error naming the declaration
+    pub fn set_max_locals(&mut self, max_locals: usize) {
+        self.max_locals = Some(max_locals);
+    }
+
+    pub fn set_limits(&mut self, limits: VmLimits) {
+        self.limits = limits;
+    }
+
+    // Keeps source text in compiled chunks, shown by the disassembler and in runtime errors
+    pub fn set_with_source(&mut self, with_source: bool) {
+        self.with_source = with_source;
+    }
+
+    // Prints heap objects still alive when main returns, see VM::live_objects
+    pub fn set_leak_report(&mut self, leak_report: bool) {
+        self.leak_report = leak_report;
+    }
+
+    // Runs the program under the step debugger reading commands from stdin, see vm::debugger
+    pub fn set_step(&mut self, step: bool) {
+        self.step = step;
+    }
+
+    // Calls the named function instead of main, arguments are parsed by its parameter types
+    // and the returned value is printed
+    pub fn set_entry(&mut self, name: &str, args: Vec<String>) {
+        self.entry = Some((name.to_string(), args));
+    }
+
+    // Prints bytecode of every function and method instead of running the program
+    pub fn set_disassemble(&mut self, disassemble: bool) {
+        self.disassemble = disassemble;
+    }
+
+    // Arguments are passed in declaration order, String arguments arrive as Value::String.
+    // Host function returning NULL type should return Value::Null
+    pub fn register_native(&mut self, name: &str, arg_types: &[TokenType], output_type: TokenType, function: fn(Vec<Value>) -> Value) -> Result<(), String> {
+        self.check_native(name, arg_types)?;
+
+        let output_type = match output_type {
+            TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRING => output_type,
+            TokenType::NULL | TokenType::KEYWORD(Keywords::NULL) => TokenType::KEYWORD(Keywords::NULL),
+            _ => return Err(format!("Native \"{}\" cannot return type {:?}, expected INT, FLOAT, BOOL, STRING or NULL", name, output_type)),
+        };
+
+        self.host_fns.push(HostFn { name: name.to_string(), arg_types: arg_types.to_vec(), output_type, output_struct: None, list_element: None, function });
+        Ok(())
+    }
+
+    // Host function returning an instance of struct declared in the script, it returns Value::HostStruct
+    // with a value of every field in declaration order. The struct is looked up when the program is compiled
+    pub fn register_native_struct(&mut self, name: &str, arg_types: &[TokenType], struct_name: &str, function: fn(Vec<Value>) -> Value) -> Result<(), String> {
+        self.check_native(name, arg_types)?;
+
+        if NativeRegistry::is_builtin(struct_name) {
+            return Err(format!("Native \"{}\" cannot return builtin struct \"{}\"", name, struct_name))
+        }
+
+        self.host_fns.push(HostFn { name: name.to_string(), arg_types: arg_types.to_vec(), output_type: TokenType::KEYWORD(Keywords::NULL), output_struct: Some(struct_name.to_string()), list_element: None, function });
+        Ok(())
+    }
+
+    // Host function returning Value::ListObj, every element has to be of element_type
+    pub fn register_native_list(&mut self, name: &str, arg_types: &[TokenType], element_type: TokenType, function: fn(Vec<Value>) -> Value) -> Result<(), String> {
+        self.check_native(name, arg_types)?;
+
+        if !matches!(element_type, TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRING) {
+            return Err(format!("Native \"{}\" cannot return list of {:?}, expected INT, FLOAT, BOOL or STRING", name, element_type))
+        }
+
+        self.host_fns.push(HostFn { name: name.to_string(), arg_types: arg_types.to_vec(), output_type: TokenType::LIST, output_struct: None, list_element: Some(element_type), function });
+        Ok(())
+    }
+
+    fn check_native(&self, name: &str, arg_types: &[TokenType]) -> Result<(), String> {
+        let is_identifier = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(format!("Native \"{}\" is not a valid identifier", name))
+        }
+
+        if NativeRegistry::is_builtin(name) || self.host_fns.iter().any(|host_fn| host_fn.name == name) {
+            return Err(format!("Native \"{}\" is already declared", name))
+        }
+
+        if let Some(arg_type) = arg_types.iter().find(|arg_type| !matches!(arg_type, TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRING)) {
+            return Err(format!("Native \"{}\" cannot take argument of type {:?}, expected INT, FLOAT, BOOL or STRING", name, arg_type))
+        }
+        Ok(())
+    }
+
+    pub fn run_file(&self, file_path: &Path) -> Result<(), Termination> {
+        let source_code = match lexer::get_file(file_path) {
+            Ok(source_code) => source_code,
+            Err(e) => {
+                errors::error_message("FILE OPEN", e.to_string());
+                return Err(Termination::Io)
+            },
+        };
+
+        self.run_source(&source_code)
+    }
+
+    // Files listed together are compiled as one program, exactly one of them declares main.
+    // Its source is the one checked for the strict pragma, errors name the file their line is from
+    pub fn run_files(&self, file_paths: &[PathBuf]) -> Result<(), Termination> {
+        let mut files: Vec<(String, Vec<Token>)> = vec![];
+        let mut names: Vec<String> = vec![];
+        let mut seen: Vec<PathBuf> = vec![];
+
+        for file_path in file_paths {
+            // the same file listed twice is compiled once
+            let canonical = file_path.canonicalize().unwrap_or(file_path.clone());
+            if seen.contains(&canonical) {
+                continue;
+            }
+            seen.push(canonical);
+
+            match lexer::get_file(file_path) {
+                Ok(source_code) => {
+                    let tokens = lexer::Scanner::init(&source_code).get_tokens();
+                    files.push((source_code, tokens));
+                    names.push(file_path.display().to_string());
+                },
+                Err(e) => {
+                    errors::error_message("FILE OPEN", e.to_string());
+                    return Err(Termination::Io)
+                },
+            }
+        }
+
+        let main_file = match check_declarations(&files, &names) {
+            Ok(main_file) => main_file,
+            Err(e) => {
+                errors::error_message("COMPILER ERROR", e);
+                return Err(Termination::Compile)
+            },
+        };
+
+        // the file with main goes last, as if it imported the others, so every struct is declared before main uses it
+        let main = files.remove(main_file);
+        files.push(main);
+        let main_name = names.remove(main_file);
+        names.push(main_name);
+
+        // tokens remember their file for diagnostics, only EOF of the main file is kept
+        let last = files.len() - 1;
+        let mut tokens = vec![];
+        let mut sources = vec![];
+        for (file, ((source_code, file_tokens), name)) in files.into_iter().zip(names).enumerate() {
+            tokens.extend(file_tokens.into_iter()
+                .filter(|token| file == last || token.token_type != TokenType::EOF)
+                .map(|token| Token { file, ..token }));
+            sources.push((name, source_code));
+        }
+
+        let debugger = if self.step { Some(Debugger::stdio()) } else { None };
+        catch_termination(|| self.run_tokens(tokens, &sources, debugger))
+    }
+
+    pub fn run_source(&self, source_code: &str) -> Result<(), Termination> {
+        let debugger = if self.step { Some(Debugger::stdio()) } else { None };
+        catch_termination(|| self.run(source_code, debugger))
+    }
+
+    // Same as run_source with --step, commands are read from the debugger input
+    pub fn run_source_with_debugger(&self, source_code: &str, debugger: Debugger) -> Result<(), Termination> {
+        catch_termination(|| self.run(source_code, Some(debugger)))
+    }
+
+    fn run(&self, source_code: &str, debugger: Option<Debugger>) {
+        let mut scanner = lexer::Scanner::init(source_code);
+        let tokens = scanner.get_tokens();
+        self.run_tokens(tokens, &[(String::new(), source_code.to_string())], debugger);
+    }
+
+    // Sources are (file name, source code) in the order of Token::file, the main file is the last one
+    fn run_tokens(&self, tokens: Vec<Token>, sources: &[(String, String)], debugger: Option<Debugger>) {
+        let (_, main_source) = sources.last().unwrap();
+        let mut flags = CompilerFlags::new(self.strict, main_source);
+        flags.allow_float_eq = self.allow_float_eq;
+        flags.quiet = self.quiet;
+        if let Some(max_locals) = self.max_locals {
+            flags.max_locals = max_locals;
+        }
+        let mut compiler = Compiler::new(tokens, flags);
+        let mut registry = NativeRegistry::new(self.host_fns.clone());
+        registry.set_native_set(self.native_set);
+        compiler.set_registry(registry.clone());
+        if self.with_source {
+            compiler.set_sources(&sources.iter().map(|(_, source_code)| source_code.as_str()).collect::<Vec<_>>());
+        }
+        // runtime errors show their source line only with --with-source
+        errors::set_sources(sources.iter().map(|(name, source_code)| SourceFile {
+            name: name.clone(),
+            lines: if self.with_source { source_code.lines().map(String::from).collect() } else { vec![] },
+        }).collect());
+
+        let main_chunk = compiler.compile();
+        if self.disassemble {
+            print!("{}", disassemble(&main_chunk));
+            return
+        }
+
+        let mut vm = VM::new(self.limits);
+        vm.set_leak_report(self.leak_report);
+        if let Some(debugger) = debugger {
+            vm.set_debugger(debugger);
+        }
+        vm.set_registry(registry);
+
+        let entry_frame = match &self.entry {
+            Some((name, args)) => match vm.declare(main_chunk) {
+                Ok(()) => call_frame(&mut vm, &compiler, name, args).map_err(|e| ("ARGUMENT ERROR", Termination::Failure, e)),
+                Err(e) => Err(("RUNTIME ERROR", Termination::Runtime, e)),
+            },
+            None => vm.declare_all(main_chunk).map_err(|e| ("RUNTIME ERROR", Termination::Runtime, e)),
+        };
+
+        match entry_frame {
+            Ok(frame) => vm.frames.push(frame),
+            Err((title, termination, e)) => {
+                errors::error_message(title, e);
+                errors::terminate(termination);
+            },
+        };
+
+        vm.run();
+
+        if let Some((name, _)) = &self.entry {
+            let output_type = vm.declared_fn(name).map(|declared| declared.output_type);
+            if let (Some(TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRING), Some(value)) = (output_type, vm.frames[0].stack.last()) {
+                println!("{}", value);
+            }
+        }
+    }
+}
+
+// Error paths unwind with the Termination once their message is printed. Any other panic is an
+// interpreter bug, it is reported as a runtime error instead of taking the embedding process down.
+// Compiler and VM are owned by the run and dropped with it, only thread locals outlive it
+fn catch_termination(run: impl FnOnce()) -> Result<(), Termination> {
+    match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(()) => Ok(()),
+        Err(payload) => match payload.downcast::<Termination>() {
+            Ok(termination) => Err(*termination),
+            Err(payload) => {
+                errors::reset_state();
+                errors::error_message("RUNTIME ERROR", format!("internal interpreter error: {}", panic_text(payload.as_ref())));
+                Err(Termination::Runtime)
+            },
+        },
+    }
+}
+
+fn panic_text(payload: &(dyn Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(text), _) => text.to_string(),
+        (_, Some(text)) => text.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+// Functions and structs declared at file scope, as (is_struct, name, line)
+fn top_level_declarations(tokens: &[Token]) -> Vec<(bool, String, u32)> {
+    let mut declarations = vec![];
+    let mut depth = 0;
+
+    for pair in tokens.windows(2) {
+        match pair[0].token_type {
+            TokenType::LEFT_BRACE => depth += 1,
+            TokenType::RIGHT_BRACE => depth -= 1,
+            TokenType::KEYWORD(keyword @ (Keywords::FN | Keywords::STRUCT)) if depth == 0 && pair[1].token_type == TokenType::IDENTIFIER => {
+                declarations.push((keyword == Keywords::STRUCT, pair[1].value.iter().collect(), pair[0].line));
+            },
+            _ => {},
+        }
+    }
+    declarations
+}
+
+// Names can be declared once in the whole program and main in exactly one file, returns the index of that file
+fn check_declarations(files: &[(String, Vec<Token>)], names: &[String]) -> Result<usize, String> {
+    // name -> (is_struct, file, line)
+    let mut declared: HashMap<String, (bool, usize, u32)> = HashMap::new();
+    let mut main_file = None;
+
+    for (file, (_, tokens)) in files.iter().enumerate() {
+        for (is_struct, name, line) in top_level_declarations(tokens) {
+            let kind = if is_struct { "Struct" } else { "Function" };
+            if let Some((_, first_file, first_line)) = declared.get(&name) {
+                return Err(format!("{} \"{}\" is declared in both {} (line {}) and {} (line {})", kind, name, names[*first_file], first_line, names[file], line))
+            }
+            declared.insert(name.clone(), (is_struct, file, line));
+
+            if !is_struct && name == "main" {
+                main_file = Some(file);
+            }
+        }
+    }
+
+    main_file.ok_or(format!("Function \"main\" has to be declared in one of the files: {}", names.join(", ")))
+}
+
+// Only scalar and String parameters and results can be passed through the command line,
+// types are named by the compiler the program was compiled with, as in compile errors
+fn call_frame(vm: &mut VM, compiler: &Compiler, name: &str, args: &[String]) -> Result<Frame, String> {
+    let declared = match vm.declared_fn(name) {
+        Some(declared) => declared.clone(),
+        None => return Err(format!("Function \"{}\" is not declared, available functions: {}", name, vm.declared_fn_names().join(", "))),
+    };
+
+    if !matches!(declared.output_type, TokenType::INT | TokenType::FLOAT | TokenType::BOOL | TokenType::STRING | TokenType::NULL | TokenType::KEYWORD(Keywords::NULL)) {
+        return Err(format!("Function \"{}\" returns {}, only int, float, bool, String or null results can be printed", name, compiler.type_name(declared.output_type)))
+    }
+
+    if args.len() != declared.arg_types.len() {
+        return Err(format!("Function \"{}\" expects {} arguments but {} were given", name, declared.arg_types.len(), args.len()))
+    }
+
+    let mut values = vec![];
+    for (i, (arg, arg_type)) in args.iter().zip(declared.arg_types.iter()).enumerate() {
+        let value = match arg_type {
+            TokenType::INT => arg.parse().ok().map(Value::Int),
+            TokenType::FLOAT => arg.parse().ok().map(Value::Float),
+            TokenType::BOOL => arg.parse().ok().map(Value::Bool),
+            TokenType::STRING => Some(Value::String(arg.clone())),
+            _ => return Err(format!("Argument {} of \"{}\" has type {}, only int, float, bool and String arguments are supported", i + 1, name, compiler.type_name(*arg_type))),
+        };
+
+        match value {
+            Some(value) => values.push(value),
+            None => return Err(format!("Argument {} of \"{}\" has to be {}, found: \"{}\"", i + 1, name, compiler.type_name(*arg_type), arg)),
+        }
+    }
+
+    vm.call_frame(name, values)
+}
+
+// Functions in declaration order, then methods of every user struct sorted by name
+pub fn disassemble(main_chunk: &Chunk) -> String {
+    let mut out = String::new();
+    for instruction in main_chunk.code.iter() {
+        match &instruction.op {
+            OpCode::FUNCTION_DEC(function) => out.push_str(&function.chunk.disassemble(&function.name)),
+            OpCode::STRUCT_DEC(struct_) if !matches!(struct_.name.as_str(), "String" | "List" | "Bytes") => {
+                let mut methods: Vec<_> = struct_.methods.values().collect();
+                methods.sort_by(|a, b| a.name.cmp(&b.name));
+                for method in methods {
+                    out.push_str(&method.chunk.disassemble(&format!("{}.{}", struct_.name, method.name)));
+                }
+            },
+            _ => {},
+        }
+    }
+    out
+}
@@ -0,0 +1,228 @@
+use crate::frontend::{lexer::Scanner, tokens::{Keywords, Token, TokenType}};
+
+const INDENT: &str = "    ";
+
+#[derive(PartialEq, Clone, Copy)]
+enum Group {
+    Block,
+    // body of do-while, its closing brace is followed by while on the same line
+    DoBlock,
+    // struct literal: {1, "a"}
+    Literal,
+    Paren,
+    Bracket,
+}
+
+struct Formatter {
+    output: String,
+    groups: Vec<Group>,
+    prev: Option<Token>,
+    prev_unary: bool,
+    // open '<' of List<...> types, nested List<List<int>> counts twice
+    generic_depth: usize,
+    // prev is a type name that can open a typed struct literal: P{1, "a"}
+    literal_name: bool,
+    line_start: bool,
+    closed_do_block: bool,
+}
+
+// Re-prints token stream of the source with canonical spacing and indentation,
+// line breaks between statements are kept as in the source (at most one blank line)
+pub fn format_source(source_code: &str) -> Result<String, String> {
+    let mut scanner = Scanner::init(source_code);
+    let tokens = scanner.get_all_tokens();
+
+    if let Some(token) = tokens.iter().find(|token| token.token_type == TokenType::ERROR) {
+        return Err(token.value.iter().collect::<String>())
+    }
+
+    let mut formatter = Formatter {
+        output: String::new(),
+        groups: vec![],
+        prev: None,
+        prev_unary: false,
+        generic_depth: 0,
+        literal_name: false,
+        line_start: true,
+        closed_do_block: false,
+    };
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.token_type == TokenType::EOF {
+            formatter.check_generic_closed()?;
+            break
+        }
+        formatter.token(token, tokens.get(i + 1))?;
+    }
+
+    let mut output = formatter.output.trim_end().to_string();
+    output.push('\n');
+    Ok(output)
+}
+
+impl Formatter {
+    fn token(&mut self, token: &Token, next: Option<&Token>) -> Result<(), String> {
+        // none of these can be a part of a type, so a '<' still open before them is never closed
+        let ends_type = matches!(token.token_type, TokenType::EQ | TokenType::GREATER_EQ |
+            TokenType::LEFT_BRACE | TokenType::RIGHT_BRACE | TokenType::LEFT_PAREN | TokenType::RIGHT_PAREN |
+            TokenType::LEFT_BRACKET | TokenType::RIGHT_BRACKET);
+        if ends_type || self.prev.as_ref().is_some_and(|prev| token.line > prev.line) {
+            self.check_generic_closed()?;
+        }
+
+        let closing = matches!(token.token_type, TokenType::RIGHT_BRACE | TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACKET);
+        let group = if closing { self.groups.pop() } else { None };
+
+        if let Some(prev) = self.prev.clone() {
+            let mut line_diff = token.line.saturating_sub(prev.line);
+            if prev.token_type == TokenType::RIGHT_BRACE && (matches!(token.token_type,
+                TokenType::KEYWORD(Keywords::ELIF) | TokenType::KEYWORD(Keywords::ELSE)) ||
+                (self.closed_do_block && token.token_type == TokenType::KEYWORD(Keywords::WHILE))) {
+                line_diff = 0;
+            }
+
+            if self.line_start || self.forces_newline(&prev, token, group) || line_diff > 0 {
+                let keep_blank = line_diff > 1 && prev.token_type != TokenType::LEFT_BRACE && token.token_type != TokenType::RIGHT_BRACE;
+                self.newline(keep_blank);
+            }else if self.needs_space(&prev, token, group) {
+                self.output.push(' ');
+            }
+        }
+
+        if self.line_start {
+            for _ in 0..self.groups.len() {
+                self.output.push_str(INDENT);
+            }
+            self.line_start = false;
+        }
+
+        self.output.push_str(&token_text(token));
+
+        let is_generic_open = token.token_type == TokenType::LESS && self.prev.as_ref().is_some_and(|prev| token_text(prev) == "List");
+        self.prev_unary = self.is_unary(token);
+
+        match token.token_type {
+            TokenType::LEFT_BRACE => {
+                let is_literal = self.literal_name || self.prev.as_ref().is_some_and(|prev| prev.token_type == TokenType::EQ || prev.token_type == TokenType::KEYWORD(Keywords::RETURN));
+                let is_do = self.prev.as_ref().is_some_and(|prev| prev.token_type == TokenType::KEYWORD(Keywords::DO));
+                self.groups.push(match (is_literal, is_do) {
+                    (true, _) => Group::Literal,
+                    (_, true) => Group::DoBlock,
+                    _ => Group::Block,
+                });
+            },
+            TokenType::LEFT_PAREN => self.groups.push(Group::Paren),
+            TokenType::LEFT_BRACKET => self.groups.push(Group::Bracket),
+            TokenType::LESS if is_generic_open => self.generic_depth += 1,
+            TokenType::GREATER if self.generic_depth > 0 => self.generic_depth -= 1,
+            _ => {},
+        }
+
+        // comment always ends the line, block brace ends it unless else/elif follows
+        let is_comment = token.token_type == TokenType::COMMENT;
+        let is_block_end = match group {
+            Some(Group::Block) => !next.is_some_and(|next| matches!(next.token_type,
+                TokenType::KEYWORD(Keywords::ELIF) | TokenType::KEYWORD(Keywords::ELSE))),
+            Some(Group::DoBlock) => !next.is_some_and(|next| next.token_type == TokenType::KEYWORD(Keywords::WHILE)),
+            _ => false,
+        };
+        self.closed_do_block = group == Some(Group::DoBlock);
+
+        self.literal_name = token.token_type == TokenType::IDENTIFIER && self.prev.as_ref().is_some_and(|prev| matches!(prev.token_type,
+            TokenType::EQ | TokenType::KEYWORD(Keywords::RETURN) | TokenType::COMMA | TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET) ||
+            (prev.token_type == TokenType::LEFT_BRACE && self.groups.last() == Some(&Group::Literal)));
+        self.prev = Some(token.clone());
+        if is_comment || is_block_end {
+            self.newline(false);
+        }
+        Ok(())
+    }
+
+    fn check_generic_closed(&self) -> Result<(), String> {
+        match &self.prev {
+            Some(prev) if self.generic_depth > 0 => Err(format!("List type is not closed with '>' {}:", prev.line)),
+            _ => Ok(()),
+        }
+    }
+
+    fn newline(&mut self, keep_blank: bool) {
+        if !self.line_start {
+            self.output.push('\n');
+            self.line_start = true;
+        }
+
+        if keep_blank && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn forces_newline(&self, prev: &Token, token: &Token, group: Option<Group>) -> bool {
+        (prev.token_type == TokenType::LEFT_BRACE && matches!(self.groups.last(), Some(Group::Block | Group::DoBlock)) && token.token_type != TokenType::COMMENT) ||
+        (token.token_type == TokenType::RIGHT_BRACE && matches!(group, Some(Group::Block | Group::DoBlock)))
+    }
+
+    fn is_unary(&self, token: &Token) -> bool {
+        match token.token_type {
+            TokenType::INTERJ => true,
+            TokenType::MINUS => match &self.prev {
+                None => true,
+                Some(prev) => self.line_start || matches!(prev.token_type,
+                    TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET | TokenType::LEFT_BRACE | TokenType::COMMA | TokenType::COLON |
+                    TokenType::EQ | TokenType::EQ_EQ | TokenType::INTERJ_EQ | TokenType::GREATER | TokenType::GREATER_EQ |
+                    TokenType::LESS | TokenType::LESS_EQ | TokenType::PLUS | TokenType::MINUS | TokenType::STAR |
+                    TokenType::SLASH | TokenType::MOD | TokenType::INTERJ | TokenType::KEYWORD(_)
+                ) && !matches!(prev.token_type, TokenType::KEYWORD(Keywords::TRUE) | TokenType::KEYWORD(Keywords::FALSE) | TokenType::KEYWORD(Keywords::NULL)),
+            },
+            _ => false,
+        }
+    }
+
+    fn needs_space(&self, prev: &Token, token: &Token, group: Option<Group>) -> bool {
+        if token.token_type == TokenType::COMMENT {
+            return true
+        }
+
+        if self.prev_unary {
+            return false
+        }
+
+        if self.generic_depth > 0 || (token.token_type == TokenType::LESS && token_text(prev) == "List") {
+            return false
+        }
+
+        match token.token_type {
+            TokenType::COMMA | TokenType::DOT | TokenType::COLON | TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACKET => return false,
+            TokenType::RIGHT_BRACE if group == Some(Group::Literal) => return false,
+            TokenType::LEFT_BRACE if self.literal_name => return false,
+            TokenType::LEFT_PAREN if prev.token_type == TokenType::IDENTIFIER => return false,
+            TokenType::LEFT_BRACKET if matches!(prev.token_type, TokenType::IDENTIFIER | TokenType::RIGHT_BRACKET | TokenType::RIGHT_PAREN) => return false,
+            _ => {},
+        }
+
+        match prev.token_type {
+            TokenType::DOT | TokenType::LEFT_PAREN | TokenType::LEFT_BRACKET => false,
+            TokenType::LEFT_BRACE => self.groups.last() != Some(&Group::Literal),
+            _ => true,
+        }
+    }
+}
+
+fn token_text(token: &Token) -> String {
+    match token.token_type {
+        TokenType::STRING => {
+            let mut text = String::from("\"");
+            for c in token.value.iter() {
+                match c {
+                    '\n' => text.push_str("\\n"),
+                    '\r' => text.push_str("\\r"),
+                    '\t' => text.push_str("\\t"),
+                    c => text.push(*c),
+                }
+            }
+            text.push('"');
+            text
+        },
+        TokenType::COMMENT => token.value.iter().collect::<String>().trim_end().to_string(),
+        _ => token.value.iter().collect::<String>(),
+    }
+}
@@ -1,7 +1,9 @@
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
+use std::path::{Path, PathBuf};
 
-use crate::{compiler::errors::error_message, frontend::tokens::{Keywords, Token, TokenType}};
+use crate::frontend::tokens::{Keywords, Token, TokenType};
 
 pub struct Scanner {
     source_code: Vec<char>,
@@ -11,9 +13,9 @@ pub struct Scanner {
 }
 
 impl Scanner {
-    pub fn init(source_code: &String) -> Self {
+    pub fn init(source_code: &str) -> Self {
         Self {
-            source_code: source_code.clone().chars().collect(),
+            source_code: source_code.chars().collect(),
             start: 0,
             cur: 0,
             line: 1,
@@ -68,6 +70,7 @@ impl Scanner {
                 token_type: TokenType::ERROR,
                 value: format!("Missing \" at the end of string {}:{}", self.line, self.cur + 1).chars().collect(),
                 line: self.line,
+                file: 0,
             };
         }
 
@@ -75,7 +78,7 @@ impl Scanner {
         
         let mut token_value: String = String::new();
         let mut esc_seq = false; 
-        for c in self.source_code[self.start..self.cur].to_vec() {
+        for c in self.source_code[self.start..self.cur].iter().copied() {
             if c == '\\' {
                 esc_seq = true;
                 continue;
@@ -99,50 +102,61 @@ impl Scanner {
             token_value.push(c);
         };
 
-        return Token {
+        Token {
             token_type: TokenType::STRING,
             value: token_value.trim_matches('"').chars().collect(),
             line: self.line,
-        };
+            file: 0,
+        }
     }
 
     fn identifier(&mut self) -> Token {
         self.next_while(|&c| {
-            (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c == '_') || c.is_digit(10)
+            c.is_ascii_alphanumeric() || c == '_'
         });
 
         let token_type = self.source_code[self.start..self.cur]
             .iter()
             .collect::<String>()
             .parse::<Keywords>()
-            .map(|keyword| TokenType::KEYWORD(keyword))
+            .map(TokenType::KEYWORD)
             .unwrap_or(TokenType::IDENTIFIER);
 
-        return Token {
-            token_type: token_type,
+        Token {
+            token_type,
             value: self.source_code[self.start..self.cur].to_vec(),
             line: self.line,
-        };
+            file: 0,
+        }
     }
 
     fn number(&mut self) -> Token {
-        self.next_while(|&c| c.is_digit(10));
+        self.next_while(|&c| c.is_ascii_digit());
 
         let mut token_type = TokenType::INT;
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.next();
-            self.next_while(|&c| c.is_digit(10));
+            self.next_while(|&c| c.is_ascii_digit());
             token_type = TokenType::FLOAT;
         }
 
-        return Token {
-            token_type: token_type,
+        Token {
+            token_type,
             value: self.source_code[self.start..self.cur].to_vec(),
             line: self.line,
-        };
+            file: 0,
+        }
     }
     
     pub fn get_tokens(&mut self) -> Vec<Token> {
+        self.get_all_tokens()
+            .into_iter()
+            .filter(|token| token.token_type != TokenType::COMMENT)
+            .collect()
+    }
+
+    // Same as get_tokens, but keeps comments (used by formatter)
+    pub fn get_all_tokens(&mut self) -> Vec<Token> {
         let mut tokens: Vec<Token> = vec![];
         
         loop {
@@ -150,16 +164,15 @@ impl Scanner {
             if token.token_type == TokenType::EOF {
                 break;
             }
-            if token.token_type != TokenType::COMMENT {
-                tokens.push(token);
-            }
+            tokens.push(token);
         }
         tokens.push(Token {
             token_type: TokenType::EOF,
             value: vec!['E', 'O', 'F'],
             line: self.line,
+            file: 0,
         });
-        return tokens
+        tokens
     }
 
     pub fn scan_token(&mut self) -> Token {
@@ -172,6 +185,7 @@ impl Scanner {
                 token_type: TokenType::EOF,
                 value: vec!['E', 'O', 'F'],
                 line: self.line,
+                file: 0,
             };
         }
 
@@ -225,12 +239,11 @@ impl Scanner {
             }
             '#' => {
                 self.next_while(|&c| c != '\n');
-                self.next();
                 TokenType::COMMENT
             }
             '"' => return self.string(),
             'a'..='z' | 'A'..='Z' | '_' => return self.identifier(),
-            _ if c.is_digit(10) => {
+            _ if c.is_ascii_digit() => {
                 return self.number();
             }
             _ => {
@@ -238,35 +251,70 @@ impl Scanner {
                     token_type: TokenType::ERROR,
                     value: format!("Invalid char ({}) {}:{}", c, self.line, self.cur + 1).chars().collect(),
                     line: self.line,
+                    file: 0,
                 }
             }
         };
 
-        return Token {
-            token_type: token_type,
+        Token {
+            token_type,
             value: self.source_code[self.start..self.cur].to_vec(),
             line: self.line,
-        };
+            file: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub action: &'static str,
+    pub error: std::io::Error,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
+        write!(output, "Error while trying to {} a file \"{}\": {}", self.action, self.path.display(), self.error)
     }
 }
 
-pub fn get_file(file_path: &String) -> String {
+pub fn get_file(file_path: &Path) -> Result<String, FileError> {
     let mut file = match File::open(file_path) {
         Ok(file) => file,
-        Err(e) => {
-            error_message("FILE OPEN", format!("Error while trying to open a file: {:?}", e));
-            std::process::exit(1);
-        },
+        Err(e) => return Err(FileError { path: file_path.to_path_buf(), action: "open", error: e }),
     };
 
     let mut buffer: String = String::new();
     match file.read_to_string(&mut buffer) {
         Ok(_) => {}
-        Err(e) => {
-            error_message("FILE OPEN", format!("Error while trying to read a file: {:?}", e));
-            std::process::exit(1);
-        }
+        Err(e) => return Err(FileError { path: file_path.to_path_buf(), action: "read", error: e }),
     };
 
-    buffer
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same_tokens(commented: &str, plain: &str) {
+        assert_eq!(Scanner::init(commented).get_tokens(), Scanner::init(plain).get_tokens());
+    }
+
+    #[test]
+    fn trailing_comments_leave_tokens_unchanged() {
+        assert_same_tokens("var x: int = 5 # init\n", "var x: int = 5\n");
+        assert_same_tokens("x = x + 1 # bump\ns.x = 2 #field\n", "x = x + 1\ns.x = 2\n");
+        assert_same_tokens("if x > 1 { # header\n    x = 0\n} # done\n", "if x > 1 {\n    x = 0\n}\n");
+        assert_same_tokens("for i in (0, 10) { # loop\n}\n", "for i in (0, 10) {\n}\n");
+        assert_same_tokens("fn f() null {\n    var x: int = 5 # init\n}\n", "fn f() null {\n    var x: int = 5\n}\n");
+        assert_same_tokens("while x < 3 {\n    x = x + 1\n    }  # close\n}", "while x < 3 {\n    x = x + 1\n    }\n}");
+    }
+
+    #[test]
+    fn comment_at_end_of_file_stops_before_eof() {
+        assert_same_tokens("fn f() null {\n} # no newline", "fn f() null {\n} ");
+        assert_same_tokens("{ x = 1 #}", "{ x = 1 ");
+        assert_same_tokens("#", "");
+    }
 }
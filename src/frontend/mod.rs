@@ -1,2 +1,4 @@
+pub mod formatter;
 pub mod lexer;
+pub mod outline;
 pub mod tokens;
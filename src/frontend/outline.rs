@@ -0,0 +1,254 @@
+use crate::frontend::{lexer::Scanner, tokens::{Keywords, Token, TokenType}};
+
+// Declarations of a file read from its tokens only, function bodies are skipped,
+// so a file with errors inside bodies is still outlined
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FileOutline {
+    pub functions: Vec<FnOutline>,
+    pub structs: Vec<StructOutline>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnOutline {
+    pub name: String,
+    pub params: Vec<ParamOutline>,
+    // None when the output type is not written
+    pub output_type: Option<String>,
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamOutline {
+    pub name: String,
+    // self parameter of a method has the struct name as its type
+    pub param_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructOutline {
+    pub name: String,
+    pub fields: Vec<ParamOutline>,
+    // methods of the struct body followed by methods of "methods Name { }" blocks
+    pub methods: Vec<FnOutline>,
+    pub line: u32,
+}
+
+struct Outliner {
+    tokens: Vec<Token>,
+    cur: usize,
+}
+
+pub fn outline(source_code: &str) -> Result<FileOutline, String> {
+    let tokens = Scanner::init(source_code).get_tokens();
+
+    if let Some(token) = tokens.iter().find(|token| token.token_type == TokenType::ERROR) {
+        return Err(token.value.iter().collect::<String>())
+    }
+
+    let mut outliner = Outliner { tokens, cur: 0 };
+    let mut file = FileOutline::default();
+    // blocks may come before their struct, they are attached at the end
+    let mut extra_methods: Vec<(String, Vec<FnOutline>)> = vec![];
+
+    while !outliner.at_end() {
+        match outliner.peek().token_type {
+            TokenType::KEYWORD(Keywords::FN) => {
+                outliner.advance();
+                if let Some(function) = outliner.signature(None) {
+                    file.functions.push(function);
+                }
+            },
+            TokenType::KEYWORD(Keywords::STRUCT) => {
+                outliner.advance();
+                if let Some(struct_) = outliner.struct_outline() {
+                    file.structs.push(struct_);
+                }
+            },
+            TokenType::KEYWORD(Keywords::METHODS) => {
+                outliner.advance();
+                let name = outliner.text(outliner.cur);
+                outliner.advance();
+                extra_methods.push((name.clone(), outliner.methods_block(&name)));
+            },
+            TokenType::LEFT_BRACE => outliner.skip_block(),
+            _ => outliner.advance(),
+        }
+    }
+
+    for (name, methods) in extra_methods {
+        if let Some(struct_) = file.structs.iter_mut().find(|struct_| struct_.name == name) {
+            struct_.methods.extend(methods);
+        }
+    }
+
+    Ok(file)
+}
+
+impl Outliner {
+    fn at_end(&self) -> bool {
+        self.cur >= self.tokens.len() || self.tokens[self.cur].token_type == TokenType::EOF
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.cur.min(self.tokens.len() - 1)]
+    }
+
+    fn advance(&mut self) {
+        if !self.at_end() {
+            self.cur += 1;
+        }
+    }
+
+    fn text(&self, index: usize) -> String {
+        self.tokens.get(index).map(|token| token.value.iter().collect::<String>()).unwrap_or_default()
+    }
+
+    // Skips a balanced {...} group starting at the current token
+    fn skip_block(&mut self) {
+        let mut depth = 0;
+        while !self.at_end() {
+            match self.peek().token_type {
+                TokenType::LEFT_BRACE => depth += 1,
+                TokenType::RIGHT_BRACE => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return
+                    }
+                },
+                _ => {},
+            }
+            self.advance();
+        }
+    }
+
+    // Type tokens are joined back into source form: List<int>, (int, String)
+    fn type_text(&self, start: usize, end: usize) -> String {
+        let mut out = String::new();
+        for index in start..end {
+            out.push_str(&self.text(index));
+            if self.tokens[index].token_type == TokenType::COMMA {
+                out.push(' ');
+            }
+        }
+        out
+    }
+
+    // name(params) type { body }, current token is the name
+    fn signature(&mut self, self_type: Option<&str>) -> Option<FnOutline> {
+        if self.peek().token_type != TokenType::IDENTIFIER {
+            return None
+        }
+        let name = self.text(self.cur);
+        let line = self.peek().line;
+        self.advance();
+
+        if self.peek().token_type != TokenType::LEFT_PAREN {
+            return None
+        }
+        self.advance();
+
+        let mut params = vec![];
+        let mut param_start = self.cur;
+        let mut depth = 0;
+        while !self.at_end() {
+            match self.peek().token_type {
+                TokenType::LEFT_PAREN | TokenType::LESS => depth += 1,
+                TokenType::GREATER => depth -= 1,
+                TokenType::RIGHT_PAREN if depth > 0 => depth -= 1,
+                TokenType::COMMA | TokenType::RIGHT_PAREN if depth == 0 => {
+                    params.extend(self.param(param_start, self.cur, self_type));
+                    param_start = self.cur + 1;
+
+                    if self.peek().token_type == TokenType::RIGHT_PAREN {
+                        break
+                    }
+                },
+                _ => {},
+            }
+            self.advance();
+        }
+        self.advance();
+
+        let output_start = self.cur;
+        while !self.at_end() && self.peek().token_type != TokenType::LEFT_BRACE {
+            self.advance();
+        }
+        let output_type = match self.cur > output_start {
+            true => Some(self.type_text(output_start, self.cur)),
+            false => None,
+        };
+        self.skip_block();
+
+        Some(FnOutline { name, params, output_type, line })
+    }
+
+    // name: type, or self inside methods
+    fn param(&self, start: usize, end: usize, self_type: Option<&str>) -> Option<ParamOutline> {
+        if start >= end {
+            return None
+        }
+
+        let name = self.text(start);
+        if end - start == 1 {
+            return Some(ParamOutline { name, param_type: self_type.unwrap_or_default().to_string() })
+        }
+        Some(ParamOutline { name, param_type: self.type_text(start + 2, end) })
+    }
+
+    fn struct_outline(&mut self) -> Option<StructOutline> {
+        if self.peek().token_type != TokenType::IDENTIFIER {
+            return None
+        }
+        let name = self.text(self.cur);
+        let line = self.peek().line;
+        self.advance();
+
+        if self.peek().token_type != TokenType::LEFT_BRACE {
+            return None
+        }
+        self.advance();
+
+        let mut fields = vec![];
+        let mut methods = vec![];
+        while !self.at_end() && self.peek().token_type != TokenType::RIGHT_BRACE {
+            match self.peek().token_type {
+                TokenType::KEYWORD(Keywords::METHODS) => {
+                    self.advance();
+                    methods.extend(self.methods_block(&name));
+                },
+                TokenType::IDENTIFIER => {
+                    // field ends with a comma or at the end of its line
+                    let start = self.cur;
+                    while !self.at_end() && self.peek().line == self.tokens[start].line && !matches!(self.peek().token_type, TokenType::COMMA | TokenType::RIGHT_BRACE | TokenType::KEYWORD(Keywords::METHODS)) {
+                        self.advance();
+                    }
+                    fields.extend(self.param(start, self.cur, None));
+                },
+                _ => self.advance(),
+            }
+        }
+        self.advance();
+
+        Some(StructOutline { name, fields, methods, line })
+    }
+
+    // { method(self, ...) type { ... } ... }, current token is the opening brace
+    fn methods_block(&mut self, struct_name: &str) -> Vec<FnOutline> {
+        let mut methods = vec![];
+        if self.peek().token_type != TokenType::LEFT_BRACE {
+            return methods
+        }
+        self.advance();
+
+        while !self.at_end() && self.peek().token_type != TokenType::RIGHT_BRACE {
+            match self.signature(Some(struct_name)) {
+                Some(method) => methods.push(method),
+                None => self.advance(),
+            }
+        }
+        self.advance();
+
+        methods
+    }
+}
@@ -1,14 +1,14 @@
 use crate::vm::value::Convert;
 use crate::compiler::errors;
 
-use std::fmt;
-
 #[derive(Debug, PartialEq)]
 #[derive(Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub value: Vec<char>,
     pub line: u32,
+    // source file of a multi-file program, the position in Engine's file list, 0 for a single file
+    pub file: usize,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
@@ -42,30 +42,69 @@ pub enum TokenType {
     KEYWORD(Keywords),
     NATIVE_FN,
     STRUCT(usize),
+    // index of the tuple shape registered by the parser
+    TUPLE(usize),
     INT,
     FLOAT,
     BOOL,
     LIST,
+    // value of a Bytes instance, e.g. returned by fromString()
+    BYTES,
     NULL,
     ERROR,
     EOF,
 }
 
-impl std::fmt::Display for TokenType {
-    fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            TokenType::INT => write!(output, "INT"),
-            TokenType::FLOAT => write!(output, "FLOAT"),
-            TokenType::BOOL => write!(output, "BOOL"),
-            TokenType::STRING => write!(output, "STRING"),
-            v => {
-                errors::error_message("DISPLAY NOT IMPLEMENTED", format!("Writing \"{:?}\" to stdout is not allowed", v));
-                std::process::exit(1);
+impl Token {
+    // Token as written in source for parser errors, names and literals are quoted
+    pub fn describe(&self) -> String {
+        match self.token_type {
+            TokenType::IDENTIFIER | TokenType::STRING | TokenType::INT | TokenType::FLOAT => {
+                format!("{} \"{}\"", self.token_type.describe(), self.value.iter().collect::<String>())
             },
+            token_type => token_type.describe(),
         }
     }
 }
 
+impl TokenType {
+    // Expected token in parser errors, punctuation and keywords are shown as written
+    pub fn describe(&self) -> String {
+        let text = match self {
+            TokenType::LEFT_PAREN => "(",
+            TokenType::RIGHT_PAREN => ")",
+            TokenType::LEFT_BRACE => "{",
+            TokenType::RIGHT_BRACE => "}",
+            TokenType::LEFT_BRACKET => "[",
+            TokenType::RIGHT_BRACKET => "]",
+            TokenType::COMMA => ",",
+            TokenType::DOT => ".",
+            TokenType::MINUS => "-",
+            TokenType::PLUS => "+",
+            TokenType::STAR => "*",
+            TokenType::COLON => ":",
+            TokenType::SLASH => "/",
+            TokenType::MOD => "%",
+            TokenType::INTERJ => "!",
+            TokenType::INTERJ_EQ => "!=",
+            TokenType::EQ => "=",
+            TokenType::EQ_EQ => "==",
+            TokenType::GREATER => ">",
+            TokenType::GREATER_EQ => ">=",
+            TokenType::LESS => "<",
+            TokenType::LESS_EQ => "<=",
+            TokenType::KEYWORD(keyword) => keyword.text(),
+            TokenType::IDENTIFIER => return "identifier".to_string(),
+            TokenType::STRING => return "string".to_string(),
+            TokenType::INT => return "int".to_string(),
+            TokenType::FLOAT => return "float".to_string(),
+            TokenType::EOF => return "end of file".to_string(),
+            token_type => return format!("{:?}", token_type),
+        };
+        format!("'{}'", text)
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
 #[allow(non_camel_case_types)]
 pub enum Keywords {
@@ -83,7 +122,11 @@ pub enum Keywords {
     ELSE,
     AND,
     OR,
+    NOT,
     WHILE,
+    DO,
+    SWITCH,
+    CASE,
     FOR,
     BREAK,
     CONTINUE,
@@ -93,6 +136,7 @@ pub enum Keywords {
     INSTANCE(usize),
     METHODS,
     RETURN,
+    DISCARD,
 }
 
 impl std::str::FromStr for Keywords {
@@ -114,7 +158,11 @@ impl std::str::FromStr for Keywords {
             "else" => Ok(Keywords::ELSE),
             "and" => Ok(Keywords::AND),
             "or" => Ok(Keywords::OR),
+            "not" => Ok(Keywords::NOT),
             "while" => Ok(Keywords::WHILE),
+            "do" => Ok(Keywords::DO),
+            "switch" => Ok(Keywords::SWITCH),
+            "case" => Ok(Keywords::CASE),
             "for" => Ok(Keywords::FOR),
             "break" => Ok(Keywords::BREAK),
             "continue" => Ok(Keywords::CONTINUE),
@@ -124,11 +172,49 @@ impl std::str::FromStr for Keywords {
             "instance" => Ok(Keywords::INSTANCE(0)),
             "methods" => Ok(Keywords::METHODS),
             "return" => Ok(Keywords::RETURN),
+            "discard" => Ok(Keywords::DISCARD),
             _ => Err(()),
         }
     }
 }
 
+impl Keywords {
+    // inverse of from_str
+    pub fn text(&self) -> &'static str {
+        match self {
+            Keywords::VAR => "var",
+            Keywords::LIST => "list",
+            Keywords::INT => "int",
+            Keywords::FLOAT => "float",
+            Keywords::BOOL => "bool",
+            Keywords::STRING => "String",
+            Keywords::TRUE => "true",
+            Keywords::FALSE => "false",
+            Keywords::NULL => "null",
+            Keywords::IF => "if",
+            Keywords::ELIF => "elif",
+            Keywords::ELSE => "else",
+            Keywords::AND => "and",
+            Keywords::OR => "or",
+            Keywords::NOT => "not",
+            Keywords::WHILE => "while",
+            Keywords::DO => "do",
+            Keywords::SWITCH => "switch",
+            Keywords::CASE => "case",
+            Keywords::FOR => "for",
+            Keywords::BREAK => "break",
+            Keywords::CONTINUE => "continue",
+            Keywords::IN => "in",
+            Keywords::FN => "fn",
+            Keywords::STRUCT => "struct",
+            Keywords::INSTANCE(_) => "instance",
+            Keywords::METHODS => "methods",
+            Keywords::RETURN => "return",
+            Keywords::DISCARD => "discard",
+        }
+    }
+}
+
 impl Convert for Keywords {
     fn convert(&self) -> TokenType {
         match self {
@@ -138,7 +224,7 @@ impl Convert for Keywords {
             Keywords::STRING => TokenType::STRING,
             _ => {
                 errors::conversion_error("Enum Keyword<_>", "TokenType");
-                std::process::exit(1);
+                errors::terminate(errors::Termination::Compile);
             },
         }
     }
@@ -0,0 +1,11 @@
+pub mod frontend;
+pub mod vm;
+pub mod compiler;
+pub mod objects;
+pub mod std;
+pub mod engine;
+
+pub use compiler::errors::Termination;
+pub use engine::Engine;
+pub use frontend::tokens::TokenType;
+pub use vm::value::Value;
@@ -1,34 +1,152 @@
-use::std::env;
+use::std::{env, ffi::OsString, fs, path::{Path, PathBuf}};
 
-mod frontend;
-mod vm;
-mod compiler;
-mod objects;
-mod std;
+use shlang::{compiler::{self, errors::Termination}, frontend, objects::registry::NativeSet, vm::vm::VmLimits, Engine};
 
-fn run(file_path: &String) {
-    let source_code = frontend::lexer::get_file(file_path);
+// Every run of the binary ends here, so scripts wrapping shlang can tell the failures apart
+fn exit(result: Result<(), Termination>) -> ! {
+    let code = match result {
+        Ok(()) => 0,
+        Err(Termination::Failure) => 1,
+        // EX_DATAERR, EX_SOFTWARE and EX_IOERR of sysexits.h
+        Err(Termination::Compile) => 65,
+        Err(Termination::Runtime) => 70,
+        Err(Termination::Io) => 74,
+    };
+    ::std::process::exit(code)
+}
+
+fn run(file_paths: &[PathBuf], engine: Engine) -> Result<(), Termination> {
+    match file_paths {
+        [file_path] => engine.run_file(file_path),
+        _ => engine.run_files(file_paths),
+    }
+}
 
-    let mut scanner = frontend::lexer::Scanner::init(&source_code);
-    let tokens = scanner.get_tokens();
+fn limit_value<T: ::std::str::FromStr>(flag: &str, value: Option<&OsString>) -> Result<T, Termination> {
+    match value.and_then(|value| value.to_str()).and_then(|value| value.parse().ok()) {
+        Some(value) => Ok(value),
+        None => {
+            compiler::errors::error_message("ARGUMENT ERROR", format!("Expected a positive number after {}", flag));
+            Err(Termination::Failure)
+        },
+    }
+}
 
-    let mut compiler = compiler::compiler::Compiler::new(tokens);
+fn fmt(file_path: &Path, check: bool, write: bool) -> Result<(), Termination> {
+    let source_code = match frontend::lexer::get_file(file_path) {
+        Ok(source_code) => source_code,
+        Err(e) => {
+            compiler::errors::error_message("FILE OPEN", e.to_string());
+            return Err(Termination::Io)
+        },
+    };
 
-    let main_chunk = compiler.compile();
-    // println!("{:?}", main_chunk);
-    let mut vm = vm::vm::VM::new();
-    let main_frame = vm.declare_all(main_chunk);
+    let formatted = match frontend::formatter::format_source(&source_code) {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            compiler::errors::error_message("FORMAT ERROR", e);
+            return Err(Termination::Compile)
+        },
+    };
 
-    vm.frames.push(main_frame);
+    if check {
+        if formatted != source_code {
+            eprintln!("{} is not formatted", file_path.display());
+            return Err(Termination::Failure)
+        }
+        return Ok(())
+    }
+
+    if write {
+        if let Err(e) = fs::write(file_path, formatted) {
+            compiler::errors::error_message("FILE WRITE", format!("Error while trying to write a file \"{}\": {}", file_path.display(), e));
+            return Err(Termination::Io)
+        }
+        return Ok(())
+    }
+
+    print!("{}", formatted);
+    Ok(())
+}
 
-    vm.run();
+// stdout carries only program output, usage goes to stderr with the diagnostics
+fn usage() {
+    eprintln!("Usage: shlang [--strict] [--allow-float-eq] [--quiet] [--max-locals N] [--sandbox] [--max-heap-objects N] [--max-instructions N] [--timeout-ms N] [--with-source] [--disassemble] [--leak-report] [--step] [file name...]");
+    eprintln!("       shlang [flags] --run [function name] [file name...] [-- arguments]");
+    eprintln!("       shlang fmt [--check | -w] [file name]");
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    exit(run_cli(env::args_os().collect()))
+}
+
+fn run_cli(args: Vec<OsString>) -> Result<(), Termination> {
+
+    if args.len() > 1 && args[1] == "fmt" {
+        let flags: Vec<&OsString> = args[2..].iter().filter(|arg| arg.to_string_lossy().starts_with('-')).collect();
+        let files: Vec<&OsString> = args[2..].iter().filter(|arg| !arg.to_string_lossy().starts_with('-')).collect();
+
+        let check = flags.iter().any(|flag| *flag == "--check");
+        let write = flags.iter().any(|flag| *flag == "-w");
+        let unknown = flags.iter().any(|flag| *flag != "--check" && *flag != "-w");
+
+        if files.len() != 1 || unknown || (check && write) {
+            usage();
+            return Err(Termination::Failure)
+        }
+        return fmt(Path::new(files[0]), check, write)
+    }
+
+    // Engine reports interpreter panics as runtime errors with their message,
+    // the default hook would print it once more with the location
+    ::std::panic::set_hook(Box::new(|_| {}));
+
+    let mut engine = Engine::new();
+    let mut limits = VmLimits::default();
+    let mut files: Vec<PathBuf> = vec![];
+    let mut entry = None;
+    let mut entry_args = vec![];
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.to_str() {
+            Some("--strict") => engine.set_strict(true),
+            Some("--allow-float-eq") => engine.set_allow_float_eq(true),
+            Some("--quiet") => engine.set_quiet(true),
+            Some("--max-locals") => engine.set_max_locals(limit_value("--max-locals", iter.next())?),
+            Some("--sandbox") => engine.set_native_set(NativeSet::Sandbox),
+            Some("--max-heap-objects") => limits.max_heap_objects = Some(limit_value("--max-heap-objects", iter.next())?),
+            Some("--max-instructions") => limits.max_instructions = Some(limit_value("--max-instructions", iter.next())?),
+            Some("--timeout-ms") => limits.timeout_ms = Some(limit_value("--timeout-ms", iter.next())?),
+            Some("--with-source") => engine.set_with_source(true),
+            Some("--disassemble") => engine.set_disassemble(true),
+            Some("--leak-report") => engine.set_leak_report(true),
+            Some("--step") => engine.set_step(true),
+            Some("--run") => match iter.next().and_then(|name| name.to_str()) {
+                Some(name) if entry.is_none() => entry = Some(name.to_string()),
+                _ => {
+                    usage();
+                    return Err(Termination::Failure)
+                },
+            },
+            Some("--") if entry.is_some() => {
+                entry_args = iter.by_ref().map(|arg| arg.to_string_lossy().to_string()).collect();
+            },
+            _ if !arg.to_string_lossy().starts_with('-') => files.push(PathBuf::from(arg)),
+            _ => {
+                usage();
+                return Err(Termination::Failure)
+            },
+        }
+    }
+    engine.set_limits(limits);
+    if let Some(entry) = entry {
+        engine.set_entry(&entry, entry_args);
+    }
 
-    match args.len() {
-        2 => run(&args[1]),
-        _ => println!("Usage: shlang [file name]"),
+    if files.is_empty() {
+        usage();
+        return Err(Termination::Failure)
     }
+    run(&files, engine)
 }
@@ -0,0 +1,138 @@
+use std::vec;
+
+use crate::{compiler::errors, frontend::tokens::TokenType, vm::value::Value};
+
+use super::{functions::{Function, NativeFn}, ordered_map::OrderedMap, structs::Struct};
+
+pub struct BytesObj {}
+
+impl BytesObj {
+    pub fn init() -> Struct {
+        Struct {
+            name: "Bytes".to_string(),
+            locals: vec![],
+            output_type: TokenType::NULL,
+            field_count: 1,
+            methods: BytesMethods::get_methods(),
+            field_indices: OrderedMap::new(),
+            sort_key: None,
+            rc_counter: 1,
+            index: 0,
+        }
+    }
+}
+
+// Bytes instance keeps Value::Bytes as its only field, methods are called as natives
+// with that value as first argument. Methods changing the bytes return the new value,
+// compiler stores it back into the instance (see is_mutating)
+pub struct BytesMethods {}
+
+impl BytesMethods {
+    pub fn get_methods() -> OrderedMap<String, Function> {
+        OrderedMap::from([
+            ("len".to_string(), BytesMethods::pack_into_fn("len".to_string(), TokenType::INT, vec![])),
+            ("get".to_string(), BytesMethods::pack_into_fn("get".to_string(), TokenType::INT, vec![TokenType::INT])),
+            ("set".to_string(), BytesMethods::pack_into_fn("set".to_string(), TokenType::BYTES, vec![TokenType::INT, TokenType::INT])),
+            ("push".to_string(), BytesMethods::pack_into_fn("push".to_string(), TokenType::BYTES, vec![TokenType::INT])),
+            ("slice".to_string(), BytesMethods::pack_into_fn("slice".to_string(), TokenType::BYTES, vec![TokenType::INT, TokenType::INT])),
+            ("toString".to_string(), BytesMethods::pack_into_fn("toString".to_string(), TokenType::STRING, vec![])),
+        ])
+    }
+
+    pub fn get_methods_rc() -> Vec<NativeFn> {
+        vec![
+            NativeFn { name: "len".to_string(), function: BytesMethods::len, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "get".to_string(), function: BytesMethods::get, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "set".to_string(), function: BytesMethods::set, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "push".to_string(), function: BytesMethods::push, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "slice".to_string(), function: BytesMethods::slice, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "toString".to_string(), function: BytesMethods::to_string, arg_count: 1, rc_counter: 1, index: 0 },
+        ]
+    }
+
+    // position of method native, counted from Bytes struct symbol
+    pub fn get_native_offset(name: &str) -> Option<usize> {
+        BytesMethods::get_methods_rc()
+            .iter()
+            .position(|native| native.name == name)
+            .map(|pos| pos + 1)
+    }
+
+    pub fn is_mutating(name: &str) -> bool {
+        name == "set" || name == "push"
+    }
+
+    fn pack_into_fn(name: String, out_type: TokenType, args: Vec<TokenType>) -> Function {
+        let mut function = Function::new(name);
+
+        function.output_type = out_type;
+        function.is_self_arg = true;
+        function.arg_count = args.len();
+        function.arg_types = args;
+
+        function
+    }
+
+    fn index(bytes: &[u8], index: i64, inclusive_end: bool) -> usize {
+        let len = bytes.len() as i64 + if inclusive_end { 1 } else { 0 };
+        if index < 0 || index >= len {
+            errors::error_message("RUNTIME ERROR", format!("Bytes index out of range {}/{} {}:", index, bytes.len(), errors::runtime_line()));
+            errors::runtime_error();
+        }
+        index as usize
+    }
+
+    fn byte(value: i64) -> u8 {
+        match u8::try_from(value) {
+            Ok(byte) => byte,
+            Err(_) => {
+                errors::error_message("RUNTIME ERROR", format!("Byte value has to be between 0 and 255, found: {} {}:", value, errors::runtime_line()));
+                errors::runtime_error();
+            },
+        }
+    }
+
+    fn len(args: Vec<Value>) -> Value {
+        Value::Int(args[0].get_bytes().len() as i64)
+    }
+
+    fn get(args: Vec<Value>) -> Value {
+        let bytes = args[0].get_bytes();
+        Value::Int(bytes[BytesMethods::index(&bytes, args[1].get_int(), false)] as i64)
+    }
+
+    fn set(args: Vec<Value>) -> Value {
+        let mut bytes = args[0].get_bytes();
+        let index = BytesMethods::index(&bytes, args[1].get_int(), false);
+        bytes[index] = BytesMethods::byte(args[2].get_int());
+        Value::Bytes(bytes)
+    }
+
+    fn push(args: Vec<Value>) -> Value {
+        let mut bytes = args[0].get_bytes();
+        bytes.push(BytesMethods::byte(args[1].get_int()));
+        Value::Bytes(bytes)
+    }
+
+    // bytes from start up to end, end excluded
+    fn slice(args: Vec<Value>) -> Value {
+        let bytes = args[0].get_bytes();
+        let start = BytesMethods::index(&bytes, args[1].get_int(), true);
+        let end = BytesMethods::index(&bytes, args[2].get_int(), true);
+        if start > end {
+            errors::error_message("RUNTIME ERROR", format!("Bytes slice start {} is after its end {} {}:", start, end, errors::runtime_line()));
+            errors::runtime_error();
+        }
+        Value::Bytes(bytes[start..end].to_vec())
+    }
+
+    fn to_string(args: Vec<Value>) -> Value {
+        match String::from_utf8(args[0].get_bytes()) {
+            Ok(text) => Value::String(text),
+            Err(e) => {
+                errors::error_message("RUNTIME ERROR", format!("Bytes are not valid UTF-8, invalid sequence at position {} {}:", e.utf8_error().valid_up_to(), errors::runtime_line()));
+                errors::runtime_error();
+            },
+        }
+    }
+}
@@ -9,6 +9,7 @@ use crate::{
 pub enum SpecialType {
     String,
     List(Value),
+    Bytes,
     Null,
 }
 
@@ -30,6 +31,7 @@ pub struct Function {
     pub instances: Vec<Local>,
     pub output_type: TokenType,
     pub arg_count: usize,
+    pub arg_types: Vec<TokenType>,
     pub is_self_arg: bool,
     rc_counter: usize,
     index: usize,
@@ -71,12 +73,13 @@ impl rc::Object for Function {
 impl Function {
     pub fn new(name: String) -> Self {
         Self {
-            name: name,
+            name,
             chunk: bytecode::Chunk::new(),
             locals: vec![],
             instances: vec![],
             output_type: TokenType::KEYWORD(Keywords::NULL),
             arg_count: 0,
+            arg_types: vec![],
             is_self_arg: false,
             rc_counter: 1,
             index: 0,
@@ -96,6 +99,7 @@ impl Function {
     }
 }
 
+#[derive(Clone)]
 pub struct NativeFn {
     pub name: String,
     pub function: fn(Vec<Value>) -> Value,
@@ -126,9 +130,35 @@ impl NativeFn {
             Symbol { name: "roundf".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::FLOAT, arg_count: 2 },
             Symbol { name: "floorf".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::FLOAT, arg_count: 2 },
             Symbol { name: "ceilf".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::FLOAT, arg_count: 2 },  
+            Symbol { name: "hash".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::INT, arg_count: 1 },
+            Symbol { name: "clamp".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::INT, arg_count: 3 },
+            Symbol { name: "clampf".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::FLOAT, arg_count: 3 },
+            Symbol { name: "eofStdin".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::BOOL, arg_count: 0 },
+            Symbol { name: "approxEq".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::BOOL, arg_count: 3 },
+            Symbol { name: "parseInt".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::INT, arg_count: 2 },
+            Symbol { name: "toHex".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::STRING, arg_count: 1 },
+            Symbol { name: "toBin".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::STRING, arg_count: 1 },
+            Symbol { name: "toBase".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::STRING, arg_count: 2 },
+            Symbol { name: "fromString".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::BYTES, arg_count: 1 },
+            Symbol { name: "readFileBytes".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::BYTES, arg_count: 1 },
+            Symbol { name: "writeFileBytes".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 2 },
+            Symbol { name: "readLine".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::STRING, arg_count: 0 },
+            Symbol { name: "readAll".to_string(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::STRING, arg_count: 0 },
         ]
     }
 
+    // INT natives that also take FLOAT arguments, such calls are resolved to the FLOAT variant at compile time
+    pub fn get_float_overload(name: &str) -> Option<&'static str> {
+        match name {
+            "abs" => Some("absf"),
+            "min" => Some("minf"),
+            "max" => Some("maxf"),
+            "sqrt" => Some("sqrtf"),
+            "clamp" => Some("clampf"),
+            _ => None,
+        }
+    }
+
     pub fn get_natives_fn() -> Vec<NativeFn> {
         vec![
             NativeFn { name: "print".to_string(), function: std::print::print, arg_count: 1, rc_counter: 1, index: 0 },
@@ -150,10 +180,47 @@ impl NativeFn {
             NativeFn { name: "roundf".to_string(), function: std::math::round, arg_count: 2, rc_counter: 1, index: 0 },
             NativeFn { name: "floorf".to_string(), function: std::math::floor, arg_count: 2, rc_counter: 1, index: 0 },
             NativeFn { name: "ceilf".to_string(), function: std::math::ceil, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "hash".to_string(), function: std::hash::hash, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "clamp".to_string(), function: std::math::clamp_int, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "clampf".to_string(), function: std::math::clamp_float, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "eofStdin".to_string(), function: std::input::eof_stdin, arg_count: 0, rc_counter: 1, index: 0 },
+            NativeFn { name: "approxEq".to_string(), function: std::math::approx_eq, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "parseInt".to_string(), function: std::conv::parse_int, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "toHex".to_string(), function: std::conv::to_hex, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "toBin".to_string(), function: std::conv::to_bin, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "toBase".to_string(), function: std::conv::to_base, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "fromString".to_string(), function: std::bytes::from_string, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "readFileBytes".to_string(), function: std::bytes::read_file_bytes, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "writeFileBytes".to_string(), function: std::bytes::write_file_bytes, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "readLine".to_string(), function: std::input::read_line, arg_count: 0, rc_counter: 1, index: 0 },
+            NativeFn { name: "readAll".to_string(), function: std::input::read_all, arg_count: 0, rc_counter: 1, index: 0 },
         ]
     }
 }
 
+// Native registered by embedding application, it is declared right after builtin natives
+#[derive(Clone)]
+pub struct HostFn {
+    pub name: String,
+    pub arg_types: Vec<TokenType>,
+    pub output_type: TokenType,
+    // returned struct, looked up by name when the program is compiled
+    pub output_struct: Option<String>,
+    // element type of returned list, output_type is LIST then
+    pub list_element: Option<TokenType>,
+    pub function: fn(Vec<Value>) -> Value,
+}
+
+impl HostFn {
+    pub fn get_symbol(&self) -> Symbol {
+        Symbol { name: self.name.clone(), symbol_type: TokenType::NATIVE_FN, output_type: self.output_type, arg_count: self.arg_types.len() }
+    }
+
+    pub fn get_native(&self) -> NativeFn {
+        NativeFn { name: self.name.clone(), function: self.function, arg_count: self.arg_types.len(), rc_counter: 1, index: 0 }
+    }
+}
+
 impl rc::Object for NativeFn {
     fn inc_counter(&mut self) {
         self.rc_counter += 1;
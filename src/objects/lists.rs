@@ -1,8 +1,8 @@
-use std::{collections::HashMap, vec};
+use std::vec;
 
-use crate::frontend::tokens::TokenType; 
+use crate::{frontend::tokens::TokenType, vm::value::Value}; 
 
-use super::structs::Struct;
+use super::{functions::{Function, NativeFn}, ordered_map::OrderedMap, structs::Struct};
 
 pub struct ListObj {}
 
@@ -13,9 +13,77 @@ impl ListObj {
             locals: vec![],
             output_type: TokenType::NULL,
             field_count: 0,
-            methods: HashMap::new(),
+            methods: ListMethods::get_methods(),
+            field_indices: OrderedMap::new(),
+            sort_key: None,
             rc_counter: 1,
             index: 0,
         }
     }
 }
+
+// List methods are called directly as natives in the caller frame,
+// list values are passed as first argument (GET_LIST)
+pub struct ListMethods {}
+
+impl ListMethods {
+    pub fn get_methods() -> OrderedMap<String, Function> {
+        OrderedMap::from([
+            ("len".to_string(), ListMethods::pack_into_fn("len".to_string(), TokenType::INT, vec![])),
+            ("join".to_string(), ListMethods::pack_into_fn("join".to_string(), TokenType::STRING, vec![TokenType::STRING])),
+            // list locals are checked by LIST_IS_EMPTY, the native is for list values (fields)
+            ("isEmpty".to_string(), ListMethods::pack_into_fn("isEmpty".to_string(), TokenType::BOOL, vec![])),
+            // compiled into LIST_REMOVE, LIST_SWAP, LIST_FILL, LIST_EXTEND (push) and LIST_INSERT_AT, they have no natives,
+            // remove returns the element, fill, push and insert take one, all typed by the list
+            ("remove".to_string(), ListMethods::pack_into_fn("remove".to_string(), TokenType::NULL, vec![TokenType::INT])),
+            ("swap".to_string(), ListMethods::pack_into_fn("swap".to_string(), TokenType::NULL, vec![TokenType::INT, TokenType::INT])),
+            ("fill".to_string(), ListMethods::pack_into_fn("fill".to_string(), TokenType::NULL, vec![TokenType::NULL])),
+            ("push".to_string(), ListMethods::pack_into_fn("push".to_string(), TokenType::NULL, vec![TokenType::NULL])),
+            ("insert".to_string(), ListMethods::pack_into_fn("insert".to_string(), TokenType::NULL, vec![TokenType::INT, TokenType::NULL])),
+            // compiled into LIST_LOWER_BOUND and LIST_SORTED_INSERT, both take an element
+            ("lowerBound".to_string(), ListMethods::pack_into_fn("lowerBound".to_string(), TokenType::INT, vec![TokenType::NULL])),
+            ("sortedInsert".to_string(), ListMethods::pack_into_fn("sortedInsert".to_string(), TokenType::NULL, vec![TokenType::NULL])),
+        ])
+    }
+
+    pub fn get_methods_rc() -> Vec<NativeFn> {
+        vec![
+            NativeFn { name: "len".to_string(), function: ListMethods::len, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "join".to_string(), function: ListMethods::join, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "isEmpty".to_string(), function: ListMethods::is_empty, arg_count: 1, rc_counter: 1, index: 0 },
+        ]
+    }
+
+    // position of method native, counted from List struct symbol
+    pub fn get_native_offset(name: &str) -> Option<usize> {
+        ListMethods::get_methods_rc()
+            .iter()
+            .position(|native| native.name == name)
+            .map(|pos| pos + 1)
+    }
+
+    fn pack_into_fn(name: String, out_type: TokenType, args: Vec<TokenType>) -> Function {
+        let mut function = Function::new(name);
+
+        function.output_type = out_type;
+        function.is_self_arg = true;
+        function.arg_count = args.len();
+        function.arg_types = args;
+
+        function
+    }
+
+    fn len(args: Vec<Value>) -> Value {
+        Value::Int(args[0].get_list().len() as i64)
+    }
+
+    fn is_empty(args: Vec<Value>) -> Value {
+        Value::Bool(args[0].get_list().is_empty())
+    }
+
+    fn join(args: Vec<Value>) -> Value {
+        let separator = args[1].get_string();
+
+        Value::String(args[0].get_list().iter().map(|value| value.to_string()).collect::<Vec<String>>().join(&separator))
+    }
+}
@@ -3,3 +3,6 @@ pub mod functions;
 pub mod structs;
 pub mod string;
 pub mod lists;
+pub mod bytes;
+pub mod ordered_map;
+pub mod registry;
@@ -1,4 +1,4 @@
-use crate::vm::value::{self, Value};
+use crate::{compiler::errors, vm::value::{self, Value}};
 
 pub trait Object {
     fn inc_counter(&mut self);
@@ -11,19 +11,66 @@ pub trait Object {
     fn get_values(&self) -> Vec<value::Value>;
     fn set_value(&mut self, pos: usize, value: value::Value); 
     fn get_arg_count(&self) -> usize;
+
+    // appends a list element, objects other than instances ignore it
+    fn push_value(&mut self, _value: value::Value) {
+    }
+
+    // removes a list element, objects other than instances hold no elements
+    fn remove_value(&mut self, _pos: usize) -> value::Value {
+        value::Value::Null
+    }
+
+    // inserts a list element before the position, objects other than instances ignore it
+    fn insert_value(&mut self, _pos: usize, _value: value::Value) {
+    }
+
+    // same as get_values()[pos], without cloning the other values
+    fn get_value(&self, pos: usize) -> value::Value {
+        self.get_values()[pos].clone()
+    }
+
+    // same as get_values().len(), without cloning the values
+    fn value_count(&self) -> usize {
+        self.get_values().len()
+    }
+
+    // only RefObject points to another object, instance fields holding refs are plain values
+    fn is_ref(&self) -> bool {
+        false
+    }
+
+    // heap position of the struct declaration, for instances only
+    fn root_struct_pos(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub struct ReferenceCounter {
     pub heap: Vec<Box<dyn Object>>,
+    max_objects: Option<usize>,
+    declared: usize,
 }
 
 impl ReferenceCounter {
-    pub fn init() -> Self {
+    pub fn init(max_objects: Option<usize>) -> Self {
         Self {
             heap: vec![],
+            max_objects,
+            declared: 0,
         }
     }
 
+    // Functions, structs and natives declared before the run do not count into the limit
+    pub fn set_declared(&mut self) {
+        self.declared = self.heap.len();
+    }
+
+    // Released objects are removed from the heap, so its length is the live objects count
+    pub fn is_over_limit(&self) -> bool {
+        self.max_objects.is_some_and(|max_objects| self.heap.len() - self.declared > max_objects)
+    }
+
     pub fn push(&mut self, object: Box<dyn Object>) {
         self.heap.push(object);
     }
@@ -38,7 +85,8 @@ impl ReferenceCounter {
                 return i;
             }
         }
-        panic!();
+        errors::error_message("RUNTIME - CORRUPTED BYTECODE", format!("Object with index {} is not on the heap", index));
+        errors::runtime_error();
     }
 
     pub fn inc_counter(&mut self, index: usize) {
@@ -58,6 +106,10 @@ impl ReferenceCounter {
         }
     }
 
+    pub fn declared(&self) -> usize {
+        self.declared
+    }
+
     pub fn remove_all(&mut self) {
         self.heap = vec![];
     }
@@ -100,4 +152,8 @@ impl Object for RefObject {
     fn get_arg_count(&self) -> usize {
         0
     }
+
+    fn is_ref(&self) -> bool {
+        true
+    }
 }
\ No newline at end of file
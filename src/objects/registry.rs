@@ -0,0 +1,105 @@
+use crate::{
+    compiler::compiler::Symbol,
+    frontend::tokens::{Keywords, TokenType},
+    objects::{bytes::BytesMethods, functions::{HostFn, NativeFn}, lists::ListMethods, string::StringMethods},
+};
+
+// (name, output type, arg count) of builtin structs, declared in this order after the natives
+const BUILTIN_STRUCTS: [(&str, TokenType, usize); 3] = [
+    ("String", TokenType::STRING, 1),
+    ("List", TokenType::INT, 0),
+    ("Bytes", TokenType::BYTES, 1),
+];
+
+// Natives reaching outside of the program, stdin and files
+const STDIN_NATIVES: [&str; 4] = ["input", "eofStdin", "readLine", "readAll"];
+const FILE_NATIVES: [&str; 2] = ["readFileBytes", "writeFileBytes"];
+
+// Which natives a program may call, all of them stay declared so symbol and heap indices don't depend on it
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum NativeSet {
+    #[default]
+    All,
+    // --sandbox, calls to STDIN_NATIVES and FILE_NATIVES are rejected at compile time
+    Sandbox,
+}
+
+// Everything declared before the program: builtin natives, host natives in registration order,
+// then String, List and Bytes, each followed by its method natives. Compiler symbols and VM heap
+// are both built from it, so a native added to any table moves symbols and heap objects together
+#[derive(Clone, Default)]
+pub struct NativeRegistry {
+    host_fns: Vec<HostFn>,
+    native_set: NativeSet,
+}
+
+impl NativeRegistry {
+    pub fn new(host_fns: Vec<HostFn>) -> Self {
+        Self { host_fns, native_set: NativeSet::All }
+    }
+
+    pub fn set_native_set(&mut self, native_set: NativeSet) {
+        self.native_set = native_set;
+    }
+
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.native_set == NativeSet::Sandbox && (STDIN_NATIVES.contains(&name) || FILE_NATIVES.contains(&name))
+    }
+
+    // --step reads debugger commands from stdin, these natives would take them
+    pub fn reads_stdin(name: &str) -> bool {
+        STDIN_NATIVES.contains(&name)
+    }
+
+    pub fn host_fns(&self) -> &[HostFn] {
+        &self.host_fns
+    }
+
+    pub fn is_builtin(name: &str) -> bool {
+        NativeFn::get_natives_symbols().iter().any(|symbol| symbol.name == name) || BUILTIN_STRUCTS.iter().any(|(struct_name, _, _)| *struct_name == name)
+    }
+
+    // Builtin natives followed by host natives
+    pub fn natives(&self) -> Vec<NativeFn> {
+        let natives = NativeFn::get_natives_fn();
+        debug_assert!(natives.iter().map(|native| &native.name).eq(NativeFn::get_natives_symbols().iter().map(|symbol| &symbol.name)));
+
+        natives.into_iter().chain(self.host_fns.iter().map(|host_fn| host_fn.get_native())).collect()
+    }
+
+    // Method natives pushed right after the builtin struct, in get_native_offset order
+    pub fn methods(struct_name: &str) -> Vec<NativeFn> {
+        match struct_name {
+            "String" => StringMethods::get_methods_rc(),
+            "List" => ListMethods::get_methods_rc(),
+            "Bytes" => BytesMethods::get_methods_rc(),
+            _ => vec![],
+        }
+    }
+
+    pub fn struct_pos(&self, struct_name: &str) -> usize {
+        let mut pos = self.natives().len();
+        for (name, _, _) in BUILTIN_STRUCTS {
+            if name == struct_name {
+                return pos
+            }
+            pos += 1 + NativeRegistry::methods(name).len();
+        }
+        panic!("\"{}\" is not a builtin struct", struct_name)
+    }
+
+    // Symbols in heap order, method natives get blank names, so they are found only through their struct
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut symbols = NativeFn::get_natives_symbols();
+        symbols.extend(self.host_fns.iter().map(|host_fn| host_fn.get_symbol()));
+
+        for (name, output_type, arg_count) in BUILTIN_STRUCTS {
+            symbols.push(Symbol { name: name.to_string(), symbol_type: TokenType::KEYWORD(Keywords::STRUCT), output_type, arg_count });
+
+            for _ in NativeRegistry::methods(name) {
+                symbols.push(Symbol { name: String::new(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: 1 });
+            }
+        }
+        symbols
+    }
+}
@@ -1,18 +1,18 @@
-use std::{collections::HashMap, vec};
-use regex::Regex;
+use std::vec;
+use regex::{NoExpand, Regex};
 
 use crate::{
     frontend::tokens::{Keywords, TokenType}, 
-    vm::{bytecode::{Instruction, OpCode}, value::Value
+    compiler::errors, vm::{bytecode::{Instruction, OpCode}, value::Value
 }};
 
-use super::{functions::{Function, Local, NativeFn, SpecialType}, structs::Struct};
+use super::{functions::{Function, Local, NativeFn, SpecialType}, ordered_map::OrderedMap, structs::Struct};
 
 pub struct StringObj {}
 
 impl StringObj {
     pub fn init(string_pos: usize) -> Struct {
-        let mut mths = StringMethods { cur_pos: string_pos };
+        let mut mths = StringMethods { string_pos };
 
         Struct {
             name: "String".to_string(),
@@ -20,6 +20,8 @@ impl StringObj {
             output_type: TokenType::NULL,
             field_count: 1,
             methods: mths.get_methods(),
+            field_indices: OrderedMap::from([("value".to_string(), 0)]),
+            sort_key: None,
             rc_counter: 1,
             index: 0,
         }
@@ -27,24 +29,35 @@ impl StringObj {
 }
 
 pub struct StringMethods {
-    cur_pos: usize,
+    string_pos: usize,
 }
 
 impl StringMethods {
-    pub fn get_methods(&mut self) -> HashMap<String, Function> {
-        HashMap::from([
-            ("len".to_string(), self.pack_into_fn("len".to_string(), TokenType::INT, 1, TokenType::NULL)),
-            ("toLower".to_string(), self.pack_into_fn("toLower".to_string(), TokenType::STRING, 1, TokenType::NULL)),
-            ("toUpper".to_string(), self.pack_into_fn("toUpper".to_string(), TokenType::STRING, 1, TokenType::NULL)),
-            ("get".to_string(), self.pack_into_fn("get".to_string(), TokenType::STRING, 2, TokenType::INT)),
-            ("count".to_string(), self.pack_into_fn("count".to_string(), TokenType::INT, 2, TokenType::STRING)),
-            ("find".to_string(), self.pack_into_fn("find".to_string(), TokenType::INT, 2, TokenType::STRING)),
-            ("isChar".to_string(), self.pack_into_fn("isChar".to_string(), TokenType::BOOL, 1, TokenType::NULL)),
-            ("isDigit".to_string(), self.pack_into_fn("isDigit".to_string(), TokenType::BOOL, 1, TokenType::NULL)),
-            ("trim".to_string(), self.pack_into_fn("trim".to_string(), TokenType::STRING, 1, TokenType::NULL)),
-            ("trimLeft".to_string(), self.pack_into_fn("trimLeft".to_string(), TokenType::STRING, 1, TokenType::NULL)),
-            ("trimRight".to_string(), self.pack_into_fn("trimRight".to_string(), TokenType::STRING, 1, TokenType::NULL)),
-            ("replace".to_string(), self.pack_into_fn("replace".to_string(), TokenType::STRING, 3, TokenType::STRING)),
+    pub fn get_methods(&mut self) -> OrderedMap<String, Function> {
+        OrderedMap::from([
+            ("len".to_string(), self.pack_into_fn("len".to_string(), TokenType::INT, vec![])),
+            ("toLower".to_string(), self.pack_into_fn("toLower".to_string(), TokenType::STRING, vec![])),
+            ("toUpper".to_string(), self.pack_into_fn("toUpper".to_string(), TokenType::STRING, vec![])),
+            ("get".to_string(), self.pack_into_fn("get".to_string(), TokenType::STRING, vec![TokenType::INT])),
+            ("count".to_string(), self.pack_into_fn("count".to_string(), TokenType::INT, vec![TokenType::STRING])),
+            ("find".to_string(), self.pack_into_fn("find".to_string(), TokenType::INT, vec![TokenType::STRING])),
+            ("isChar".to_string(), self.pack_into_fn("isChar".to_string(), TokenType::BOOL, vec![])),
+            ("isDigit".to_string(), self.pack_into_fn("isDigit".to_string(), TokenType::BOOL, vec![])),
+            ("trim".to_string(), self.pack_into_fn("trim".to_string(), TokenType::STRING, vec![])),
+            ("trimLeft".to_string(), self.pack_into_fn("trimLeft".to_string(), TokenType::STRING, vec![])),
+            ("trimRight".to_string(), self.pack_into_fn("trimRight".to_string(), TokenType::STRING, vec![])),
+            ("replace".to_string(), self.pack_into_fn("replace".to_string(), TokenType::STRING, vec![TokenType::STRING, TokenType::STRING])),
+            ("chars".to_string(), self.pack_into_fn("chars".to_string(), TokenType::LIST, vec![])),
+            ("matches".to_string(), self.pack_into_fn("matches".to_string(), TokenType::BOOL, vec![TokenType::STRING])),
+            ("findPattern".to_string(), self.pack_into_fn("findPattern".to_string(), TokenType::INT, vec![TokenType::STRING])),
+            ("replacePattern".to_string(), self.pack_into_fn("replacePattern".to_string(), TokenType::STRING, vec![TokenType::STRING, TokenType::STRING])),
+            ("trimChars".to_string(), self.pack_into_fn("trimChars".to_string(), TokenType::STRING, vec![TokenType::STRING])),
+            ("trimLeftChars".to_string(), self.pack_into_fn("trimLeftChars".to_string(), TokenType::STRING, vec![TokenType::STRING])),
+            ("trimRightChars".to_string(), self.pack_into_fn("trimRightChars".to_string(), TokenType::STRING, vec![TokenType::STRING])),
+            ("findFrom".to_string(), self.pack_into_fn("findFrom".to_string(), TokenType::INT, vec![TokenType::STRING, TokenType::INT])),
+            ("countFrom".to_string(), self.pack_into_fn("countFrom".to_string(), TokenType::INT, vec![TokenType::STRING, TokenType::INT])),
+            ("replaceFirst".to_string(), self.pack_into_fn("replaceFirst".to_string(), TokenType::STRING, vec![TokenType::STRING, TokenType::STRING])),
+            ("isEmpty".to_string(), self.pack_into_fn("isEmpty".to_string(), TokenType::BOOL, vec![])),
         ])
     }
 
@@ -62,12 +75,31 @@ impl StringMethods {
             NativeFn { name: "trimLeft".to_string(), function: StringMethods::trim_left, arg_count: 1, rc_counter: 1, index: 0 },
             NativeFn { name: "trimRight".to_string(), function: StringMethods::trim_right, arg_count: 1, rc_counter: 1, index: 0 },
             NativeFn { name: "replace".to_string(), function: StringMethods::replace, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "chars".to_string(), function: StringMethods::chars, arg_count: 1, rc_counter: 1, index: 0 },
+            NativeFn { name: "matches".to_string(), function: StringMethods::matches, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "findPattern".to_string(), function: StringMethods::find_pattern, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "replacePattern".to_string(), function: StringMethods::replace_pattern, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "trimChars".to_string(), function: StringMethods::trim_chars, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "trimLeftChars".to_string(), function: StringMethods::trim_left_chars, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "trimRightChars".to_string(), function: StringMethods::trim_right_chars, arg_count: 2, rc_counter: 1, index: 0 },
+            NativeFn { name: "findFrom".to_string(), function: StringMethods::find_from, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "countFrom".to_string(), function: StringMethods::count_from, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "replaceFirst".to_string(), function: StringMethods::replace_first, arg_count: 3, rc_counter: 1, index: 0 },
+            NativeFn { name: "isEmpty".to_string(), function: StringMethods::is_empty, arg_count: 1, rc_counter: 1, index: 0 },
         ]
     }
 
-    pub fn pack_into_fn(&mut self, name: String, out_type: TokenType, arg_count: usize, arg_type: TokenType) -> Function {
-        self.cur_pos += 1;
+    // position of method native, counted from String struct symbol
+    pub fn get_native_offset(name: &str) -> Option<usize> {
+        StringMethods::get_methods_rc()
+            .iter()
+            .position(|native| native.name == name)
+            .map(|pos| pos + 1)
+    }
 
+    // Native gets the arguments in call order followed by self. String arguments are
+    // instances 1.., other arguments stay on the method stack
+    pub fn pack_into_fn(&mut self, name: String, out_type: TokenType, arg_types: Vec<TokenType>) -> Function {
         let mut function = Function::new(name);
 
         function.chunk.push_value(Value::String(String::new()));
@@ -75,19 +107,26 @@ impl StringMethods {
 
         function.output_type = out_type;
         function.is_self_arg = true;
-        function.arg_count = arg_count - 1;
+        function.arg_count = arg_types.len();
 
         function.instances.push(Local { name: "self".to_string(), local_type: TokenType::KEYWORD(Keywords::INSTANCE(3)), is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::Null });
-        
-        if arg_type == TokenType::STRING {
-            for i in 1..arg_count {
+
+        let mut stack_pos = 0;
+        for arg_type in arg_types.iter() {
+            if *arg_type == TokenType::STRING {
                 function.instances.push(Local { name: "".to_string(), local_type: TokenType::KEYWORD(Keywords::INSTANCE(3)), is_redirected: false, redirect_pos: 0, rf_index: 0, is_special: SpecialType::String });
-                function.chunk.push(Instruction { op: OpCode::GET_INSTANCE_FIELD(i, 0), line: 1});
+                function.chunk.push(Instruction { op: OpCode::GET_INSTANCE_FIELD(function.instances.len() - 1, 0), line: 1});
+            }else {
+                function.chunk.push(Instruction { op: OpCode::VAR_CALL(stack_pos), line: 1});
+                stack_pos += 1;
             }
         }
+        function.arg_types = arg_types;
 
         function.chunk.push(Instruction { op: OpCode::GET_INSTANCE_FIELD(0, 0), line: 1});
-        function.chunk.push(Instruction { op: OpCode::NATIVE_FN_CALL(self.cur_pos), line: 1});
+        // line 0 tells VM to report errors with the line of the method call
+        let native_pos = self.string_pos + StringMethods::get_native_offset(&function.name).unwrap();
+        function.chunk.push(Instruction { op: OpCode::NATIVE_FN_CALL(native_pos), line: 0});
 
         if out_type != TokenType::NULL {
             function.chunk.push(Instruction { op: OpCode::RETURN, line: 1});
@@ -95,7 +134,9 @@ impl StringMethods {
 
         function.chunk.push(Instruction { op: OpCode::CONSTANT_NULL(1), line: 1});
         function.chunk.push(Instruction { op: OpCode::RETURN, line: 1});
-        function.chunk.push(Instruction { op: OpCode::DEC_RC(0), line: 1});
+        for i in 0..function.instances.len() {
+            function.chunk.push(Instruction { op: OpCode::DEC_RC(i), line: 1});
+        }
         function.chunk.push(Instruction { op: OpCode::END_OF_FN, line: 1});
 
         function
@@ -134,16 +175,51 @@ impl StringMethods {
         }
     }
 
-    fn is_char(args: Vec<Value>) -> Value {
-        let pattern = Regex::new(r"^[^0-9]*$").unwrap();
+    // Byte offset of char index start, start past the end is clamped to the length
+    fn start_offset(str: &str, start: i64, method: &str) -> usize {
+        if start < 0 {
+            errors::error_message("RUNTIME ERROR", format!("Start index {} of {}() cannot be negative {}:", start, method, errors::runtime_line()));
+            errors::runtime_error();
+        }
 
-        Value::Bool(pattern.is_match(&args[0].get_string()))
+        str.char_indices().nth(start as usize).map(|(offset, _)| offset).unwrap_or(str.len())
     }
 
-    pub fn is_digit(args: Vec<Value>) -> Value {
-        let pattern = Regex::new(r"^[^a-zA-Z]*$").unwrap();
+    fn find_from(args: Vec<Value>) -> Value {
+        let str = args[2].get_string();
+        let offset = StringMethods::start_offset(&str, args[1].get_int(), "findFrom");
 
-        Value::Bool(pattern.is_match(&args[0].get_string()))
+        match str[offset..].find(&args[0].get_string()) {
+            Some(found) => Value::Int(str[..offset + found].chars().count() as i64),
+            None => Value::Int(-1),
+        }
+    }
+
+    fn count_from(args: Vec<Value>) -> Value {
+        let str = args[2].get_string();
+        let offset = StringMethods::start_offset(&str, args[1].get_int(), "countFrom");
+
+        Value::Int(str[offset..].match_indices(&args[0].get_string()).count() as i64)
+    }
+
+    fn replace_first(args: Vec<Value>) -> Value {
+        Value::String(args[2].get_string().replacen(&args[0].get_string(), &args[1].get_string(), 1))
+    }
+
+    // Non-empty and only letters, unicode letters included
+    fn is_char(args: Vec<Value>) -> Value {
+        let str = args[0].get_string();
+        Value::Bool(!str.is_empty() && str.chars().all(char::is_alphabetic))
+    }
+
+    // Non-empty and only ASCII digits, sign and fraction are left to conv and convf
+    fn is_digit(args: Vec<Value>) -> Value {
+        let str = args[0].get_string();
+        Value::Bool(!str.is_empty() && str.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    fn is_empty(args: Vec<Value>) -> Value {
+        Value::Bool(args[0].get_string().is_empty())
     }
 
     fn trim(args: Vec<Value>) -> Value {
@@ -158,7 +234,60 @@ impl StringMethods {
         Value::String(args[0].get_string().trim_end().to_string())
     }
 
+    fn trim_chars(args: Vec<Value>) -> Value {
+        let set = args[0].get_string().chars().collect::<Vec<char>>();
+        Value::String(args[1].get_string().trim_matches(|c| set.contains(&c)).to_string())
+    }
+
+    fn trim_left_chars(args: Vec<Value>) -> Value {
+        let set = args[0].get_string().chars().collect::<Vec<char>>();
+        Value::String(args[1].get_string().trim_start_matches(|c| set.contains(&c)).to_string())
+    }
+
+    fn trim_right_chars(args: Vec<Value>) -> Value {
+        let set = args[0].get_string().chars().collect::<Vec<char>>();
+        Value::String(args[1].get_string().trim_end_matches(|c| set.contains(&c)).to_string())
+    }
+
     fn replace(args: Vec<Value>) -> Value {
         Value::String(args[2].get_string().replace(&args[0].get_string(), &args[1].get_string()))
     }
+
+    fn chars(args: Vec<Value>) -> Value {
+        Value::ListObj(args[0].get_string().chars().map(|c| Value::String(c.to_string())).collect())
+    }
+
+    fn compile_pattern(pattern: &str) -> Regex {
+        match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                errors::error_message("RUNTIME ERROR", format!("Invalid pattern \"{}\": {} {}:", pattern, e, errors::runtime_line()));
+                errors::runtime_error();
+            },
+        }
+    }
+
+    fn matches(args: Vec<Value>) -> Value {
+        let pattern = args[0].get_string();
+        StringMethods::compile_pattern(&pattern);
+
+        // validated alone first, so error text does not show the anchors
+        let full = StringMethods::compile_pattern(&format!("^(?:{})$", pattern));
+        Value::Bool(full.is_match(&args[1].get_string()))
+    }
+
+    fn find_pattern(args: Vec<Value>) -> Value {
+        let str = args[1].get_string();
+
+        match StringMethods::compile_pattern(&args[0].get_string()).find(&str) {
+            Some(found) => Value::Int(str[..found.start()].chars().count() as i64),
+            None => Value::Int(-1),
+        }
+    }
+
+    fn replace_pattern(args: Vec<Value>) -> Value {
+        let pattern = StringMethods::compile_pattern(&args[0].get_string());
+
+        Value::String(pattern.replace_all(&args[2].get_string(), NoExpand(&args[1].get_string())).to_string())
+    }
 }
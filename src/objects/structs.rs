@@ -1,9 +1,7 @@
-use std::collections::HashMap;
-
 use crate::{
     frontend::tokens::{TokenType, Keywords},
     vm::value::Value,
-    objects::{rc::Object, functions::Local},
+    objects::{rc::Object, functions::Local, ordered_map::OrderedMap},
 };
 
 use super::functions::Function;
@@ -14,7 +12,11 @@ pub struct Struct {
     pub locals: Vec<Local>,
     pub output_type: TokenType,
     pub field_count: usize,
-    pub methods: HashMap<String, Function>,
+    pub methods: OrderedMap<String, Function>,
+    // field name -> position in locals, see index_fields
+    pub field_indices: OrderedMap<String, usize>,
+    // field compared by List.sort(), declared with "sortkey field" in the struct body
+    pub sort_key: Option<usize>,
     pub rc_counter: usize,
     pub index: usize,
 }
@@ -55,21 +57,38 @@ impl Object for Struct {
 impl Struct {
     pub fn new(name: String) -> Self {
         Self {
-            name: name,
+            name,
             locals: vec![],
             output_type: TokenType::KEYWORD(Keywords::NULL),
             field_count: 0,
-            methods: HashMap::new(),
+            methods: OrderedMap::new(),
+            field_indices: OrderedMap::new(),
+            sort_key: None,
             rc_counter: 1,
             index: 0,
         }
     }
+
+    // Called once every field is in locals, the first of repeated names wins
+    pub fn index_fields(&mut self) {
+        for (index, local) in self.locals.iter().enumerate() {
+            if !self.field_indices.contains_key(&local.name) {
+                self.field_indices.insert(local.name.clone(), index);
+            }
+        }
+    }
+
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.field_indices.get(name).copied()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructInstance {
     pub root_struct_pos: usize,
     pub fields_values: Vec<Value>,
+    // set by the VM for instances created by LIST_DEC and LIST_DEC_VALUE
+    pub is_list: bool,
     rc_counter: usize,
     index: usize,
 }
@@ -103,9 +122,33 @@ impl Object for StructInstance {
         self.fields_values[pos] = value;
     }
 
+    fn push_value(&mut self, value: Value) {
+        self.fields_values.push(value);
+    }
+
+    fn remove_value(&mut self, pos: usize) -> Value {
+        self.fields_values.remove(pos)
+    }
+
+    fn insert_value(&mut self, pos: usize, value: Value) {
+        self.fields_values.insert(pos, value);
+    }
+
+    fn get_value(&self, pos: usize) -> Value {
+        self.fields_values[pos].clone()
+    }
+
+    fn value_count(&self) -> usize {
+        self.fields_values.len()
+    }
+
     fn get_arg_count(&self) -> usize {
         0
     }
+
+    fn root_struct_pos(&self) -> Option<usize> {
+        Some(self.root_struct_pos)
+    }
 }
 
 impl StructInstance {
@@ -113,6 +156,7 @@ impl StructInstance {
         Self {
             root_struct_pos: pos,
             fields_values: vec![],
+            is_list: false,
             rc_counter: 1,
             index: 0,
         }
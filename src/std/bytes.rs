@@ -0,0 +1,27 @@
+use crate::{compiler::errors::{error_message, runtime_error, runtime_line}, vm::value::Value};
+
+pub fn from_string(args: Vec<Value>) -> Value {
+    Value::Bytes(args[0].get_string().into_bytes())
+}
+
+pub fn read_file_bytes(args: Vec<Value>) -> Value {
+    let path = args[0].get_string();
+
+    match std::fs::read(&path) {
+        Ok(bytes) => Value::Bytes(bytes),
+        Err(e) => {
+            error_message("RUNTIME ERROR", format!("Cannot read file \"{}\": {} {}:", path, e, runtime_line()));
+            runtime_error();
+        },
+    }
+}
+
+pub fn write_file_bytes(args: Vec<Value>) -> Value {
+    let path = args[0].get_string();
+
+    if let Err(e) = std::fs::write(&path, args[1].get_bytes()) {
+        error_message("RUNTIME ERROR", format!("Cannot write file \"{}\": {} {}:", path, e, runtime_line()));
+        runtime_error();
+    }
+    Value::Null
+}
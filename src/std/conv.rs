@@ -1,38 +1,35 @@
-use crate::{compiler::errors::error_message, vm::value::Value, objects::string::StringMethods};
+use crate::{compiler::errors::{error_message, runtime_error, runtime_line}, vm::value::Value};
 
+// Sign, digits with optional fraction and exponent, inf or nan in any case,
+// everything f64::from_str takes, so convstr output is always read back
 pub fn conv_to_float(args: Vec<Value>) -> Value {
     if args.len() != 1 {
         error_message("RUNTIME ERROR", "CONV only takes one argument".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     match args[0].clone() {
         Value::String(val_untrimed) => {
             let val = val_untrimed.trim().to_string();
-            
-            if !StringMethods::is_digit(args).get_bool() {
-                error_message("RUNTIME ERROR", format!("Cannot CONV this string, because it doesn't contains only digits"));
-                std::process::exit(1);
-            }
-            
+
             if val.is_empty() {
                 return Value::Float(0.0);
             }
 
             match val.parse::<f64>() {
-                Ok(v) => return Value::Float(v),
-                Err(e) => {
-                    error_message("RUNTIME ERROR", format!("Cannot CONV this string, because: {:?}", e));
-                    std::process::exit(1);
+                Ok(v) => Value::Float(v),
+                Err(_) => {
+                    error_message("RUNTIME ERROR", format!("Cannot CONV \"{}\" to float, expected digits with optional sign, fraction and exponent, inf or nan {}:", val, runtime_line()));
+                    runtime_error();
                 },
             }
         }
         Value::Int(val) => {
-            return Value::Float(val as f64);
+            Value::Float(val as f64)
         }
         _ => {
             error_message("RUNTIME ERROR", format!("CONV not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     }
 }
@@ -40,36 +37,31 @@ pub fn conv_to_float(args: Vec<Value>) -> Value {
 pub fn conv_to_int(args: Vec<Value>) -> Value {
     if args.len() != 1 {
         error_message("RUNTIME ERROR", "CONV only takes one argument".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     match args[0].clone() {
         Value::String(val_untrimed) => {
             let val = val_untrimed.trim().to_string();
-            
-            if !StringMethods::is_digit(args).get_bool() {
-                error_message("RUNTIME ERROR", format!("Cannot CONV this string, because it doesn't contains only digits"));
-                std::process::exit(1);
-            }
-            
+
             if val.is_empty() {
                 return Value::Int(0);
             }
 
             match val.parse::<i64>() {
-                Ok(v) => return Value::Int(v),
-                Err(e) => {
-                    error_message("RUNTIME ERROR", format!("Cannot CONV this string, because: {:?}", e));
-                    std::process::exit(1);
+                Ok(v) => Value::Int(v),
+                Err(_) => {
+                    error_message("RUNTIME ERROR", format!("Cannot CONV \"{}\" to int, expected digits with optional sign {}:", val, runtime_line()));
+                    runtime_error();
                 },
             }
         }
         Value::Float(val) => {
-            return Value::Int(val as i64);
+            Value::Int(val as i64)
         }
         _ => {
             error_message("RUNTIME ERROR", format!("CONV not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     }
 }
@@ -77,19 +69,174 @@ pub fn conv_to_int(args: Vec<Value>) -> Value {
 pub fn conv_to_string(args: Vec<Value>) -> Value {
     if args.len() != 1 {
         error_message("RUNTIME ERROR", "CONV only takes one argument".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     match args[0].clone() {
         Value::Int(val) => {
-            return Value::String(val.to_string());
+            Value::String(val.to_string())
         }
         Value::Float(val) => {
-            return Value::String(val.to_string());
+            Value::String(format_float(val))
         }
         _ => {
             error_message("RUNTIME ERROR", format!("CONV not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
+        }
+    }
+}
+
+// Magnitudes printed without exponent, 1e300 would take 301 digits otherwise
+const PLAIN_FLOATS: std::ops::Range<f64> = 1e-5..1e16;
+
+// Shortest text parsing back to the same float, both Display and LowerExp pick the fewest digits
+fn format_float(val: f64) -> String {
+    if val == 0.0 || !val.is_finite() || PLAIN_FLOATS.contains(&val.abs()) {
+        return val.to_string()
+    }
+    format!("{:e}", val)
+}
+
+fn check_radix(radix: i64) -> u32 {
+    if !(2..=36).contains(&radix) {
+        error_message("RUNTIME ERROR", format!("Radix has to be between 2 and 36, found: {} {}:", radix, runtime_line()));
+        runtime_error();
+    }
+    radix as u32
+}
+
+// Optional sign followed by digits of given radix, letters in any case
+pub fn parse_int(args: Vec<Value>) -> Value {
+    let (text, radix) = match (&args[0], &args[1]) {
+        (Value::String(text), Value::Int(radix)) => (text.clone(), check_radix(*radix)),
+        _ => {
+            error_message("RUNTIME ERROR", format!("PARSEINT expects String and int, found: \"{:?}\" {}:", args, runtime_line()));
+            runtime_error();
+        }
+    };
+
+    let digits = text.strip_prefix(['+', '-']).unwrap_or(&text);
+    if digits.is_empty() {
+        error_message("RUNTIME ERROR", format!("Cannot parse \"{}\" as int, no digits found {}:", text, runtime_line()));
+        runtime_error();
+    }
+
+    // positions are counted from 1 in the whole string, sign included
+    let sign_len = text.len() - digits.len();
+    if let Some((index, invalid)) = digits.chars().enumerate().find(|(_, c)| !c.is_digit(radix)) {
+        error_message("RUNTIME ERROR", format!("Invalid digit '{}' at position {} for radix {} in \"{}\" {}:", invalid, sign_len + index + 1, radix, text, runtime_line()));
+        runtime_error();
+    }
+
+    match i64::from_str_radix(&text, radix) {
+        Ok(value) => Value::Int(value),
+        Err(_) => {
+            error_message("RUNTIME ERROR", format!("Number \"{}\" does not fit in int {}:", text, runtime_line()));
+            runtime_error();
         }
     }
 }
+
+// Lowercase digits without prefix, negative numbers get a leading minus
+fn format_radix(value: i64, radix: u32) -> Value {
+    let mut magnitude = value.unsigned_abs();
+    let mut digits = vec![];
+    loop {
+        digits.push(std::char::from_digit((magnitude % radix as u64) as u32, radix).unwrap());
+        magnitude /= radix as u64;
+        if magnitude == 0 {
+            break
+        }
+    }
+    if value < 0 {
+        digits.push('-');
+    }
+
+    Value::String(digits.iter().rev().collect())
+}
+
+fn int_arg(name: &str, arg: &Value) -> i64 {
+    match arg {
+        Value::Int(val) => *val,
+        _ => {
+            error_message("RUNTIME ERROR", format!("{} not implemnted for this type: \"{:?}\" {}:", name, arg, runtime_line()));
+            runtime_error();
+        }
+    }
+}
+
+pub fn to_hex(args: Vec<Value>) -> Value {
+    format_radix(int_arg("TOHEX", &args[0]), 16)
+}
+
+pub fn to_bin(args: Vec<Value>) -> Value {
+    format_radix(int_arg("TOBIN", &args[0]), 2)
+}
+
+pub fn to_base(args: Vec<Value>) -> Value {
+    let radix = check_radix(int_arg("TOBASE", &args[1]));
+    format_radix(int_arg("TOBASE", &args[0]), radix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(val: f64) -> f64 {
+        conv_to_float(vec![conv_to_string(vec![Value::Float(val)])]).get_float()
+    }
+
+    #[test]
+    fn floats_survive_convstr_and_convf() {
+        let floats = [
+            0.0, -0.0, 0.1 + 0.2, 1.0 / 3.0, -2.5, 1e15, 1e16, 1e20, -1e300, f64::MAX, f64::MIN,
+            1e-5, 9.999e-6, f64::MIN_POSITIVE, f64::MIN_POSITIVE / 3.0, 5e-324, -5e-324,
+            f64::EPSILON, 123456789.12345679, f64::INFINITY, f64::NEG_INFINITY,
+        ];
+        for val in floats {
+            assert_eq!(round_trip(val).to_bits(), val.to_bits(), "{} printed as {:?}", val, conv_to_string(vec![Value::Float(val)]));
+        }
+        assert!(round_trip(f64::NAN).is_nan());
+
+        // spread of magnitudes and mantissas, xorshift keeps it deterministic
+        let mut state: u64 = 0x2545f4914f6cdd1d;
+        for _ in 0..10000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let val = f64::from_bits(state);
+            if !val.is_nan() {
+                assert_eq!(round_trip(val).to_bits(), val.to_bits(), "{:e}", val);
+            }
+        }
+    }
+
+    #[test]
+    fn convstr_uses_exponent_outside_plain_range() {
+        let text = |val: f64| conv_to_string(vec![Value::Float(val)]).get_string();
+        assert_eq!(text(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(text(1e20), "1e20");
+        assert_eq!(text(-2.5e-7), "-2.5e-7");
+        assert_eq!(text(123.5), "123.5");
+        assert_eq!(text(-0.0), "-0");
+    }
+
+    #[test]
+    fn convf_accepts_exponents_signs_and_special_values() {
+        let parse = |text: &str| conv_to_float(vec![Value::String(text.to_string())]).get_float();
+        assert_eq!(parse("1e20"), 1e20);
+        assert_eq!(parse(" +1.5E-3 "), 1.5e-3);
+        assert_eq!(parse("-inf"), f64::NEG_INFINITY);
+        assert_eq!(parse("Infinity"), f64::INFINITY);
+        assert!(parse("NaN").is_nan() && parse("nan").is_nan());
+    }
+
+    #[test]
+    fn conv_accepts_signs_and_surrounding_whitespace() {
+        let parse = |text: &str| conv_to_int(vec![Value::String(text.to_string())]).get_int();
+        assert_eq!(parse("-5"), -5);
+        assert_eq!(parse(" +42 "), 42);
+        assert_eq!(parse("007"), 7);
+        assert_eq!(parse(""), 0);
+    }
+}
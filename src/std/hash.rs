@@ -0,0 +1,44 @@
+use crate::{compiler::errors::{error_message, runtime_error}, vm::value::Value};
+
+// Stable across runs, results are non-negative (top bit is cleared):
+// STRING - FNV-1a 64 over UTF-8 bytes
+// INT, BOOL (as 0/1) - splitmix64 finalizer of the value
+// FLOAT - splitmix64 finalizer of IEEE bits (-0.0 as 0.0), so hash(1.0) != hash(1)
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+pub fn hash(args: Vec<Value>) -> Value {
+    if args.len() != 1 {
+        error_message("RUNTIME ERROR", "HASH only takes one argument".to_string());
+        runtime_error();
+    }
+
+    let hash = match args[0].clone() {
+        Value::String(val) => fnv1a(val.as_bytes()),
+        Value::Int(val) => mix(val as u64),
+        Value::Bool(val) => mix(val as u64),
+        Value::Float(val) => mix(if val == 0.0 { 0 } else { val.to_bits() }),
+        _ => {
+            error_message("RUNTIME ERROR", format!("HASH not implemnted for this type: \"{:?}\"", args[0]));
+            runtime_error();
+        }
+    };
+
+    Value::Int((hash & i64::MAX as u64) as i64)
+}
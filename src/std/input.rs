@@ -1,31 +1,57 @@
-use crate::{compiler::errors::error_message, vm::value::Value};
-use std::io::{self, BufRead};
+use crate::{compiler::errors::{error_message, runtime_error, runtime_line}, vm::value::Value};
+use std::{cell::Cell, io::{self, BufRead, Read}};
 
 use super::print::print;
 
+thread_local! {
+    // set when the last input() call found stdin exhausted
+    static STDIN_EOF: Cell<bool> = const { Cell::new(false) };
+}
+
 pub fn input(args: Vec<Value>) -> Value {
     if args.len() > 1 {
         error_message("RUNTIME ERROR", "Too much arguments for INPUT function".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     if args.len() == 1 {
         print(args);
     }
 
-    let stdin = io::stdin();
-    let mut input = stdin.lock();
+    read_line(vec![])
+}
 
+// Next line without its newline, empty at EOF
+pub fn read_line(_args: Vec<Value>) -> Value {
     let mut buffer = String::new();
-    match input.read_line(&mut buffer) {
-        Ok(_) => {
+    match io::stdin().lock().read_line(&mut buffer) {
+        Ok(read) => {
+            STDIN_EOF.with(|eof| eof.set(read == 0));
             buffer = buffer.trim_end_matches('\n').to_string();
         },
-        Err(_) => {
-            error_message("INPUT ERROR", "Failed to get input".to_string());
-            std::process::exit(1);
-        },
+        Err(err) => read_error(err),
     }
 
     Value::String(buffer)
-}
\ No newline at end of file
+}
+
+// Rest of stdin, including lines not ended by a newline
+pub fn read_all(_args: Vec<Value>) -> Value {
+    let mut buffer = String::new();
+    if let Err(err) = io::stdin().lock().read_to_string(&mut buffer) {
+        read_error(err);
+    }
+    STDIN_EOF.with(|eof| eof.set(true));
+
+    Value::String(buffer)
+}
+
+fn read_error(err: io::Error) -> ! {
+    error_message("RUNTIME ERROR", format!("Failed to read input: {} {}:", err, runtime_line()));
+    runtime_error();
+}
+
+// Empty line read from stdin is not EOF, only a read of zero bytes is
+pub fn eof_stdin(_args: Vec<Value>) -> Value {
+    Value::Bool(STDIN_EOF.with(|eof| eof.get()))
+}
@@ -1,23 +1,23 @@
-use crate::{compiler::errors::error_message, vm::value::Value};
+use crate::{compiler::errors::{error_message, runtime_error}, vm::value::Value};
 
 
 pub fn abs_int(args: Vec<Value>) -> Value {
     if args.len() != 1 {
         error_message("RUNTIME ERROR", "ABS only takes one argument".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use ABS on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use ABS on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
-            return Value::Int(val.abs());
+            Value::Int(val.abs())
         },
         _ => {
             error_message("RUNTIME ERROR", format!("ABSINT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     }
 }
@@ -25,20 +25,20 @@ pub fn abs_int(args: Vec<Value>) -> Value {
 pub fn abs_float(args: Vec<Value>) -> Value {
     if args.len() != 1 {
         error_message("RUNTIME ERROR", "ABS only takes one argument".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use ABS on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use ABS on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
-            return Value::Float(val.abs());
+            Value::Float(val.abs())
         },
         _ => {
             error_message("RUNTIME ERROR", format!("ABSFLOAT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     }
 }
@@ -46,34 +46,34 @@ pub fn abs_float(args: Vec<Value>) -> Value {
 pub fn pow_int(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "POW only takes two arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use POW on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use POW on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("POWINT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
     let b = match args[1].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use POW on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use POW on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("POWINT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -83,34 +83,34 @@ pub fn pow_int(args: Vec<Value>) -> Value {
 pub fn pow_float(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "POW only takes two arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use POW on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use POW on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("POWFLOAT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
     let b = match args[1].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use POW on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use POW on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("POWFLOAT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -120,34 +120,34 @@ pub fn pow_float(args: Vec<Value>) -> Value {
 pub fn min_int(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "MIN takes only two arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MIN on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MIN on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MININT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
     let b = match args[1].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MIN on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MIN on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MININT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -157,34 +157,34 @@ pub fn min_int(args: Vec<Value>) -> Value {
 pub fn min_float(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "MIN takes only two arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MIN on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MIN on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MININT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
     let b = match args[1].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MIN on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MIN on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MINFLOAT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -194,34 +194,34 @@ pub fn min_float(args: Vec<Value>) -> Value {
 pub fn max_int(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "MAX takes only two arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MAX on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MAX on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MAXINT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
     let b = match args[1].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MAX on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MAX on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MAXINT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -231,34 +231,34 @@ pub fn max_int(args: Vec<Value>) -> Value {
 pub fn max_float(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "MAX takes only two arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MAX on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MAX on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MAXFLOAT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
     let b = match args[1].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use MAX on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use MAX on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("MAXFLOAT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -268,20 +268,20 @@ pub fn max_float(args: Vec<Value>) -> Value {
 pub fn sqrt_int(args: Vec<Value>) -> Value {
     if args.len() != 1{
         error_message("RUNTIME ERROR", "SQRT takes only one arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use SQRT on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use SQRT on string type".to_string());
+            runtime_error();
         },
         Value::Int(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("SQRTINT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -291,20 +291,20 @@ pub fn sqrt_int(args: Vec<Value>) -> Value {
 pub fn sqrt_float(args: Vec<Value>) -> Value {
     if args.len() != 1{
         error_message("RUNTIME ERROR", "SQRT takes only one arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use SQRT on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use SQRT on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("SQRTFLOAT not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -314,20 +314,20 @@ pub fn sqrt_float(args: Vec<Value>) -> Value {
 pub fn round(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "ROUND takes only one arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use ROUND on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use ROUND on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("ROUND not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -337,7 +337,7 @@ pub fn round(args: Vec<Value>) -> Value {
         }
         _ => {
             error_message("RUNTIME ERROR", format!("ROUND: point to where number should be rounded can by only INT TYPE, not: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -348,20 +348,20 @@ pub fn round(args: Vec<Value>) -> Value {
 pub fn floor(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "ROUND takes only one arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use ROUND on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use ROUND on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("ROUND not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -371,7 +371,7 @@ pub fn floor(args: Vec<Value>) -> Value {
         }
         _ => {
             error_message("RUNTIME ERROR", format!("ROUND: point to where number should be rounded can by only INT TYPE, not: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -382,20 +382,20 @@ pub fn floor(args: Vec<Value>) -> Value {
 pub fn ceil(args: Vec<Value>) -> Value {
     if args.len() != 2 {
         error_message("RUNTIME ERROR", "ROUND takes only one arguments".to_string());
-        std::process::exit(1);
+        runtime_error();
     }
 
     let a = match args[0].clone() {
         Value::String(_) => {
-            error_message("RUNTIME ERROR", format!("Cannot use ROUND on string type"));
-            std::process::exit(1);
+            error_message("RUNTIME ERROR", "Cannot use ROUND on string type".to_string());
+            runtime_error();
         },
         Value::Float(val) => {
             val
         }
         _ => {
             error_message("RUNTIME ERROR", format!("ROUND not implemnted for this type: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
@@ -405,10 +405,72 @@ pub fn ceil(args: Vec<Value>) -> Value {
         }
         _ => {
             error_message("RUNTIME ERROR", format!("ROUND: point to where number should be rounded can by only INT TYPE, not: \"{:?}\"", args[0]));
-            std::process::exit(1);
+            runtime_error();
         }
     };
 
     let scale_factor = 10f64.powi(to as i32);
     Value::Float((a * scale_factor).ceil() / scale_factor) 
-}
\ No newline at end of file
+}
+pub fn clamp_int(args: Vec<Value>) -> Value {
+    if args.len() != 3 {
+        error_message("RUNTIME ERROR", "CLAMP takes only three arguments".to_string());
+        runtime_error();
+    }
+
+    let values: Vec<i64> = args.iter().map(|arg| match arg {
+        Value::Int(val) => *val,
+        _ => {
+            error_message("RUNTIME ERROR", format!("CLAMPINT not implemnted for this type: \"{:?}\"", arg));
+            runtime_error();
+        }
+    }).collect();
+
+    if values[1] > values[2] {
+        error_message("RUNTIME ERROR", format!("CLAMP lower bound {} is greater than upper bound {}", values[1], values[2]));
+        runtime_error();
+    }
+
+    Value::Int(values[0].clamp(values[1], values[2]))
+}
+
+pub fn clamp_float(args: Vec<Value>) -> Value {
+    if args.len() != 3 {
+        error_message("RUNTIME ERROR", "CLAMP takes only three arguments".to_string());
+        runtime_error();
+    }
+
+    let values: Vec<f64> = args.iter().map(|arg| match arg {
+        Value::Float(val) => *val,
+        _ => {
+            error_message("RUNTIME ERROR", format!("CLAMPFLOAT not implemnted for this type: \"{:?}\"", arg));
+            runtime_error();
+        }
+    }).collect();
+
+    if values[1] > values[2] || values[1].is_nan() || values[2].is_nan() {
+        error_message("RUNTIME ERROR", format!("CLAMP lower bound {} is greater than upper bound {}", values[1], values[2]));
+        runtime_error();
+    }
+
+    Value::Float(values[0].clamp(values[1], values[2]))
+}
+
+// |a - b| <= eps, so the difference of exactly eps is still equal.
+// Any NaN argument gives false, as do two infinities of the same sign (their difference is NaN)
+pub fn approx_eq(args: Vec<Value>) -> Value {
+    if args.len() != 3 {
+        error_message("RUNTIME ERROR", "APPROXEQ takes only three arguments".to_string());
+        runtime_error();
+    }
+
+    let values: Vec<f64> = args.iter().map(|arg| match arg {
+        Value::Float(val) => *val,
+        _ => {
+            error_message("RUNTIME ERROR", format!("APPROXEQ not implemnted for this type: \"{:?}\"", arg));
+            runtime_error();
+        }
+    }).collect();
+
+    Value::Bool((values[0] - values[1]).abs() <= values[2])
+}
@@ -1,4 +1,6 @@
 pub mod print;
 pub mod input;
 pub mod conv;
-pub mod math;
\ No newline at end of file
+pub mod math;
+pub mod hash;
+pub mod bytes;
@@ -8,11 +8,11 @@ pub fn println(args: Vec<Value>) -> Value {
     let stdout = io::stdout();
     let mut output = stdout.lock();
 
-    match write!(output, "\n") {
+    match writeln!(output) {
         Ok(_) => {},
         Err(_) => {
-            errors::error_message("PRINTING ERROR", format!("Failed to write newline to stdout"));
-            std::process::exit(1);
+            errors::error_message("PRINTING ERROR", "Failed to write newline to stdout".to_string());
+            errors::runtime_error();
         },
     };
 
@@ -28,7 +28,7 @@ pub fn print(args: Vec<Value>) -> Value {
             Ok(_) => {},
             Err(_) => {
                 errors::error_message("PRINTING ERROR", format!("Failed to write to stdout {}", arg));
-                std::process::exit(1);
+                errors::runtime_error();
             },
         };
     }
@@ -36,8 +36,8 @@ pub fn print(args: Vec<Value>) -> Value {
     match output.flush() {
         Ok(_) => {},
         Err(_) => {
-            errors::error_message("PRINTING ERROR", format!("Failed to flush stdout"));
-            std::process::exit(1);
+            errors::error_message("PRINTING ERROR", "Failed to flush stdout".to_string());
+            errors::runtime_error();
         },
     }
 
@@ -12,26 +12,83 @@ pub enum OpCode {
     
     STRUCT_DEC(structs::Struct),
     INSTANCE_DEC(structs::StructInstance, usize),
+    // pops Value::HostStruct returned by the host native and declares the instance from its fields
+    HOST_STRUCT_DEC(usize, structs::StructInstance),
     GET_INSTANCE_FIELD(usize, usize),
     SET_INSTANCE_FIELD(usize, usize),
     GET_INSTANCE_RF(usize),
     GET_INSTANCE_W_OFFSET_RF(usize),
+    // pushes fields of the instance as InstanceObj, struct copied into a List field
+    INSTANCE_VALUE(usize),
+    // pops InstanceObj and declares the instance from its fields
+    INSTANCE_DEC_VALUE(structs::StructInstance),
     METHOD_CALL(functions::Function),
 
     GET_LIST_FIELD(usize),
     GET_LIST(usize),
     SET_LIST_FIELD(usize),
+    LIST_DEC(structs::StructInstance, usize),
+    LIST_DEC_VALUE(structs::StructInstance),
+    GET_LIST_ELEMENT_FIELD(usize, usize),
+    SET_LIST_ELEMENT_FIELD(usize, usize),
+    GET_LIST_ELEMENT_RF(usize),
+    LIST_FILLED,
+    LIST_FROM_RANGE,
+    // stable in-place sort by key, None sorts String elements by length,
+    // otherwise the zero-argument method is called once per struct element
+    LIST_SORT_KEY(usize, Option<functions::Function>),
+    // stable in-place sort, struct elements are compared by the field at the index (sortkey),
+    // None compares int, float or String elements themselves
+    LIST_SORT(usize, Option<usize>),
+    // pops list (pushed by GET_LIST) and value, pushes whether list has an equal element
+    LIST_CONTAINS,
+    // pops two lists (pushed by GET_LIST), pushes list with elements of both
+    LIST_CONCAT,
+    // pops list and appends its elements to the list local
+    LIST_EXTEND(usize),
+    // pops index, removes the element from the list local and pushes it
+    LIST_REMOVE(usize),
+    // pops two indices and swaps the elements of the list local, pushes Null
+    LIST_SWAP(usize),
+    // pops value and index and inserts the value into the list local at the index, pushes Null
+    LIST_INSERT_AT(usize),
+    // pops value and sets every element of the list local to it, pushes Null
+    LIST_FILL(usize),
+    // pushes whether the list local has no elements, the list is not copied on the stack
+    LIST_IS_EMPTY(usize),
+    // pops value and pushes the index of the first element of the sorted list local not less than it
+    LIST_LOWER_BOUND(usize),
+    // pops value and inserts it into the sorted list local at its lower bound, pushes Null
+    LIST_SORTED_INSERT(usize),
+    // List<T> fields of structs hold their elements by value, struct elements as InstanceObj:
+    // pops count elements and pushes them as a list value
+    LIST_PACK(usize),
+    // pops element, index and list value, pushes the list with the element inserted at the index
+    LIST_INSERT,
+    // pops index and list value, pushes the element, or the field at the index of a struct element
+    LIST_INDEX(Option<usize>),
+
+    TUPLE_PACK(usize),
+    // String elements are moved into new instances, other elements are pushed on the stack
+    TUPLE_UNPACK(Vec<Option<structs::StructInstance>>),
 
     IF_STMT_OFFSET(usize),
+    // and/or short circuit, jumps forward when the Bool on top is false, unlike IF_STMT_OFFSET
+    // it leaves a pending break to the loop condition
+    JUMP_IF_FALSE(usize),
     JUMP(usize),
 
     LOOP(usize),
     BREAK,
+    // for loop step: counter += step, jumps forward by the offset when counter > limit
+    FOR_LOOP(usize, usize, usize, usize),
 
     VAR_CALL(usize),
     VAR_SET(usize),
 
     POP,
+    // instance returned by a call (top of the heap) gets the index the caller refers to it by
+    INSTANCE_INDEX(usize),
     DEC_RC(usize),
     DEC_TO(usize),
     INC_RC(usize),
@@ -40,9 +97,11 @@ pub enum OpCode {
 
     STRING_DEC(structs::StructInstance),
     STRING_DEC_VALUE(structs::StructInstance),
+    BYTES_DEC_VALUE(structs::StructInstance),
     ADD_STRING,
     EQ_STRING,
     NEG_EQ_STRING,
+    CONTAINS_STRING,
 
     CONSTANT_BOOL(usize),
     EQ_BOOL,
@@ -78,6 +137,10 @@ pub enum OpCode {
 
     NEGATE,
 
+    // dbg(): prints the stack top to stderr with its type name, the String constant at the index,
+    // the value stays on the stack. Struct variable is printed from its instance instead, nothing is pushed
+    DBG(u32, usize, Option<usize>),
+
     RETURN,
     END_OF_FN,
 }
@@ -88,10 +151,52 @@ pub struct Instruction {
     pub line: u32,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub start: usize,
+    pub end: usize,
+    pub kept: usize,
+    pub line: u32,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Chunk { 
     pub code: Vec<Instruction>,
     pub values: ValuesArray,
+    pub statements: Vec<Statement>,
+    // text of the whole source file, retained only when compiling with --with-source
+    pub source_lines: Vec<String>,
+    // source file the chunk was compiled from, see Token::file
+    pub file: usize,
+    // deepest the stack gets, arguments included, frames are preallocated with it.
+    // 0 for chunks not built by the compiler, compiled chunks always return a value
+    pub max_stack: usize,
+}
+
+// Nested functions and instances are shown by name or root struct position, not dumped whole
+pub(crate) fn op_text(op: &OpCode) -> String {
+    match op {
+        OpCode::FUNCTION_DEC(function) => format!("FUNCTION_DEC({}, {} args)", function.name, function.arg_count),
+        OpCode::METHOD_CALL(function) => format!("METHOD_CALL({}, {} args)", function.name, function.arg_count),
+        OpCode::STRUCT_DEC(struct_) => format!("STRUCT_DEC({})", struct_.name),
+        OpCode::INSTANCE_DEC(instance, count) => format!("INSTANCE_DEC(struct {}, {})", instance.root_struct_pos, count),
+        OpCode::HOST_STRUCT_DEC(index, instance) => format!("HOST_STRUCT_DEC({}, struct {})", index, instance.root_struct_pos),
+        OpCode::INSTANCE_DEC_VALUE(instance) => format!("INSTANCE_DEC_VALUE(struct {})", instance.root_struct_pos),
+        OpCode::LIST_DEC(_, count) => format!("LIST_DEC({})", count),
+        OpCode::LIST_DEC_VALUE(_) => "LIST_DEC_VALUE".to_string(),
+        OpCode::STRING_DEC(_) => "STRING_DEC".to_string(),
+        OpCode::STRING_DEC_VALUE(_) => "STRING_DEC_VALUE".to_string(),
+        OpCode::BYTES_DEC_VALUE(_) => "BYTES_DEC_VALUE".to_string(),
+        OpCode::TUPLE_UNPACK(elements) => format!("TUPLE_UNPACK({})", elements.len()),
+        OpCode::LIST_SORT_KEY(pos, mth) => format!("LIST_SORT_KEY({}, {})", pos, mth.as_ref().map_or("len", |mth| mth.name.as_str())),
+        op => format!("{:?}", op),
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chunk {
@@ -99,7 +204,39 @@ impl Chunk {
         Self {
             code: vec![],
             values: ValuesArray::init(),
+            statements: vec![],
+            source_lines: vec![],
+            file: 0,
+            max_stack: 0,
+        }
+    }
+
+    pub fn source_line(&self, line: u32) -> Option<&str> {
+        (line as usize).checked_sub(1).and_then(|index| self.source_lines.get(index)).map(|text| text.trim())
+    }
+
+    // Instructions are grouped under the source line they were compiled from,
+    // without retained source only the line number is shown
+    pub fn disassemble(&self, name: &str) -> String {
+        let mut out = format!("== {} ==\n     max stack {}\n", name, self.max_stack);
+        let mut cur_line = None;
+
+        for (offset, instruction) in self.code.iter().enumerate() {
+            if cur_line != Some(instruction.line) {
+                cur_line = Some(instruction.line);
+                match self.source_line(instruction.line) {
+                    Some(text) => out.push_str(&format!("{:>4} | {}\n", instruction.line, text)),
+                    None => out.push_str(&format!("{:>4}\n", instruction.line)),
+                }
+            }
+            out.push_str(&format!("     {:04} {}\n", offset, op_text(&instruction.op)));
         }
+        out
+    }
+
+    // kept - number of values statement leaves on the stack on purpose (declared locals)
+    pub fn mark_statement(&mut self, start: usize, kept: usize, line: u32) {
+        self.statements.push(Statement { start, end: self.code.len(), kept, line });
     }
 
     pub fn push(&mut self, instruction: Instruction) {
@@ -0,0 +1,90 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    compiler::errors,
+    objects::registry::NativeRegistry,
+    vm::{bytecode::{op_text, Instruction, OpCode}, vm::VM},
+};
+
+const SHOWN_STACK: usize = 5;
+
+// --step mode, the VM stops before every instruction of the main dispatch loop and waits for a command:
+//   s or empty line - run this instruction, c - run to the end without stopping,
+//   p n - print stack slot n, h - heap summary, q - quit
+// End of input works as c. Frames run by natives (sortKey keys) are not stopped in
+pub struct Debugger {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    stepping: bool,
+}
+
+impl Debugger {
+    pub fn new(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Self { input, output, stepping: true }
+    }
+
+    pub fn stdio() -> Self {
+        Self::new(Box::new(io::BufReader::new(io::stdin())), Box::new(io::stdout()))
+    }
+
+    // Returns false when the program should stop
+    pub fn pause(&mut self, vm: &VM, instruction: &Instruction) -> bool {
+        // debugger commands come from stdin, program cannot read it too
+        if let OpCode::NATIVE_FN_CALL(index) | OpCode::IO_FN_CALL(index, _) = instruction.op {
+            if let Some(name) = vm.fn_name(index).filter(|name| NativeRegistry::reads_stdin(name)) {
+                errors::error_message("RUNTIME ERROR", format!("{}() cannot be used in --step mode, stdin is read by the debugger {}:", name, instruction.line));
+                errors::runtime_error();
+            }
+        }
+
+        if !self.stepping {
+            return true
+        }
+
+        let frame = &vm.frames[vm.ip];
+        let shown = frame.stack.len().saturating_sub(SHOWN_STACK);
+        let stack: Vec<String> = frame.stack[shown..].iter().map(|value| vm.render_value(value)).collect();
+        self.write(format!("#{} {} {:04} {} [{}]", vm.ip, frame.name, frame.ip - 1, op_text(&instruction.op), stack.join(", ")));
+
+        loop {
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    self.stepping = false;
+                    return true
+                },
+                Ok(_) => {},
+            }
+
+            let command: Vec<&str> = line.split_whitespace().collect();
+            match command.as_slice() {
+                [] | ["s"] => return true,
+                ["c"] => {
+                    self.stepping = false;
+                    return true
+                },
+                ["q"] => return false,
+                ["p", slot] => {
+                    let text = match slot.parse::<usize>().ok().and_then(|slot| frame.stack.get(slot)) {
+                        Some(value) => format!("slot {} = {}", slot, vm.render_value(value)),
+                        None => format!("no stack slot {}, stack has {} values", slot, frame.stack.len()),
+                    };
+                    self.write(text);
+                },
+                ["h"] => {
+                    let start = vm.rc.declared();
+                    self.write(format!("heap: {} objects, {} declared", vm.rc.heap.len(), start));
+                    for pos in start..vm.rc.heap.len() {
+                        self.write(format!("    [{}] {} rc={}", pos, vm.describe_object(pos), vm.rc.heap[pos].get_rc_counter()));
+                    }
+                },
+                _ => self.write("commands: s step, c continue, p n print stack slot, h heap, q quit".to_string()),
+            }
+        }
+    }
+
+    fn write(&mut self, text: String) {
+        let _ = writeln!(self.output, "{}", text);
+        let _ = self.output.flush();
+    }
+}
@@ -0,0 +1,371 @@
+// Property test of the VM against malformed bytecode: random but plausible chunks have to
+// run to the end or stop with a clean runtime error (Termination unwind), never with a Rust panic.
+// Every case runs in a child process of the test binary, so an abort or stack overflow is caught too.
+//   cargo test --release -- --ignored fuzz_vm
+// SHLANG_FUZZ_CASES - number of cases (default 300), SHLANG_FUZZ_SEED - seed of the first case
+use std::{env, panic::{self, AssertUnwindSafe}, process::Command};
+
+use crate::{
+    compiler::errors::Termination,
+    objects::{rc::Object, structs::StructInstance},
+    vm::{bytecode::{Chunk, Instruction, OpCode}, value::Value, vm::{Frame, VmLimits, VM}},
+};
+
+const CASE_VAR: &str = "SHLANG_FUZZ_CASE";
+const DEFAULT_CASES: u64 = 300;
+const MAX_INSTRUCTIONS: u64 = 20_000;
+// index of the first String instance, list instances are numbered from 0
+const STRING_INDEX: usize = 1000;
+
+// xorshift64*, cases are reproducible from the seed alone
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Kind {
+    Int,
+    Float,
+    Bool,
+    Str,
+    // tuples and list copies, only popped
+    Other,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    Jump,
+    Loop,
+    If,
+    ForLoop(usize, usize, usize),
+}
+
+struct Generator {
+    rng: Rng,
+    chunk: Chunk,
+    types: Vec<Kind>,
+    // heap positions of int lists and of String instances (with their index)
+    lists: Vec<usize>,
+    strings: Vec<usize>,
+    heap_len: usize,
+    jumps: Vec<(usize, Target)>,
+}
+
+impl Generator {
+    fn new(seed: u64) -> Self {
+        Generator { rng: Rng::new(seed), chunk: Chunk::new(), types: vec![], lists: vec![], strings: vec![], heap_len: 0, jumps: vec![] }
+    }
+
+    fn emit(&mut self, op: OpCode) {
+        self.chunk.push(Instruction { op, line: self.chunk.code.len() as u32 + 1 });
+    }
+
+    fn int(&mut self) -> i64 {
+        match self.rng.below(20) {
+            0 => i64::MAX,
+            1 => i64::MIN,
+            2 => -1,
+            _ => self.rng.below(40) as i64 - 5,
+        }
+    }
+
+    fn push_constant(&mut self, kind: Kind) {
+        match kind {
+            Kind::Int => {
+                let value = self.int();
+                let pos = self.chunk.push_value(Value::Int(value));
+                self.emit(OpCode::CONSTANT_INT(pos));
+            },
+            Kind::Float => {
+                let value = self.int() as f64 / 4.0;
+                let pos = self.chunk.push_value(Value::Float(value));
+                self.emit(OpCode::CONSTANT_FLOAT(pos));
+            },
+            Kind::Bool => {
+                let value = self.rng.chance(50);
+                let pos = self.chunk.push_value(Value::Bool(value));
+                self.emit(OpCode::CONSTANT_BOOL(pos));
+            },
+            Kind::Str => match self.strings.is_empty() || self.rng.chance(50) {
+                true => {
+                    let text = "ab".repeat(self.rng.below(3));
+                    self.emit(OpCode::PUSH_STACK(Value::String(text)));
+                },
+                false => {
+                    let index = STRING_INDEX + self.rng.below(self.strings.len() + 1);
+                    self.emit(OpCode::PUSH_STACK(Value::StringRef(index)));
+                },
+            },
+            Kind::Other => {
+                let count = self.rng.below(3);
+                for _ in 0..count {
+                    self.push_constant(Kind::Int);
+                }
+                self.emit(OpCode::TUPLE_PACK(count));
+                self.types.truncate(self.types.len().saturating_sub(count));
+            },
+        }
+        self.types.push(kind);
+    }
+
+    fn random_kind(&mut self) -> Kind {
+        [Kind::Int, Kind::Int, Kind::Float, Kind::Bool, Kind::Str, Kind::Other][self.rng.below(6)]
+    }
+
+    // Lists of ints and String instances the list and string opcodes work on
+    fn heap_setup(&mut self) {
+        for _ in 0..self.rng.below(3) {
+            let count = self.rng.below(4);
+            for _ in 0..count {
+                self.push_constant(Kind::Int);
+            }
+            self.types.truncate(self.types.len() - count);
+
+            let mut list = StructInstance::new(0);
+            list.set_index(self.heap_len);
+            self.emit(OpCode::LIST_DEC(list, count));
+            self.lists.push(self.heap_len);
+            self.heap_len += 1;
+        }
+
+        for i in 0..self.rng.below(3) {
+            let mut string = StructInstance::new(0);
+            string.fields_values = vec![Value::String("s".repeat(i))];
+            string.set_index(STRING_INDEX + i);
+            self.emit(OpCode::STRING_DEC(string));
+            self.strings.push(self.heap_len);
+            self.heap_len += 1;
+        }
+    }
+
+    // Heap position, sometimes one past the last object
+    fn heap_pos(&mut self, positions: &[usize]) -> usize {
+        match positions.is_empty() || self.rng.chance(5) {
+            true => self.heap_len + self.rng.below(2),
+            false => positions[self.rng.below(positions.len())],
+        }
+    }
+
+    // Stack slot, sometimes past the top
+    fn slot(&mut self) -> usize {
+        let past = usize::from(self.rng.chance(5));
+        self.rng.below(self.types.len() + 1 + past)
+    }
+
+    fn top(&self, count: usize) -> Option<&[Kind]> {
+        self.types.len().checked_sub(count).map(|start| &self.types[start..])
+    }
+
+    fn binary(&mut self) {
+        let kinds = match self.top(2) {
+            Some(kinds) => [kinds[0], kinds[1]],
+            None => return self.push_constant(Kind::Int),
+        };
+        // mismatched operands are still used now and then, to reach the conversion errors
+        if kinds[0] != kinds[1] && !self.rng.chance(5) {
+            self.types.pop();
+            return self.emit(OpCode::POP)
+        }
+
+        let (op, output) = match kinds[1] {
+            Kind::Int => {
+                let ops = [OpCode::ADD_INT, OpCode::SUB_INT, OpCode::MUL_INT, OpCode::DIV_INT, OpCode::MOD_INT,
+                    OpCode::EQ_INT, OpCode::NEG_EQ_INT, OpCode::GREATER_INT, OpCode::EQ_GREATER_INT, OpCode::LESS_INT, OpCode::EQ_LESS_INT];
+                let index = self.rng.below(ops.len());
+                (ops[index].clone(), if index < 5 { Kind::Int } else { Kind::Bool })
+            },
+            Kind::Float => {
+                let ops = [OpCode::ADD_FLOAT, OpCode::SUB_FLOAT, OpCode::MUL_FLOAT, OpCode::DIV_FLOAT, OpCode::MOD_FLOAT,
+                    OpCode::EQ_FLOAT, OpCode::NEG_EQ_FLOAT, OpCode::GREATER_FLOAT, OpCode::EQ_GREATER_FLOAT, OpCode::LESS_FLOAT, OpCode::EQ_LESS_FLOAT];
+                let index = self.rng.below(ops.len());
+                (ops[index].clone(), if index < 5 { Kind::Float } else { Kind::Bool })
+            },
+            Kind::Bool => ([OpCode::EQ_BOOL, OpCode::NEG_EQ_BOOL][self.rng.below(2)].clone(), Kind::Bool),
+            Kind::Str => match self.rng.below(3) {
+                0 => (OpCode::ADD_STRING, Kind::Str),
+                1 => (OpCode::EQ_STRING, Kind::Bool),
+                _ => (OpCode::NEG_EQ_STRING, Kind::Bool),
+            },
+            Kind::Other => {
+                self.types.pop();
+                return self.emit(OpCode::POP)
+            },
+        };
+        self.emit(op);
+        self.types.truncate(self.types.len() - 2);
+        self.types.push(output);
+    }
+
+    fn list_op(&mut self) {
+        let pos = self.heap_pos(&self.lists.clone());
+        match self.rng.below(3) {
+            0 => {
+                self.push_constant(Kind::Int);
+                self.emit(OpCode::GET_LIST_FIELD(pos));
+                self.types.pop();
+                self.types.push(Kind::Int);
+            },
+            1 => {
+                self.push_constant(Kind::Int);
+                self.push_constant(Kind::Int);
+                self.emit(OpCode::SET_LIST_FIELD(pos));
+                self.emit(OpCode::POP);
+                self.types.truncate(self.types.len() - 2);
+            },
+            _ => {
+                self.emit(OpCode::GET_LIST(pos));
+                self.types.push(Kind::Other);
+            },
+        }
+    }
+
+    fn string_op(&mut self) {
+        let pos = self.heap_pos(&self.strings.clone());
+        self.emit(OpCode::GET_INSTANCE_FIELD(pos, 0));
+        self.types.push(Kind::Str);
+    }
+
+    fn jump(&mut self, target: Target) {
+        self.jumps.push((self.chunk.code.len(), target));
+        self.emit(OpCode::JUMP(0));
+    }
+
+    fn step(&mut self) {
+        match self.rng.below(16) {
+            0..=3 => {
+                let kind = self.random_kind();
+                self.push_constant(kind);
+            },
+            4..=6 => self.binary(),
+            7 => match self.types.last() {
+                Some(Kind::Int) | Some(Kind::Float) => self.emit(OpCode::NEGATE),
+                Some(_) => {
+                    self.emit(OpCode::POP);
+                    self.types.pop();
+                },
+                None => {},
+            },
+            8 => {
+                let slot = self.slot();
+                self.emit(OpCode::VAR_CALL(slot));
+                let kind = self.types.get(slot).copied().unwrap_or(Kind::Other);
+                self.types.push(kind);
+            },
+            9 => if !self.types.is_empty() {
+                let slot = self.slot();
+                self.emit(OpCode::VAR_SET(slot));
+            },
+            10 => self.list_op(),
+            11 => self.string_op(),
+            12 => {
+                let target = [Target::Jump, Target::Loop, Target::If][self.rng.below(3)];
+                self.jump(target);
+            },
+            13 => {
+                let (counter, limit, step) = (self.slot(), self.slot(), self.slot());
+                self.jump(Target::ForLoop(counter, limit, step));
+            },
+            _ => if !self.types.is_empty() {
+                self.emit(OpCode::POP);
+                self.types.pop();
+            },
+        }
+    }
+
+    // Jump offsets are chosen once the chunk is complete, targets always stay inside of it
+    fn patch_jumps(&mut self) {
+        let len = self.chunk.code.len();
+        for (index, target) in self.jumps.clone() {
+            let forward = self.rng.below(len - index - 1);
+            let op = match target {
+                Target::Jump => OpCode::JUMP(forward),
+                Target::If => OpCode::IF_STMT_OFFSET(forward),
+                Target::Loop => OpCode::LOOP(1 + self.rng.below(index + 1)),
+                Target::ForLoop(counter, limit, step) => OpCode::FOR_LOOP(counter, limit, step, forward),
+            };
+            self.chunk.code[index].op = op;
+        }
+    }
+
+    fn generate(mut self) -> Chunk {
+        self.heap_setup();
+        for _ in 0..8 + self.rng.below(40) {
+            self.step();
+        }
+
+        let pos = self.chunk.push_value(Value::Null);
+        self.emit(OpCode::CONSTANT_NULL(pos));
+        self.emit(OpCode::RETURN);
+        self.emit(OpCode::END_OF_FN);
+
+        self.patch_jumps();
+        self.chunk
+    }
+}
+
+fn run_case(seed: u64) {
+    let chunk = Generator::new(seed).generate();
+
+    let mut vm = VM::new(VmLimits { max_heap_objects: Some(1000), max_instructions: Some(MAX_INSTRUCTIONS), timeout_ms: None });
+    vm.frames.push(Frame { name: "main".to_string(), chunk, stack: vec![], ip: 0, offset: 0, instance_args: 0 });
+    // runtime errors unwind with Termination after printing the message, that is a clean stop
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| vm.run())) {
+        if !payload.is::<Termination>() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+// Child side, runs the case given in SHLANG_FUZZ_CASE
+#[test]
+#[ignore]
+fn fuzz_vm_case() {
+    if let Some(seed) = env::var(CASE_VAR).ok().and_then(|seed| seed.parse().ok()) {
+        run_case(seed);
+    }
+}
+
+#[test]
+#[ignore]
+fn fuzz_vm() {
+    let cases = env::var("SHLANG_FUZZ_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(DEFAULT_CASES);
+    let first = env::var("SHLANG_FUZZ_SEED").ok().and_then(|seed| seed.parse().ok()).unwrap_or(1);
+    let exe = env::current_exe().unwrap();
+
+    let mut failures = vec![];
+    for seed in first..first + cases {
+        let output = Command::new(&exe)
+            .args(["vm::fuzz::fuzz_vm_case", "--exact", "--ignored", "--nocapture", "--test-threads", "1"])
+            .env(CASE_VAR, seed.to_string())
+            .output()
+            .unwrap();
+
+        // 0 - finished or stopped by a runtime error reported by the VM, anything else is a panic or abort
+        if output.status.code() != Some(0) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let panic = stderr.lines().find(|line| line.contains("panicked")).unwrap_or("no panic message").to_string();
+            failures.push(format!("seed {}: {:?} {}", seed, output.status.code(), panic));
+        }
+    }
+
+    assert!(failures.is_empty(), "{} of {} cases crashed:\n{}", failures.len(), cases, failures.join("\n"));
+}
@@ -1,3 +1,9 @@
 pub mod bytecode;
+pub mod debugger;
 pub mod value;
-pub mod vm;
\ No newline at end of file
+pub mod verifier;
+#[allow(clippy::module_inception, reason = "the vm module holds the VM itself")]
+pub mod vm;
+
+#[cfg(test)]
+mod fuzz;
\ No newline at end of file
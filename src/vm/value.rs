@@ -8,6 +8,10 @@ use std::fmt;
 pub use std::ops::Neg;
 
 #[derive(Debug, Clone, PartialEq)]
+#[allow(
+    unpredictable_function_pointer_comparisons,
+    reason = "natives are compared by identity, a duplicated fn address only makes two natives equal"
+)]
 pub enum Value {
     Float(f64),
     Int(i64),
@@ -16,7 +20,12 @@ pub enum Value {
     String(String),
     List,
     ListObj(Vec<Value>),
+    Bytes(Vec<u8>),
+    // values returned together from a function, strings are stored inline
+    Tuple(Vec<Value>),
     InstanceObj(Vec<Value>),
+    // fields, in declaration order, of a struct instance returned by a host function
+    HostStruct(Vec<Value>),
     Chunk(Chunk),
     InstanceRef(usize),
     StringRef(usize),
@@ -26,62 +35,90 @@ pub enum Value {
 impl Value {
     pub fn get_float(&self) -> f64 {
         match self {
-            Value::Float(val) => return *val,
+            Value::Float(val) => *val,
             _ => {
                 errors::conversion_error(&format!("Enum Value<{:?}>", self), "f64");
-                std::process::exit(1);
+                errors::runtime_error();
+            },
+        }
+    }
+
+    pub fn get_ref(&self) -> usize {
+        match self {
+            Value::InstanceRef(val) | Value::StringRef(val) => *val,
+            _ => {
+                errors::conversion_error(&format!("Enum Value<{:?}>", self), "reference");
+                errors::runtime_error();
             },
         }
     }
 
     pub fn get_int(&self) -> i64 {
         match self {
-            Value::Int(val) => return *val,
+            Value::Int(val) => *val,
             _ => {
                 errors::conversion_error(&format!("Enum Value<{:?}>", self), "i64");
-                std::process::exit(1);
+                errors::runtime_error();
             },
         }
     }
 
     pub fn get_bool(&self) -> bool {
         match self {
-            Value::Bool(val) => return *val,
+            Value::Bool(val) => *val,
             _ => {
                 errors::conversion_error(&format!("Enum Value<{:?}>", self), "bool");
-                std::process::exit(1);
+                errors::runtime_error();
             },
         }
     }
 
     pub fn get_chunk(&self) -> Chunk {
         match self {
-            Value::Chunk(val) => return val.clone(),
+            Value::Chunk(val) => val.clone(),
             _ => {
                 errors::conversion_error(&format!("Enum Value<{:?}>", self), "chunk");
-                std::process::exit(1);
+                errors::runtime_error();
             },
         }
     }
 
     pub fn get_fn(&self) -> fn(Vec<Value>) -> Value {
         match self {
-            Value::Fn(val) => return *val,
-            val => {
-                println!("Value: {:?}", val);
+            Value::Fn(val) => *val,
+            _ => {
                 errors::conversion_error(&format!("Enum Value<{:?}>", self), "fn");
-                std::process::exit(1);
+                errors::runtime_error();
+            },
+        }
+    }
+
+    pub fn get_list(&self) -> Vec<Value> {
+        match self {
+            Value::ListObj(val) => val.clone(),
+            _ => {
+                errors::conversion_error(&format!("Enum Value<{:?}>", self), "List");
+                errors::runtime_error();
+            },
+        }
+    }
+
+    pub fn get_bytes(&self) -> Vec<u8> {
+        match self {
+            Value::Bytes(val) => val.clone(),
+            _ => {
+                errors::conversion_error(&format!("Enum Value<{:?}>", self), "Bytes");
+                errors::runtime_error();
             },
         }
     }
 
     pub fn get_string(&self) -> String {
         match self {
-            Value::String(val) => return val.clone(),
+            Value::String(val) => val.clone(),
             _ => {
-                println!("{:?}", self);
                 errors::conversion_error(&format!("Enum Value<{:?}>", self), "String");
-                std::process::exit(1);
+                errors::runtime_error();
             },
         }
     }
@@ -91,20 +128,19 @@ impl Neg for Value {
     type Output = Self;
 
     fn neg(self) -> Self {
-        let value: Value; 
-        match self {
+        let value: Value = match self {
             Value::Float(val) => {
-                value = Value::Float(-val);
+                Value::Float(-val)
             }
             Value::Int(val) => {
-                value = Value::Int(-val);
+                Value::Int(-val)
             }
             Value::Bool(val) => {
-                value = Value::Bool(!val);
+                Value::Bool(!val)
             }
             _ => {
                 errors::conversion_error("Enum Value<_>", "NEG Enum Value<_>");
-                std::process::exit(1);
+                errors::runtime_error();
             },
         };
 
@@ -126,9 +162,10 @@ impl Convert for Value {
             Value::String(_) => TokenType::STRING,
             Value::InstanceRef(val) => TokenType::STRUCT(val), 
             Value::List | Value::ListObj(_) => TokenType::LIST,
+            Value::Bytes(_) => TokenType::BYTES,
             _ => {
                 errors::conversion_error("Enum Value<_>", "TokenType");
-                std::process::exit(1);
+                errors::runtime_error();
             },
         }
     }
@@ -142,14 +179,15 @@ impl std::fmt::Display for Value {
             Value::Bool(val) => write!(output, "{}", val),
             Value::String(val) => write!(output, "{}", val),
             Value::ListObj(val) => write!(output, "{:?}", val),
+            Value::Bytes(val) => write!(output, "{:?}", val),
             Value::Null => write!(output, "null"),
             Value::InstanceRef(_) => {
-                errors::error_message("DISPLAY NOT IMPLEMENTED", format!("Writing \"Struct Object\" to stdout is not allowed"));
-                std::process::exit(1);
+                errors::error_message("DISPLAY NOT IMPLEMENTED", "Writing \"Struct Object\" to stdout is not allowed".to_string());
+                errors::runtime_error();
             },
             v => {
                 errors::error_message("DISPLAY NOT IMPLEMENTED", format!("Writing \"{:?}\" to stdout is not allowed", v));
-                std::process::exit(1);
+                errors::runtime_error();
             },
         }
     }
@@ -178,5 +216,9 @@ impl ValuesArray {
     pub fn len(&self) -> usize {
         self.values.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
 }
 
@@ -0,0 +1,97 @@
+use crate::{
+    compiler::{analyzer, compiler::Symbol},
+    frontend::tokens::{Keywords, TokenType},
+    objects::{functions::Function, registry::NativeRegistry},
+    vm::bytecode::{Chunk, OpCode},
+};
+
+// Heap objects the chunk declares, after the natives, in the order VM::declare pushes them
+pub fn heap_symbols(natives: Vec<Symbol>, chunk: &Chunk) -> Vec<Symbol> {
+    let mut symbols = natives;
+
+    for instruction in chunk.code.iter() {
+        match &instruction.op {
+            OpCode::FUNCTION_DEC(function) => {
+                symbols.push(Symbol { name: function.name.clone(), symbol_type: TokenType::KEYWORD(Keywords::FN), output_type: function.output_type, arg_count: function.arg_count });
+            },
+            OpCode::STRUCT_DEC(struct_) => {
+                symbols.push(Symbol { name: struct_.name.clone(), symbol_type: TokenType::KEYWORD(Keywords::STRUCT), output_type: struct_.output_type, arg_count: struct_.field_count });
+
+                for native in NativeRegistry::methods(&struct_.name) {
+                    symbols.push(Symbol { name: String::new(), symbol_type: TokenType::NATIVE_FN, output_type: TokenType::KEYWORD(Keywords::NULL), arg_count: native.arg_count });
+                }
+            },
+            _ => {},
+        }
+    }
+    symbols
+}
+
+// Arguments passed on the stack, String, Bytes and struct arguments are instances instead
+fn stack_params(function: &Function) -> usize {
+    function.arg_types.iter()
+        .filter(|arg_type| matches!(arg_type, TokenType::INT | TokenType::FLOAT | TokenType::BOOL))
+        .count()
+}
+
+// Checks a function before it is declared, so malformed bytecode is reported once up front
+// instead of failing in the middle of execution. `symbols` describe the whole declared heap
+pub fn verify_function(function: &Function, name: &str, symbols: &[Symbol]) -> Result<(), String> {
+    // self, arguments and at most one instance created by every instruction, scoped instances
+    // are already gone from function.instances, so it cannot be used as the bound
+    let max_instances = 1 + function.arg_count + function.chunk.code.len();
+    verify_chunk(&function.chunk, stack_params(function), max_instances, symbols)
+        .map_err(|(offset, reason)| format!("bytecode verification failed: {} at offset {} in function {}", reason, offset, name))
+}
+
+fn verify_chunk(chunk: &Chunk, params: usize, max_instances: usize, symbols: &[Symbol]) -> Result<(), (usize, String)> {
+    for (offset, instruction) in chunk.code.iter().enumerate() {
+        let op = &instruction.op;
+        match op {
+            OpCode::CONSTANT_INT(index) | OpCode::CONSTANT_FLOAT(index) | OpCode::CONSTANT_BOOL(index) | OpCode::CONSTANT_NULL(index) | OpCode::DBG(_, index, _) if *index >= chunk.values.len() => {
+                return Err((offset, format!("{:?} refers to constant {}, chunk has {} constants", op, index, chunk.values.len())))
+            },
+            OpCode::FUNCTION_CALL(index) | OpCode::NATIVE_FN_CALL(index) | OpCode::IO_FN_CALL(index, _) => {
+                let expected = match op {
+                    OpCode::FUNCTION_CALL(_) => TokenType::KEYWORD(Keywords::FN),
+                    _ => TokenType::NATIVE_FN,
+                };
+                match symbols.get(*index) {
+                    None => return Err((offset, format!("{:?} refers to heap object {}, heap has {} objects", op, index, symbols.len()))),
+                    Some(symbol) if symbol.symbol_type != expected => {
+                        return Err((offset, format!("{:?} refers to heap object {} which is not a {}", op, index, if expected == TokenType::NATIVE_FN { "native" } else { "function" })))
+                    },
+                    Some(_) => {},
+                }
+            },
+            OpCode::GET_LIST_FIELD(pos) | OpCode::SET_LIST_FIELD(pos) | OpCode::GET_LIST(pos) | OpCode::LIST_SORT_KEY(pos, _) | OpCode::LIST_SORT(pos, _) | OpCode::LIST_EXTEND(pos) |
+            OpCode::LIST_REMOVE(pos) | OpCode::LIST_SWAP(pos) | OpCode::LIST_INSERT_AT(pos) | OpCode::LIST_FILL(pos) | OpCode::LIST_IS_EMPTY(pos) |
+            OpCode::LIST_LOWER_BOUND(pos) | OpCode::LIST_SORTED_INSERT(pos) |
+            OpCode::GET_LIST_ELEMENT_FIELD(pos, _) | OpCode::SET_LIST_ELEMENT_FIELD(pos, _) | OpCode::GET_LIST_ELEMENT_RF(pos) |
+            OpCode::GET_INSTANCE_FIELD(pos, _) | OpCode::SET_INSTANCE_FIELD(pos, _) | OpCode::GET_INSTANCE_RF(pos) |
+            OpCode::GET_INSTANCE_W_OFFSET_RF(pos) | OpCode::INSTANCE_VALUE(pos) | OpCode::DEC_RC(pos) | OpCode::INC_RC(pos) | OpCode::DBG(_, _, Some(pos)) if *pos >= max_instances => {
+                return Err((offset, format!("{:?} uses instance {}, function can hold at most {} instances", op, pos, max_instances)))
+            },
+            _ => {},
+        }
+    }
+
+    let depths = analyzer::stack_depths(chunk, params, symbols)?;
+
+    for (offset, instruction) in chunk.code.iter().enumerate() {
+        let slots = match &instruction.op {
+            OpCode::VAR_CALL(slot) | OpCode::VAR_SET(slot) => vec![*slot],
+            OpCode::FOR_LOOP(counter, limit, step, _) => vec![*counter, *limit, *step],
+            _ => continue,
+        };
+
+        // unreachable instructions are never executed
+        if let Some(depth) = depths[offset] {
+            if let Some(slot) = slots.iter().find(|slot| **slot >= depth) {
+                return Err((offset, format!("{:?} uses stack slot {}, stack has {} values", instruction.op, slot, depth)))
+            }
+        }
+    }
+
+    Ok(())
+}
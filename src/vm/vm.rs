@@ -1,16 +1,45 @@
 use crate::{
-    objects::{rc::RefObject, string::StringMethods}, vm::{bytecode::{Chunk, Instruction, OpCode},
+    objects::rc::RefObject, vm::{bytecode::{Chunk, Instruction, OpCode},
     value::Value,
 }};
 
-use crate::objects::{rc, functions::NativeFn};
+use crate::objects::{rc::{self, Object}, functions::Function, ordered_map::OrderedMap, registry::NativeRegistry, structs::StructInstance};
 use crate::compiler::errors;
+use crate::frontend::tokens::TokenType;
+use crate::vm::{debugger::Debugger, verifier};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// number of executed instructions kept for corrupted bytecode diagnostic (debug builds only)
+const TRACE_LEN: usize = 8;
+// clock is read once per this many instructions when timeout_ms is set
+const TIMEOUT_CHECK_INTERVAL: u64 = 1024;
 
 pub struct Frame {
+    pub name: String,
     pub chunk: Chunk,
     pub stack: Vec<Value>,
     pub ip: usize,
     pub offset: usize,
+    // instances passed as arguments, the first objects of the frame's heap region, owned by the caller
+    pub instance_args: usize,
+}
+
+// User function declared by declare_all, see VM::call_frame
+#[derive(Debug, Clone)]
+pub struct DeclaredFn {
+    pub index: usize,
+    pub arg_types: Vec<TokenType>,
+    pub output_type: TokenType,
+}
+
+// Resource caps for untrusted scripts, None means unlimited
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VmLimits {
+    pub max_heap_objects: Option<usize>,
+    pub max_instructions: Option<u64>,
+    // wall time from the start of run
+    pub timeout_ms: Option<u64>,
 }
 
 pub struct VM {
@@ -18,101 +47,823 @@ pub struct VM {
     pub ip: usize,
     pub rc: rc::ReferenceCounter,
     break_loop: bool,
+    fn_names: HashMap<usize, String>,
+    // user functions by name in declaration order, natives and methods are not included
+    declared_fns: OrderedMap<String, DeclaredFn>,
+    string_struct_pos: usize,
+    trace: VecDeque<(String, usize, Instruction)>,
+    registry: NativeRegistry,
+    limits: VmLimits,
+    executed: u64,
+    deadline: Option<Instant>,
+    // --leak-report, objects still alive after main has released its locals are printed at exit
+    leak_report: bool,
+    debugger: Option<Debugger>,
+    // deepest stack seen in every function, compared with the compiler's max_stack
+    #[cfg(test)]
+    stack_peaks: HashMap<String, usize>,
 }
 
 impl VM {
-    pub fn new() -> Self {
+    pub fn new(limits: VmLimits) -> Self {
         Self {
             frames: vec![],
             ip: 0,
-            rc: rc::ReferenceCounter::init(),
+            rc: rc::ReferenceCounter::init(limits.max_heap_objects),
             break_loop: false,
+            fn_names: HashMap::new(),
+            declared_fns: OrderedMap::new(),
+            string_struct_pos: 0,
+            trace: VecDeque::new(),
+            registry: NativeRegistry::default(),
+            limits,
+            executed: 0,
+            deadline: None,
+            leak_report: false,
+            debugger: None,
+            #[cfg(test)]
+            stack_peaks: HashMap::new(),
         }
     }
 
+    pub fn set_leak_report(&mut self, leak_report: bool) {
+        self.leak_report = leak_report;
+    }
+
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(debugger);
+    }
+
+    // Name of the function or native declared at given heap position
+    pub fn fn_name(&self, index: usize) -> Option<&str> {
+        self.fn_names.get(&index).map(|name| name.as_str())
+    }
+
+    pub fn declared_fn(&self, name: &str) -> Option<&DeclaredFn> {
+        self.declared_fns.get(name)
+    }
+
+    pub fn declared_fn_names(&self) -> Vec<String> {
+        self.declared_fns.keys().cloned().collect()
+    }
+
+    // Has to be the registry the program was compiled with, see NativeRegistry
+    pub fn set_registry(&mut self, registry: NativeRegistry) {
+        self.registry = registry;
+    }
+
     pub fn get_instruction(&mut self) -> &Instruction {
+        if let Err(msg) = self.check_fetch() {
+            self.corrupted_bytecode(msg);
+        }
+
+        if let Some(max_instructions) = self.limits.max_instructions {
+            if self.executed == max_instructions {
+                let frame = &self.frames[self.ip];
+                let line = frame.chunk.get_instruction(frame.ip).line;
+                self.limit_exceeded(format!("instruction limit exceeded ({} instructions)", max_instructions), line);
+            }
+        }
+
+        if let Some(deadline) = self.deadline {
+            if self.executed.is_multiple_of(TIMEOUT_CHECK_INTERVAL) && Instant::now() >= deadline {
+                let frame = &self.frames[self.ip];
+                let line = frame.chunk.get_instruction(frame.ip).line;
+                self.limit_exceeded(format!("timeout exceeded ({} ms)", self.limits.timeout_ms.unwrap_or_default()), line);
+            }
+        }
+        self.executed += 1;
+
         let frame = &mut self.frames[self.ip];
         frame.ip += 1;
+
+        if cfg!(debug_assertions) {
+            if self.trace.len() == TRACE_LEN {
+                self.trace.pop_front();
+            }
+            self.trace.push_back((frame.name.clone(), frame.ip - 1, frame.chunk.get_instruction(frame.ip - 1).clone()));
+        }
+
         frame.chunk.get_instruction(frame.ip - 1)
     }
 
-    pub fn declare_native(&mut self) {
-        let natives_fn = NativeFn::get_natives_fn();
+    // Current frame has to exist and its ip has to point inside of its chunk
+    pub fn check_fetch(&self) -> Result<(), String> {
+        let frame = match self.frames.get(self.ip) {
+            Some(frame) => frame,
+            None => return Err(format!("Frame {} does not exist, call stack has {} frames", self.ip, self.frames.len())),
+        };
+
+        if frame.ip >= frame.chunk.code.len() {
+            return Err(format!("Instruction pointer {} is past the end of function \"{}\" (chunk length {})",
+                frame.ip,
+                frame.name,
+                frame.chunk.code.len(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Target of relative jump from current instruction, it has to stay inside of the chunk
+    pub fn jump_target(&self, offset: usize, forward: bool) -> Result<usize, String> {
+        let frame = &self.frames[self.ip];
+        let target = if forward { frame.ip.checked_add(offset) } else { frame.ip.checked_sub(offset) };
+
+        match target {
+            Some(target) if target < frame.chunk.code.len() => Ok(target),
+            _ => Err(format!("Jump by {}{} at ip {} leaves function \"{}\" (chunk length {})",
+                if forward { "+" } else { "-" },
+                offset,
+                frame.ip - 1,
+                frame.name,
+                frame.chunk.code.len(),
+            )),
+        }
+    }
+
+    fn jump(&mut self, offset: usize, forward: bool) {
+        match self.jump_target(offset, forward) {
+            Ok(target) => self.frames[self.ip].ip = target,
+            Err(msg) => self.corrupted_bytecode(msg),
+        }
+    }
+
+    // Stack of current frame has to hold at least `count` values for given opcode
+    pub fn check_depth(&self, opcode: &str, count: usize) -> Result<(), String> {
+        let depth = self.frames[self.ip].stack.len();
+        if depth < count {
+            return Err(format!("{} expects {} values on the stack, found: {}", opcode, count, depth))
+        }
+        Ok(())
+    }
+
+    // Opcode name without operands, for error messages
+    fn op_name(op: &OpCode) -> String {
+        format!("{:?}", op).split('(').next().unwrap_or_default().to_string()
+    }
+
+    fn check_heap(&self, op: &OpCode, pos: usize) -> Result<(), String> {
+        if pos >= self.rc.heap.len() {
+            return Err(format!("{} refers to heap object {}, heap has {} objects", VM::op_name(op), pos, self.rc.heap.len()))
+        }
+        Ok(())
+    }
 
-        for native in natives_fn {
+    // Values, stack slots and heap objects used by the instruction have to exist. Compiled code
+    // always passes, this keeps malformed bytecode from panicking inside of the handlers
+    pub fn check_operands(&self, op: &OpCode) -> Result<(), String> {
+        let frame = &self.frames[self.ip];
+
+        let needed = match op {
+            OpCode::FUNCTION_CALL(index) | OpCode::NATIVE_FN_CALL(index) => {
+                self.check_heap(op, *index)?;
+                self.rc.heap[*index].get_arg_count()
+            },
+            OpCode::IO_FN_CALL(index, arg_count) => {
+                self.check_heap(op, *index)?;
+                *arg_count
+            },
+            OpCode::METHOD_CALL(mth) => mth.arg_count + usize::from(mth.is_self_arg),
+
+            OpCode::INSTANCE_DEC(_, count) | OpCode::LIST_DEC(_, count) | OpCode::TUPLE_PACK(count) | OpCode::LIST_PACK(count) => *count,
+
+            OpCode::STRING_DEC_VALUE(_) | OpCode::BYTES_DEC_VALUE(_) | OpCode::LIST_DEC_VALUE(_) | OpCode::HOST_STRUCT_DEC(_, _) | OpCode::TUPLE_UNPACK(_) |
+            OpCode::SET_INSTANCE_FIELD(_, _) | OpCode::GET_LIST_FIELD(_) | OpCode::VAR_SET(_) |
+            OpCode::GET_LIST_ELEMENT_FIELD(_, _) | OpCode::GET_LIST_ELEMENT_RF(_) |
+            OpCode::IF_STMT_OFFSET(_) | OpCode::JUMP_IF_FALSE(_) | OpCode::NEGATE | OpCode::DBG(_, _, None) | OpCode::POP | OpCode::RETURN | OpCode::LIST_EXTEND(_) |
+            OpCode::LIST_REMOVE(_) | OpCode::LIST_FILL(_) | OpCode::INSTANCE_DEC_VALUE(_) |
+            OpCode::LIST_LOWER_BOUND(_) | OpCode::LIST_SORTED_INSERT(_) => 1,
+
+            OpCode::SET_LIST_FIELD(_) | OpCode::SET_LIST_ELEMENT_FIELD(_, _) | OpCode::LIST_FILLED | OpCode::LIST_FROM_RANGE | OpCode::LIST_CONTAINS | OpCode::LIST_CONCAT | OpCode::LIST_SWAP(_) | OpCode::LIST_INSERT_AT(_) | OpCode::LIST_INDEX(_) |
+            OpCode::ADD_STRING | OpCode::EQ_STRING | OpCode::NEG_EQ_STRING | OpCode::CONTAINS_STRING | OpCode::EQ_BOOL | OpCode::NEG_EQ_BOOL |
+            OpCode::ADD_FLOAT | OpCode::SUB_FLOAT | OpCode::MUL_FLOAT | OpCode::DIV_FLOAT | OpCode::MOD_FLOAT |
+            OpCode::EQ_FLOAT | OpCode::NEG_EQ_FLOAT | OpCode::GREATER_FLOAT | OpCode::EQ_GREATER_FLOAT |
+            OpCode::LESS_FLOAT | OpCode::EQ_LESS_FLOAT |
+            OpCode::ADD_INT | OpCode::SUB_INT | OpCode::MUL_INT | OpCode::DIV_INT | OpCode::MOD_INT |
+            OpCode::EQ_INT | OpCode::NEG_EQ_INT | OpCode::GREATER_INT | OpCode::EQ_GREATER_INT |
+            OpCode::LESS_INT | OpCode::EQ_LESS_INT => 2,
+
+            OpCode::LIST_INSERT => 3,
+
+            _ => 0,
+        };
+        if frame.stack.len() < needed {
+            return self.check_depth(&VM::op_name(op), needed)
+        }
+
+        match op {
+            OpCode::VAR_CALL(slot) | OpCode::VAR_SET(slot) => self.check_slots(op, &[*slot]),
+            OpCode::FOR_LOOP(counter, limit, step, _) => self.check_slots(op, &[*counter, *limit, *step]),
+
+            OpCode::GET_LIST_FIELD(pos) | OpCode::SET_LIST_FIELD(pos) | OpCode::GET_LIST(pos) | OpCode::LIST_SORT_KEY(pos, _) | OpCode::LIST_SORT(pos, _) | OpCode::LIST_EXTEND(pos) |
+            OpCode::LIST_REMOVE(pos) | OpCode::LIST_SWAP(pos) | OpCode::LIST_INSERT_AT(pos) | OpCode::LIST_FILL(pos) | OpCode::LIST_IS_EMPTY(pos) |
+            OpCode::LIST_LOWER_BOUND(pos) | OpCode::LIST_SORTED_INSERT(pos) |
+            OpCode::GET_LIST_ELEMENT_FIELD(pos, _) | OpCode::SET_LIST_ELEMENT_FIELD(pos, _) | OpCode::GET_LIST_ELEMENT_RF(pos) |
+            OpCode::GET_INSTANCE_FIELD(pos, _) | OpCode::SET_INSTANCE_FIELD(pos, _) | OpCode::GET_INSTANCE_RF(pos) |
+            OpCode::GET_INSTANCE_W_OFFSET_RF(pos) | OpCode::INSTANCE_VALUE(pos) | OpCode::DEC_RC(pos) | OpCode::INC_RC(pos) | OpCode::DBG(_, _, Some(pos)) => self.check_heap(op, frame.offset + pos),
+
+            _ => Ok(()),
+        }
+    }
+
+    fn check_slots(&self, op: &OpCode, slots: &[usize]) -> Result<(), String> {
+        let depth = self.frames[self.ip].stack.len();
+        match slots.iter().find(|slot| **slot >= depth) {
+            Some(slot) => Err(format!("{} uses stack slot {}, stack has {} values", VM::op_name(op), slot, depth)),
+            None => Ok(()),
+        }
+    }
+
+    // Pops `count` values, the deepest one comes first
+    fn pop_values(&mut self, opcode: &str, count: usize, line: u32) -> Vec<Value> {
+        if let Err(msg) = self.check_depth(opcode, count) {
+            errors::error_message("RUNTIME - VM ERROR", format!("VM - {} {}:", msg, line));
+            errors::runtime_error();
+        }
+
+        let stack = &mut self.frames[self.ip].stack;
+        stack.split_off(stack.len() - count)
+    }
+
+    // Pops index of list element and returns heap position of the struct instance stored there
+    fn list_element(&mut self, list_pos: usize, line: u32) -> usize {
+        let list_pos = self.resolve_ref(self.frames[self.ip].offset + list_pos);
+        let list_fields = self.rc.get_object(list_pos).get_values();
+
+        let index = self.frames[self.ip].stack.pop().unwrap().get_int();
+        if index < 0 || index as usize >= list_fields.len() {
+            errors::error_message("RUNTIME - VM ERROR",
+                format!("VM - List index out of range  {}/{} {}:", index, list_fields.len(), line));
+            errors::runtime_error();
+        }
+
+        match list_fields[index as usize] {
+            Value::InstanceRef(index) => {
+                let pos = self.rc.find_object(index);
+                self.resolve_ref(pos)
+            },
+            _ => {
+                errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: list element is not an instance {}:", line));
+                errors::runtime_error();
+            },
+        }
+    }
+
+    // Pops index of list element, it has to be inside the list at given heap position
+    fn list_index(&mut self, list_pos: usize, line: u32) -> usize {
+        let len = self.rc.get_object(list_pos).value_count();
+
+        let index = self.frames[self.ip].stack.pop().unwrap().get_int();
+        if index < 0 {
+            errors::error_message("RUNTIME - VM ERROR", format!("VM - Index cannot be negative {}:", line));
+            errors::runtime_error();
+        }
+        if index as usize >= len {
+            errors::error_message("RUNTIME - VM ERROR", format!("VM - List index out of range  {}/{} {}:", index, len, line));
+            errors::runtime_error();
+        }
+        index as usize
+    }
+
+    // Index of the first element not less than the value, elements are probed one at a time
+    fn lower_bound(&mut self, list_pos: usize, value: &Value) -> usize {
+        let (mut low, mut high) = (0, self.rc.get_object(list_pos).value_count());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let element = self.rc.get_object(list_pos).get_value(mid);
+            let element = self.inline_string(element);
+            if compare_elements(&element, value) == std::cmp::Ordering::Less {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    // Stores elements back into the list ordered by their keys, pushes Null as the method result
+    fn sort_keyed(&mut self, list_pos: usize, mut keyed: Vec<(Value, Value)>) {
+        // sort_by is stable, equal keys keep their order
+        keyed.sort_by(|(a, _), (b, _)| compare_elements(a, b));
+
+        for (index, (_, element)) in keyed.into_iter().enumerate() {
+            self.rc.get_object(list_pos).set_value(index, element);
+        }
+        self.frames[self.ip].stack.push(Value::Null);
+    }
+
+    // Calls key method with list element as self, same as GET_LIST_ELEMENT_RF followed by METHOD_CALL
+    fn sort_key(&mut self, mth: Function, element: &Value, line: u32) -> Value {
+        let element_pos = match element {
+            Value::InstanceRef(index) => {
+                let pos = self.rc.find_object(*index);
+                self.resolve_ref(pos)
+            },
+            _ => {
+                errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: list element is not an instance {}:", line));
+                errors::runtime_error();
+            },
+        };
+
+        self.rc.push(Box::new(RefObject { ref_index: element_pos, rc_counter: 1, index: 0}));
+        self.rc.inc_counter(element_pos);
+        self.frames[self.ip].stack.push(Value::InstanceRef(element_pos));
+
+        let depth = self.ip;
+        self.run_instruction(Instruction { op: OpCode::METHOD_CALL(mth), line });
+        self.run_until_return(depth);
+
+        self.frames[self.ip].stack.pop().unwrap()
+    }
+
+    fn corrupted_bytecode(&self, msg: String) -> ! {
+        let mut msg = msg;
+        if !self.trace.is_empty() {
+            msg.push_str("\nLast executed instructions:");
+            for (name, ip, instruction) in self.trace.iter() {
+                msg.push_str(&format!("\n    {} {}: {:?} (line {})", name, ip, instruction.op, instruction.line));
+            }
+        }
+
+        errors::error_message("RUNTIME - CORRUPTED BYTECODE", msg);
+        errors::runtime_error();
+    }
+
+    fn int_overflow(&self, operator: &str, line: u32) -> ! {
+        errors::error_message("RUNTIME ERROR", format!("Integer overflow in '{}' {}:", operator, line));
+        errors::runtime_error();
+    }
+
+    // None when the result overflows (MIN / -1)
+    fn int_division(&self, b: i64, a: i64, operator: &str, line: u32) -> Option<i64> {
+        if a == 0 {
+            errors::error_message("RUNTIME ERROR", format!("Division by zero in '{}' {}:", operator, line));
+            errors::runtime_error();
+        }
+        match operator {
+            "/" => b.checked_div(a),
+            _ => b.checked_rem(a),
+        }
+    }
+
+    fn limit_exceeded(&mut self, msg: String, line: u32) -> ! {
+        errors::error_message("RUNTIME ERROR", format!("{} in function \"{}\" {}:", msg, self.frames[self.ip].name, line));
+        self.rc.remove_all();
+        errors::runtime_error();
+    }
+
+    // Caller takes the returned instance as its next local, right after the instances it passed
+    // as arguments. Everything else the frame left there (refs created by the return itself) is dropped
+    fn keep_returned_instance(&mut self, frame: &Frame, instance_pos: usize) {
+        let caller_end = frame.offset + frame.instance_args;
+        if instance_pos >= caller_end {
+            let instance = self.rc.heap.remove(instance_pos);
+            self.rc.heap.truncate(caller_end);
+            self.rc.push(instance);
+        }else {
+            // instance owned by a caller, returned through a parameter
+            self.rc.heap.truncate(caller_end);
+            self.rc.push(Box::new(RefObject { ref_index: instance_pos, rc_counter: 1, index: 0}));
+        }
+    }
+
+    // Fields returned by a host native have to match the struct it is registered with
+    fn host_struct_fields(&mut self, index: usize, struct_pos: usize, line: u32) -> Vec<Value> {
+        let native_name = self.fn_names.get(&index).cloned().unwrap_or_default();
+        let struct_name = self.rc.get_object(struct_pos).get_values()[0].get_string();
+        let field_count = self.rc.get_object(struct_pos).get_arg_count();
+
+        let fields = match self.pop_values("HOST_STRUCT_DEC", 1, line).pop() {
+            Some(Value::HostStruct(fields)) => fields,
+            value => {
+                errors::error_message("RUNTIME ERROR", format!("Native \"{}\" has to return HostStruct of struct \"{}\", found: {:?} {}:", native_name, struct_name, value, line));
+                errors::runtime_error();
+            },
+        };
+
+        if fields.len() != field_count {
+            errors::error_message("RUNTIME ERROR", format!("Native \"{}\" returned {} fields of struct \"{}\" which has {} fields {}:", native_name, fields.len(), struct_name, field_count, line));
+            errors::runtime_error();
+        }
+
+        if let Some(field) = fields.iter().find(|field| !matches!(field, Value::Int(_) | Value::Float(_) | Value::Bool(_) | Value::String(_))) {
+            errors::error_message("RUNTIME ERROR", format!("Native \"{}\" returned {:?} as field of struct \"{}\", expected Int, Float, Bool or String {}:", native_name, field, struct_name, line));
+            errors::runtime_error();
+        }
+        fields
+    }
+
+    // A RefObject holds a count of the instance it refers to, both are released
+    fn release(&mut self, mut pos: usize) {
+        while self.rc.get_object(pos).is_ref() {
+            self.rc.dec_counter(pos);
+            pos = self.rc.get_object(pos).get_values()[0].get_ref();
+        }
+        self.rc.dec_counter(pos);
+    }
+
+    // Follows RefObjects to the heap position of the referenced instance
+    fn resolve_ref(&mut self, mut pos: usize) -> usize {
+        while self.rc.get_object(pos).is_ref() {
+            pos = self.rc.get_object(pos).get_values()[0].get_ref();
+        }
+        pos
+    }
+
+    // String operands reach the stack either as a reference to a String instance or by value,
+    // every consumer goes through this to get the text
+    fn pop_string(&mut self) -> String {
+        let value = self.frames[self.ip].stack.pop().unwrap();
+        self.inline_string(value).get_string()
+    }
+
+    // Operands of String +, == and !=, the deepest one first
+    fn pop_string_operands(&mut self, op: &OpCode, line: u32) -> (String, String) {
+        match self.string_operands(op, line) {
+            Ok(operands) => operands,
+            Err(msg) => {
+                errors::error_message("RUNTIME ERROR", msg);
+                errors::runtime_error();
+            },
+        }
+    }
+
+    // Compiled code leaves only Strings there, anything else (null of a function without return)
+    // is reported with its type instead of failing the conversion
+    fn string_operands(&mut self, op: &OpCode, line: u32) -> Result<(String, String), String> {
+        let values = self.pop_values(&VM::op_name(op), 2, line);
+        let operator = match op {
+            OpCode::ADD_STRING => "+",
+            OpCode::EQ_STRING => "==",
+            _ => "!=",
+        };
+
+        let mut texts = vec![];
+        for value in values {
+            match self.inline_string(value) {
+                Value::String(text) => texts.push(text),
+                value => return Err(format!("Operator '{}' expects String operands, found: {} {}:", operator, operand_type(&value), line)),
+            }
+        }
+        let a = texts.pop().unwrap_or_default();
+        Ok((texts.pop().unwrap_or_default(), a))
+    }
+
+    // Strings are stored by value in instance fields, a literal reference is replaced with its text
+    fn inline_string(&mut self, mut value: Value) -> Value {
+        while let Value::StringRef(index) = value {
+            let pos = self.rc.find_object(index);
+            value = self.rc.get_object(pos).get_values()[0].clone();
+        }
+        value
+    }
+
+    // Element stored in a List field, a struct element of a list local (by its object index) is copied
+    // by its fields, INSTANCE_VALUE already copied the others
+    fn copied_element(&mut self, value: Value) -> Value {
+        match value {
+            Value::InstanceRef(index) => {
+                let pos = self.rc.find_object(index);
+                let pos = self.resolve_ref(pos);
+                Value::InstanceObj(self.rc.get_object(pos).get_values())
+            },
+            value => self.inline_string(value),
+        }
+    }
+
+    // String method wrappers call natives with line 0, error is reported at the line of the method call
+    fn native_call_line(&self, line: u32) -> u32 {
+        if line != 0 || self.ip == 0 {
+            return line
+        }
+
+        let caller = &self.frames[self.ip - 1];
+        caller.chunk.code.get(caller.ip.wrapping_sub(1)).map_or(0, |instruction| instruction.line)
+    }
+
+    pub fn declare_native(&mut self) {
+        for native in self.registry.natives() {
+            self.fn_names.insert(self.rc.heap.len(), native.name.clone());
             self.rc.push(Box::new(native));
         }
     }
 
-    pub fn declare_all(&mut self, chunk: Chunk) -> Frame {
+    pub fn declare_all(&mut self, chunk: Chunk) -> Result<Frame, String> {
+        self.declare(chunk)?;
+
+        let main_function_index = match self.declared_fns.keys().zip(self.declared_fns.values()).find(|(name, _)| name.eq_ignore_ascii_case("main")) {
+            Some((_, declared)) => declared.index,
+            None => return Err("Cannot find \"main\" function object in declared chunk".to_string()),
+        };
+
+        let name = self.fn_names.get(&main_function_index).cloned().unwrap_or_default();
+        let chunk = self.rc.get_object(main_function_index).get_values()[0].get_chunk();
+        let stack = Vec::with_capacity(chunk.max_stack);
+        Ok(Frame{name, chunk, stack, ip: 0, offset: 0, instance_args: 0 })
+    }
+
+    // Pushes natives, functions and structs of the compiled program on the heap, every function
+    // and method is checked by the verifier first
+    pub fn declare(&mut self, chunk: Chunk) -> Result<(), String> {
+        let natives = self.registry.symbols().into_iter().take(self.registry.natives().len()).collect();
+        let symbols = verifier::heap_symbols(natives, &chunk);
+
         self.declare_native();
 
-        let mut main_function_index: usize = 0;
         for instruction in chunk.code {
             match instruction.op {
                 OpCode::FUNCTION_DEC(function) => {
-                    if function.name.to_ascii_lowercase() == "main" {
-                        main_function_index = self.rc.heap.len();
-                    }
+                    verifier::verify_function(&function, &function.name, &symbols)?;
+
+                    let declared = DeclaredFn { index: self.rc.heap.len(), arg_types: function.arg_types.clone(), output_type: function.output_type };
+                    self.declared_fns.insert(function.name.clone(), declared);
+                    self.fn_names.insert(self.rc.heap.len(), function.name.clone());
                     self.rc.push(Box::new(function));
                 },
                 OpCode::STRUCT_DEC(struct_) => {
-                    let name = struct_.name == "String";
+                    for method in struct_.methods.values() {
+                        verifier::verify_function(method, &format!("{}.{}", struct_.name, method.name), &symbols)?;
+                    }
 
+                    if struct_.name == "String" {
+                        self.string_struct_pos = self.rc.heap.len();
+                    }
+                    let methods = NativeRegistry::methods(&struct_.name);
                     self.rc.push(Box::new(struct_));
 
-                    if name {
-                        let mths_string = StringMethods::get_methods_rc();
-
-                        for obj in mths_string {
-                            self.rc.push(Box::new(obj));
-                        }
+                    for obj in methods {
+                        self.rc.push(Box::new(obj));
                     }
-                    
                 },
-                _ => errors::error_message("RUNTIME ERROR", format!("Declare all - this error should never prints out")),
+                _ => errors::error_message("RUNTIME ERROR", "Declare all - this error should never prints out".to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    // Frame calling the declared function with given arguments, its result is left on the frame stack.
+    // String arguments are declared as String instances, the same way as string literal arguments
+    pub fn call_frame(&mut self, name: &str, args: Vec<Value>) -> Result<Frame, String> {
+        let declared = match self.declared_fns.get(name) {
+            Some(declared) => declared.clone(),
+            None => return Err(format!("Function \"{}\" is not declared", name)),
+        };
+
+        let mut chunk = Chunk::new();
+        for (i, arg) in args.into_iter().enumerate() {
+            match arg {
+                Value::String(_) => {
+                    // index is only used to find the instance, compiled code uses symbol positions
+                    let index = usize::MAX - i;
+                    let mut instance = StructInstance::new(self.string_struct_pos);
+                    instance.fields_values = vec![arg];
+                    instance.set_index(index);
+
+                    chunk.push(Instruction { op: OpCode::STRING_DEC(instance), line: 0 });
+                    chunk.push(Instruction { op: OpCode::PUSH_STACK(Value::StringRef(index)), line: 0 });
+                },
+                arg => chunk.push(Instruction { op: OpCode::PUSH_STACK(arg), line: 0 }),
             }
         }
+        chunk.push(Instruction { op: OpCode::FUNCTION_CALL(declared.index), line: 0 });
+        chunk.push(Instruction { op: OpCode::RETURN, line: 0 });
+        chunk.push(Instruction { op: OpCode::END_OF_FN, line: 0 });
 
-        Frame{chunk: self.rc.get_object(main_function_index).get_values()[0].get_chunk(), stack: vec![], ip: 0, offset: 0 }
+        Ok(Frame { name: format!("--run {}", name), chunk, stack: vec![], ip: 0, offset: 0, instance_args: 0 })
     }
 
     pub fn run(&mut self) {
+        self.deadline = self.limits.timeout_ms.map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms));
+        self.execute();
+
+        if self.leak_report {
+            let leaks = self.live_objects();
+            if !leaks.is_empty() {
+                errors::error_message("LEAK REPORT", format!("{} objects alive at exit:\n{}", leaks.len(), leaks.join("\n")));
+            }
+        }
+        self.rc.remove_all();
+    }
+
+    // Instances released by DEC_RC after the last RETURN of the frame are its locals
+    fn frame_locals(frame: &Frame) -> Vec<usize> {
+        frame.chunk.code.iter().rev()
+            .skip_while(|instruction| instruction.op != OpCode::END_OF_FN)
+            .take_while(|instruction| instruction.op != OpCode::RETURN)
+            .filter_map(|instruction| match instruction.op {
+                OpCode::DEC_RC(pos) => Some(frame.offset + pos),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Heap objects created while running, apart from locals of live frames. Objects reachable from
+    // those locals or from values on frame stacks are still referenced, the rest has rc > 0
+    // without any path to it (reference cycle or missed release)
+    pub fn live_objects(&mut self) -> Vec<String> {
+        let start = self.rc.declared();
+        let mut reachable = vec![false; self.rc.heap.len()];
+
+        let locals: Vec<usize> = self.frames.iter().flat_map(VM::frame_locals).collect();
+        let mut pending = locals.clone();
+        for frame in self.frames.iter() {
+            for value in frame.stack.iter() {
+                if let Value::InstanceRef(index) | Value::StringRef(index) = value {
+                    pending.extend(self.rc.heap.iter().position(|obj| obj.get_index() == *index));
+                }
+            }
+        }
+
+        while let Some(pos) = pending.pop() {
+            if pos >= reachable.len() || reachable[pos] {
+                continue
+            }
+            reachable[pos] = true;
+
+            let obj = &self.rc.heap[pos];
+            if obj.is_ref() {
+                pending.push(obj.get_values()[0].get_ref());
+                continue
+            }
+            for value in obj.get_values() {
+                if let Value::InstanceRef(index) | Value::StringRef(index) = value {
+                    pending.extend(self.rc.heap.iter().position(|obj| obj.get_index() == index));
+                }
+            }
+        }
+
+        let mut report = vec![];
+        for pos in (start..self.rc.heap.len()).filter(|pos| !locals.contains(pos)) {
+            let status = if reachable[pos] { "still referenced" } else { "unreachable" };
+            report.push(format!("    [{}] {} rc={} ({})", pos, self.describe_object(pos), self.rc.heap[pos].get_rc_counter(), status));
+        }
+        report
+    }
+
+    // Struct name of an instance, "ref" for RefObject, used by the leak report and the debugger
+    pub(crate) fn object_type(&self, pos: usize) -> String {
+        let obj = &self.rc.heap[pos];
+        match obj.root_struct_pos() {
+            _ if obj.is_ref() => "ref".to_string(),
+            Some(root_struct_pos) => match self.rc.heap.get(root_struct_pos).map(|root| root.get_values()) {
+                Some(values) if !values.is_empty() => values[0].to_string(),
+                _ => "?".to_string(),
+            },
+            None => "object".to_string(),
+        }
+    }
+
+    pub(crate) fn describe_object(&self, pos: usize) -> String {
+        let obj = &self.rc.heap[pos];
+        match obj.root_struct_pos() {
+            _ if obj.is_ref() => format!("ref to [{}]", obj.get_values()[0].get_ref()),
+            Some(_) => format!("{} instance {:?}", self.object_type(pos), obj.get_values()),
+            None => "object".to_string(),
+        }
+    }
+
+    // Stack value as shown by the debugger, refs are rendered as @heapIndex with the object type
+    pub(crate) fn render_value(&self, value: &Value) -> String {
+        match value {
+            Value::InstanceRef(index) | Value::StringRef(index) => match self.rc.heap.iter().position(|obj| obj.get_index() == *index) {
+                Some(pos) => format!("@{} {}", pos, self.object_type(pos)),
+                None => format!("@? (index {})", index),
+            },
+            Value::Int(_) | Value::Float(_) | Value::Bool(_) | Value::Null => value.to_string(),
+            Value::String(text) => format!("{:?}", text),
+            value => format!("{:?}", value),
+        }
+    }
+
+    // dbg() output, Strings are quoted and struct instances are shown as their literal, P{1, "a"}
+    fn dbg_value(&mut self, value: Value) -> String {
+        match value {
+            Value::String(text) => format!("{:?}", text),
+            Value::StringRef(_) => {
+                let text = self.inline_string(value);
+                self.dbg_value(text)
+            },
+            Value::ListObj(elements) => {
+                let elements: Vec<String> = elements.into_iter().map(|element| self.dbg_value(element)).collect();
+                format!("[{}]", elements.join(", "))
+            },
+            // struct element of a list, unwrapped by GET_LIST without its struct
+            Value::InstanceObj(fields) => {
+                let fields: Vec<String> = fields.into_iter().map(|field| self.dbg_value(field)).collect();
+                format!("{{{}}}", fields.join(", "))
+            },
+            Value::InstanceRef(index) => {
+                let pos = self.rc.find_object(index);
+                self.dbg_instance(pos)
+            },
+            value => value.to_string(),
+        }
+    }
+
+    fn dbg_instance(&mut self, pos: usize) -> String {
+        let pos = self.resolve_ref(pos);
+        let fields: Vec<String> = self.rc.get_object(pos).get_values().into_iter().map(|field| self.dbg_value(field)).collect();
+        format!("{}{{{}}}", self.object_type(pos), fields.join(", "))
+    }
+
+    fn execute(&mut self) {
         self.frames[self.ip].offset = self.rc.heap.len();
+        self.rc.set_declared();
+        errors::set_cur_file(self.frames[self.ip].chunk.file);
         loop {
             let instruction = self.get_instruction().clone();
+            if let Some(mut debugger) = self.debugger.take() {
+                let go_on = debugger.pause(self, &instruction);
+                self.debugger = Some(debugger);
+                if !go_on {
+                    break
+                }
+            }
+
             match instruction.op {
                 OpCode::RETURN => {
                     if self.ip == 0 {
-                        println!("Stack: {:?}", self.frames[self.ip].stack);
                         break
                     }
+                    self.return_from_call();
+                },
+                _ => self.run_checked(instruction),
+            };
+        }
+    }
+
+    // Runs called frames until control is back in the frame at `depth`
+    fn run_until_return(&mut self, depth: usize) {
+        while self.ip > depth {
+            let instruction = self.get_instruction().clone();
+            match instruction.op {
+                OpCode::RETURN => self.return_from_call(),
+                _ => self.run_checked(instruction),
+            }
+        }
+    }
 
-                    let return_val = self.frames[self.ip].stack.pop().unwrap();
-                    
-                    let mut instr = self.get_instruction().clone();
+    fn run_checked(&mut self, instruction: Instruction) {
+        if let Err(msg) = self.check_operands(&instruction.op) {
+            self.corrupted_bytecode(format!("{} {}:", msg, instruction.line));
+        }
 
-                    while instr.op != OpCode::END_OF_FN {
-                        if matches!(instr.op, OpCode::DEC_RC(_)) || matches!(instr.op, OpCode::POP) {
-                            self.run_instruction(instr);
-                        }
-                        
-                        instr = self.get_instruction().clone();
-                    }
-                    self.frames.pop();
-                    
-                    self.rc.remove();
+        let line = instruction.line;
+        self.run_instruction(instruction);
+        self.check_max_stack();
 
-                    self.ip -= 1;
+        if self.rc.is_over_limit() {
+            self.limit_exceeded(format!("heap limit exceeded ({} objects)", self.limits.max_heap_objects.unwrap_or_default()), line);
+        }
+    }
 
-                    if !matches!(return_val, Value::InstanceRef(_)) {
-                        self.frames[self.ip].stack.push(return_val);
-                    }
-                },
-                _ => self.run_instruction(instruction),
-            };
+    // Frame stacks are preallocated with the compiler's max_stack, going over it means the
+    // stack analysis under-estimated and the frame reallocates
+    fn check_max_stack(&mut self) {
+        let frame = &self.frames[self.ip];
+        debug_assert!(frame.chunk.max_stack == 0 || frame.stack.len() <= frame.chunk.max_stack,
+            "stack of \"{}\" has {} values, compiler recorded max {}", frame.name, frame.stack.len(), frame.chunk.max_stack);
+
+        #[cfg(test)]
+        {
+            let peak = self.stack_peaks.entry(frame.name.clone()).or_default();
+            *peak = (*peak).max(frame.stack.len());
+        }
+    }
+
+    fn return_from_call(&mut self) {
+        // returned String instance is freed with the frame, only its text is passed back
+        let return_val = self.frames[self.ip].stack.pop().unwrap();
+        let return_val = self.inline_string(return_val);
+
+        let returned_instance = match return_val {
+            Value::InstanceRef(pos) => Some(self.resolve_ref(pos)),
+            _ => None,
+        };
+        
+        let mut instr = self.get_instruction().clone();
+
+        while instr.op != OpCode::END_OF_FN {
+            if matches!(instr.op, OpCode::DEC_RC(_)) || matches!(instr.op, OpCode::POP) {
+                self.run_instruction(instr);
+            }
+            
+            instr = self.get_instruction().clone();
+        }
+        let frame = self.frames.pop().unwrap();
+
+        if let Some(instance_pos) = returned_instance {
+            self.keep_returned_instance(&frame, instance_pos);
+        }
+        
+        self.rc.remove();
+
+        self.ip -= 1;
+        errors::set_cur_file(self.frames[self.ip].chunk.file);
+
+        if !matches!(return_val, Value::InstanceRef(_)) {
+            self.frames[self.ip].stack.push(return_val);
         }
-        self.rc.remove_all();
     }
 
     fn run_instruction(&mut self, instruction: Instruction) {
@@ -126,57 +877,51 @@ impl VM {
             OpCode::STRING_DEC(instance) => {
                 self.rc.push(Box::new(instance));
             },
+            OpCode::BYTES_DEC_VALUE(mut instance) => {
+                instance.fields_values = self.pop_values("BYTES_DEC_VALUE", 1, instruction.line);
+                self.rc.push(Box::new(instance));
+            },
             OpCode::STRING_DEC_VALUE(mut instance) => {
-                instance.fields_values.push(self.frames[self.ip].stack.pop().unwrap());
+                instance.fields_values = self.pop_values("STRING_DEC_VALUE", 1, instruction.line)
+                    .into_iter()
+                    .map(|value| self.inline_string(value))
+                    .collect();
                 self.rc.push(Box::new(instance));
             },
 
             OpCode::INSTANCE_DEC(mut instance, field_count) => {
-                for _ in 0..field_count {
-                    instance.fields_values.push(self.frames[self.ip].stack.pop().unwrap())
-                }
-                instance.fields_values.reverse();
+                instance.fields_values = self.pop_values("INSTANCE_DEC", field_count, instruction.line)
+                    .into_iter()
+                    .map(|value| self.inline_string(value))
+                    .collect();
                 
                 self.rc.push(Box::new(instance));
             },
+            OpCode::HOST_STRUCT_DEC(index, mut instance) => {
+                instance.fields_values = self.host_struct_fields(index, instance.root_struct_pos, instruction.line);
+                self.rc.push(Box::new(instance));
+            },
+            OpCode::LIST_DEC(mut instance, element_count) => {
+                instance.fields_values = self.pop_values("LIST_DEC", element_count, instruction.line);
+                instance.is_list = true;
+
+                self.rc.push(Box::new(instance));
+            },
             OpCode::GET_INSTANCE_FIELD(pos, field_pos) => {
-                let instance_fields = self.rc.get_object(self.frames[self.ip].offset+pos).get_values();
+                let instance_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let value = self.rc.get_object(instance_pos).get_values()[field_pos].clone();
 
-                match instance_fields[0] {
-                    Value::InstanceRef(index) | Value::StringRef(index)  => {
-                        let fields = self.rc.get_object(index).get_values();
-                        self.frames[self.ip].stack.push(fields[field_pos].clone());
-                        return
-                    },
-                    _ => {},
-                };
-                println!("{:?}", instance_fields[field_pos]);
-                self.frames[self.ip].stack.push(instance_fields[field_pos].clone());
+                self.frames[self.ip].stack.push(value);
             },
             OpCode::SET_INSTANCE_FIELD(pos, field_pos) => {
                 let len = self.frames[self.ip].stack.len() - 1;
-                let value = self.frames[self.ip].stack[len].clone();
-
-                match self.rc.get_object(self.frames[self.ip].offset + pos).get_values()[0] {
-                    Value::InstanceRef(index) | Value::StringRef(index) => {
-                        self.rc.get_object(index).set_value(field_pos, value);
-                        return
-                    },
-                    _ => {},
-                };
+                let value = self.inline_string(self.frames[self.ip].stack[len].clone());
 
-                self.rc.get_object(self.frames[self.ip].offset + pos).set_value(field_pos, value);
+                let instance_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                self.rc.get_object(instance_pos).set_value(field_pos, value);
             },
             OpCode::GET_INSTANCE_W_OFFSET_RF(index) => {
-                let mut offset = self.frames[self.ip].offset + index;
-                while matches!(self.rc.get_object(offset).get_values()[0], Value::InstanceRef(_)) {
-                    match self.rc.get_object(offset).get_values()[0] {
-                        Value::InstanceRef(pos) => {
-                            offset = pos;
-                        }
-                        _ => {},
-                    }
-                }
+                let offset = self.resolve_ref(self.frames[self.ip].offset + index);
                 self.rc.push(Box::new(RefObject { ref_index: offset, rc_counter: 1, index: 0}));
                 self.frames[self.ip].stack.push(Value::InstanceRef(offset));
             },
@@ -186,46 +931,88 @@ impl VM {
                 
                 self.rc.push(Box::new(RefObject { ref_index: offset+pos, rc_counter: 1, index: 0}));
                 self.frames[self.ip].stack.push(Value::InstanceRef(offset+pos));
-                println!("{:?}", offset+pos)
+            },
+
+            OpCode::INSTANCE_VALUE(pos) => {
+                let instance_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let fields = self.rc.get_object(instance_pos).get_values();
+
+                self.frames[self.ip].stack.push(Value::InstanceObj(fields));
+            },
+            OpCode::INSTANCE_DEC_VALUE(mut instance) => {
+                instance.fields_values = match self.pop_values("INSTANCE_DEC_VALUE", 1, instruction.line).pop() {
+                    Some(Value::InstanceObj(fields)) => fields,
+                    value => {
+                        errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: expected struct value on stack, found: {:?} {}:", value, instruction.line));
+                        errors::runtime_error();
+                    },
+                };
+                self.rc.push(Box::new(instance));
             },
 
             OpCode::GET_LIST_FIELD(pos) => {
-                let list_fields = self.rc.get_object(self.frames[self.ip].offset+pos).get_values();
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let list_fields = self.rc.get_object(list_pos).get_values();
 
                 let field_pos = match self.frames[self.ip].stack.pop() {
                     Some(Value::Int(val)) => {
                         if val < 0 {     
                             errors::error_message("RUNTIME - VM ERROR", 
                                 format!("VM - Index cannot be negative {}:", instruction.line));
-                            std::process::exit(1);
+                            errors::runtime_error();
                         };
                         val as usize
                     }
                     _ => {                        
                         errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: run out of stack {}:", instruction.line));
-                        std::process::exit(1);
+                        errors::runtime_error();
                     },
                 };
 
                 if field_pos >= list_fields.len() {                
                     errors::error_message("RUNTIME - VM ERROR", 
                         format!("VM - List index out of range  {}/{} {}:", field_pos, list_fields.len(), instruction.line));
-                    std::process::exit(1);
+                    errors::runtime_error();
                 };
                 
                 self.frames[self.ip].stack.push(list_fields[field_pos].clone());
             },
+            OpCode::GET_LIST_ELEMENT_FIELD(pos, field_pos) => {
+                let element_pos = self.list_element(pos, instruction.line);
+                let value = self.rc.get_object(element_pos).get_values()[field_pos].clone();
+
+                self.frames[self.ip].stack.push(value);
+            },
+            OpCode::SET_LIST_ELEMENT_FIELD(pos, field_pos) => {
+                let value = self.frames[self.ip].stack.pop().unwrap();
+                let element_pos = self.list_element(pos, instruction.line);
+
+                self.rc.get_object(element_pos).set_value(field_pos, value.clone());
+                self.frames[self.ip].stack.push(value);
+            },
+            OpCode::GET_LIST_ELEMENT_RF(pos) => {
+                // same as GET_INSTANCE_RF + INC_RC, callee releases it with DEC_RC of self
+                let element_pos = self.list_element(pos, instruction.line);
+
+                self.rc.push(Box::new(RefObject { ref_index: element_pos, rc_counter: 1, index: 0}));
+                self.rc.inc_counter(element_pos);
+                self.frames[self.ip].stack.push(Value::InstanceRef(element_pos));
+            },
             OpCode::GET_LIST(pos) => {
-                let list_fields = self.rc.get_object(self.frames[self.ip].offset+pos).get_values();
+                // List<T> param is a ref to the caller's list
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let list_fields = self.rc.get_object(list_pos).get_values();
 
                 let mut list_fields_unwrap = vec![];
                 for field in list_fields {
                     match field {
                         Value::InstanceRef(index) => {
-                            list_fields_unwrap.push(Value::InstanceObj(self.rc.get_object(index).get_values()));   
+                            let pos = self.rc.find_object(index);
+                            let pos = self.resolve_ref(pos);
+                            list_fields_unwrap.push(Value::InstanceObj(self.rc.get_object(pos).get_values()));   
                         },
-                        Value::StringRef(index) => {
-                            list_fields_unwrap.push(self.rc.get_object(index).get_values()[0].clone());
+                        Value::StringRef(_) => {
+                            list_fields_unwrap.push(self.inline_string(field));
                         },
                         _ => {
                             list_fields_unwrap.push(field);
@@ -235,6 +1022,224 @@ impl VM {
                 
                 self.frames[self.ip].stack.push(Value::ListObj(list_fields_unwrap));
             },
+            OpCode::LIST_CONTAINS => {
+                let list = self.frames[self.ip].stack.pop().unwrap();
+                let value = self.frames[self.ip].stack.pop().unwrap();
+                let value = self.inline_string(value);
+
+                self.frames[self.ip].stack.push(Value::Bool(list.get_list().contains(&value)));
+            },
+            OpCode::LIST_CONCAT => {
+                let elements = self.pop_values("LIST_CONCAT", 2, instruction.line)
+                    .into_iter()
+                    .flat_map(|list| list.get_list())
+                    .collect();
+
+                self.frames[self.ip].stack.push(Value::ListObj(elements));
+            },
+            OpCode::LIST_EXTEND(pos) => {
+                let elements = self.frames[self.ip].stack.pop().unwrap().get_list();
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+
+                for element in elements {
+                    self.rc.get_object(list_pos).push_value(element);
+                }
+
+                self.frames[self.ip].stack.push(Value::Null);
+            },
+            OpCode::LIST_REMOVE(pos) => {
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let index = self.list_index(list_pos, instruction.line);
+
+                let element = self.rc.get_object(list_pos).remove_value(index);
+                let element = self.inline_string(element);
+                self.frames[self.ip].stack.push(element);
+            },
+            OpCode::LIST_SWAP(pos) => {
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let j = self.list_index(list_pos, instruction.line);
+                let i = self.list_index(list_pos, instruction.line);
+
+                let elements = self.rc.get_object(list_pos).get_values();
+                self.rc.get_object(list_pos).set_value(i, elements[j].clone());
+                self.rc.get_object(list_pos).set_value(j, elements[i].clone());
+                self.frames[self.ip].stack.push(Value::Null);
+            },
+            OpCode::LIST_INSERT_AT(pos) => {
+                let value = self.frames[self.ip].stack.pop().unwrap();
+                let value = self.inline_string(value);
+                let index = self.frames[self.ip].stack.pop().unwrap().get_int();
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+
+                // inserting at len appends
+                let len = self.rc.get_object(list_pos).value_count();
+                if index < 0 || index as usize > len {
+                    errors::error_message("RUNTIME ERROR", format!("List insert index out of range {}/{} {}:", index, len, instruction.line));
+                    errors::runtime_error();
+                }
+                self.rc.get_object(list_pos).insert_value(index as usize, value);
+                self.frames[self.ip].stack.push(Value::Null);
+            },
+            OpCode::LIST_FILL(pos) => {
+                // string is copied by value, elements don't keep a reference to the argument
+                let value = self.frames[self.ip].stack.pop().unwrap();
+                let value = self.inline_string(value);
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+
+                for index in 0..self.rc.get_object(list_pos).value_count() {
+                    self.rc.get_object(list_pos).set_value(index, value.clone());
+                }
+                self.frames[self.ip].stack.push(Value::Null);
+            },
+            OpCode::LIST_IS_EMPTY(pos) => {
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let is_empty = self.rc.get_object(list_pos).value_count() == 0;
+                self.frames[self.ip].stack.push(Value::Bool(is_empty));
+            },
+            OpCode::LIST_LOWER_BOUND(pos) => {
+                let value = self.frames[self.ip].stack.pop().unwrap();
+                let value = self.inline_string(value);
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+
+                let index = self.lower_bound(list_pos, &value);
+                self.frames[self.ip].stack.push(Value::Int(index as i64));
+            },
+            OpCode::LIST_SORTED_INSERT(pos) => {
+                // string is copied by value, like the elements of list literals
+                let value = self.frames[self.ip].stack.pop().unwrap();
+                let value = self.inline_string(value);
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+
+                let index = self.lower_bound(list_pos, &value);
+                self.rc.get_object(list_pos).insert_value(index, value);
+                self.frames[self.ip].stack.push(Value::Null);
+            },
+            OpCode::LIST_PACK(count) => {
+                let elements = self.pop_values("LIST_PACK", count, instruction.line)
+                    .into_iter()
+                    .map(|value| self.copied_element(value))
+                    .collect();
+
+                self.frames[self.ip].stack.push(Value::ListObj(elements));
+            },
+            OpCode::LIST_INSERT => {
+                let element = self.frames[self.ip].stack.pop().unwrap();
+                let element = self.copied_element(element);
+                let index = self.frames[self.ip].stack.pop().unwrap().get_int();
+                let mut elements = self.frames[self.ip].stack.pop().unwrap().get_list();
+
+                // inserting at len appends
+                if index < 0 || index as usize > elements.len() {
+                    errors::error_message("RUNTIME ERROR", format!("List insert index out of range {}/{} {}:", index, elements.len(), instruction.line));
+                    errors::runtime_error();
+                }
+                elements.insert(index as usize, element);
+                self.frames[self.ip].stack.push(Value::ListObj(elements));
+            },
+            OpCode::LIST_INDEX(field) => {
+                let index = self.frames[self.ip].stack.pop().unwrap().get_int();
+                let elements = self.frames[self.ip].stack.pop().unwrap().get_list();
+
+                if index < 0 || index as usize >= elements.len() {
+                    errors::error_message("RUNTIME - VM ERROR",
+                        format!("VM - List index out of range  {}/{} {}:", index, elements.len(), instruction.line));
+                    errors::runtime_error();
+                }
+
+                let element = match (field, elements[index as usize].clone()) {
+                    (Some(field), Value::InstanceObj(fields)) => fields[field].clone(),
+                    (_, element) => element,
+                };
+                self.frames[self.ip].stack.push(element);
+            },
+            OpCode::LIST_SORT_KEY(pos, mth) => {
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+
+                let mut keyed = vec![];
+                for element in self.rc.get_object(list_pos).get_values() {
+                    let key = match &mth {
+                        Some(mth) => self.sort_key(mth.clone(), &element, instruction.line),
+                        None => Value::Int(self.inline_string(element.clone()).get_string().len() as i64),
+                    };
+                    keyed.push((key, element));
+                }
+                self.sort_keyed(list_pos, keyed);
+            },
+            OpCode::LIST_SORT(pos, field) => {
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+
+                let mut keyed = vec![];
+                for element in self.rc.get_object(list_pos).get_values() {
+                    let key = match (field, &element) {
+                        (Some(field), Value::InstanceRef(index)) => {
+                            let pos = self.rc.find_object(*index);
+                            let pos = self.resolve_ref(pos);
+                            self.rc.get_object(pos).get_values()[field].clone()
+                        },
+                        _ => element.clone(),
+                    };
+                    keyed.push((self.inline_string(key), element));
+                }
+                self.sort_keyed(list_pos, keyed);
+            },
+            OpCode::LIST_DEC_VALUE(mut instance) => {
+                instance.fields_values = self.pop_values("LIST_DEC_VALUE", 1, instruction.line)[0].get_list();
+                instance.is_list = true;
+                self.rc.push(Box::new(instance));
+            },
+            OpCode::LIST_FILLED => {
+                // string is copied by value into every element, so no reference is kept
+                let value = match self.frames[self.ip].stack.pop().unwrap() {
+                    Value::StringRef(index) => {
+                        let pos = self.rc.find_object(index);
+
+                        self.rc.get_object(pos).get_values()[0].clone()
+                    },
+                    value => value,
+                };
+                let count = self.frames[self.ip].stack.pop().unwrap().get_int();
+
+                if count < 0 {
+                    errors::error_message("RUNTIME ERROR", format!("List.filled count cannot be negative, found: {} {}:", count, instruction.line));
+                    errors::runtime_error();
+                }
+
+                self.frames[self.ip].stack.push(Value::ListObj(vec![value; count as usize]));
+            },
+            OpCode::TUPLE_PACK(count) => {
+                let elements = self.pop_values("TUPLE_PACK", count, instruction.line)
+                    .into_iter()
+                    .map(|value| self.inline_string(value))
+                    .collect();
+
+                self.frames[self.ip].stack.push(Value::Tuple(elements));
+            },
+            OpCode::TUPLE_UNPACK(instances) => {
+                let elements = match self.frames[self.ip].stack.pop() {
+                    Some(Value::Tuple(elements)) => elements,
+                    value => {
+                        errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: expected tuple on stack, found: {:?} {}:", value, instruction.line));
+                        errors::runtime_error();
+                    },
+                };
+
+                for (element, instance) in elements.into_iter().zip(instances) {
+                    match instance {
+                        Some(mut instance) => {
+                            instance.fields_values = vec![element];
+                            self.rc.push(Box::new(instance));
+                        },
+                        None => self.frames[self.ip].stack.push(element),
+                    }
+                }
+            },
+            OpCode::LIST_FROM_RANGE => {
+                let stop = self.frames[self.ip].stack.pop().unwrap().get_int();
+                let start = self.frames[self.ip].stack.pop().unwrap().get_int();
+
+                // bounds are inclusive, as in for loop range
+                self.frames[self.ip].stack.push(Value::ListObj((start..=stop).map(Value::Int).collect()));
+            },
             OpCode::SET_LIST_FIELD(pos) => {                
                 let len = self.frames[self.ip].stack.len() - 1;
                 
@@ -242,7 +1247,7 @@ impl VM {
                     Some(val) => val,
                     _ => {
                         errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: missing value on stack {}:", instruction.line));
-                        std::process::exit(1);
+                        errors::runtime_error();
                     }    
                 };
 
@@ -251,20 +1256,31 @@ impl VM {
                         if val < 0 {     
                             errors::error_message("RUNTIME - VM ERROR", 
                                 format!("VM - Index cannot be negative {}:", instruction.line));
+                            errors::runtime_error();
                         };
                         val as usize
                     }
                     _ => {                        
                         errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: bad value on stack {}:", instruction.line));
-                        std::process::exit(1);
+                        errors::runtime_error();
                     },
                 };
 
-                self.rc.get_object(self.frames[self.ip].offset + pos).set_value(field_pos, value);
+                let list_pos = self.resolve_ref(self.frames[self.ip].offset + pos);
+                let list_len = self.rc.get_object(list_pos).value_count();
+                if field_pos >= list_len {
+                    errors::error_message("RUNTIME - VM ERROR",
+                        format!("VM - List index out of range  {}/{} {}:", field_pos, list_len, instruction.line));
+                    errors::runtime_error();
+                };
+
+                // list may outlive the frame owning a String instance, strings are stored by value
+                let value = self.inline_string(value);
+                self.rc.get_object(list_pos).set_value(field_pos, value);
             },
 
             OpCode::METHOD_CALL(mth) => {
-                let mut stack: Vec<Value> = vec![];
+                let mut stack: Vec<Value> = Vec::with_capacity(mth.chunk.max_stack);
                 let mut instance_rf_count = 0;
 
                 let adder: usize = if mth.is_self_arg { 1 }else { 0 };
@@ -278,15 +1294,16 @@ impl VM {
                 }
                 stack.reverse();
 
-                self.frames.push(Frame { chunk: mth.chunk, stack: stack, ip: 0, offset: self.rc.heap.len() - instance_rf_count });
+                self.frames.push(Frame { name: mth.name, chunk: mth.chunk, stack, ip: 0, offset: self.rc.heap.len() - instance_rf_count, instance_args: instance_rf_count });
 
                 self.ip += 1;
+                errors::set_cur_file(self.frames[self.ip].chunk.file);
             }
 
             OpCode::FUNCTION_CALL(index) => {
-                let chunk = self.rc.get_object(index).get_values()[0].clone();
+                let chunk = self.rc.get_object(index).get_values()[0].get_chunk();
 
-                let mut stack: Vec<Value> = vec![];
+                let mut stack: Vec<Value> = Vec::with_capacity(chunk.max_stack);
                 let mut instance_rf_count = 0;
 
                 for _ in 0..self.rc.get_object(index).get_arg_count() {
@@ -299,128 +1316,121 @@ impl VM {
                 }
                 stack.reverse();
 
-                self.frames.push(Frame { chunk: chunk.get_chunk().clone(), stack: stack, ip: 0, offset: self.rc.heap.len() - instance_rf_count });
+                let name = self.fn_names.get(&index).cloned().unwrap_or_default();
+                self.frames.push(Frame { name, chunk, stack, ip: 0, offset: self.rc.heap.len() - instance_rf_count, instance_args: instance_rf_count });
                 
                 self.ip += 1;
+                errors::set_cur_file(self.frames[self.ip].chunk.file);
             },
             OpCode::NATIVE_FN_CALL(index) => {
                 let native_fn = self.rc.get_object(index).get_values()[0].get_fn();
 
                 let mut stack: Vec<Value> = vec![];
-                let len = self.frames[self.ip].stack.len() - 1;
+                let len = self.frames[self.ip].stack.len();
 
-                for i in 0..self.rc.get_object(index).get_arg_count() {
+                for i in 1..=self.rc.get_object(index).get_arg_count() {
                     let value = self.frames[self.ip].stack[len - i].clone();
-                    match value {
-                        Value::StringRef(index) => {
-                            let fields = self.rc.get_object(index).get_values();
-
-                            stack.push(fields[0].clone());
-                        },
-                        _ => stack.push(value),
-                    }
+                    let value = self.inline_string(value);
+                    stack.push(value);
                 }
                 stack.reverse();
+                errors::set_runtime_line(self.native_call_line(instruction.line));
                 let output = native_fn(stack);
-                if output != Value::Null {
-                    for _ in 0..self.rc.get_object(index).get_arg_count() { self.frames[self.ip].stack.pop(); }; 
+                for _ in 0..self.rc.get_object(index).get_arg_count() { self.frames[self.ip].stack.pop(); };
 
-                    self.frames[self.ip].stack.push(output);
-                }
+                self.frames[self.ip].stack.push(output);
             },
             OpCode::IO_FN_CALL(index, arg_count) => {
                 let native_fn = self.rc.get_object(index).get_values()[0].get_fn();
 
                 let mut stack: Vec<Value> = vec![];
-                let len: usize = if self.frames[self.ip].stack.len() != 0 {
+                let len: usize = if !self.frames[self.ip].stack.is_empty() {
                     self.frames[self.ip].stack.len() - 1
                 }else { 0 };
 
                 for i in 0..arg_count {
                     let value = self.frames[self.ip].stack[len - i].clone();
-                    match value {
-                        Value::StringRef(index) => {
-                            let pos = self.rc.find_object(index);
-                            let fields = self.rc.get_object(pos).get_values();
-                            stack.push(fields[0].clone());
-                        },
-                        _ => {
-                            stack.push(value);
-                        }
-                    }
+                    let value = self.inline_string(value);
+                    stack.push(value);
                 }
                 stack.reverse();
 
+                errors::set_runtime_line(instruction.line);
                 let output = native_fn(stack);
                 if output != Value::Null {
-                    self.frames[self.ip].stack.pop();
+                    for _ in 0..arg_count { self.frames[self.ip].stack.pop(); };
+
                     self.frames[self.ip].stack.push(output);
                 }
             },
 
             OpCode::IF_STMT_OFFSET(offset) => {
                 let index = self.frames[self.ip].stack.len();
-                if self.frames[self.ip].stack[index - 1].get_bool() == false || self.break_loop {
-                    self.frames[self.ip].ip += offset;
+                if !self.frames[self.ip].stack[index - 1].get_bool() || self.break_loop {
+                    self.jump(offset, true);
                     self.break_loop = false;
                 }
             },
 
+            OpCode::JUMP_IF_FALSE(offset) => {
+                if !self.frames[self.ip].stack.last().unwrap().get_bool() {
+                    self.jump(offset, true);
+                }
+            },
+
             OpCode::JUMP(offset) => {
-                self.frames[self.ip].ip += offset;
+                self.jump(offset, true);
             },
 
             OpCode::LOOP(offset) => {
-                self.frames[self.ip].ip -= offset;
+                self.jump(offset, false);
             },
 
             OpCode::BREAK => {
                 self.break_loop = true;
             }
 
+            OpCode::FOR_LOOP(counter, limit, step, offset) => {
+                let stack = &self.frames[self.ip].stack;
+                let value = match stack[counter].get_int().checked_add(stack[step].get_int()) {
+                    Some(value) => value,
+                    None => self.int_overflow("+", instruction.line),
+                };
+                let limit = stack[limit].get_int();
+
+                self.frames[self.ip].stack[counter] = Value::Int(value);
+                if value > limit {
+                    self.jump(offset, true);
+                }
+            },
+
             OpCode::POP => {
                 self.frames[self.ip].stack.pop();
             },
 
+            OpCode::INSTANCE_INDEX(index) => {
+                let len = self.rc.heap.len();
+                self.rc.get_object(len - 1).set_index(index);
+            },
+
             OpCode::DEC_RC(pos) => {
-                let mut offset = self.frames[self.ip].offset+pos;
-                while matches!(self.rc.get_object(offset).get_values()[0], Value::InstanceRef(_)) ||
-                    matches!(self.rc.get_object(offset).get_values()[0], Value::StringRef(_))
-                {
-                    match self.rc.get_object(offset).get_values()[0] {
-                        Value::InstanceRef(pos) | Value::StringRef(pos) => {
-                            self.rc.dec_counter(offset);
-                            offset = pos;
-                        }
-                        _ => {},
-                    }
-                }
-                self.rc.dec_counter(offset);
+                let offset = self.frames[self.ip].offset+pos;
+                self.release(offset);
             },
             OpCode::DEC_TO(index) => {
                 for i in (self.frames[self.ip].offset+index..self.rc.heap.len()).rev() {
-                    self.rc.dec_counter(i);
+                    self.release(i);
                 }
             },
             OpCode::INC_RC(pos) => {
-                let mut offset = self.frames[self.ip].offset+pos;
-                while matches!(self.rc.get_object(offset).get_values()[0], Value::InstanceRef(_)) ||
-                    matches!(self.rc.get_object(offset).get_values()[0], Value::StringRef(_))
-                {
-                    match self.rc.get_object(offset).get_values()[0] {
-                        Value::InstanceRef(pos) | Value::StringRef(pos) => {
-                            offset = pos;
-                        }
-                        _ => {},
-                    }
-                }
+                let offset = self.resolve_ref(self.frames[self.ip].offset + pos);
                 self.rc.inc_counter(offset);
             },
             OpCode::PUSH_STACK(val) => {
                 self.frames[self.ip].stack.push(val);
             },
             OpCode::RF_REMOVE => {
-                self.rc.remove();
+                        self.rc.remove();
             },
 
             OpCode::VAR_CALL(index) => {
@@ -470,6 +1480,12 @@ impl VM {
     
                 self.frames[self.ip].stack.push(Value::Bool(a!=b));
             },
+            OpCode::CONTAINS_STRING => {
+                let a = self.pop_string();
+                let b = self.pop_string();
+
+                self.frames[self.ip].stack.push(Value::Bool(a.contains(&b)));
+            },
             OpCode::GREATER_FLOAT => {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_float();
                 let b = self.frames[self.ip].stack.pop().unwrap().get_float();
@@ -499,27 +1515,46 @@ impl VM {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_int();
                 let b = self.frames[self.ip].stack.pop().unwrap().get_int();
 
-                self.frames[self.ip].stack.push(Value::Int(b+a));
+                match b.checked_add(a) {
+                    Some(value) => self.frames[self.ip].stack.push(Value::Int(value)),
+                    None => self.int_overflow("+", instruction.line),
+                }
             },
             OpCode::SUB_INT => {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_int();
                 let b = self.frames[self.ip].stack.pop().unwrap().get_int();
-                self.frames[self.ip].stack.push(Value::Int(b-a));
+
+                match b.checked_sub(a) {
+                    Some(value) => self.frames[self.ip].stack.push(Value::Int(value)),
+                    None => self.int_overflow("-", instruction.line),
+                }
             },
             OpCode::MUL_INT => {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_int();
                 let b = self.frames[self.ip].stack.pop().unwrap().get_int();
-                self.frames[self.ip].stack.push(Value::Int(b*a));
+
+                match b.checked_mul(a) {
+                    Some(value) => self.frames[self.ip].stack.push(Value::Int(value)),
+                    None => self.int_overflow("*", instruction.line),
+                }
             },
             OpCode::DIV_INT => {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_int();
                 let b = self.frames[self.ip].stack.pop().unwrap().get_int();
-                self.frames[self.ip].stack.push(Value::Int(b/a));
+
+                match self.int_division(b, a, "/", instruction.line) {
+                    Some(value) => self.frames[self.ip].stack.push(Value::Int(value)),
+                    None => self.int_overflow("/", instruction.line),
+                }
             },
             OpCode::MOD_INT => {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_int();
                 let b = self.frames[self.ip].stack.pop().unwrap().get_int();
-                self.frames[self.ip].stack.push(Value::Int(b%a));
+
+                match self.int_division(b, a, "%", instruction.line) {
+                    Some(value) => self.frames[self.ip].stack.push(Value::Int(value)),
+                    None => self.int_overflow("%", instruction.line),
+                }
             },
             OpCode::EQ_INT => {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_int();
@@ -562,6 +1597,18 @@ impl VM {
                 let a = self.frames[self.ip].stack.pop().unwrap();
                 self.frames[self.ip].stack.push(-a);
             },
+
+            OpCode::DBG(line, index, instance) => {
+                let type_name = self.frames[self.ip].chunk.get_value(index);
+                let rendered = match instance {
+                    Some(pos) => self.dbg_instance(self.frames[self.ip].offset + pos),
+                    None => {
+                        let value = self.frames[self.ip].stack.last().unwrap().clone();
+                        self.dbg_value(value)
+                    },
+                };
+                eprintln!("[dbg line {}] {}: {}", line, type_name, rendered);
+            },
     
             OpCode::EQ_BOOL => {
                 let a = self.frames[self.ip].stack.pop().unwrap().get_bool();
@@ -577,84 +1624,374 @@ impl VM {
             },
     
             OpCode::ADD_STRING => {
-                let a = match self.frames[self.ip].stack.pop().unwrap() {
-                    Value::StringRef(index) => {
-                        let pos = self.rc.find_object(index);
+                let (b, a) = self.pop_string_operands(&OpCode::ADD_STRING, instruction.line);
+    
+                self.frames[self.ip].stack.push(Value::String(b+&a));
+            },
+            OpCode::EQ_STRING => {
+                let (b, a) = self.pop_string_operands(&OpCode::EQ_STRING, instruction.line);
 
-                        let fields = self.rc.get_object(pos).get_values();
+                self.frames[self.ip].stack.push(Value::Bool(a==b));
+            },
+            OpCode::NEG_EQ_STRING => {
+                let (b, a) = self.pop_string_operands(&OpCode::NEG_EQ_STRING, instruction.line);
+    
+                self.frames[self.ip].stack.push(Value::Bool(a!=b));
+            },
 
-                        fields[0].clone()
-                    },
-                    _ => Value::Null,
-                };
-                let b = match self.frames[self.ip].stack.pop().unwrap() {
-                    Value::StringRef(index) => {
-                        let pos = self.rc.find_object(index);
+            opcode => errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: {:?}", opcode)),
+        }
+    }
+}
 
-                        let fields = self.rc.get_object(pos).get_values();
+// Type of a runtime value as written in source, for runtime errors
+// Order of int, float and String list elements, sort() and the sorted list methods agree on it
+fn compare_elements(a: &Value, b: &Value) -> std::cmp::Ordering {
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
+        (a, b) => a.get_string().cmp(&b.get_string()),
+    }
+}
 
-                        fields[0].clone()
-                    },
-                    Value::String(val) => Value::String(val),
-                    _ => {
-                        Value::Null
-                    },
-                };
-    
-                self.frames[self.ip].stack.push(Value::String(b.get_string()+&a.get_string()));
-            },
-            OpCode::EQ_STRING => {
-                let a = match self.frames[self.ip].stack.pop().unwrap() {
-                    Value::StringRef(index) => {
-                        let pos = self.rc.find_object(index);
+fn operand_type(value: &Value) -> &'static str {
+    match value {
+        Value::Int(_) => "int",
+        Value::Float(_) => "float",
+        Value::Bool(_) => "bool",
+        Value::Null => "null",
+        Value::String(_) | Value::StringRef(_) => "String",
+        Value::List | Value::ListObj(_) => "List",
+        Value::Bytes(_) => "Bytes",
+        Value::Tuple(_) => "tuple",
+        Value::InstanceRef(_) | Value::InstanceObj(_) | Value::HostStruct(_) => "struct",
+        Value::Chunk(_) | Value::Fn(_) => "function",
+    }
+}
 
-                        let fields = self.rc.get_object(pos).get_values();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{functions, rc::Object};
 
-                        fields[0].clone()
-                    },
-                    Value::String(val) => Value::String(val),
-                    _ => Value::Null,
-                };
-                let b = match self.frames[self.ip].stack.pop().unwrap() {
-                    Value::StringRef(index) => {
-                        let pos = self.rc.find_object(index);
+    fn vm_with(code: Vec<OpCode>, ip: usize) -> VM {
+        let mut chunk = Chunk::new();
+        for op in code {
+            chunk.push(Instruction { op, line: 1 });
+        }
 
-                        let fields = self.rc.get_object(pos).get_values();
+        let mut vm = VM::new(VmLimits::default());
+        vm.frames.push(Frame { name: "main".to_string(), chunk, stack: vec![], ip, offset: 0, instance_args: 0 });
+        vm
+    }
 
-                        fields[0].clone()
-                    },
-                    Value::String(val) => Value::String(val),
-                    _ => Value::Null,
-                };
+    #[test]
+    fn fetch_past_end_of_chunk_is_reported() {
+        let vm = vm_with(vec![OpCode::POP, OpCode::END_OF_FN], 2);
+        assert_eq!(vm.check_fetch(), Err("Instruction pointer 2 is past the end of function \"main\" (chunk length 2)".to_string()));
 
-                self.frames[self.ip].stack.push(Value::Bool(a.get_string()==b.get_string()));
-            },
-            OpCode::NEG_EQ_STRING => {
-                let a = match self.frames[self.ip].stack.pop().unwrap() {
-                    Value::StringRef(index) => {
-                        let pos = self.rc.find_object(index);
+        let vm = vm_with(vec![OpCode::POP, OpCode::END_OF_FN], 1);
+        assert_eq!(vm.check_fetch(), Ok(()));
+    }
 
-                        let fields = self.rc.get_object(pos).get_values();
+    #[test]
+    fn non_string_operand_is_reported() {
+        let mut vm = vm_with(vec![OpCode::ADD_STRING, OpCode::END_OF_FN], 0);
+        vm.frames[0].stack.extend([Value::String("x".to_string()), Value::Null]);
+        assert_eq!(vm.string_operands(&OpCode::ADD_STRING, 9), Err("Operator '+' expects String operands, found: null 9:".to_string()));
 
-                        fields[0].clone()
-                    },
-                    _ => Value::Null,
-                };
-                let b = match self.frames[self.ip].stack.pop().unwrap() {
-                    Value::StringRef(index) => {
-                        let pos = self.rc.find_object(index);
+        vm.frames[0].stack.extend([Value::Int(1), Value::String("x".to_string())]);
+        assert_eq!(vm.string_operands(&OpCode::NEG_EQ_STRING, 4), Err("Operator '!=' expects String operands, found: int 4:".to_string()));
 
-                        let fields = self.rc.get_object(pos).get_values();
+        vm.frames[0].stack.extend([Value::String("a".to_string()), Value::String("b".to_string())]);
+        assert_eq!(vm.string_operands(&OpCode::EQ_STRING, 1), Ok(("a".to_string(), "b".to_string())));
+    }
 
-                        fields[0].clone()
-                    },
-                    _ => Value::Null,
-                };
-    
-                self.frames[self.ip].stack.push(Value::Bool(a!=b));
-            },
+    #[test]
+    fn fetch_without_frame_is_reported() {
+        let mut vm = vm_with(vec![OpCode::END_OF_FN], 0);
+        vm.ip = 1;
+        assert_eq!(vm.check_fetch(), Err("Frame 1 does not exist, call stack has 1 frames".to_string()));
+    }
 
-            opcode => errors::error_message("RUNTIME - VM ERROR", format!("VM - this error should never prints out: {:?}", opcode)),
+    #[test]
+    fn chunk_without_main_is_rejected() {
+        let mut helper = functions::Function::new("helper".to_string());
+        helper.chunk.push(Instruction { op: OpCode::END_OF_FN, line: 1 });
+
+        let mut chunk = Chunk::new();
+        chunk.push(Instruction { op: OpCode::FUNCTION_DEC(helper), line: 1 });
+
+        let error = VM::new(VmLimits::default()).declare_all(chunk).err();
+        assert_eq!(error, Some("Cannot find \"main\" function object in declared chunk".to_string()));
+        assert!(VM::new(VmLimits::default()).declare_all(Chunk::new()).is_err());
+    }
+
+    #[test]
+    fn dec_opcodes_check_stack_depth() {
+        let mut vm = vm_with(vec![OpCode::END_OF_FN], 0);
+        vm.frames[0].stack.push(Value::Int(1));
+
+        assert_eq!(vm.check_depth("LIST_DEC", 1), Ok(()));
+        assert_eq!(vm.check_depth("LIST_DEC", 3), Err("LIST_DEC expects 3 values on the stack, found: 1".to_string()));
+        assert_eq!(vm.check_depth("INSTANCE_DEC", 2), Err("INSTANCE_DEC expects 2 values on the stack, found: 1".to_string()));
+    }
+
+    #[test]
+    fn jump_outside_of_chunk_is_reported() {
+        // ip points after the fetched jump instruction
+        let vm = vm_with(vec![OpCode::JUMP(5), OpCode::POP, OpCode::END_OF_FN], 1);
+        assert_eq!(vm.jump_target(5, true), Err("Jump by +5 at ip 0 leaves function \"main\" (chunk length 3)".to_string()));
+        assert_eq!(vm.jump_target(1, true), Ok(2));
+
+        let vm = vm_with(vec![OpCode::POP, OpCode::LOOP(4), OpCode::END_OF_FN], 2);
+        assert_eq!(vm.jump_target(4, false), Err("Jump by -4 at ip 1 leaves function \"main\" (chunk length 3)".to_string()));
+        assert_eq!(vm.jump_target(2, false), Ok(0));
+    }
+
+    // Runs main of the source without the final heap cleanup, returns heap length before and after
+    fn heap_after_run(source: &str) -> (usize, usize) {
+        let tokens = crate::frontend::lexer::Scanner::init(source).get_tokens();
+        let chunk = crate::compiler::compiler::Compiler::new(tokens, Default::default()).compile();
+
+        let mut vm = VM::new(VmLimits::default());
+        let main_frame = vm.declare_all(chunk).unwrap();
+        let declared = vm.rc.heap.len();
+
+        vm.frames.push(main_frame);
+        vm.execute();
+        (declared, vm.rc.heap.len())
+    }
+
+    #[test]
+    fn string_fields_keep_heap_balanced() {
+        let source = "struct User {\n    name: String,\n    age: int,\n\n    methods {\n        nameLen(self) int {\n            return self.name.len()\n        }\n    }\n}\n\n\
+            fn show(s: String) null {\n    discard s.len()\n}\n\n\
+            fn rename(u: User, name: String) null {\n    u.name = name\n}\n\n\
+            fn work() null {\n    var u: User = {\"ann\", 3}\n    var other: String = \"bob\"\n    u.name = other\n    var copy: String = u.name\n\
+                var n: int = u.name.len() + u.nameLen()\n    show(u.name)\n    rename(u, \"dora\")\n    u.name = \"carl\"\n\
+                var i: int = 0\n    while i < 3 {\n        u.name = u.name + \"!\"\n        show(u.name)\n        i = i + 1\n    }\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn list_extend_keeps_heap_balanced() {
+        let source = "fn work() int {\n    var s: String = \"x\"\n    var names: List<String> = [\"ann\", s]\n    var more: List<String> = [\"bob\"]\n\
+                names.extend(more)\n    names.extend(names)\n    var all: List<String> = names + more\n    return all.len()\n}\n\n\
+            fn main() null {\n    discard work()\n    discard work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn string_accumulation_keeps_heap_balanced() {
+        let source = "struct Log {\n    text: String,\n}\n\n\
+            fn work() int {\n    var s: String = \"\"\n    var piece: String = \"ab\"\n    var log: Log = {\"\"}\n    var i: int = 0\n\
+                while i < 10000 {\n        s = s + piece\n        log.text = log.text + \"c\"\n        i = i + 1\n    }\n    return s.len() + log.text.len()\n}\n\n\
+            fn main() null {\n    discard work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn self_field_method_compared_with_field_keeps_heap_balanced() {
+        let source = "struct Bag {\n    name: String,\n    capacity: int,\n\n    methods {\n        full(self) bool {\n            var n: int = 0\n\
+            while n < 3 {\n                if self.name.len() > self.capacity {\n                    return true\n                }\n                n = n + 1\n            }\n\
+            return self.capacity < self.name.len()\n        }\n    }\n}\n\n\
+            fn work() null {\n    var a: Bag = {\"abc\", 2}\n    var b: Bag = {\"a\", 2}\n    var i: int = 0\n\
+                while i < 3 {\n        if a.full() and !b.full() {\n            i = i + 1\n        }\n        i = i + 1\n    }\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn destructured_tuples_keep_heap_balanced() {
+        let source = "fn pair(s: String) (String, int) {\n    return (s, s.len())\n}\n\n\
+            fn work() null {\n    var a, n = pair(\"abc\")\n    var i: int = 0\n    while i < 3 {\n        var b, m = pair(a)\n        i = i + m\n    }\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn struct_literal_args_keep_heap_balanced() {
+        let source = "struct P {\n    name: String,\n    n: int,\n}\n\n\
+            fn size(tag: String, p: P) int {\n    return p.n + tag.len()\n}\n\n\
+            fn work() null {\n    var i: int = 0\n    while i < 3 {\n        i = i + size(\"ab\", P{\"x\", 1})\n    }\n    var ps: List<P> = [P{\"a\", 1}, P{\"b\", 2}]\n    i = i + ps[1].n\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn runtime_strings_keep_heap_balanced() {
+        let source = "fn show(s: String) int {\n    return s.len()\n}\n\n\
+            fn name() String {\n    return \"bob\"\n}\n\n\
+            fn work() null {\n    var s: String = \"ab\"\n    var i: int = 0\n    while i < 3 {\n\
+                    i = i + show(convstr(i)) + show(s + name()) + name().len() + s.find(\"a\" + \"b\")\n    }\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn struct_returns_keep_heap_balanced() {
+        let source = "struct P {\n    x: int,\n\n    methods {\n        twice(self) int {\n            return sum(self) * 2\n        }\n    }\n}\n\n\
+            fn sum(p: P) int {\n    return p.x\n}\n\n\
+            fn make(x: int) P {\n    var tmp: P = P{0}\n    var p: P = P{x}\n    return p\n}\n\n\
+            fn id(p: P) P {\n    return p\n}\n\n\
+            fn work() null {\n    var a: P = make(3)\n    var b: P = id(a)\n    var c: P = P{4}\n    var n: int = a.twice() + sum(b) + c.x\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    // String arguments are the first objects of the callee's heap region, they belong to the caller
+    #[test]
+    fn struct_returns_with_string_params_keep_heap_balanced() {
+        let source = "struct P {\n    s: String,\n    x: int,\n}\n\n\
+            fn mk(a: int, s: String) P {\n    return P{s, a + s.len()}\n}\n\n\
+            fn pick(tag: String, p: P) P {\n    return p\n}\n\n\
+            fn work() null {\n    var name: String = \"ab\"\n    var i: int = 0\n    while i < 3 {\n        var p: P = mk(i, name)\n        var q: P = pick(\"t\", p)\n        i = i + q.x\n    }\n\
+            var p: P = mk(1, \"cd\")\n    var q: P = pick(name, p)\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    #[test]
+    fn list_fields_keep_heap_balanced() {
+        let source = "struct Node {\n    id: int,\n    name: String,\n}\n\n\
+            struct Bag {\n    items: List<Node>,\n\n    methods {\n        add(self, id: int) {\n            self.items.push(Node{id, \"n\"})\n        }\n    }\n}\n\n\
+            fn show(n: Node) int {\n    return n.id\n}\n\n\
+            fn make() Bag {\n    var bag: Bag = Bag{[Node{1, \"a\"}]}\n    bag.add(2)\n    return bag\n}\n\n\
+            fn work() null {\n    var bag: Bag = make()\n    var n: Node = bag.items[1]\n    var i: int = n.id + show(bag.items[0]) + bag.items[1].name.len()\n    bag.items.insert(0, n)\n}\n\n\
+            fn main() null {\n    work()\n    work()\n}\n";
+
+        let (declared, after) = heap_after_run(source);
+        assert_eq!(declared, after);
+    }
+
+    // (recorded max_stack, deepest stack seen while running) of every function and method
+    fn stack_bounds(source: &str) -> Vec<(String, usize, usize)> {
+        let tokens = crate::frontend::lexer::Scanner::init(source).get_tokens();
+        let chunk = crate::compiler::compiler::Compiler::new(tokens, Default::default()).compile();
+
+        let mut recorded = vec![];
+        for instruction in chunk.code.iter() {
+            match &instruction.op {
+                OpCode::FUNCTION_DEC(function) => recorded.push((function.name.clone(), function.chunk.max_stack)),
+                // builtin methods are hand-built native wrappers
+                OpCode::STRUCT_DEC(struct_) if !NativeRegistry::is_builtin(&struct_.name) => {
+                    recorded.extend(struct_.methods.values().map(|method| (method.name.clone(), method.chunk.max_stack)));
+                },
+                _ => {},
+            }
+        }
+
+        let mut vm = VM::new(VmLimits::default());
+        let main_frame = vm.declare_all(chunk).unwrap();
+        vm.frames.push(main_frame);
+        vm.execute();
+
+        recorded.into_iter().map(|(name, max)| {
+            let peak = vm.stack_peaks.get(&name).copied().unwrap_or_default();
+            (name, max, peak)
+        }).collect()
+    }
+
+    #[test]
+    fn max_stack_bounds_observed_depth() {
+        let source = "struct V {\n    x: int,\n    y: float,\n\n    methods {\n        scaled(self, k: int, f: float) float {\n            return self.y * f + convf(self.x * k)\n        }\n    }\n}\n\n\
+            fn fib(n: int) int {\n    if n < 2 {\n        return n\n    }\n    return fib(n - 1) + fib(n - 2)\n}\n\n\
+            fn mix(a: int, b: int, c: int, d: int) int {\n    return a * b + c * d - a + b * c - d\n}\n\n\
+            fn loops(n: int) int {\n    var total: int = 0\n    for i in (0, n) {\n        for j in (0, i) {\n            total = total + mix(i, j, i + j, 1)\n        }\n    }\n    return total\n}\n\n\
+            fn words(s: String) int {\n    var xs: List<int> = [1, 2, s.len()]\n    xs.swap(0, 2)\n    return xs[0] + xs.len() + s.find(\"b\")\n}\n\n\
+            fn main() null {\n    var v: V = V{2, 1.5}\n    var n: int = fib(10) + loops(4) + words(\"abc\")\n    println(n, v.scaled(3, 2.0))\n}\n";
+
+        let bounds = stack_bounds(source);
+        assert_eq!(bounds.len(), 6, "{:?}", bounds);
+        for (name, max, peak) in bounds {
+            assert!(peak > 0 && peak <= max, "{}: observed {} values, recorded max {}", name, peak, max);
+            // every function runs its deepest path
+            assert_eq!(peak, max, "{}", name);
+        }
+    }
+
+    #[test]
+    fn leak_report_separates_cycles_from_referenced_objects() {
+        let mut vm = vm_with(vec![OpCode::END_OF_FN], 0);
+        vm.rc.push(Box::new(crate::objects::structs::Struct::new("Node".to_string())));
+        vm.rc.set_declared();
+
+        // two nodes pointing at each other, nothing else points at them
+        for (index, other) in [(10, 11), (11, 10)] {
+            let mut node = crate::objects::structs::StructInstance::new(0);
+            node.fields_values = vec![Value::InstanceRef(other), Value::Int(index as i64)];
+            node.set_index(index);
+            vm.rc.push(Box::new(node));
         }
+        assert_eq!(vm.live_objects(), vec![
+            "    [1] Node instance [InstanceRef(11), Int(10)] rc=1 (unreachable)".to_string(),
+            "    [2] Node instance [InstanceRef(10), Int(11)] rc=1 (unreachable)".to_string(),
+        ]);
+
+        vm.frames[0].stack.push(Value::InstanceRef(11));
+        assert!(vm.live_objects().iter().all(|line| line.ends_with("(still referenced)")));
+    }
+
+    // Compiles the source, lets `corrupt` change the code of main and returns the verifier error
+    fn verify_error(source: &str, corrupt: impl Fn(&mut Vec<Instruction>)) -> String {
+        let tokens = crate::frontend::lexer::Scanner::init(source).get_tokens();
+        let mut chunk = crate::compiler::compiler::Compiler::new(tokens, Default::default()).compile();
+
+        for instruction in chunk.code.iter_mut() {
+            if let OpCode::FUNCTION_DEC(function) = &mut instruction.op {
+                if function.name == "main" {
+                    corrupt(&mut function.chunk.code);
+                }
+            }
+        }
+        VM::new(VmLimits::default()).declare_all(chunk).err().unwrap_or_default()
+    }
+
+    fn replace_op(code: &mut [Instruction], find: fn(&OpCode) -> bool, op: OpCode) {
+        code.iter_mut().find(|instruction| find(&instruction.op)).unwrap().op = op;
+    }
+
+    #[test]
+    fn verifier_rejects_corrupted_chunks() {
+        let source = "fn add(a: int, b: int) int {\n    return a + b\n}\n\nfn main() null {\n    var x: int = 1\n    var s: String = \"a\"\n\
+            if x > 0 {\n        x = add(x, 2)\n    }\n    println(x, s.len())\n}\n";
+        assert_eq!(verify_error(source, |_| {}), "");
+
+        assert_eq!(verify_error(source, |code| replace_op(code, |op| matches!(op, OpCode::CONSTANT_INT(_)), OpCode::CONSTANT_INT(40))),
+            "bytecode verification failed: CONSTANT_INT(40) refers to constant 40, chunk has 12 constants at offset 0 in function main");
+        assert_eq!(verify_error(source, |code| replace_op(code, |op| matches!(op, OpCode::IF_STMT_OFFSET(_)), OpCode::IF_STMT_OFFSET(500))),
+            "bytecode verification failed: jump target 508 of IF_STMT_OFFSET(500) is out of chunk with 30 instructions at offset 7 in function main");
+        assert_eq!(verify_error(source, |code| replace_op(code, |op| matches!(op, OpCode::VAR_CALL(_)), OpCode::VAR_CALL(7))),
+            "bytecode verification failed: VAR_CALL(7) uses stack slot 7, stack has 1 values at offset 4 in function main");
+        // heap size depends on the number of natives
+        let error = verify_error(source, |code| replace_op(code, |op| matches!(op, OpCode::FUNCTION_CALL(_)), OpCode::FUNCTION_CALL(9000)));
+        assert!(error.starts_with("bytecode verification failed: FUNCTION_CALL(9000) refers to heap object 9000, heap has "), "{}", error);
+        assert!(error.ends_with(" objects at offset 11 in function main"), "{}", error);
+        assert_eq!(verify_error(source, |code| replace_op(code, |op| matches!(op, OpCode::FUNCTION_CALL(_)), OpCode::FUNCTION_CALL(0))),
+            "bytecode verification failed: FUNCTION_CALL(0) refers to heap object 0 which is not a function at offset 11 in function main");
+        assert_eq!(verify_error(source, |code| replace_op(code, |op| matches!(op, OpCode::DEC_RC(_)), OpCode::DEC_RC(9000))),
+            "bytecode verification failed: DEC_RC(9000) uses instance 9000, function can hold at most 31 instances at offset 28 in function main");
+        assert_eq!(verify_error(source, |code| code.insert(0, Instruction { op: OpCode::POP, line: 6 })),
+            "bytecode verification failed: stack underflow, POP needs 1 values, but found 0 at offset 0 in function main");
     }
 }
@@ -0,0 +1,42 @@
+// Helpers shared by the tests that run the shlang binary, each test crate uses only some of them
+#![allow(dead_code, reason = "every test crate compiles its own copy and uses a different subset")]
+
+use std::{fs, io::Write, path::PathBuf, process::{Command, Stdio}};
+
+pub struct Outcome {
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Writes source into the test temp dir as "<name>.shl", name should carry the test file prefix
+pub fn write_source(name: &str, source: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.shl", name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+// Runs the binary with given flags followed by the files, stdin is closed after writing `stdin`
+pub fn run_shlang(args: &[&str], files: &[PathBuf], stdin: &[u8]) -> Outcome {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_shlang"))
+        .args(args)
+        .args(files)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // program may exit before reading everything
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    let output = child.wait_with_output().unwrap();
+
+    Outcome {
+        exit_code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    }
+}
+
+pub fn run_source(name: &str, source: &str, args: &[&str]) -> Outcome {
+    run_shlang(args, &[write_source(name, source)], b"")
+}
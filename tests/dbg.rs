@@ -0,0 +1,41 @@
+mod common;
+
+use common::Outcome;
+
+fn run_source(name: &str, source: &str) -> Outcome {
+    common::run_source(&format!("dbg_{}", name), source, &["--leak-report"])
+}
+
+const SOURCE: &str = "struct P {\n    x: int,\n    name: String,\n}\n\n\
+    fn twice(x: int) int {\n    return x * 2\n}\n\n\
+    fn main() null {\n    var x: int = 2\n    var y: int = dbg(twice(x)) + 1\n    println(y)\n\
+    var s: String = \"hi\"\n    println(dbg(s) + \"?\")\n\
+    var xs: List<int> = [1, 2]\n    println(2 in dbg(xs))\n\
+    var p: P = {1, \"a\"}\n    dbg(p)\n\
+    var ps: List<P> = [P{3, \"c\"}]\n    dbg(ps[0])\n    println(dbg(p.x) + ps[0].x)\n}\n";
+
+#[test]
+fn dbg_prints_type_and_value_to_stderr() {
+    let outcome = run_source("values", SOURCE);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stderr, "[dbg line 12] int: 4\n\
+        [dbg line 15] String: \"hi\"\n\
+        [dbg line 17] List<int>: [1, 2]\n\
+        [dbg line 19] P: P{1, \"a\"}\n\
+        [dbg line 21] P: P{3, \"c\"}\n\
+        [dbg line 22] int: 1\n");
+}
+
+#[test]
+fn dbg_leaves_value_in_expression() {
+    let outcome = run_source("expression", SOURCE);
+    assert_eq!(outcome.stdout, "5\nhi?\ntrue\n4\n");
+}
+
+#[test]
+fn declared_dbg_function_is_called() {
+    let outcome = run_source("shadowed", "fn main() null {\n    println(dbg(3))\n}\n\nfn dbg(x: int) int {\n    return x + 100\n}\n");
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "103\n");
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+}
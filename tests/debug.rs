@@ -0,0 +1,168 @@
+mod common;
+
+use std::{io::{Cursor, Write}, sync::{Arc, Mutex}};
+
+use shlang::{vm::debugger::Debugger, Engine};
+
+fn run_source(name: &str, source: &str, args: &[&str]) -> (Option<i32>, String, String) {
+    let outcome = common::run_source(&format!("debug_{}", name), source, args);
+    (outcome.exit_code, outcome.stdout, outcome.stderr)
+}
+
+const ADD: &str = "fn add(a: int, b: int) int {\n    var c: int = a + b\n    return c\n}\n\nfn main() null {\n    println(add(1, 2))\n}\n";
+
+#[test]
+fn disassembly_groups_instructions_by_source_line() {
+    let (code, stdout, stderr) = run_source("grouped", ADD, &["--with-source", "--disassemble"]);
+    assert_eq!(code, Some(0), "{}", stderr);
+
+    let add = stdout.split("== main ==").next().unwrap();
+    let expected = [
+        "== add ==",
+        "     max stack 4",
+        "   2 | var c: int = a + b",
+        "     0000 VAR_CALL(0)",
+        "     0001 VAR_CALL(1)",
+        "     0002 ADD_INT",
+        "   3 | return c",
+        "     0003 VAR_CALL(2)",
+        "     0004 RETURN",
+        "   4 | }",
+        "     0005 CONSTANT_NULL(3)",
+        "     0006 RETURN",
+        "     0007 END_OF_FN",
+        "",
+    ];
+    assert_eq!(add, expected.join("\n"));
+    assert!(stdout.contains("   7 | println(add(1, 2))\n"), "{}", stdout);
+}
+
+#[test]
+fn disassembly_without_source_shows_line_numbers() {
+    let (code, stdout, stderr) = run_source("numbers", ADD, &["--disassemble"]);
+    assert_eq!(code, Some(0), "{}", stderr);
+
+    assert!(stdout.starts_with("== add ==\n     max stack 4\n   2\n     0000 VAR_CALL(0)\n"), "{}", stdout);
+    assert!(!stdout.contains(" | "), "{}", stdout);
+}
+
+#[test]
+fn string_literal_concatenation_is_folded() {
+    let source = "fn main() null {\n    var s: String = \"tab\\t\" + \"|\" +\n        \"\\nend\"\n    println(s, \"a\" \"b\")\n}\n";
+
+    let (code, stdout, stderr) = run_source("folded", source, &["--disassemble"]);
+    assert_eq!(code, Some(0), "{}", stderr);
+    let main = stdout.split("== main ==").nth(1).unwrap();
+    assert_eq!(main.matches("STRING_DEC").count(), 1, "{}", main);
+    assert!(!main.contains("ADD_STRING"), "{}", main);
+    assert!(main.contains("PUSH_STACK(String(\"ab\"))"), "{}", main);
+
+    let (code, stdout, stderr) = run_source("folded_output", source, &[]);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "tab\t|\nendab\n");
+}
+
+#[test]
+fn runtime_error_shows_source_line() {
+    let source = "fn main() null {\n    var xs: List<int> = [1, 2]\n    var i: int = 5\n    println(xs[i])\n}\n";
+
+    let (code, _, stderr) = run_source("runtime", source, &["--with-source"]);
+    assert_eq!(code, Some(70));
+    assert!(stderr.contains("List index out of range  5/2 4:\n   4 | println(xs[i])\n"), "{}", stderr);
+
+    let (code, _, stderr) = run_source("runtime_plain", source, &[]);
+    assert_eq!(code, Some(70));
+    assert!(!stderr.contains("println(xs[i])"), "{}", stderr);
+}
+
+#[test]
+fn leak_report_is_empty_for_balanced_program() {
+    let source = "struct P {\n    name: String,\n    n: int,\n}\n\nfn size(p: P) int {\n    return p.n + p.name.len()\n}\n\n\
+        fn main() null {\n    var p: P = {\"ann\", 3}\n    var s: String = \"abc\"\n    println(size(p) + s.len())\n}\n";
+
+    let (code, stdout, stderr) = run_source("leaks", source, &["--leak-report"]);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert_eq!(stdout, "9\n");
+    assert!(stderr.is_empty(), "{}", stderr);
+}
+
+// Debugger output kept after the engine run, the debugger owns its writer
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn step_trace(source: &str, commands: &str) -> String {
+    let output = SharedOutput::default();
+    let debugger = Debugger::new(Box::new(Cursor::new(commands.to_string())), Box::new(output.clone()));
+    Engine::new().run_source_with_debugger(source, debugger).unwrap();
+
+    let trace = output.0.lock().unwrap().clone();
+    String::from_utf8(trace).unwrap()
+}
+
+#[test]
+fn step_mode_prints_state_before_every_instruction() {
+    let trace = step_trace(ADD, "s\ns\ns\ns\np 1\np 7\ns\nq\n");
+
+    let expected = [
+        "#0 main 0000 CONSTANT_INT(0) []",
+        "#0 main 0001 CONSTANT_INT(1) [1]",
+        "#0 main 0002 FUNCTION_CALL(68) [1, 2]",
+        "#1 add 0000 VAR_CALL(0) [1, 2]",
+        "#1 add 0001 VAR_CALL(1) [1, 2, 1]",
+        "slot 1 = 2",
+        "no stack slot 7, stack has 3 values",
+        "#1 add 0002 ADD_INT [1, 2, 1, 2]",
+        "",
+    ];
+    assert_eq!(trace, expected.join("\n"));
+}
+
+#[test]
+fn step_mode_continue_and_heap_summary() {
+    let source = "struct P {\n    x: int,\n}\n\nfn main() null {\n    var a: P = {1}\n    var s: String = \"ab\"\n    println(s.len() + a.x)\n}\n";
+    let trace = step_trace(source, "s\ns\nh\nc\n");
+
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", trace);
+    assert!(lines[2].starts_with("#0 main 0002 STRING_DEC"), "{}", trace);
+    assert!(lines[3].starts_with("heap: ") && lines[4].ends_with("] P instance [Int(1)] rc=1"), "{}", trace);
+}
+
+#[test]
+fn step_mode_forbids_input() {
+    let source = "fn main() null {\n    var name: String = input(\"name: \")\n    println(name)\n}\n";
+
+    let (code, _, stderr) = run_source("step_input", source, &["--step"]);
+    assert_eq!(code, Some(70));
+    assert!(stderr.contains("input() cannot be used in --step mode, stdin is read by the debugger 2:"), "{}", stderr);
+}
+
+#[test]
+fn step_mode_forbids_other_stdin_natives() {
+    for (i, call) in ["readLine()", "readAll()", "eofStdin()"].iter().enumerate() {
+        let source = format!("fn main() null {{\n    println(\"start\")\n    println({})\n}}\n", call);
+        let name = call.trim_end_matches("()");
+
+        let (code, _, stderr) = run_source(&format!("step_stdin_{}", i), &source, &["--step"]);
+        assert_eq!(code, Some(70), "{}", stderr);
+        assert!(stderr.contains(&format!("{}() cannot be used in --step mode, stdin is read by the debugger 3:", name)), "{}", stderr);
+    }
+}
+
+#[test]
+fn step_mode_shows_top_five_stack_values() {
+    let source = "fn main() null {\n    var a: int = 1\n    var b: int = 2\n    var c: int = 3\n    var d: int = 4\n    var e: int = 5\n    var f: int = 6\n    println(a)\n}\n";
+    let trace = step_trace(source, "s\ns\ns\ns\ns\ns\nq\n");
+
+    assert!(trace.ends_with("#0 main 0006 VAR_CALL(0) [2, 3, 4, 5, 6]\n"), "{}", trace);
+}
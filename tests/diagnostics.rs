@@ -0,0 +1,774 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+// Compiles given source with the shlang binary and returns (exit code, stderr)
+fn run_source(name: &str, source: &str) -> (Option<i32>, String) {
+    let outcome = common::run_source(name, source, &[]);
+    (outcome.exit_code, outcome.stderr)
+}
+
+fn assert_error(name: &str, source: &str, expected: &str) {
+    let (code, stderr) = run_source(name, source);
+    // compile errors exit with 65, runtime errors with 70
+    let expected_code = if stderr.contains("==== RUNTIME") { Some(70) } else { Some(65) };
+    assert_eq!(code, expected_code, "{}: expected error exit code, stderr:\n{}", name, stderr);
+    assert!(stderr.contains(expected), "{}: expected stderr to contain {:?}, found:\n{}", name, expected, stderr);
+}
+
+#[test]
+fn arithmetic_on_bool_and_null_names_operator() {
+    for (i, op) in ["+", "-", "*", "/", "%"].iter().enumerate() {
+        assert_error(&format!("bool_op_{}", i), &format!("fn main() {{\n    println(true {} false)\n}}\n", op),
+            &format!("Operator '{}' is not defined for bool operands 2:", op));
+
+        assert_error(&format!("null_op_{}", i), &format!("fn main() {{\n    println(null {} null)\n}}\n", op),
+            &format!("Operator '{}' is not defined for null operands 2:", op));
+    }
+}
+
+#[test]
+fn not_requires_bool_operand() {
+    assert_error("not_int", "fn main() {\n    println(not 5)\n}\n", "Operator 'not' expects bool operand, found: int 2:");
+    assert_error("bang_int", "fn main() {\n    println(!5)\n}\n", "Operator '!' expects bool operand, found: int 2:");
+}
+
+#[test]
+fn missing_source_file_reports_path() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("dir").join("..").join("missing.shl");
+    let outcome = common::run_shlang(&[], std::slice::from_ref(&path), b"");
+
+    assert_eq!(outcome.exit_code, Some(74));
+    assert!(outcome.stderr.contains("==== FILE OPEN ===="), "{}", outcome.stderr);
+    assert!(outcome.stderr.contains(&format!("Error while trying to open a file \"{}\"", path.display())), "{}", outcome.stderr);
+}
+
+#[test]
+fn chars_result_must_be_list_of_strings() {
+    assert_error("chars_int_list", "fn main() {\n    var s: String = \"ab\"\n    var xs: List<int> = s.chars()\n}\n",
+        "Expected to find List<int> but found List<String> 3:");
+}
+
+#[test]
+fn return_type_is_checked_for_each_expression_shape() {
+    let cases = [
+        ("arith_int", "fn f(a: int) float {\n    return a * 2\n}\n", "expected: float found: int"),
+        ("arith_float", "fn f(a: float) int {\n    return a + 1.5\n}\n", "expected: int found: float"),
+        ("comparison", "fn f(a: int) int {\n    return a < 2\n}\n", "expected: int found: bool"),
+        ("comparison_float", "fn f(a: float) float {\n    return a == 2.0\n}\n", "expected: float found: bool"),
+        ("logic", "fn f(a: int) int {\n    return a > 1 and a < 3\n}\n", "expected: int found: bool"),
+        ("fn_call", "fn g() float {\n    return 1.0\n}\nfn f() int {\n    return g()\n}\n", "expected: int found: float"),
+        ("native_call", "fn f(a: int) bool {\n    return abs(a)\n}\n", "expected: bool found: int"),
+        ("method_call", "fn f(s: String) bool {\n    return s.len()\n}\n", "expected: bool found: int"),
+        ("list_index", "fn f() String {\n    var xs: List<int> = [1]\n    return xs[0]\n}\n", "expected: String found: int"),
+        ("concat", "fn f(s: String) int {\n    return s + \"!\"\n}\n", "expected: int found: String"),
+    ];
+
+    for (name, source, expected) in cases {
+        let source = format!("{}\nfn main() {{\n}}\n", source);
+        assert_error(&format!("return_{}", name), &source, &format!("Mismatched types while returning function, {}", expected));
+    }
+}
+
+#[test]
+fn bool_argument_type_is_checked() {
+    let header = "struct S {\n    on: bool,\n\n    methods {\n        set(self, n: int, flag: bool) {\n            self.on = flag\n        }\n    }\n}\nfn f(n: int, flag: bool) {\n}\n";
+    let cases = [
+        ("mth_literal", "s.set(1, 2)", "Expected to find bool as argument type but found: int"),
+        ("mth_swapped", "s.set(true, 1)", "Expected to find int as argument type but found: bool"),
+        ("mth_comparison", "s.set(1 < 2, 1 < 2)", "Expected to find int as argument type but found: bool"),
+        ("fn_literal", "f(1, 2.5)", "Expected to find bool as argument type but found: float"),
+        ("fn_field", "f(s.on, true)", "Expected to find int as argument type but found: bool"),
+        ("fn_result", "f(1, abs(1))", "Expected to find bool as argument type but found: int"),
+    ];
+
+    for (name, call, expected) in cases {
+        let source = format!("{}fn main() {{\n    var s: S = {{false}}\n    {}\n}}\n", header, call);
+        assert_error(&format!("arg_{}", name), &source, &format!("{} 14:", expected));
+    }
+}
+
+#[test]
+fn do_while_requires_condition() {
+    assert_error("do_while_no_cond", "fn main() {\n    do {\n        println(1)\n    } while\n}\n",
+        "Expected to find expression after WHILE statement 4:");
+    assert_error("do_while_no_while", "fn main() {\n    do {\n        println(1)\n    }\n}\n",
+        "Expected 'while', but found '}' 4:");
+}
+
+#[test]
+fn switch_labels_are_checked() {
+    let cases = [
+        ("duplicate_int", "var n: int = 1\n    switch n {\n        case 1 {\n        }\n        case 1 {\n        }\n    }", "Duplicate case label \"1\" 6:"),
+        ("duplicate_string", "var s: String = \"a\"\n    switch s {\n        case \"a\" {\n        }\n        case \"a\" {\n        }\n    }", "Duplicate case label \"a\" 6:"),
+        ("non_literal", "var n: int = 1\n    switch n {\n        case n {\n        }\n    }", "Case label has to be int literal, found: identifier \"n\" 4:"),
+        ("wrong_literal", "var n: int = 1\n    switch n {\n        case \"1\" {\n        }\n    }", "Case label has to be int literal, found: string \"1\" 4:"),
+        ("bool_value", "switch true {\n    }", "Switch expects int or String value, found: bool 2:"),
+    ];
+
+    for (name, body, expected) in cases {
+        assert_error(&format!("switch_{}", name), &format!("fn main() {{\n    {}\n}}\n", body), expected);
+    }
+}
+
+#[test]
+fn statements_at_file_scope_are_rejected() {
+    let cases = [
+        ("stray_call", "println(\"hi\")", "println"),
+        ("stray_expression", "1 + 2", "1"),
+        ("stray_if", "if true {\n    println(1)\n}", "if"),
+        ("stray_var", "var x: int = 5", "var"),
+    ];
+
+    for (name, statement, first_token) in cases {
+        let source = format!("fn main() {{\n}}\n{}\n", statement);
+        let expected = format!("Only function, struct and methods declarations are allowed at file scope (found a statement starting with '{}') 3:", first_token);
+        assert_error(&format!("file_scope_{}", name), &source, &expected);
+
+        let (_, stderr) = run_source(&format!("file_scope_{}", name), &source);
+        assert_eq!(stderr.matches("====").count(), 2, "{}: expected a single error, found:\n{}", name, stderr);
+    }
+}
+
+#[test]
+fn unknown_symbols_suggest_close_names() {
+    assert_error("suggest_native", "fn main() {\n    pritnln(\"x\")\n}\n",
+        "Symbol: \"pritnln\" is not defined as function in this scope, did you mean \"println\"? 2:");
+    assert_error("suggest_local", "fn main() {\n    var count: int = 1\n    println(cuont)\n}\n",
+        "Symbol: \"cuont\" is not defined as var in this scope, did you mean \"count\"? 3:");
+    assert_error("suggest_field", "struct P {\n    xpos: int,\n    ypos: int,\n}\nfn main() {\n    var p: P = {1, 2}\n    println(p.xps)\n}\n",
+        "Field: \"xps\" is not declared in struct \"P\", did you mean \"xpos\", \"ypos\"? 7:");
+    assert_error("suggest_method", "fn main() {\n    var s: String = \"ab\"\n    println(s.lenght())\n}\n",
+        "Method: \"lenght\" is not declared in struct \"String\", did you mean \"len\"? 3:");
+    assert_error("suggest_struct", "struct Point {\n    x: int,\n}\nfn main() {\n    var p: Poitn = {1}\n}\n",
+        "Symbol: \"Poitn\" is not defined as struct in this scope, did you mean \"Point\"? 5:");
+    assert_error("suggest_fn_many", "fn max2() int {\n    return 1\n}\nfn main() {\n    println(mxa(1, 2))\n}\n",
+        "Symbol: \"mxa\" is not defined as function in this scope, did you mean \"max\", \"max2\", \"maxf\"? 5:");
+}
+
+#[test]
+fn unknown_symbols_without_close_names_have_no_suggestion() {
+    assert_error("suggest_none", "fn main() {\n    println(completely_unknown)\n}\n",
+        "Symbol: \"completely_unknown\" is not defined as var in this scope 2:");
+    assert_error("suggest_none_fn", "fn main() {\n    zzzzzz()\n}\n",
+        "Symbol: \"zzzzzz\" is not defined as function in this scope 2:");
+}
+
+#[test]
+fn concatenating_string_with_other_type_hints_conversion() {
+    let cases = [
+        ("int_right", "var n: int = 1\n    println(\"count: \" + n)", "Cannot concatenate String and int; use convstr() to convert the int operand 3:"),
+        ("int_left", "var n: int = 1\n    println(n + \"!\")", "Cannot concatenate int and String; use convstr() to convert the int operand 3:"),
+        ("float_right", "var f: float = 1.5\n    println(\"f: \" + f)", "Cannot concatenate String and float; use convstr() to convert the float operand 3:"),
+        ("float_left", "println(2.5 + \"x\")", "Cannot concatenate float and String; use convstr() to convert the float operand 2:"),
+        ("bool_right", "println(\"ok: \" + true)", "Cannot concatenate String and bool; bool cannot be converted to String 2:"),
+        ("bool_left", "var b: bool = false\n    println(b + \"x\")", "Cannot concatenate bool and String; bool cannot be converted to String 3:"),
+    ];
+
+    for (name, body, expected) in cases {
+        assert_error(&format!("concat_{}", name), &format!("fn main() {{\n    {}\n}}\n", body), expected);
+    }
+}
+
+#[test]
+fn list_constructors_are_checked() {
+    let cases = [
+        ("filled_value", "var xs: List<int> = List.filled(3, \"a\")", "Expected to find int as List.filled argument but found: String 2:"),
+        ("filled_count", "var xs: List<int> = List.filled(1.5, 0)", "Expected to find int as List.filled argument but found: float 2:"),
+        ("from_range_type", "var xs: List<float> = List.fromRange(0, 3)", "List.fromRange creates List<int>, found: List<float> 2:"),
+        ("unknown", "var xs: List<int> = List.fill(3, 0)", "Constructor: \"fill\" is not declared in struct \"List\", did you mean \"filled\"? 2:"),
+    ];
+
+    for (name, body, expected) in cases {
+        assert_error(&format!("list_{}", name), &format!("fn main() {{\n    {}\n}}\n", body), expected);
+    }
+}
+
+#[test]
+fn list_filled_rejects_negative_count() {
+    let (code, stderr) = run_source("list_filled_negative", "fn main() {\n    var n: int = 0 - 2\n    var xs: List<int> = List.filled(n, 0)\n}\n");
+    assert_eq!(code, Some(70), "{}", stderr);
+    assert!(stderr.contains("==== RUNTIME ERROR ===="), "{}", stderr);
+    assert!(stderr.contains("List.filled count cannot be negative, found: -2 3:"), "{}", stderr);
+}
+
+#[test]
+fn list_struct_element_fields_are_checked() {
+    let header = "struct P {\n    x: int,\n}\nfn main() {\n    var a: P = {1}\n    var ps: List<P> = [a]\n";
+    let cases = [
+        ("field_type", "ps[0].x = true", "Expected to find int but found: bool 7:"),
+        ("unknown_field", "println(ps[0].y)", "Field: \"y\" is not declared in struct \"P\" 7:"),
+        ("unknown_method", "println(ps[0].xx())", "Method: \"xx\" is not declared in struct \"P\" 7:"),
+        ("read_type", "var b: bool = ps[0].x", "Mismatched types while declaring var, expected: bool found: int 7:"),
+    ];
+
+    for (name, line, expected) in cases {
+        assert_error(&format!("list_element_{}", name), &format!("{}    {}\n}}\n", header, line), expected);
+    }
+
+    assert_error("list_element_int", "fn main() {\n    var xs: List<int> = [1]\n    println(xs[0].x)\n}\n",
+        "Cannot access fields of int list element 3:");
+}
+
+#[test]
+fn operator_at_start_of_line_is_rejected() {
+    let cases = [
+        ("plus", "var a: int = 1\n    var b: int = a\n        + 2", "Unexpected '+' at start of line, operators must end the previous line 4:"),
+        ("and", "var ok: bool = true\n        and false", "Unexpected 'and' at start of line, operators must end the previous line 3:"),
+        ("after_call", "println(abs(1))\n        * 2", "Unexpected '*' at start of line, operators must end the previous line 3:"),
+    ];
+
+    for (name, body, expected) in cases {
+        let source = format!("fn main() {{\n    {}\n}}\n", body);
+        assert_error(&format!("line_start_{}", name), &source, expected);
+
+        let (_, stderr) = run_source(&format!("line_start_{}", name), &source);
+        assert_eq!(stderr.matches("ERROR ====").count(), 1, "{}: expected a single error, found:\n{}", name, stderr);
+    }
+
+    // the operator's line is skipped with the statement, it is not compiled again as one of its own
+    let source = "fn main() null {\n    var a: int = 1\n    + 2 * (3 +\n    4)\n    println(a)\n    var b: int = \"s\"\n}\n";
+    let (_, stderr) = run_source("line_start_recovery", source);
+    assert_eq!(stderr.matches("ERROR ====").count(), 2, "{}", stderr);
+    assert!(stderr.contains("Unexpected '+' at start of line, operators must end the previous line 3:"), "{}", stderr);
+    assert!(stderr.contains("Mismatched types while declaring var, expected: int found: String 6:"), "{}", stderr);
+}
+
+#[test]
+fn hash_rejects_instances() {
+    assert_error("hash_struct", "struct P {\n    x: int,\n}\nfn main() {\n    var p: P = {1}\n    println(hash(p))\n}\n",
+        "hash() expects int, float, bool or String argument, found: P 6:");
+    assert_error("hash_list", "fn main() {\n    var xs: List<int> = [1]\n    println(hash(xs))\n}\n",
+        "hash() expects int, float, bool or String argument, found: List 3:");
+}
+
+#[test]
+fn main_cannot_take_parameters() {
+    assert_error("main_params", "fn main(n: int) {\n    println(n)\n}\n", "Function \"main\" cannot take parameters, found: 1 1:");
+    assert_error("main_params_late", "fn helper() {\n}\n\nfn main(a: int, b: String) {\n}\n", "Function \"main\" cannot take parameters, found: 2 4:");
+}
+
+#[test]
+fn numeric_overloads_require_matching_types() {
+    assert_error("overload_mixed", "fn main() {\n    println(min(1, 2.5))\n}\n",
+        "Cannot resolve min() for argument types (int, float), expected all int or all float 2:");
+    assert_error("overload_clamp_mixed", "fn main() {\n    var x: float = 0.5\n    println(clamp(x, 0, 1))\n}\n",
+        "Cannot resolve clamp() for argument types (float, int, int), expected all int or all float 3:");
+    assert_error("overload_bool", "fn main() {\n    println(abs(true))\n}\n",
+        "Cannot resolve abs() for argument types (bool), expected all int or all float 2:");
+
+    let (code, stderr) = run_source("clamp_bounds", "fn main() {\n    println(clamp(1, 5, 2))\n}\n");
+    assert_eq!(code, Some(70), "{}", stderr);
+    assert!(stderr.contains("CLAMP lower bound 5 is greater than upper bound 2"), "{}", stderr);
+}
+
+#[test]
+fn unbalanced_brackets_report_opening_location() {
+    let cases = [
+        ("missing_fn_brace", "fn helper(a: int) int {\n    if a > 1 {\n        return 1\n    return 2\n}\n\nfn main() {\n}\n",
+            "Unclosed '{' opened at line 1 (function 'helper') 1:"),
+        ("missing_struct_brace", "struct P {\n    x: int,\n\nfn main() {\n}\n",
+            "Unclosed '{' opened at line 1 (struct 'P') 1:"),
+        ("unclosed_paren", "fn main() {\n    println(max(1, 2)\n}\n",
+            "Mismatched '}' closes '(' opened at line 2 (function 'main') 3:"),
+        ("extra_closer", "fn main() {\n    println(1)\n}\n}\n",
+            "Unexpected '}' without matching opening bracket 4:"),
+        ("interleaved", "fn main() {\n    var xs: List<int> = [1, 2)\n}\n",
+            "Mismatched ')' closes '[' opened at line 2 (function 'main') 2:"),
+    ];
+
+    for (name, source, expected) in cases {
+        assert_error(&format!("brackets_{}", name), source, expected);
+    }
+}
+
+#[test]
+fn invalid_pattern_reports_regex_error_and_line() {
+    let (code, stderr) = run_source("invalid_pattern", "fn check(s: String) bool {\n    return s.matches(\"[a-z\")\n}\n\nfn main() {\n    var s: String = \"abc\"\n    println(s.findPattern(\"b+\"))\n    println(check(s))\n}\n");
+    assert_eq!(code, Some(70));
+    assert!(stderr.contains("==== RUNTIME ERROR ===="), "{}", stderr);
+    assert!(stderr.contains("Invalid pattern \"[a-z\": regex parse error"), "{}", stderr);
+    assert!(stderr.contains("unclosed character class"), "{}", stderr);
+    assert!(stderr.trim_end().ends_with(" 2:"), "{}", stderr);
+
+    assert_error("invalid_replace_pattern", "fn main() {\n    var s: String = \"abc\"\n\n    println(s.replacePattern(\"(a\", \"b\"))\n}\n",
+        "unclosed group 4:");
+}
+
+#[test]
+fn negative_search_start_is_runtime_error() {
+    assert_error("find_from_negative", "fn main() {\n    var s: String = \"abc\"\n    println(s.findFrom(\"b\", 0))\n    println(s.findFrom(\"b\", -1))\n}\n",
+        "Start index -1 of findFrom() cannot be negative 4:");
+    assert_error("count_from_negative", "fn main() {\n    var s: String = \"abc\"\n    println(s.countFrom(\"b\", 0 - 2))\n}\n",
+        "Start index -2 of countFrom() cannot be negative 3:");
+}
+
+#[test]
+fn string_var_from_field_checks_type() {
+    assert_error("string_from_int_field", "struct User {\n    name: String,\n    age: int,\n}\n\nfn main() {\n    var u: User = {\"ann\", 3}\n    var s: String = u.age\n}\n",
+        "Mismatched types while assigning var, expected: String found: int 8:");
+}
+
+#[test]
+fn tuples_are_checked() {
+    let divmod = "fn divmod(a: int, b: int) (int, int) {\n    return (a / b, a % b)\n}\n\n";
+
+    assert_error("tuple_arity", &format!("{}fn main() {{\n    var q, r, x = divmod(7, 2)\n}}\n", divmod),
+        "Cannot destructure tuple of 2 values into 3 vars 6:");
+    assert_error("tuple_return_type", "fn parse(s: String) (String, bool) {\n    return (s, 1)\n}\n\nfn main() {\n    var a, b = parse(\"x\")\n}\n",
+        "Mismatched types while returning function, expected: (String, bool) found: (String, int) 2:");
+    assert_error("tuple_return_count", &format!("{}fn one() (int, int) {{\n    return (1, 2, 3)\n}}\n\nfn main() {{\n}}\n", divmod),
+        "Mismatched types while returning function, expected: (int, int) found: (int, int, int) 6:");
+    assert_error("tuple_not_destructured", &format!("{}fn main() {{\n    println(divmod(7, 2))\n}}\n", divmod),
+        "Tuple returned by \"divmod\" has to be destructured, e.g. var a, b = divmod(...) 6:");
+    assert_error("tuple_from_int", "fn main() {\n    var a, b = abs(1)\n}\n",
+        "Cannot destructure int into 2 vars, expected tuple 2:");
+    assert_error("tuple_struct_element", "struct P {\n    x: int,\n}\n\nfn make() (P, int) {\n}\n\nfn main() {\n}\n",
+        "Tuple element has to be int, float, bool or String, found: \"P\" 5:");
+}
+
+#[test]
+fn struct_literals_are_checked() {
+    let structs = "struct Point {\n    x: int,\n    y: int,\n}\n\nstruct User {\n    name: String,\n    age: int,\n}\n\n";
+
+    assert_error("untyped_literal_arg", &format!("{}fn main() {{\n    println({{1, 2}})\n}}\n", structs),
+        "Cannot determine type of struct literal, write it as TypeName{...} 12:");
+    assert_error("literal_field_type", &format!("{}fn main() {{\n    var u: User = User{{\"ann\", true}}\n}}\n", structs),
+        "Expected to find int but found: bool 12:");
+    assert_error("literal_nested_field", &format!("{}fn main() {{\n    var u: User = User{{Point{{1, 2}}, 3}}\n}}\n", structs),
+        "Expected to find String but found: Point 12:");
+    assert_error("literal_too_many_fields", &format!("{}fn main() {{\n    var p: Point = Point{{1, 2, 3}}\n}}\n", structs),
+        "Expected to find 2 fields but found more 12:");
+    assert_error("literal_wrong_type", &format!("{}fn main() {{\n    var p: Point = User{{\"ann\", 3}}\n}}\n", structs),
+        "Mismatched types while assigning var, expected: Point found: User 12:");
+}
+
+#[test]
+fn input_read_error_reports_line() {
+    let path = common::write_source("input_invalid_utf8", "fn main() {\n    println(\"start\")\n    var s: String = input()\n}\n");
+    let outcome = common::run_shlang(&[], &[path], &[0xff, 0xfe, b'\n']);
+
+    let stderr = outcome.stderr;
+    assert_eq!(outcome.exit_code, Some(70));
+    assert!(stderr.contains("RUNTIME ERROR") && stderr.contains("Failed to read input: ") && stderr.contains(" 3:"), "found:\n{}", stderr);
+}
+
+#[test]
+fn independent_compile_errors_are_all_reported() {
+    let source = "fn helper(a: int) int {\n    var b: int = a + true\n    return b\n}\n\nfn main() null {\n    var x: int = 1\n    println(y)\n    if x > 0 {\n        println(undefinedFn(1))\n    }\n    x = x + 1\n}\n";
+    let (code, stderr) = run_source("three_errors", source);
+
+    assert_eq!(code, Some(65), "{}", stderr);
+    assert_eq!(stderr.matches("ERROR ====").count(), 3, "{}", stderr);
+    assert!(stderr.contains("Mismatched types: int + bool 2:"), "{}", stderr);
+    assert!(stderr.contains("Symbol: \"y\" is not defined as var in this scope 8:"), "{}", stderr);
+    assert!(stderr.contains("Symbol: \"undefinedFn\" is not defined as function in this scope 10:"), "{}", stderr);
+}
+
+#[test]
+fn failed_declaration_does_not_cascade() {
+    let header = "struct P {\n    name: String,\n\n    methods {\n        get(self) int {\n            return self.name.len()\n        }\n    }\n}\n\n\
+        fn size(p: P, s: String, xs: List<int>) int {\n    return 1\n}\n\n";
+    let cases = [
+        ("cascade", "var x: int = \"text\"", "println(x)\n    println(x + 1)\n    x = 2"),
+        ("cascade_string", "var s: String = 5", "println(s, s.len(), s + \"a\")\n    var t: String = s\n    s = \"b\"\n    discard size(P{\"a\"}, s, [1])"),
+        ("cascade_list", "var xs: List<int> = [1, \"a\"]",
+            "println(xs, xs[0] + 1, xs.len())\n    xs.push(2)\n    xs[0] = 3\n    var ys: List<int> = xs\n    discard size(P{\"a\"}, \"s\", xs)"),
+        ("cascade_struct", "var p: P = {5}", "println(p.get(), p.name, p.name.len())\n    p.name = \"b\"\n    var q: P = p\n    discard size(p, \"s\", [1])"),
+    ];
+    for (name, declaration, uses) in cases {
+        let source = format!("{}fn main() null {{\n    {}\n    {}\n}}\n", header, declaration, uses);
+        let (code, stderr) = run_source(name, &source);
+
+        assert_eq!(code, Some(65), "{}: {}", name, stderr);
+        assert_eq!(stderr.matches("ERROR ====").count(), 1, "{}: {}", name, stderr);
+    }
+}
+
+#[test]
+fn error_reporting_stops_at_cap() {
+    let body: String = (0..30).map(|i| format!("    println(missing{})\n", i)).collect();
+    let (code, stderr) = run_source("error_cap", &format!("fn main() null {{\n{}}}\n", body));
+
+    assert_eq!(code, Some(65), "{}", stderr);
+    assert_eq!(stderr.matches("ERROR ====").count(), 20, "{}", stderr);
+    assert!(stderr.contains("Too many errors, stopping after 20"), "{}", stderr);
+}
+
+#[test]
+fn methods_blocks_are_checked() {
+    assert_error("extension_duplicate",
+        "struct P {\n    x: int,\n\n    methods {\n        get(self) int {\n            return self.x\n        }\n    }\n}\n\nmethods P {\n    get(self) int {\n        return 0\n    }\n}\n\nfn main() null {\n}\n",
+        "Method: \"get\" is already defined for struct: \"P\" 12:");
+    assert_error("extension_unknown_struct",
+        "methods Q {\n    get(self) int {\n        return 0\n    }\n}\n\nfn main() null {\n}\n",
+        "Symbol: \"Q\" is not defined as struct in this scope 1:");
+    assert_error("extension_before_struct",
+        "methods P {\n    get(self) int {\n        return self.x\n    }\n}\n\nstruct P {\n    x: int,\n}\n\nfn main() null {\n}\n",
+        "Struct \"P\" has to be declared before its methods block 1:");
+    assert_error("extension_builtin",
+        "methods String {\n    get(self) int {\n        return 0\n    }\n}\n\nfn main() null {\n}\n",
+        "Cannot add methods to builtin struct \"String\" 1:");
+}
+
+#[test]
+fn method_cannot_shadow_field() {
+    assert_error("method_shadows_field",
+        "struct P {\n    len: int,\n\n    methods {\n        len(self) int {\n            return self.len\n        }\n    }\n}\n\nfn main() null {\n}\n",
+        "Method: \"len\" of struct: \"P\" declared at line 5 has the same name as its field declared at line 2 5:");
+    assert_error("extension_shadows_field",
+        "struct P {\n    x: int,\n    size: int,\n}\n\nmethods P {\n    get(self) int {\n        return self.x\n    }\n\n    size(self) int {\n        return 1\n    }\n}\n\nfn main() null {\n}\n",
+        "Method: \"size\" of struct: \"P\" declared at line 11 has the same name as its field declared at line 3 11:");
+}
+
+#[test]
+fn builtin_constants_are_read_only() {
+    assert_error("assign_constant", "fn main() null {\n    E = 2.0\n}\n", "Cannot assign to builtin constant \"E\" 2:");
+    assert_error("var_named_constant", "fn main() null {\n    var PI: float = 3.0\n}\n",
+        "Symbol: \"PI\" is a builtin constant and cannot be declared 2:");
+    assert_error("arg_named_constant", "fn f(MAX_INT: int) int {\n    return MAX_INT\n}\n\nfn main() null {\n}\n",
+        "Symbol: \"MAX_INT\" is a builtin constant and cannot be declared 1:");
+}
+
+#[test]
+fn int_overflow_is_runtime_error() {
+    assert_error("max_int_overflow", "fn main() null {\n    var x: int = MAX_INT\n    println(x + 1)\n}\n", "Integer overflow in '+' 3:");
+    assert_error("min_int_overflow", "fn main() null {\n    var x: int = MIN_INT\n    println(x - 1)\n}\n", "Integer overflow in '-' 3:");
+    assert_error("mul_overflow", "fn main() null {\n    var x: int = MAX_INT\n    println(x * 2)\n}\n", "Integer overflow in '*' 3:");
+    assert_error("div_overflow", "fn main() null {\n    var x: int = MIN_INT\n    println(x / -1)\n}\n", "Integer overflow in '/' 3:");
+}
+
+#[test]
+fn division_by_zero_is_runtime_error() {
+    assert_error("div_zero", "fn main() null {\n    var x: int = 0\n    println(7 / x)\n}\n", "Division by zero in '/' 3:");
+    assert_error("mod_zero", "fn main() null {\n    var x: int = 0\n    println(7 % x)\n}\n", "Division by zero in '%' 3:");
+}
+
+#[test]
+fn list_assignment_index_is_checked() {
+    assert_error("list_set_range", "fn main() null {\n    var xs: List<int> = [1, 2]\n    xs[5] = 3\n}\n", "List index out of range  5/2 3:");
+    assert_error("list_set_negative", "fn main() null {\n    var xs: List<int> = [1, 2]\n    var i: int = 0 - 1\n    xs[i] = 3\n}\n",
+        "Index cannot be negative 4:");
+}
+
+#[test]
+fn struct_argument_type_is_checked() {
+    let source = "struct Point {\n    x: int,\n\n    methods {\n        show(self) int {\n            return size(self)\n        }\n    }\n}\n\n\
+        struct Foo {\n    x: int,\n}\n\nfn size(f: Foo) int {\n    return f.x\n}\n\nfn main() null {\n}\n";
+    assert_error("wrong_self_arg", source, "Expected to find Foo as argument type but found: Point 6:");
+
+    assert_error("wrong_struct_arg",
+        "struct A {\n    x: int,\n}\n\nstruct B {\n    x: int,\n}\n\nfn f(a: A) int {\n    return a.x\n}\n\nfn main() null {\n    var b: B = B{1}\n    println(f(b))\n}\n",
+        "Expected to find A as argument type but found: B 15:");
+}
+
+#[test]
+fn radix_errors_are_reported() {
+    assert_error("parse_int_digit", "fn main() null {\n    println(parseInt(\"-12g4\", 16))\n}\n",
+        "Invalid digit 'g' at position 4 for radix 16 in \"-12g4\" 2:");
+    assert_error("parse_int_radix", "fn main() null {\n    println(parseInt(\"10\", 37))\n}\n", "Radix has to be between 2 and 36, found: 37 2:");
+    assert_error("to_base_radix", "fn main() null {\n    println(toBase(10, 1))\n}\n", "Radix has to be between 2 and 36, found: 1 2:");
+    assert_error("parse_int_empty", "fn main() null {\n    println(parseInt(\"-\", 10))\n}\n", "Cannot parse \"-\" as int, no digits found 2:");
+    assert_error("parse_int_overflow", "fn main() null {\n    println(parseInt(\"8000000000000000\", 16))\n}\n",
+        "Number \"8000000000000000\" does not fit in int 2:");
+}
+
+#[test]
+fn sort_key_is_checked() {
+    let header = "struct P {\n    x: int,\n\n    methods {\n        getX(self) int {\n            return self.x\n        }\n\n        shift(self, d: int) int {\n            return self.x + d\n        }\n    }\n}\n";
+    assert_error("sort_key_unknown", &format!("{}fn main() null {{\n    var a: P = {{1}}\n    var ps: List<P> = [a]\n    ps.sortKey(\"getY\")\n}}\n", header),
+        "Method: \"getY\" is not declared in struct \"P\", did you mean \"getX\"? 17:");
+    assert_error("sort_key_args", &format!("{}fn main() null {{\n    var a: P = {{1}}\n    var ps: List<P> = [a]\n    ps.sortKey(\"shift\")\n}}\n", header),
+        "Method: \"shift\" cannot be used as sort key, it has to take only self and return int, float or String 17:");
+    assert_error("sort_key_string", "fn main() null {\n    var xs: List<String> = [\"a\"]\n    xs.sortKey(\"toUpper\")\n}\n",
+        "Method: \"toUpper\" cannot be used as sort key of List<String>, only \"len\" is supported 3:");
+    assert_error("sort_key_int", "fn main() null {\n    var xs: List<int> = [1]\n    xs.sortKey(\"len\")\n}\n",
+        "sortKey needs a list of String or struct elements 3:");
+}
+
+#[test]
+fn bytes_round_trip_through_file() {
+    let data = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("bytes_round_trip.bin");
+    let source = format!("fn main() null {{\n    var b: Bytes = fromString(\"ab\")\n    b.push(0)\n    b.push(255)\n    writeFileBytes(\"{0}\", b)\n    var back: Bytes = readFileBytes(\"{0}\")\n    println(back.len())\n    println(back.get(3))\n}}\n", data.display());
+    let outcome = common::run_source("bytes_round_trip", &source, &[]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "4\n255\n");
+    assert_eq!(fs::read(&data).unwrap(), vec![b'a', b'b', 0, 255]);
+}
+
+#[test]
+fn bytes_errors_are_reported() {
+    assert_error("bytes_set_range", "fn main() null {\n    var b: Bytes = fromString(\"a\")\n    b.set(0, 256)\n}\n",
+        "Byte value has to be between 0 and 255, found: 256 3:");
+    assert_error("bytes_utf8", "fn main() null {\n    var b: Bytes = fromString(\"a\")\n    b.push(255)\n    println(b.toString())\n}\n",
+        "Bytes are not valid UTF-8, invalid sequence at position 1 4:");
+    assert_error("bytes_index", "fn main() null {\n    var b: Bytes = fromString(\"a\")\n    println(b.get(1))\n}\n", "Bytes index out of range 1/1 3:");
+    assert_error("bytes_method", "fn main() null {\n    var b: Bytes = fromString(\"a\")\n    b.pop()\n}\n",
+        "Method: \"pop\" is not declared in struct \"Bytes\"");
+    assert_error("bytes_type", "fn main() null {\n    var b: Bytes = \"a\"\n}\n", "Mismatched types while assigning var, expected: Bytes found: String 2:");
+}
+
+#[test]
+fn list_struct_elements_are_checked() {
+    let header = "struct Point {\n    x: int,\n}\n\nstruct Circle {\n    r: int,\n}\n\nfn makeCircle() Circle {\n    var c: Circle = {2}\n    return c\n}\n";
+    assert_error("list_literal_wrong_var", &format!("{}fn main() null {{\n    var p: Point = {{1}}\n    var c: Circle = {{3}}\n    var pts: List<Point> = [p, p, c]\n}}\n", header),
+        "Expected to find Point but found Circle at element 2 16:");
+    assert_error("list_literal_wrong_call", &format!("{}fn main() null {{\n    var p: Point = {{1}}\n    var pts: List<Point> = [makeCircle(), p]\n}}\n", header),
+        "Expected to find Point but found Circle at element 0 15:");
+    assert_error("list_set_wrong_var", &format!("{}fn main() null {{\n    var p: Point = {{1}}\n    var c: Circle = {{3}}\n    var pts: List<Point> = [p]\n    pts[0] = c\n}}\n", header),
+        "Expected to find Point but found: Circle 17:");
+    assert_error("list_set_wrong_call", &format!("{}fn main() null {{\n    var p: Point = {{1}}\n    var pts: List<Point> = [p]\n    pts[0] = makeCircle()\n}}\n", header),
+        "Expected to find Point but found: Circle 16:");
+}
+
+#[test]
+fn in_operator_types_are_checked() {
+    assert_error("in_int_string", "fn main() null {\n    println(5 in \"abc\")\n}\n",
+        "Operator 'in' cannot check int in String, expected String in String or element in List of the same type 2:");
+    assert_error("in_string_int_list", "fn main() null {\n    var xs: List<int> = [1]\n    println(\"a\" in xs)\n}\n",
+        "Operator 'in' cannot check String in List<int>, expected String in String or element in List of the same type 3:");
+}
+
+#[test]
+fn struct_typed_fields_are_rejected() {
+    assert_error("self_referential_struct", "struct Node {\n    next: Node,\n    value: int,\n}\n\nfn main() null {\n}\n",
+        "Field \"next\" of struct \"Node\": a struct cannot contain itself, fields can only hold int, float, bool, String or List<T> values 2:");
+    assert_error("struct_typed_field", "struct Point {\n    x: int,\n}\n\nstruct Line {\n    a: Point,\n}\n\nfn main() null {\n}\n",
+        "Field \"a\" of struct \"Line\": struct type \"Point\" is not supported, fields can only hold int, float, bool, String or List<T> values 6:");
+    assert_error("nested_list_field", "struct Grid {\n    rows: List<List>,\n}\n\nfn main() null {\n}\n",
+        "Field \"rows\" of struct \"Grid\": List<List> is not supported, list fields can hold int, float, bool, String or struct elements 2:");
+}
+
+#[test]
+fn list_field_elements_are_copies() {
+    let source = |body: &str| format!("struct Node {{\n    id: int,\n}}\n\nstruct Bag {{\n    items: List<Node>,\n}}\n\nfn main() null {{\n    var bag: Bag = Bag{{[]}}\n    {}\n}}\n", body);
+    assert_error("list_field_element_set", &source("bag.items[0].id = 3"),
+        "Elements of a List field are copies, field \"id\" cannot be assigned; copy the element into a variable first: var n: Node = <list>[i] 11:");
+    assert_error("list_field_assign", &source("bag.items[0] = Node{1}"),
+        "Elements of a List field cannot be assigned, assign the whole field or use insert 11:");
+    assert_error("list_field_push_type", &source("bag.items.push(3)"),
+        "Expected to find Node as List.push argument but found: int 11:");
+    assert_error("list_field_sort", &source("bag.items.sort()"),
+        "Method: \"sort\" is not supported for List fields, only push, insert, len, isEmpty and join 11:");
+    assert_error("list_field_copy", &source("var ns: List<Node> = bag.items"),
+        "List<Node> has to be declared with a list literal, struct elements of a list value are copies 11:");
+}
+
+#[test]
+fn list_concat_types_are_checked() {
+    assert_error("concat_elements", "fn main() null {\n    var a: List<int> = [1]\n    var b: List<String> = [\"x\"]\n    var c: List<int> = a + b\n}\n",
+        "Cannot concatenate List<int> and List<String>; element types differ 4:");
+    assert_error("concat_non_list", "fn main() null {\n    var a: List<int> = [1]\n    var c: List<int> = a + 1\n}\n",
+        "Cannot concatenate List<int> and int; both operands have to be lists 3:");
+    assert_error("list_minus", "fn main() null {\n    var a: List<int> = [1]\n    var c: List<int> = a - a\n}\n",
+        "Operator '-' is not defined for List operands 3:");
+    assert_error("extend_elements", "fn main() null {\n    var a: List<int> = [1]\n    var b: List<String> = [\"x\"]\n    a.extend(b)\n}\n",
+        "Cannot extend List<int> with List<String>; element types have to match 4:");
+    assert_error("extend_struct_list", "struct P {\n    x: int,\n}\n\nfn main() null {\n    var p: P = {1}\n    var ps: List<P> = [p]\n    ps.extend(ps)\n}\n",
+        "Lists of P elements cannot be concatenated or extended, only int, float, bool and String elements are copied 8:");
+}
+
+#[test]
+fn list_swap_and_remove_check_bounds() {
+    let cases = [
+        ("swap_past_end", "xs.swap(0, 3)", "VM - List index out of range  3/3 3:"),
+        ("swap_negative", "xs.swap(0 - 1, 1)", "VM - Index cannot be negative 3:"),
+        ("remove_past_end", "println(xs.remove(5))", "VM - List index out of range  5/3 3:"),
+        ("insert_past_end", "xs.insert(4, 1)", "List insert index out of range 4/3 3:"),
+        ("insert_negative", "xs.insert(0 - 1, 1)", "List insert index out of range -1/3 3:"),
+    ];
+
+    for (name, call, expected) in cases {
+        let (code, stderr) = run_source(&format!("list_{}", name), &format!("fn main() {{\n    var xs: List<int> = [1, 2, 3]\n    {}\n}}\n", call));
+        assert_eq!(code, Some(70), "{}", stderr);
+        assert!(stderr.contains(expected), "{}: expected {:?}, found:\n{}", name, expected, stderr);
+    }
+}
+
+#[test]
+fn list_remove_and_fill_reject_struct_elements() {
+    let source = |call: &str| format!("struct P {{\n    x: int,\n}}\n\nfn main() {{\n    var a: P = {{1}}\n    var ps: List<P> = [a]\n    {}\n}}\n", call);
+
+    assert_error("list_fill_struct", &source("ps.fill(a)"), "Method: \"fill\" is not supported for List<P>, only int, float, bool and String elements are copied 8:");
+    assert_error("list_remove_struct", &source("ps.remove(0)"), "Method: \"remove\" is not supported for List<P>");
+    assert_error("list_push_struct", &source("ps.push(a)"), "Method: \"push\" is not supported for List<P>");
+    assert_error("list_insert_struct", &source("ps.insert(0, a)"), "Method: \"insert\" is not supported for List<P>");
+    assert_error("list_push_type", "fn main() {\n    var xs: List<int> = [1]\n    xs.push(\"a\")\n}\n", "Expected to find int but found: String 3:");
+    assert_error("list_param_insert_type", "fn f(xs: List<String>) null {\n    xs.insert(0, 1)\n}\n\nfn main() {\n}\n", "Expected to find String but found: int 2:");
+    assert_error("list_fill_type", "fn main() {\n    var xs: List<int> = [1]\n    xs.fill(\"a\")\n}\n", "Expected to find int but found: String 3:");
+}
+
+#[test]
+fn list_params_check_element_type() {
+    let total = "fn total(xs: List<int>) int {\n    return xs.len()\n}\n\n";
+    assert_error("list_param_element", &format!("{}fn main() {{\n    var ss: List<String> = [\"a\"]\n    println(total(ss))\n}}\n", total),
+        "Expected to find List<int> as argument type but found: List<String> 7:");
+    assert_error("list_param_untyped", "fn total(xs: List) int {\n    return xs.len()\n}\n\nfn main() {\n}\n",
+        "Expected '<' in parameter list of 'total', but found ')' 1:");
+}
+
+#[test]
+fn type_errors_render_types_as_written() {
+    let point = "struct Point {\n    x: int,\n}\n\nfn px(p: Point) int {\n    return p.x\n}\n\n";
+    assert_error("type_list_arg", &format!("{}fn main() {{\n    var xs: List<int> = [1]\n    println(px(xs))\n}}\n", point),
+        "Expected to find Point as argument type but found: List<int> 11:");
+    assert_error("type_struct_assign", &format!("{}fn main() {{\n    var p: Point = {{1}}\n    var n: int = 1\n    n = p\n}}\n", point),
+        "Mismatched types while assigning var, expected: int found: Point 12:");
+    assert_error("type_list_declare", "fn main() {\n    var xs: List<String> = [\"a\"]\n    var n: float = xs\n}\n",
+        "Mismatched types while declaring var, expected: float found: List<String> 3:");
+    assert_error("type_list_return", "fn f(xs: List<bool>) int {\n    return xs\n}\n\nfn main() {\n}\n",
+        "Mismatched types while returning function, expected: int found: List<bool> 2:");
+    assert_error("type_null_return", "fn f() null {\n    return 1.5\n}\n\nfn main() {\n}\n",
+        "Mismatched types while returning function, expected: null found: float 2:");
+    assert_error("type_list_elem_declare", "fn main() {\n    var a: List<int> = [1]\n    var b: List<String> = a\n}\n",
+        "Expected to find List<String> but found List<int> 3:");
+    assert_error("type_list_elem_assign", "fn main() {\n    var a: List<int> = [1]\n    var b: List<String> = [\"x\"]\n    b = a\n}\n",
+        "Mismatched types while assigning var, expected: List<String> found: List<int> 4:");
+    assert_error("type_list_string_assign", "fn main() {\n    var a: List<int> = [1]\n    var s: String = \"x\"\n    s = a\n}\n",
+        "Mismatched types while assigning var, expected: String found: List<int> 4:");
+    assert_error("type_list_same_declare", "fn main() {\n    var a: List<int> = [1]\n    var b: List<int> = a\n}\n",
+        "List<int> has to be declared with a list literal or a list value, not with another list variable 3:");
+    assert_error("type_list_same_assign", "fn main() {\n    var a: List<int> = [1]\n    var b: List<int> = [2]\n    b = a\n}\n",
+        "List \"b\" cannot be assigned a new list, only its elements can be changed 4:");
+}
+
+#[test]
+fn consume_errors_name_the_construct_being_parsed() {
+    let add = "fn add(a: int, b: int) int {\n    return a + b\n}\n\n";
+    assert_error("missing_call_paren", &format!("{}fn main() {{\n    println(add(1 \"extra\"))\n}}\n", add),
+        "Expected ')' in call to 'add', but found string \"extra\" 6:");
+    assert_error("missing_var_colon", "fn main() {\n    var x int = 5\n}\n",
+        "Expected ':' in declaration of 'x', but found 'int' 2:");
+    assert_error("missing_for_in", "fn main() {\n    for i (0, 3) {\n        println(i)\n    }\n}\n",
+        "Expected 'in' in range of for loop over 'i', but found '(' 2:");
+    assert_error("missing_struct_comma", "struct P {\n    x: int y: int,\n}\n\nfn main() {\n}\n",
+        "Expected ',' in declaration of struct 'P', but found identifier \"y\" 2:");
+    assert_error("missing_param_comma", "fn f(a: int b: int) int {\n    return a\n}\n\nfn main() {\n}\n",
+        "Expected ')' in parameter list of 'f', but found identifier \"b\" 1:");
+    assert_error("missing_literal_comma", "struct P {\n    x: int,\n    y: int,\n}\n\nfn main() {\n    var p: P = P{1 2}\n}\n",
+        "Expected '}' in struct literal for 'P', but found int \"2\" 7:");
+}
+
+#[test]
+fn convf_rejects_malformed_float() {
+    let (code, stderr) = run_source("convf_malformed", "fn main() {\n    var s: String = \"1.5e\"\n    println(convf(s))\n}\n");
+    assert_eq!(code, Some(70), "{}", stderr);
+    assert!(stderr.contains("Cannot CONV \"1.5e\" to float, expected digits with optional sign, fraction and exponent, inf or nan 3:"), "{}", stderr);
+}
+
+#[test]
+fn conv_rejects_non_integer_string() {
+    let (code, stderr) = run_source("conv_malformed", "fn main() {\n    var s: String = \"1.5\"\n    println(conv(s))\n}\n");
+    assert_eq!(code, Some(70), "{}", stderr);
+    assert!(stderr.contains("Cannot CONV \"1.5\" to int, expected digits with optional sign 3:"), "{}", stderr);
+}
+
+#[test]
+fn struct_has_one_methods_block() {
+    assert_error("two_methods_blocks", "struct P {\n    methods {\n        a(self) {\n        }\n    }\n    x: int,\n    methods {\n        b(self) {\n        }\n    }\n}\n\nfn main() {\n}\n",
+        "Struct \"P\" can have only one methods block 7:");
+}
+
+#[test]
+fn stray_tokens_in_expressions_are_reported() {
+    let cases = [
+        ("colon_prefix", "var x: int = :", "Cannot get a parse rule for: COLON: \":\", 2:"),
+        ("colon_after_value", "var x: int = 5 :", "Cannot get a parse rule for: COLON: \":\", 2:"),
+        ("colon_in_call", "println(1 : 2)", "Expected ')' in call to 'println', but found ':' 2:"),
+        ("brace_prefix", "if true {\n        var x: int = }", "Expected prefix for: RIGHT_BRACE, 3:"),
+    ];
+
+    for (name, body, expected) in cases {
+        let (code, stderr) = run_source(&format!("stray_{}", name), &format!("fn main() {{\n    {}\n}}\n", body));
+        assert_eq!(code, Some(65), "{}: {}", name, stderr);
+        assert!(!stderr.contains("panicked"), "{}: {}", name, stderr);
+        assert!(stderr.contains(expected), "{}: expected {:?}, found:\n{}", name, expected, stderr);
+    }
+}
+
+#[test]
+fn struct_fields_on_one_line_need_commas() {
+    assert_error("struct_same_line_fields", "struct P {\n    x: int, y: int z: int\n}\n\nfn main() {\n}\n",
+        "Expected ',' in declaration of struct 'P', but found identifier \"z\" 2:");
+}
+
+#[test]
+fn dbg_rejects_call_returning_struct() {
+    assert_error("dbg_struct_call", "struct P {\n    x: int,\n}\n\nfn make() P {\n    return P{1}\n}\n\nfn main() null {\n    dbg(make())\n}\n",
+        "dbg() cannot wrap a call returning a struct, store the result in a variable first 10:");
+}
+
+#[test]
+fn function_reaching_its_end_without_return_is_rejected() {
+    assert_error("fallthrough_string", "fn name(flag: bool) String {\n    if flag {\n        return \"a\"\n    }\n}\n\nfn main() null {\n    var s: String = \"x\"\n    println(s + name(false))\n}\n",
+        "Function \"name\" can reach its end without returning String 5:");
+    assert_error("fallthrough_int", "fn num() int {\n    var x: int = 1\n}\n\nfn main() null {\n    println(num())\n}\n",
+        "Function \"num\" can reach its end without returning int 3:");
+}
+
+#[test]
+fn every_path_returning_is_accepted() {
+    let source = "fn name(flag: bool) String {\n    if flag {\n        return \"a\"\n    } else {\n        return \"b\"\n    }\n}\n\n\
+        fn first(n: int) int {\n    var i: int = 0\n    while true {\n        if i * i >= n {\n            return i\n        }\n        i = i + 1\n    }\n}\n\n\
+        fn main() null {\n    println(name(false), first(10))\n}\n";
+    let (code, stderr) = run_source("all_paths_return", source);
+    assert_eq!(code, Some(0), "{}", stderr);
+}
+
+#[test]
+fn and_or_require_bool_operands() {
+    assert_error("and_int_right", "fn main() null {\n    var x: int = 1\n    var ok: bool = x > 0 and x\n}\n", "Operator 'and' expects bool operands, found: int 3:");
+    assert_error("or_int_left", "fn main() null {\n    var x: int = 1\n    var ok: bool = x or x > 0\n}\n", "Operator 'or' expects bool operands, found: int 3:");
+}
+
+#[test]
+fn sort_needs_comparable_key() {
+    assert_error("sort_no_key", "struct P {\n    x: int,\n}\n\nfn main() null {\n    var ps: List<P> = [P{2}, P{1}]\n    ps.sort()\n}\n",
+        "Struct \"P\" has no sort key, declare one with \"sortkey field\" in its body or use sortKey(\"method\") 7:");
+    assert_error("sortkey_bool", "struct P {\n    x: int,\n    ok: bool,\n    sortkey ok\n}\n\nfn main() null {\n}\n",
+        "sortkey \"ok\" of struct \"P\" has type bool, only int, float and String fields can be compared 4:");
+    assert_error("sortkey_unknown", "struct P {\n    value: int,\n    sortkey valeu\n}\n\nfn main() null {\n}\n",
+        "sortkey \"valeu\" is not a field of struct \"P\", did you mean \"value\"? 3:");
+    assert_error("sort_bool_list", "fn main() null {\n    var bs: List<bool> = [true, false]\n    bs.sort()\n}\n",
+        "sort needs a list of int, float, String or struct elements, found: List<bool> 3:");
+}
+
+#[test]
+fn nested_calls_keep_their_own_target() {
+    let source = |body: &str| format!("struct P {{\n    x: int,\n\n    methods {{\n        get(self) int {{\n            return self.x\n        }}\n    }}\n}}\n\n\
+        fn add(a: int, b: int) int {{\n    return a + b\n}}\n\n\
+        fn main() null {{\n    var p: P = P{{1}}\n    var xs: List<int> = [1, 2]\n    {}\n}}\n", body);
+
+    assert_error("nested_user_arity", &source("var n: int = add(add(1, 2))"), "Expected to find 2 arguments but found: 1 18:");
+    assert_error("nested_user_type", &source("var s: String = add(add(1, 2), 3)"), "Mismatched types while assigning var, expected: String found: int 18:");
+    assert_error("nested_native_arity", &source("var n: int = add(abs(-2, 1), 3)"), "Expected to find 1 arguments but found: 2 18:");
+    assert_error("nested_native_type", &source("var s: String = add(abs(-2), 3)"), "Mismatched types while assigning var, expected: String found: int 18:");
+    assert_error("nested_method_arity", &source("var n: int = add(p.get(), 3, 4)"), "Expected to find 2 arguments but found: 3 18:");
+    assert_error("nested_method_type", &source("var s: String = add(p.get(), 3)"), "Mismatched types while assigning var, expected: String found: int 18:");
+    assert_error("list_index_arity", &source("var n: int = add(xs[0], xs[1], 3)"), "Expected to find 2 arguments but found: 3 18:");
+    assert_error("list_index_type", &source("var s: String = add(xs[0], 3)"), "Mismatched types while assigning var, expected: String found: int 18:");
+}
+
+#[test]
+fn sorted_list_methods_need_ordered_elements() {
+    let source = |body: &str| format!("struct P {{\n    x: int,\n}}\n\nstruct Bag {{\n    xs: List<int>,\n}}\n\nfn main() null {{\n    var ps: List<P> = [P{{1}}]\n    var xs: List<int> = [1, 2]\n    var b: Bag = Bag{{[1]}}\n    {}\n}}\n", body);
+
+    assert_error("sorted_insert_struct", &source("ps.sortedInsert(P{2})"),
+        "Method: \"sortedInsert\" is not supported for List<P>, only int, float and String elements are ordered 13:");
+    assert_error("lower_bound_bool", &source("var flags: List<bool> = [true]\n    println(flags.lowerBound(false))"),
+        "Method: \"lowerBound\" is not supported for List<bool>, only int, float and String elements are ordered 14:");
+    assert_error("lower_bound_type", &source("println(xs.lowerBound(1.5))"), "Expected to find int but found: float 13:");
+    assert_error("sorted_insert_field", &source("b.xs.sortedInsert(3)"),
+        "Method: \"sortedInsert\" is not supported for List fields, only push, insert, len, isEmpty and join 13:");
+}
+
+#[test]
+fn function_locals_are_limited() {
+    let vars = (0..257).map(|i| format!("    var v{}: int = {}\n", i, i)).collect::<String>();
+    assert_error("too_many_locals", &format!("fn main() null {{\n{}}}\n", vars),
+        "Function \"main\" declares more than 256 locals, var \"v256\" exceeds the limit 258:");
+
+    let vars = (0..255).map(|i| format!("    var v{}: int = {}\n", i, i)).collect::<String>();
+    assert_error("too_many_locals_for", &format!("fn main() null {{\n{}    for i in (0, 3) {{\n        println(i)\n    }}\n}}\n", vars),
+        "Function \"main\" declares more than 256 locals, for loop over \"i\" exceeds the limit 257:");
+}
@@ -0,0 +1,162 @@
+use std::{process::Command, sync::Mutex};
+
+use shlang::{Engine, Termination, TokenType, Value};
+
+static RECORDED: Mutex<Vec<Vec<Value>>> = Mutex::new(vec![]);
+
+fn record(args: Vec<Value>) -> Value {
+    RECORDED.lock().unwrap().push(args);
+    Value::Null
+}
+
+fn twice(args: Vec<Value>) -> Value {
+    Value::Int(args[0].get_int() * 2)
+}
+
+#[test]
+fn host_functions_are_callable_from_script() {
+    let mut engine = Engine::new();
+    engine.register_native("record", &[TokenType::STRING, TokenType::INT], TokenType::NULL, record).unwrap();
+    engine.register_native("twice", &[TokenType::INT], TokenType::INT, twice).unwrap();
+
+    engine.run_source("fn main() null {\n    var s: String = \"instance\"\n    record(s, twice(21))\n    record(\"literal\", 1)\n    record(s, s.len() + abs(-3))\n}\n").unwrap();
+
+    assert_eq!(*RECORDED.lock().unwrap(), vec![
+        vec![Value::String("instance".to_string()), Value::Int(42)],
+        vec![Value::String("literal".to_string()), Value::Int(1)],
+        vec![Value::String("instance".to_string()), Value::Int(11)],
+    ]);
+}
+
+#[test]
+fn invalid_registrations_are_rejected() {
+    let mut engine = Engine::new();
+    engine.register_native("twice", &[TokenType::INT], TokenType::INT, twice).unwrap();
+
+    assert_eq!(engine.register_native("twice", &[], TokenType::INT, twice), Err("Native \"twice\" is already declared".to_string()));
+    assert_eq!(engine.register_native("abs", &[], TokenType::INT, twice), Err("Native \"abs\" is already declared".to_string()));
+    assert_eq!(engine.register_native("2x", &[], TokenType::INT, twice), Err("Native \"2x\" is not a valid identifier".to_string()));
+    assert!(engine.register_native("list", &[TokenType::LIST], TokenType::INT, twice).unwrap_err().contains("cannot take argument of type LIST"));
+}
+
+static MIXED: Mutex<Vec<Vec<Value>>> = Mutex::new(vec![]);
+
+fn report(args: Vec<Value>) -> Value {
+    MIXED.lock().unwrap().push(args);
+    Value::Null
+}
+
+fn shout(args: Vec<Value>) -> Value {
+    Value::String(format!("{}!", args[0].get_string()))
+}
+
+#[test]
+fn host_functions_mix_with_builtin_natives_and_methods() {
+    let mut engine = Engine::new();
+    engine.register_native("report", &[TokenType::STRING, TokenType::INT], TokenType::NULL, report).unwrap();
+    engine.register_native("shout", &[TokenType::STRING], TokenType::STRING, shout).unwrap();
+
+    engine.run_source("fn main() null {\n    var s: String = \"hey\"\n    var xs: List<String> = [s, \"you\"]\n    \
+        report(shout(s.toUpper()), max(s.len(), abs(-2)))\n    report(xs.join(\",\"), xs.len() + pow(2, 3))\n    \
+        report(shout(s).toLower(), s.find(\"y\"))\n}\n").unwrap();
+
+    assert_eq!(*MIXED.lock().unwrap(), vec![
+        vec![Value::String("HEY!".to_string()), Value::Int(3)],
+        vec![Value::String("hey,you".to_string()), Value::Int(10)],
+        vec![Value::String("hey!".to_string()), Value::Int(2)],
+    ]);
+}
+
+static USERS: Mutex<Vec<Vec<Value>>> = Mutex::new(vec![]);
+
+fn fetch_user(args: Vec<Value>) -> Value {
+    let id = args[0].get_int();
+    Value::HostStruct(vec![Value::String(format!("user{}", id)), Value::Int(20 + id), Value::Bool(id == 1)])
+}
+
+fn scores(args: Vec<Value>) -> Value {
+    Value::ListObj((1..=args[0].get_int()).map(|n| Value::Int(n * 10)).collect())
+}
+
+fn tags(_args: Vec<Value>) -> Value {
+    Value::ListObj(vec![Value::String("a".to_string()), Value::String("b".to_string())])
+}
+
+fn keep_user(args: Vec<Value>) -> Value {
+    USERS.lock().unwrap().push(args);
+    Value::Null
+}
+
+const USER_STRUCT: &str = "struct User {\n    name: String,\n    age: int,\n    admin: bool,\n\n    methods {\n        \
+    label(self) String {\n            return self.name + \"!\"\n        }\n        \
+    older(self, years: int) int {\n            return self.age + years\n        }\n    }\n}\n\n";
+
+#[test]
+fn host_functions_return_structs_and_lists() {
+    let mut engine = Engine::new();
+    engine.register_native_struct("fetchUser", &[TokenType::INT], "User", fetch_user).unwrap();
+    engine.register_native_list("scores", &[TokenType::INT], TokenType::INT, scores).unwrap();
+    engine.register_native_list("tags", &[], TokenType::STRING, tags).unwrap();
+    engine.register_native("keepUser", &[TokenType::STRING, TokenType::INT, TokenType::BOOL], TokenType::NULL, keep_user).unwrap();
+
+    engine.run_source(&format!("{}fn main() null {{\n    var u: User = fetchUser(1)\n    keepUser(u.name, u.age, u.admin)\n    \
+        var v: User = fetchUser(2)\n    keepUser(v.label(), v.older(5), v.admin)\n    \
+        var xs: List<int> = scores(3)\n    var ts: List<String> = tags()\n    keepUser(ts.join(\",\"), xs[2] + xs.len(), ts[1] == \"b\")\n}}\n", USER_STRUCT)).unwrap();
+
+    assert_eq!(*USERS.lock().unwrap(), vec![
+        vec![Value::String("user1".to_string()), Value::Int(21), Value::Bool(true)],
+        vec![Value::String("user2!".to_string()), Value::Int(27), Value::Bool(false)],
+        vec![Value::String("a,b".to_string()), Value::Int(33), Value::Bool(true)],
+    ]);
+}
+
+fn broken_user(_args: Vec<Value>) -> Value {
+    Value::HostStruct(vec![Value::String("nobody".to_string()), Value::Int(1)])
+}
+
+// Runtime error is reported to stderr, so the failing program runs in a child process of this test binary
+#[test]
+fn host_struct_with_wrong_field_count_is_rejected() {
+    if std::env::var("SHLANG_EMBED_CHILD").is_ok() {
+        let mut engine = Engine::new();
+        engine.register_native_struct("brokenUser", &[], "User", broken_user).unwrap();
+        let result = engine.run_source(&format!("{}fn main() null {{\n    var u: User = brokenUser()\n    println(u.name)\n}}\n", USER_STRUCT));
+        assert_eq!(result, Err(Termination::Runtime));
+        return
+    }
+
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "host_struct_with_wrong_field_count_is_rejected", "--nocapture"])
+        .env("SHLANG_EMBED_CHILD", "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("nobody"));
+    assert!(stderr.contains("Native \"brokenUser\" returned 2 fields of struct \"User\" which has 3 fields 17:"), "{}", stderr);
+}
+
+#[test]
+fn invalid_struct_and_list_registrations_are_rejected() {
+    let mut engine = Engine::new();
+    assert_eq!(engine.register_native_struct("text", &[], "String", broken_user), Err("Native \"text\" cannot return builtin struct \"String\"".to_string()));
+    assert!(engine.register_native_list("nested", &[], TokenType::LIST, scores).unwrap_err().contains("cannot return list of LIST"));
+}
+
+fn explode(_: Vec<Value>) -> Value {
+    panic!("host native exploded")
+}
+
+#[test]
+fn interpreter_panic_is_returned_as_runtime_error() {
+    let mut engine = Engine::new();
+    engine.register_native("explode", &[TokenType::INT], TokenType::INT, explode).unwrap();
+
+    let result = engine.run_source("fn main() null {\n    println(explode(1))\n}\n");
+    assert_eq!(result, Err(Termination::Runtime));
+
+    // a builtin panicking on bad input and a clean run afterwards, nothing is left poisoned
+    assert_eq!(engine.run_source("fn main() null {\n    var s: String = \"ab\"\n    println(s.get(5))\n}\n"), Err(Termination::Runtime));
+    assert_eq!(engine.run_source("fn main() null {\n    var n: int = 1\n}\n"), Ok(()));
+}
@@ -0,0 +1,77 @@
+use std::{fs, path::PathBuf, process::Command};
+
+struct Outcome {
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+const SOURCE: &str = "struct P {\n    x: int,\n}\n\n\
+    fn add(a: int, b: int) int {\n    return a + b\n}\n\n\
+    fn half(x: float) float {\n    return x / 2.0\n}\n\n\
+    fn pick(flag: bool, a: String, b: String) String {\n    if flag {\n        return a\n    }\n    return b.toUpper()\n}\n\n\
+    fn shout(s: String) null {\n    println(s, \"!\")\n}\n\n\
+    fn px(p: P) int {\n    return p.x\n}\n\n\
+    fn total(xs: List<int>) int {\n    return xs.len()\n}\n\n\
+    fn divmod(a: int, b: int) (int, int) {\n    return (a / b, a % b)\n}\n\n\
+    fn main() null {\n    println(\"main\")\n}\n";
+
+fn run_entry(name: &str, args: &[&str]) -> Outcome {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("entry.shl");
+    fs::write(&path, SOURCE).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_shlang"))
+        .args(["--leak-report", "--run", name])
+        .arg(&path)
+        .arg("--")
+        .args(args)
+        .output()
+        .unwrap();
+
+    Outcome {
+        exit_code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    }
+}
+
+fn assert_prints(name: &str, args: &[&str], expected: &str) {
+    let outcome = run_entry(name, args);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, expected);
+}
+
+fn assert_rejected(name: &str, args: &[&str], expected: &str) {
+    let outcome = run_entry(name, args);
+    assert_eq!(outcome.exit_code, Some(1));
+    assert!(outcome.stdout.is_empty(), "{}", outcome.stdout);
+    assert!(outcome.stderr.contains("==== ARGUMENT ERROR ===="), "{}", outcome.stderr);
+    assert!(outcome.stderr.contains(expected), "expected: {}\nfound:\n{}", expected, outcome.stderr);
+}
+
+#[test]
+fn function_is_called_with_parsed_arguments() {
+    assert_prints("add", &["2", "40"], "42\n");
+    assert_prints("add", &["-2", "1"], "-1\n");
+    assert_prints("half", &["5"], "2.5\n");
+    assert_prints("pick", &["true", "yes", "no"], "yes\n");
+    assert_prints("pick", &["false", "yes", "no"], "NO\n");
+}
+
+#[test]
+fn null_result_is_not_printed() {
+    assert_prints("shout", &["hey"], "hey!\n");
+    assert_prints("main", &[], "main\n");
+}
+
+#[test]
+fn bad_entry_is_rejected() {
+    assert_rejected("nope", &[], "Function \"nope\" is not declared, available functions: add, half, pick, shout, px, total, divmod, main");
+    assert_rejected("add", &["1"], "Function \"add\" expects 2 arguments but 1 were given");
+    assert_rejected("add", &["1", "x"], "Argument 2 of \"add\" has to be int, found: \"x\"");
+    assert_rejected("pick", &["yes", "a", "b"], "Argument 1 of \"pick\" has to be bool, found: \"yes\"");
+    assert_rejected("px", &["1"], "Argument 1 of \"px\" has type P, only int, float, bool and String arguments are supported");
+    assert_rejected("total", &["1"], "Argument 1 of \"total\" has type List, only int, float, bool and String arguments are supported");
+    assert_rejected("divmod", &["7", "2"], "Function \"divmod\" returns (int, int), only int, float, bool, String or null results can be printed");
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::path::PathBuf;
+
+// Runs the shlang binary on the given file and returns (exit code, stderr)
+fn run_file(path: PathBuf) -> (Option<i32>, String) {
+    let outcome = common::run_shlang(&[], &[path], b"");
+    (outcome.exit_code, outcome.stderr)
+}
+
+fn run_args(args: &[&str]) -> (Option<i32>, String) {
+    let outcome = common::run_shlang(args, &[], b"");
+    (outcome.exit_code, outcome.stderr)
+}
+
+fn run_source(name: &str, source: &str) -> (Option<i32>, String) {
+    run_file(common::write_source(&format!("exit_{}", name), source))
+}
+
+#[test]
+fn clean_program_exits_with_0() {
+    let (code, stderr) = run_source("clean", "fn main() null {\n    println(\"ok\")\n}\n");
+    assert_eq!(code, Some(0), "{}", stderr);
+}
+
+#[test]
+fn compile_error_exits_with_65() {
+    let (code, stderr) = run_source("compile", "fn main() null {\n    var x: int = \"text\"\n}\n");
+    assert_eq!(code, Some(65), "{}", stderr);
+    assert!(stderr.contains("ERROR ===="), "{}", stderr);
+}
+
+#[test]
+fn runtime_error_exits_with_70() {
+    let (code, stderr) = run_source("runtime", "fn main() null {\n    var zero: int = 0\n    println(10 / zero)\n}\n");
+    assert_eq!(code, Some(70), "{}", stderr);
+    assert!(stderr.contains("==== RUNTIME ERROR ===="), "{}", stderr);
+}
+
+#[test]
+fn interpreter_panic_exits_with_70() {
+    let (code, stderr) = run_source("panic", "fn main() null {\n    var s: String = \"ab\"\n    println(s.get(5))\n}\n");
+    assert_eq!(code, Some(70), "{}", stderr);
+    assert!(stderr.starts_with("==== RUNTIME ERROR ====\ninternal interpreter error: index out of bounds"), "{}", stderr);
+    assert!(!stderr.contains("panicked at"), "{}", stderr);
+}
+
+#[test]
+fn missing_source_file_exits_with_74() {
+    let (code, stderr) = run_file(PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("exit_missing.shl"));
+    assert_eq!(code, Some(74), "{}", stderr);
+    assert!(stderr.contains("==== FILE OPEN ===="), "{}", stderr);
+}
+
+// bad command line prints usage and never runs the program
+#[test]
+fn bad_arguments_exit_with_1() {
+    let path = common::write_source("exit_args", "fn main() null {\n    println(\"ran\")\n}\n");
+    let path = path.to_str().unwrap();
+
+    for args in [&["--bogus", path][..], &["--run"][..], &["--run", "main", "--run", "main", path][..], &[][..], &["--strict"][..]] {
+        let (code, stderr) = run_args(args);
+        assert_eq!(code, Some(1), "{:?}: {}", args, stderr);
+        assert!(stderr.starts_with("Usage: shlang"), "{:?}: {}", args, stderr);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+// tests/fmt/<name>.shl is formatted and compared with <name>.expected,
+// every tests/programs/<name>.shl is used as idempotence corpus
+const FMT_DIR: &str = "tests/fmt";
+const PROGRAMS_DIR: &str = "tests/programs";
+
+fn shlang(args: &[&Path], flag: Option<&str>) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_shlang"));
+    command.arg("fmt");
+    if let Some(flag) = flag {
+        command.arg(flag);
+    }
+
+    command.args(args).output().unwrap()
+}
+
+fn format_file(path: &Path) -> String {
+    let output = shlang(&[path], None);
+    assert_eq!(output.status.code(), Some(0), "{}: fmt failed, stderr:\n{}",
+        path.display(), String::from_utf8_lossy(&output.stderr));
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn tmp_file(name: &str, content: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("fmt_{}.shl", name));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+fn collect(dir: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "shl"))
+        .collect();
+
+    files.sort();
+    files
+}
+
+#[test]
+fn formats_fixtures() {
+    for source in collect(FMT_DIR) {
+        let expected = fs::read_to_string(source.with_extension("expected")).unwrap();
+        assert_eq!(format_file(&source), expected, "{}: unexpected formatting", source.display());
+    }
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let mut corpus = collect(FMT_DIR);
+    corpus.extend(collect(PROGRAMS_DIR));
+
+    for source in corpus {
+        let name = source.file_stem().unwrap().to_string_lossy().to_string();
+        let once = format_file(&source);
+        let twice = format_file(&tmp_file(&name, &once));
+        assert_eq!(once, twice, "{}: formatting twice differs from formatting once", source.display());
+    }
+}
+
+#[test]
+fn formatted_programs_keep_output() {
+    for source in collect(PROGRAMS_DIR) {
+        let name = source.file_stem().unwrap().to_string_lossy().to_string();
+        let formatted = tmp_file(&name, &format_file(&source));
+
+        let stdin = match fs::File::open(source.with_extension("stdin")) {
+            Ok(file) => Stdio::from(file),
+            Err(_) => Stdio::null(),
+        };
+        let output = Command::new(env!("CARGO_BIN_EXE_shlang"))
+            .arg(&formatted)
+            .stdin(stdin)
+            .output()
+            .unwrap();
+
+        let expected = fs::read_to_string(source.with_extension("expected")).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected, "{}: output changed after formatting", source.display());
+    }
+}
+
+#[test]
+fn check_reports_unformatted_file() {
+    let unformatted = tmp_file("check_unformatted", "fn main(){println(1+2)}");
+    let output = shlang(&[&unformatted], Some("--check"));
+    assert_eq!(output.status.code(), Some(1));
+
+    let formatted = tmp_file("check_formatted", "fn main() {\n    println(1 + 2)\n}\n");
+    let output = shlang(&[&formatted], Some("--check"));
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn write_formats_in_place() {
+    let path = tmp_file("write", "fn main(){\r\nprintln(1+2)\r\n}");
+    let output = shlang(&[&path], Some("-w"));
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fn main() {\n    println(1 + 2)\n}\n");
+}
+
+#[test]
+fn scanner_error_fails_formatting() {
+    let path = tmp_file("error", "fn main() {\n    println(\"unterminated)\n}\n");
+    let output = shlang(&[&path], None);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("FORMAT ERROR"));
+}
+
+#[test]
+fn nested_list_type_keeps_closing_brackets_together() {
+    let source = "fn main() null {\n    var xs: List<List<int>> = []\n    var b: bool = not true and false\n}\n";
+    assert_eq!(format_file(&tmp_file("nested_generic", source)), source);
+}
+
+// ">=" is a single token, so List<int>= never closes the type and nothing after it may lose its spaces
+#[test]
+fn unclosed_list_type_fails_formatting() {
+    let source = "fn main() null {\n    var xs: List<int>= [1]\n    var b: bool = not true and false\n}\n";
+    let path = tmp_file("unclosed_generic", source);
+
+    for flag in [None, Some("--check"), Some("-w")] {
+        let output = shlang(&[&path], flag);
+        assert_eq!(output.status.code(), Some(65), "{:?}", flag);
+        assert!(output.stdout.is_empty(), "{:?}: {}", flag, String::from_utf8_lossy(&output.stdout));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("List type is not closed with '>' 2:"), "{:?}", flag);
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), source);
+}
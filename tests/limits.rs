@@ -0,0 +1,167 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::Outcome;
+
+fn run_source(name: &str, source: &str, args: &[&str]) -> Outcome {
+    run_with_stdin(name, source, args, "")
+}
+
+fn run_with_stdin(name: &str, source: &str, args: &[&str], stdin: &str) -> Outcome {
+    let path = common::write_source(&format!("limits_{}", name), source);
+    common::run_shlang(args, &[path], stdin.as_bytes())
+}
+
+// every call of nest keeps its instance alive until the innermost call returns
+const ALLOCATING: &str = "struct P {\n    x: int,\n}\n\nfn nest(n: int) null {\n    var p: P = {n}\n    if n > 0 {\n        nest(n - 1)\n    }\n}\n\n\
+    fn main() null {\n    var i: int = 0\n    while i < 100 {\n        nest(i)\n        i = i + 1\n    }\n    println(\"done\")\n}\n";
+
+const SPINNING: &str = "fn spin() null {\n    var i: int = 0\n    while true {\n        i = i + 1\n    }\n}\n\nfn main() null {\n    spin()\n}\n";
+
+#[test]
+fn heap_limit_stops_allocating_script() {
+    let outcome = run_source("heap", ALLOCATING, &["--max-heap-objects", "50"]);
+    assert_eq!(outcome.exit_code, Some(70));
+    assert!(outcome.stdout.is_empty(), "{}", outcome.stdout);
+    assert!(outcome.stderr.contains("==== RUNTIME ERROR ===="), "{}", outcome.stderr);
+    assert!(outcome.stderr.contains("heap limit exceeded (50 objects) in function \"nest\" 6:"), "{}", outcome.stderr);
+
+    let outcome = run_source("heap_unlimited", ALLOCATING, &[]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "done\n");
+}
+
+#[test]
+fn instruction_limit_stops_infinite_loop() {
+    let outcome = run_source("instructions", SPINNING, &["--max-instructions", "10000"]);
+    assert_eq!(outcome.exit_code, Some(70));
+    assert!(outcome.stderr.contains("instruction limit exceeded (10000 instructions) in function \"spin\""), "{}", outcome.stderr);
+
+    let outcome = run_source("instructions_enough", ALLOCATING, &["--max-instructions", "1000000", "--strict"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+}
+
+#[test]
+fn timeout_stops_infinite_loop() {
+    let started = Instant::now();
+    let outcome = run_source("timeout", SPINNING, &["--timeout-ms", "200"]);
+    let elapsed = started.elapsed();
+
+    assert_eq!(outcome.exit_code, Some(70));
+    assert!(elapsed >= Duration::from_millis(200) && elapsed < Duration::from_secs(3), "{:?}", elapsed);
+    // reported inside the while loop of spin, lines 3 to 5
+    assert!(["3:", "4:", "5:"].iter().any(|line| outcome.stderr.contains(&format!("timeout exceeded (200 ms) in function \"spin\" {}", line))), "{}", outcome.stderr);
+
+    let outcome = run_source("timeout_enough", ALLOCATING, &["--timeout-ms", "10000"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "done\n");
+}
+
+#[test]
+fn limit_requires_number() {
+    let outcome = run_source("bad_value", SPINNING, &["--max-instructions", "many"]);
+    assert_eq!(outcome.exit_code, Some(1));
+    assert!(outcome.stderr.contains("Expected a positive number after --max-instructions"), "{}", outcome.stderr);
+}
+
+#[test]
+fn string_accumulation_keeps_heap_bounded() {
+    let source = "struct Log {\n    text: String,\n}\n\nfn main() null {\n    var s: String = \"\"\n    var piece: String = \"ab\"\n    var log: Log = {\"\"}\n\
+        var i: int = 0\n    while i < 10000 {\n        s = s + piece\n        log.text = log.text + \"c\"\n        i = i + 1\n    }\n\
+        println(s.len(), \" \", log.text.len(), \" \", s.get(19999))\n}\n";
+
+    // the three locals and the temporaries of one statement fit, an instance leaked per iteration would not
+    let outcome = run_source("accumulation", source, &["--max-heap-objects", "8", "--leak-report"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "20000 10000 b\n");
+}
+
+#[test]
+fn sandbox_rejects_io_natives_at_compile_time() {
+    let source = "fn main() null {\n    println(\"start\")\n    var line: String = input(\"\")\n    println(line.len(), \" \", abs(-1))\n}\n";
+
+    let outcome = run_source("sandbox", source, &["--sandbox"]);
+    assert_eq!(outcome.exit_code, Some(65));
+    assert!(outcome.stdout.is_empty(), "{}", outcome.stdout);
+    assert!(outcome.stderr.contains("native 'input' is disabled in sandbox mode 3:"), "{}", outcome.stderr);
+
+    let outcome = run_source("sandbox_off", source, &[]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "start\n0 1\n");
+
+    let outcome = run_source("sandbox_pure", "fn main() null {\n    println(abs(-1), \" \", \"ok\".toUpper())\n}\n", &["--sandbox"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "1 OK\n");
+}
+
+// condition calls a method on the string the loop body reassigns, the false branch leaves the loop
+#[test]
+fn read_loop_with_method_condition_keeps_heap_bounded() {
+    let source = "fn main() null {\n    var total: int = 0\n    var line: String = input(\"\")\n    while line.len() > 0 {\n\
+        total = total + line.len()\n        line = input(\"\")\n    }\n    println(total, \" [\", line, \"] \", line.len())\n}\n";
+    let stdin = format!("{}\nnot read\n", "abc\n".repeat(5000));
+
+    let outcome = run_with_stdin("read_loop", source, &["--max-heap-objects", "2", "--leak-report"], &stdin);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "15000 [] 0\n");
+}
+
+// filled and removed strings are copied out of their instances, nothing is left on the heap
+#[test]
+fn list_fill_and_remove_on_strings_keep_heap_bounded() {
+    let source = "fn main() null {\n    var s: String = \"ab\"\n    var xs: List<String> = [s, \"c\", \"d\"]\n    var i: int = 0\n\
+        while i < 5000 {\n        xs.fill(s + \"!\")\n        var r: String = xs.remove(0)\n        var ys: List<String> = [r]\n        xs.extend(ys)\n        i = i + 1\n    }\n\
+        println(xs.join(\",\"), \" \", s)\n}\n";
+
+    let outcome = run_source("list_fill", source, &["--max-heap-objects", "8", "--leak-report"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "ab!,ab!,ab! ab\n");
+}
+
+// break and continue release the instances of the loop body, nothing is left for the leak report
+#[test]
+fn break_and_continue_release_body_instances() {
+    let source = "struct P {\n    x: int,\n}\n\nfn main() null {\n    for i in (0, 5) {\n        var p: P = P{i}\n        if i == 1 {\n            continue\n        }\n        if i == 2 {\n            break\n        }\n    }\n\
+        var q: P = P{9}\n    println(q.x)\n}\n";
+
+    let outcome = run_source("loop_exit", source, &["--leak-report"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "9\n");
+}
+
+// List<T> param is a reference to the caller's list, every call releases it again
+#[test]
+fn list_params_keep_heap_bounded() {
+    let source = "fn rotate(xs: List<String>, tag: String) null {\n    var r: String = xs.remove(0)\n    var ys: List<String> = [r + tag]\n    xs.extend(ys)\n    xs[0] = xs[0]\n}\n\n\
+        fn main() null {\n    var xs: List<String> = [\"a\", \"b\"]\n    var i: int = 0\n    while i < 5000 {\n        rotate(xs, \"\")\n        i = i + 1\n    }\n\
+        println(xs.join(\",\"), \" \", xs.len())\n}\n";
+
+    let outcome = run_source("list_params", source, &["--max-heap-objects", "12", "--leak-report"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "a,b 2\n");
+}
+
+// locals of finished scopes are popped, only the deepest point counts against --max-locals
+#[test]
+fn max_locals_counts_live_locals() {
+    let source = "fn pair(a: int, b: int) int {\n    return a + b\n}\n\n\
+        fn main() null {\n    var x: int = pair(1, 2)\n    if x > 0 {\n        var y: int = x\n        println(y)\n    }\n    for i in (0, 2) {\n        println(i)\n    }\n}\n";
+
+    let outcome = run_source("max_locals_ok", source, &["--max-locals", "4"]);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "3\n0\n1\n2\n");
+
+    let outcome = run_source("max_locals_for", source, &["--max-locals", "3"]);
+    assert_eq!(outcome.exit_code, Some(65));
+    assert!(outcome.stderr.contains("Function \"main\" declares more than 3 locals, for loop over \"i\" exceeds the limit 11:"), "{}", outcome.stderr);
+
+    let outcome = run_source("max_locals_param", source, &["--max-locals", "1"]);
+    assert_eq!(outcome.exit_code, Some(65));
+    assert!(outcome.stderr.contains("Function \"pair\" declares more than 1 locals, param \"b\" exceeds the limit 1:"), "{}", outcome.stderr);
+}
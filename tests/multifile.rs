@@ -0,0 +1,82 @@
+mod common;
+
+use std::path::PathBuf;
+
+const MAIN: &str = "fn main() null {\n    var r: Rect = {3, 4}\n    println(describe(r), \" \", double(area(r)))\n}\n";
+const UTILS: &str = "fn double(x: int) int {\n    return x * 2\n}\n";
+const SHAPES: &str = "struct Rect {\n    w: int,\n    h: int,\n}\n\nfn area(r: Rect) int {\n    return r.w * r.h\n}\n\n\
+    fn describe(r: Rect) String {\n    return \"rect \" + convstr(r.w) + \"x\" + convstr(r.h)\n}\n";
+
+fn write(name: &str, source: &str) -> PathBuf {
+    common::write_source(&format!("multifile_{}", name), source)
+}
+
+fn run(files: &[PathBuf]) -> (Option<i32>, String, String) {
+    run_with(&["--leak-report"], files)
+}
+
+fn run_with(args: &[&str], files: &[PathBuf]) -> (Option<i32>, String, String) {
+    let outcome = common::run_shlang(args, files, b"");
+    (outcome.exit_code, outcome.stdout, outcome.stderr)
+}
+
+fn assert_rejected(files: &[PathBuf], expected: &str) {
+    let (code, stdout, stderr) = run(files);
+    assert_eq!(code, Some(65));
+    assert!(stdout.is_empty(), "{}", stdout);
+    assert!(stderr.contains(expected), "expected: {}\nfound:\n{}", expected, stderr);
+}
+
+#[test]
+fn listed_files_run_as_one_program() {
+    let (main, utils, shapes) = (write("main", MAIN), write("utils", UTILS), write("shapes", SHAPES));
+    let single = write("single", &format!("{}\n{}\n{}", UTILS, SHAPES, MAIN));
+
+    let expected = run(&[single]);
+    assert_eq!(expected, (Some(0), "rect 3x4 24\n".to_string(), String::new()));
+
+    assert_eq!(run(&[main.clone(), utils.clone(), shapes.clone()]), expected);
+    assert_eq!(run(&[shapes.clone(), main.clone(), utils.clone()]), expected);
+    // a file listed twice is compiled once
+    assert_eq!(run(&[main.clone(), utils.clone(), shapes.clone(), utils]), expected);
+}
+
+#[test]
+fn declarations_are_checked_across_files() {
+    let (main, utils, shapes) = (write("main_dup", MAIN), write("utils_dup", UTILS), write("shapes_dup", SHAPES));
+    let other = write("other_dup", "fn double(x: int) int {\n    return x + x\n}\n");
+    let second_main = write("second_main", "struct Rect {\n    x: int,\n}\n\nfn main() null {\n}\n");
+
+    assert_rejected(&[main.clone(), utils.clone(), shapes.clone(), other.clone()],
+        &format!("Function \"double\" is declared in both {} (line 1) and {} (line 1)", utils.display(), other.display()));
+    assert_rejected(&[main.clone(), utils.clone(), shapes.clone(), second_main.clone()],
+        &format!("Struct \"Rect\" is declared in both {} (line 1) and {} (line 1)", shapes.display(), second_main.display()));
+    assert_rejected(&[main, second_main.clone()],
+        "Function \"main\" is declared in both");
+    assert_rejected(&[utils.clone(), shapes.clone()],
+        &format!("Function \"main\" has to be declared in one of the files: {}, {}", utils.display(), shapes.display()));
+}
+
+#[test]
+fn errors_name_the_file_of_their_line() {
+    let main = write("main_err", "fn main() null {\n    println(\"start\")\n    println(helper(4))\n}\n");
+    let util = write("util_err", "fn helper(x: int) int {\n    var zero: int = x - x\n    return x / zero\n}\n");
+    let broken = write("broken_err", "fn broken() int {\n    var x: int = \"text\"\n    return x\n}\n");
+
+    // source line is taken from the file the error happened in, not from the main file
+    let (code, stdout, stderr) = run_with(&["--with-source"], &[main.clone(), util.clone()]);
+    assert_eq!(code, Some(70));
+    assert_eq!(stdout, "start\n");
+    assert!(stderr.contains(&format!("Division by zero in '/' 3:\n  --> {}:3\n   3 | return x / zero\n", util.display())), "{}", stderr);
+    assert!(!stderr.contains("println(helper(4))"), "{}", stderr);
+
+    let (_, _, stderr) = run_with(&[], &[main.clone(), util.clone()]);
+    assert!(stderr.ends_with(&format!("  --> {}:3\n", util.display())), "{}", stderr);
+
+    assert_rejected(&[main.clone(), util.clone(), broken.clone()],
+        &format!("Mismatched types while declaring var, expected: int found: String 2:\n  --> {}:2", broken.display()));
+
+    // unbalanced brackets are checked per file
+    let unclosed = write("unclosed_err", "fn unclosed() null {\n    println(1)\n");
+    assert_rejected(&[unclosed.clone(), main], &format!("Unclosed '{{' opened at line 1 (function 'unclosed') 1:\n  --> {}:1", unclosed.display()));
+}
@@ -0,0 +1,74 @@
+use shlang::frontend::outline::{outline, FnOutline, ParamOutline};
+
+fn param(name: &str, param_type: &str) -> ParamOutline {
+    ParamOutline { name: name.to_string(), param_type: param_type.to_string() }
+}
+
+const SOURCE: &str = "struct Point {
+    x: int,
+    tags: List<String>,
+
+    methods {
+        sum(self) int {
+            return self.x
+        }
+    }
+}
+
+methods Point {
+    shifted(self, dx: int) Point {
+        return Point{self.x + dx, self.tags}
+    }
+}
+
+fn pair(a: int, b: float) (int, float) {
+    return (a, b)
+}
+
+fn main() {
+    var p: Point = {1, [\"a\"]}
+    println(p.sum())
+}
+";
+
+#[test]
+fn outline_lists_functions_and_structs() {
+    let file = outline(SOURCE).unwrap();
+
+    assert_eq!(file.functions, vec![
+        FnOutline { name: "pair".to_string(), params: vec![param("a", "int"), param("b", "float")], output_type: Some("(int, float)".to_string()), line: 18 },
+        FnOutline { name: "main".to_string(), params: vec![], output_type: None, line: 22 },
+    ]);
+
+    assert_eq!(file.structs.len(), 1);
+    let point = &file.structs[0];
+    assert_eq!((point.name.as_str(), point.line), ("Point", 1));
+    assert_eq!(point.fields, vec![param("x", "int"), param("tags", "List<String>")]);
+    assert_eq!(point.methods, vec![
+        FnOutline { name: "sum".to_string(), params: vec![param("self", "Point")], output_type: Some("int".to_string()), line: 6 },
+        FnOutline { name: "shifted".to_string(), params: vec![param("self", "Point"), param("dx", "int")], output_type: Some("Point".to_string()), line: 13 },
+    ]);
+}
+
+#[test]
+fn outline_ignores_errors_in_bodies() {
+    let source = "fn broken(a: int) bool {\n    var x: int = \"oops\" +\n    return undefined(\n}\n\nfn main() null {\n    broken(1)\n}\n";
+    let file = outline(source).unwrap();
+
+    let names: Vec<&str> = file.functions.iter().map(|function| function.name.as_str()).collect();
+    assert_eq!(names, vec!["broken", "main"]);
+    assert_eq!(file.functions[0].params, vec![param("a", "int")]);
+    assert_eq!(file.functions[0].output_type, Some("bool".to_string()));
+    assert_eq!(file.functions[1].line, 6);
+}
+
+#[test]
+fn outline_reports_token_errors() {
+    assert!(outline("fn main() null {\n    var s: String = \"open\n}\n").is_err());
+}
+
+#[test]
+fn outline_reads_newline_separated_fields() {
+    let file = outline("struct P {\n    x: int\n    y: float, z: String\n    w: bool\n}\n\nfn main() {\n}\n").unwrap();
+    assert_eq!(file.structs[0].fields, vec![param("x", "int"), param("y", "float"), param("z", "String"), param("w", "bool")]);
+}
@@ -0,0 +1,149 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Every tests/programs/<name>.shl is run through the shlang binary and compared with:
+//   <name>.expected - expected stdout (required)
+//   <name>.stdin    - fed to the program (optional)
+//   <name>.exitcode - expected exit code, 0 when missing (optional)
+const PROGRAMS_DIR: &str = "tests/programs";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Outcome {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+}
+
+fn run_program(path: &Path, stdin: &str) -> Result<Outcome, String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_shlang"))
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to spawn shlang: {}", e))?;
+
+    // program may exit before reading everything, broken pipe is fine here
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+
+    let mut stdout_pipe = child.stdout.take().unwrap();
+    let mut stderr_pipe = child.stderr.take().unwrap();
+    let stdout_reader = thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout_pipe.read_to_string(&mut buf);
+        buf
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr_pipe.read_to_string(&mut buf);
+        buf
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if start.elapsed() > TIMEOUT => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {:?}", TIMEOUT));
+            },
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+
+    Ok(Outcome {
+        stdout: stdout_reader.join().unwrap(),
+        stderr: stderr_reader.join().unwrap(),
+        exit_code: status.code(),
+    })
+}
+
+fn first_difference(expected: &str, found: &str) -> String {
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let found_lines: Vec<&str> = found.lines().collect();
+
+    for i in 0..expected_lines.len().max(found_lines.len()) {
+        let exp = expected_lines.get(i);
+        let got = found_lines.get(i);
+        if exp != got {
+            return format!("first difference at line {}:\n    expected: {:?}\n    found:    {:?}", i + 1, exp, got);
+        }
+    }
+
+    "outputs differ only in trailing newline".to_string()
+}
+
+fn check_program(source: &Path) -> Result<(), String> {
+    let expected_path = source.with_extension("expected");
+    let expected = fs::read_to_string(&expected_path)
+        .map_err(|_| format!("missing {}", expected_path.display()))?;
+
+    let stdin = fs::read_to_string(source.with_extension("stdin")).unwrap_or_default();
+    let expected_code = match fs::read_to_string(source.with_extension("exitcode")) {
+        Ok(code) => code.trim().parse::<i32>().map_err(|e| format!("invalid .exitcode file: {}", e))?,
+        Err(_) => 0,
+    };
+
+    let outcome = run_program(source, &stdin)?;
+
+    let mut report = String::new();
+    if outcome.exit_code != Some(expected_code) {
+        report.push_str(&format!("exit code: expected {}, found {:?}\n", expected_code, outcome.exit_code));
+    }
+    if outcome.stdout != expected {
+        report.push_str(&format!("stdout mismatch, {}\n", first_difference(&expected, &outcome.stdout)));
+        report.push_str(&format!("--- expected stdout ---\n{}\n--- found stdout ---\n{}\n", expected, outcome.stdout));
+    }
+
+    if report.is_empty() {
+        return Ok(())
+    }
+
+    if !outcome.stderr.is_empty() {
+        report.push_str(&format!("--- stderr ---\n{}\n", outcome.stderr));
+    }
+    Err(report)
+}
+
+fn collect_programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(PROGRAMS_DIR);
+    let mut programs: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "shl"))
+        .collect();
+
+    programs.sort();
+    programs
+}
+
+#[test]
+fn programs() {
+    let programs = collect_programs();
+    assert!(!programs.is_empty(), "no programs found in {}", PROGRAMS_DIR);
+
+    let mut failures: Vec<(String, String)> = vec![];
+    for program in programs.iter() {
+        let name = program.file_stem().unwrap().to_string_lossy().to_string();
+        if let Err(report) = check_program(program) {
+            failures.push((name, report));
+        }
+    }
+
+    for (name, report) in failures.iter() {
+        eprintln!("==== {} ====\n{}", name, report);
+    }
+
+    assert!(failures.is_empty(), "{} of {} programs failed: {}",
+        failures.len(),
+        programs.len(),
+        failures.iter().map(|(name, _)| name.as_str()).collect::<Vec<&str>>().join(", "),
+    );
+}
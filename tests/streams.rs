@@ -0,0 +1,54 @@
+mod common;
+
+use common::Outcome;
+
+fn run(args: &[&str], name: &str, source: &str) -> Outcome {
+    common::run_source(&format!("streams_{}", name), source, args)
+}
+
+// unused result and float equality warnings
+const NOISY: &str = "fn two() int {\n    return 2\n}\n\n\
+    fn main() null {\n    two()\n    var x: float = 0.5\n    println(\"eq \", x == 0.5)\n    print(\"a\", 1)\n    println()\n    println(two())\n}\n";
+
+#[test]
+fn stdout_holds_only_program_output() {
+    let outcome = run(&["--leak-report"], "noisy", NOISY);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "eq true\na1\n2\n");
+    assert!(outcome.stderr.contains("[S003] Result of 'two' of type int is ignored"), "{}", outcome.stderr);
+    assert!(outcome.stderr.contains("[S004] Operator '==' checks float operands"), "{}", outcome.stderr);
+}
+
+#[test]
+fn quiet_hides_warnings() {
+    let outcome = run(&["--quiet"], "quiet", NOISY);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "eq true\na1\n2\n");
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+
+    // errors are still reported
+    let outcome = run(&["--quiet"], "quiet_error", "fn main() null {\n    var x: int = \"text\"\n}\n");
+    assert_eq!(outcome.exit_code, Some(65));
+    assert!(outcome.stdout.is_empty(), "{}", outcome.stdout);
+    assert!(outcome.stderr.contains("ERROR ===="), "{}", outcome.stderr);
+}
+
+#[test]
+fn errors_do_not_write_to_stdout() {
+    let outcome = run(&[], "runtime", "fn main() null {\n    println(\"before\")\n    var zero: int = 0\n    println(1 / zero)\n}\n");
+    assert_eq!(outcome.exit_code, Some(70));
+    assert_eq!(outcome.stdout, "before\n");
+    assert!(outcome.stderr.contains("==== RUNTIME ERROR ===="), "{}", outcome.stderr);
+
+    let outcome = run(&[], "struct_from_int", "struct P {\n    x: int,\n}\n\nfn num() int {\n    return 1\n}\n\nfn main() null {\n    var p: P = num()\n}\n");
+    assert_eq!(outcome.exit_code, Some(65));
+    assert!(outcome.stdout.is_empty(), "{}", outcome.stdout);
+    assert!(outcome.stderr.contains("Mismatched types while assigning var, expected: P found: int 10:"), "{}", outcome.stderr);
+}
+
+#[test]
+fn usage_goes_to_stderr() {
+    let outcome = common::run_shlang(&["--no-such-flag"], &[], b"");
+    assert!(outcome.stdout.is_empty(), "{}", outcome.stdout);
+    assert!(outcome.stderr.starts_with("Usage: shlang"));
+}
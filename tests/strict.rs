@@ -0,0 +1,143 @@
+mod common;
+
+use common::Outcome;
+
+fn run_source(name: &str, source: &str, strict: bool) -> Outcome {
+    run_source_with(name, source, strict, &[])
+}
+
+fn run_source_with(name: &str, source: &str, strict: bool, args: &[&str]) -> Outcome {
+    let mut all_args = Vec::from(args);
+    if strict {
+        all_args.insert(0, "--strict");
+    }
+    common::run_source(&format!("strict_{}", name), source, &all_args)
+}
+
+// Program has to compile in normal mode and fail with given strict code in strict mode
+fn assert_strict_only(name: &str, source: &str, expected: &str) {
+    let normal = run_source(name, source, false);
+    assert_eq!(normal.exit_code, Some(0), "{}: expected program to run in normal mode, stderr:\n{}", name, normal.stderr);
+
+    let strict = run_source(name, source, true);
+    assert_eq!(strict.exit_code, Some(65), "{}: expected strict mode error, stderr:\n{}", name, strict.stderr);
+    assert!(strict.stderr.contains("==== STRICT MODE ERROR ===="), "{}: {}", name, strict.stderr);
+    assert!(strict.stderr.contains(expected), "{}: expected stderr to contain {:?}, found:\n{}", name, expected, strict.stderr);
+}
+
+#[test]
+fn var_without_initializer() {
+    assert_strict_only("var_init", "fn main() null {\n    var a: int\n    a = 2\n    println(a)\n}\n",
+        "[S001] Var \"a\" has to be initialized 2:");
+    assert_strict_only("var_init_float", "fn main() null {\n    var a: float\n}\n",
+        "[S001] Var \"a\" has to be initialized 2:");
+}
+
+#[test]
+fn implicit_output_type() {
+    assert_strict_only("output_type", "fn hello() {\n    println(\"hi\")\n}\nfn main() null {\n    hello()\n}\n",
+        "[S002] Function \"hello\" has no output type, write \"null\" if it returns nothing 1:");
+    assert_strict_only("output_type_main", "fn main() {\n}\n",
+        "[S002] Function \"main\" has no output type, write \"null\" if it returns nothing 1:");
+}
+
+#[test]
+fn unused_result() {
+    let header = "fn two() int {\n    return 2\n}\n";
+    assert_strict_only("unused_fn", &format!("{}fn main() null {{\n    two()\n}}\n", header),
+        "[S003] Result of 'two' of type int is ignored, assign it or drop it with \"discard\" 5:");
+    assert_strict_only("unused_native", "fn main() null {\n    abs(2)\n}\n",
+        "[S003] Result of 'abs' of type int is ignored, assign it or drop it with \"discard\" 2:");
+    assert_strict_only("unused_method", "fn main() null {\n    var s: String = \"abc\"\n    s.len()\n}\n",
+        "[S003] Result of 'len' of type int is ignored, assign it or drop it with \"discard\" 3:");
+}
+
+#[test]
+fn unused_result_warns() {
+    let source = "fn scale(a: int) int {\n    return a * 2\n}\nfn main() null {\n    scale(2)\n    discard scale(3)\n    println(scale(4))\n}\n";
+
+    let normal = run_source("unused_warn", source, false);
+    assert_eq!(normal.exit_code, Some(0), "{}", normal.stderr);
+    assert_eq!(normal.stdout, "8\n");
+    assert!(normal.stderr.contains("[S003] Result of 'scale' of type int is ignored, assign it or drop it with \"discard\" 5:"), "{}", normal.stderr);
+    assert_eq!(normal.stderr.matches("==== WARNING ====").count(), 1, "{}", normal.stderr);
+
+    let strict = run_source("unused_warn", source, true);
+    assert_eq!(strict.exit_code, Some(65), "{}", strict.stderr);
+    assert!(strict.stderr.contains("==== STRICT MODE ERROR ===="), "{}", strict.stderr);
+}
+
+#[test]
+fn float_equality_warns() {
+    let source = "fn main() null {\n    var x: float = 0.5\n    println(x == 0.5)\n    println(x != 0.5)\n}\n";
+
+    let normal = run_source("float_eq", source, false);
+    assert_eq!(normal.exit_code, Some(0), "{}", normal.stderr);
+    assert_eq!(normal.stdout, "true\nfalse\n");
+    assert!(normal.stderr.contains("==== WARNING ===="), "{}", normal.stderr);
+    assert!(normal.stderr.contains("[S004] Operator '==' checks float operands for exact equality, use approxEq(a, b, eps) instead 3:"), "{}", normal.stderr);
+    assert!(normal.stderr.contains("[S004] Operator '!=' checks float operands for exact equality, use approxEq(a, b, eps) instead 4:"), "{}", normal.stderr);
+
+    let strict = run_source("float_eq", source, true);
+    assert_eq!(strict.exit_code, Some(65), "{}", strict.stderr);
+    assert!(strict.stderr.contains("==== STRICT MODE ERROR ===="), "{}", strict.stderr);
+    assert!(strict.stderr.contains("[S004] Operator '==' checks float operands for exact equality"), "{}", strict.stderr);
+}
+
+#[test]
+fn truncated_division_warns() {
+    let source = "fn main() null {\n    var a: int = 7\n    var b: int = 2\n    var ratio: float = convf(a / b)\n    println(ratio)\n}\n";
+
+    let normal = run_source("int_div_convf", source, false);
+    assert_eq!(normal.exit_code, Some(0), "{}", normal.stderr);
+    assert_eq!(normal.stdout, "3\n");
+    assert!(normal.stderr.contains("[S005] Integer division truncates before conversion to float; convert the operands instead (convf(a) / convf(b)) 4:"), "{}", normal.stderr);
+
+    let strict = run_source("int_div_convf", source, true);
+    assert_eq!(strict.exit_code, Some(65), "{}", strict.stderr);
+    assert!(strict.stderr.contains("==== STRICT MODE ERROR ===="), "{}", strict.stderr);
+}
+
+#[test]
+fn converted_operands_and_int_division_do_not_warn() {
+    let source = "fn main() null {\n    var a: int = 7\n    var b: int = 2\n    var ratio: float = convf(a) / convf(b)\n    var q: int = a / b\n    println(ratio, \" \", q, \" \", convf(a * b))\n}\n";
+
+    let outcome = run_source("int_div_ok", source, true);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+    assert_eq!(outcome.stdout, "3.5 3 14\n");
+    assert!(outcome.stderr.is_empty(), "{}", outcome.stderr);
+}
+
+#[test]
+fn allow_float_eq_flag_suppresses_warning() {
+    let source = "fn main() null {\n    var x: float = 0.5\n    println(x == 0.5)\n}\n";
+
+    for strict in [false, true] {
+        let allowed = run_source_with("float_eq_allowed", source, strict, &["--allow-float-eq"]);
+        assert_eq!(allowed.exit_code, Some(0), "{}", allowed.stderr);
+        assert_eq!(allowed.stdout, "true\n");
+        assert!(allowed.stderr.is_empty(), "{}", allowed.stderr);
+    }
+}
+
+#[test]
+fn strict_program_is_accepted() {
+    let source = "fn two() int {\n    return 2\n}\nfn main() null {\n    var a: int = two()\n    discard two()\n    discard abs(-1)\n    println(a)\n}\n";
+
+    for strict in [false, true] {
+        let outcome = run_source("accepted", source, strict);
+        assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+        assert_eq!(outcome.stdout, "2\n");
+    }
+}
+
+#[test]
+fn pragma_enables_strict_mode() {
+    let outcome = run_source("pragma", "#!strict\nfn main() null {\n    var a: int\n}\n", false);
+    assert_eq!(outcome.exit_code, Some(65));
+    assert!(outcome.stderr.contains("[S001] Var \"a\" has to be initialized 3:"), "{}", outcome.stderr);
+
+    // pragma is recognized only in the first line
+    let outcome = run_source("pragma_late", "fn main() null {\n#!strict\n    var a: int\n}\n", false);
+    assert_eq!(outcome.exit_code, Some(0), "{}", outcome.stderr);
+}